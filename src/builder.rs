@@ -0,0 +1,383 @@
+//! Client configuration
+
+use crate::client::Client;
+use crate::fault::FaultInjection;
+use crate::file_accessor::FileAccess;
+use crate::message::ClientMessage;
+use crate::recording::Recorder;
+use crate::retry::{ExponentialBackoff, RetryPolicy};
+use crate::sync::DEFAULT_SYNC_BATCH_WINDOW;
+use crate::tls::TlsConfig;
+use crate::transport::TransportFactory;
+use crate::unix_socket::PeerCheck;
+use crate::wire::WireFormat;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long each kind of operation may wait for the server
+#[derive(Debug, Clone)]
+pub struct Timeouts {
+    /// Opening the connection
+    pub connect: Duration,
+
+    /// Authenticating to a tenant
+    pub authenticate: Duration,
+
+    /// Reading variables
+    pub read: Duration,
+
+    /// Writing, allocating and deallocating variables
+    pub write: Duration,
+
+    /// Creating, getting and deleting services and tenants
+    pub admin: Duration,
+
+    /// Waiting for the server to answer a heartbeat
+    pub heartbeat: Duration,
+}
+
+impl Timeouts {
+    /// Use the same timeout for every operation
+    pub fn uniform(timeout: Duration) -> Self {
+        Self {
+            connect: timeout,
+            authenticate: timeout,
+            read: timeout,
+            write: timeout,
+            admin: timeout,
+            heartbeat: timeout,
+        }
+    }
+
+    /// Timeout for the response to a request
+    pub(crate) fn for_message(&self, msg: &ClientMessage) -> Duration {
+        match msg {
+            ClientMessage::Hello { .. } => self.connect,
+            ClientMessage::Authenticate { .. } => self.authenticate,
+            ClientMessage::ReadVariable { .. } | ClientMessage::GetServiceFilePath { .. } => {
+                self.read
+            }
+            ClientMessage::WriteVariable { .. }
+            | ClientMessage::CompareAndSwap { .. }
+            | ClientMessage::AllocateVariable { .. }
+            | ClientMessage::DeallocateVariable { .. }
+            | ClientMessage::ReportVariableChanges { .. }
+            | ClientMessage::Subscribe { .. }
+            | ClientMessage::Unsubscribe { .. }
+            | ClientMessage::Disconnect { .. } => self.write,
+            ClientMessage::CreateTenant { .. }
+            | ClientMessage::DeleteTenant { .. }
+            | ClientMessage::CreateService { .. }
+            | ClientMessage::GetService { .. }
+            | ClientMessage::DeleteService { .. } => self.admin,
+            ClientMessage::Heartbeat { .. } => self.heartbeat,
+        }
+    }
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self::uniform(Duration::from_secs(10))
+    }
+}
+
+/// Builder for a configured `Client`
+///
+/// ```no_run
+/// use commy_sdk_rust::retry::FixedDelay;
+/// use commy_sdk_rust::Client;
+/// use std::time::Duration;
+///
+/// let client = Client::builder("wss://localhost:9000")
+///     .read_timeout(Duration::from_secs(2))
+///     .retry_policy(FixedDelay::new(Duration::from_millis(500)))
+///     .max_reconnect_attempts(10)
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    pub(crate) server_url: String,
+    pub(crate) client_id: Option<String>,
+    pub(crate) timeouts: Timeouts,
+    pub(crate) retry_policy: Arc<dyn RetryPolicy>,
+    pub(crate) heartbeat_interval: Duration,
+    pub(crate) max_missed_heartbeats: u32,
+    pub(crate) max_reconnect_attempts: u32,
+    pub(crate) wire_format: WireFormat,
+    pub(crate) capability_handshake: bool,
+    pub(crate) tls: Option<TlsConfig>,
+    pub(crate) peer_check: PeerCheck,
+    pub(crate) transport: Option<Arc<dyn TransportFactory>>,
+    pub(crate) recorder: Option<Recorder>,
+    pub(crate) fault_injection: Option<FaultInjection>,
+    pub(crate) file_access: FileAccess,
+    pub(crate) sync_batch_window: Duration,
+}
+
+impl ClientBuilder {
+    /// Start configuring a client for a server URL
+    pub fn new(server_url: impl Into<String>) -> Self {
+        Self {
+            server_url: server_url.into(),
+            client_id: None,
+            timeouts: Timeouts::default(),
+            retry_policy: Arc::new(ExponentialBackoff::default()),
+            heartbeat_interval: Duration::from_secs(30),
+            max_missed_heartbeats: 3,
+            max_reconnect_attempts: 5,
+            wire_format: WireFormat::default(),
+            capability_handshake: true,
+            tls: None,
+            peer_check: PeerCheck::default(),
+            transport: None,
+            recorder: None,
+            fault_injection: None,
+            file_access: FileAccess::default(),
+            sync_batch_window: DEFAULT_SYNC_BATCH_WINDOW,
+        }
+    }
+
+    /// Use a fixed client ID instead of a random one
+    pub fn client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    /// Replace all operation timeouts
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Set the timeout for opening the connection
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.connect = timeout;
+        self
+    }
+
+    /// Set the timeout for authenticating to a tenant
+    pub fn authenticate_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.authenticate = timeout;
+        self
+    }
+
+    /// Set the timeout for reading variables
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.read = timeout;
+        self
+    }
+
+    /// Set the timeout for writing, allocating and deallocating variables
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.write = timeout;
+        self
+    }
+
+    /// Set the timeout for service and tenant management
+    pub fn admin_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.admin = timeout;
+        self
+    }
+
+    /// Set how long to wait for the server to answer a heartbeat
+    pub fn heartbeat_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.heartbeat = timeout;
+        self
+    }
+
+    /// Set the policy deciding the delay before each reconnection attempt
+    pub fn retry_policy(mut self, policy: impl RetryPolicy + 'static) -> Self {
+        self.retry_policy = Arc::new(policy);
+        self
+    }
+
+    /// Set the interval between heartbeats
+    ///
+    /// A zero interval disables the background heartbeat.
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Set how many heartbeats in a row may go unanswered before the
+    /// connection is considered dead and a reconnect is started
+    pub fn max_missed_heartbeats(mut self, misses: u32) -> Self {
+        self.max_missed_heartbeats = misses.max(1);
+        self
+    }
+
+    /// Set how many reconnection attempts are made before giving up
+    pub fn max_reconnect_attempts(mut self, attempts: u32) -> Self {
+        self.max_reconnect_attempts = attempts;
+        self
+    }
+
+    /// Set the wire format offered to the server
+    ///
+    /// MessagePack is offered by default; servers that do not accept it are
+    /// spoken to in JSON. Choosing JSON skips the offer.
+    pub fn wire_format(mut self, format: WireFormat) -> Self {
+        self.wire_format = format;
+        self
+    }
+
+    /// Set the TLS settings for `wss://` connections
+    ///
+    /// Without this, server certificates are checked against the operating
+    /// system's trusted roots only.
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Set which server processes a `unix://` connection accepts
+    ///
+    /// By default the server must run as the same user as the client, or
+    /// as root.
+    pub fn peer_check(mut self, check: PeerCheck) -> Self {
+        self.peer_check = check;
+        self
+    }
+
+    /// Open connections through `factory` instead of from the server URL
+    ///
+    /// The URL is then only used to identify the server in messages. See
+    /// `transport::ChannelFactory` for testing without a server.
+    pub fn transport(mut self, factory: impl TransportFactory + 'static) -> Self {
+        self.transport = Some(Arc::new(factory));
+        self
+    }
+
+    /// Set how virtual service files reach the service's data
+    ///
+    /// By default, files are memory-mapped when the server URL points at
+    /// this host, falling back to syncing over the connection.
+    pub fn file_access(mut self, access: FileAccess) -> Self {
+        self.file_access = access;
+        self
+    }
+
+    /// Set how long locally detected changes are collected before they are
+    /// reported to the server in one batch
+    ///
+    /// A zero window reports each change event on its own.
+    pub fn sync_batch_window(mut self, window: Duration) -> Self {
+        self.sync_batch_window = window;
+        self
+    }
+
+    /// Record every message exchanged with the server
+    ///
+    /// Each connection, including reconnections, is appended to the same
+    /// recording. See `recording::Replay` for playing it back.
+    pub fn recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Inject faults into every connection, for chaos testing
+    ///
+    /// See `fault::FaultInjection` for what can go wrong.
+    pub fn fault_injection(mut self, faults: FaultInjection) -> Self {
+        self.fault_injection = Some(faults);
+        self
+    }
+
+    /// Enable or disable the capability handshake after connecting
+    ///
+    /// Servers that silently ignore the hello delay every connect by the
+    /// connect timeout; disabling the handshake avoids that, and leaves
+    /// operations ungated.
+    pub fn capability_handshake(mut self, enabled: bool) -> Self {
+        self.capability_handshake = enabled;
+        self
+    }
+
+    /// Create the client (not yet connected)
+    pub fn build(self) -> Client {
+        Client::from_builder(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::NoRetry;
+
+    #[test]
+    fn test_builder_defaults() {
+        let builder = ClientBuilder::new("wss://test");
+        assert_eq!(builder.server_url, "wss://test");
+        assert!(builder.client_id.is_none());
+        assert_eq!(builder.timeouts.read, Duration::from_secs(10));
+        assert_eq!(builder.heartbeat_interval, Duration::from_secs(30));
+        assert_eq!(builder.max_missed_heartbeats, 3);
+        assert_eq!(builder.max_reconnect_attempts, 5);
+        assert_eq!(builder.wire_format, WireFormat::MessagePack);
+        assert!(builder.capability_handshake);
+        assert!(builder.tls.is_none());
+        assert_eq!(builder.peer_check, PeerCheck::SameUserOrRoot);
+        assert!(builder.transport.is_none());
+        assert!(builder.recorder.is_none());
+        assert!(builder.fault_injection.is_none());
+        assert_eq!(builder.file_access, FileAccess::Auto);
+        assert_eq!(builder.sync_batch_window, DEFAULT_SYNC_BATCH_WINDOW);
+    }
+
+    #[test]
+    fn test_builder_setters() {
+        let builder = ClientBuilder::new("wss://test")
+            .timeouts(Timeouts::uniform(Duration::from_secs(3)))
+            .read_timeout(Duration::from_secs(1))
+            .retry_policy(NoRetry)
+            .heartbeat_interval(Duration::from_secs(5))
+            .max_missed_heartbeats(0)
+            .max_reconnect_attempts(2)
+            .peer_check(PeerCheck::Uids(vec![0]))
+            .sync_batch_window(Duration::ZERO);
+
+        assert_eq!(builder.timeouts.read, Duration::from_secs(1));
+        assert_eq!(builder.timeouts.write, Duration::from_secs(3));
+        assert_eq!(builder.retry_policy.next_delay(0), None);
+        assert_eq!(builder.heartbeat_interval, Duration::from_secs(5));
+        assert_eq!(builder.max_missed_heartbeats, 1, "at least one miss");
+        assert_eq!(builder.max_reconnect_attempts, 2);
+        assert_eq!(builder.peer_check, PeerCheck::Uids(vec![0]));
+        assert_eq!(builder.sync_batch_window, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_build_uses_client_id() {
+        let client = ClientBuilder::new("wss://test").client_id("fixed").build();
+        assert_eq!(client.id(), "fixed");
+        assert_eq!(client.server_url(), "wss://test");
+    }
+
+    #[test]
+    fn test_timeouts_follow_operation_kind() {
+        let timeouts = Timeouts {
+            connect: Duration::from_secs(1),
+            authenticate: Duration::from_secs(2),
+            read: Duration::from_secs(3),
+            write: Duration::from_secs(4),
+            admin: Duration::from_secs(5),
+            heartbeat: Duration::from_secs(6),
+        };
+        let read = ClientMessage::ReadVariable {
+            service_id: "svc".to_string(),
+            variable_name: "v".to_string(),
+        };
+        let write = ClientMessage::WriteVariable {
+            service_id: "svc".to_string(),
+            variable_name: "v".to_string(),
+            data: vec![],
+        };
+        let admin = ClientMessage::DeleteTenant {
+            tenant_id: "t".to_string(),
+        };
+
+        assert_eq!(timeouts.for_message(&read), Duration::from_secs(3));
+        assert_eq!(timeouts.for_message(&write), Duration::from_secs(4));
+        assert_eq!(timeouts.for_message(&admin), Duration::from_secs(5));
+    }
+}
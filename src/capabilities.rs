@@ -0,0 +1,159 @@
+//! Protocol capabilities exchanged when connecting
+//!
+//! Right after the socket opens, the client sends `ClientMessage::Hello` with
+//! its own capabilities and the server answers `ServerMessage::Welcome` with
+//! what it supports. Servers that predate the handshake reject or ignore the
+//! hello and are treated as supporting only the original protocol.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Protocol version spoken by this SDK
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Optional protocol features a server may or may not support
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// MessagePack framing (see `wire::WireFormat`)
+    MessagePack,
+
+    /// Compressed frames
+    Compression,
+
+    /// Request IDs echoed on responses
+    RequestIds,
+
+    /// `ClientMessage::CompareAndSwap`
+    CompareAndSwap,
+
+    /// Several variables in one request, such as `ReportVariableChanges`
+    BatchOperations,
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Feature::MessagePack => "MessagePack framing",
+            Feature::Compression => "compression",
+            Feature::RequestIds => "request IDs",
+            Feature::CompareAndSwap => "compare-and-swap",
+            Feature::BatchOperations => "batch operations",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What one side of the connection supports
+///
+/// Every field defaults when missing, so either side may add fields without
+/// breaking the other.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Protocol version; 0 for servers that predate the handshake
+    #[serde(default)]
+    pub protocol_version: u32,
+
+    /// Wire codecs by name (`"json"`, `"msgpack"`)
+    #[serde(default)]
+    pub codecs: Vec<String>,
+
+    /// Compression algorithms by name
+    #[serde(default)]
+    pub compression: Vec<String>,
+
+    /// Request IDs are echoed on responses
+    #[serde(default)]
+    pub request_ids: bool,
+
+    /// Compare-and-swap writes are accepted
+    #[serde(default)]
+    pub compare_and_swap: bool,
+
+    /// Batched variable operations are accepted
+    #[serde(default)]
+    pub batch_operations: bool,
+}
+
+impl Capabilities {
+    /// What this SDK supports, sent in the hello
+    pub fn client() -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            codecs: vec!["json".to_string(), "msgpack".to_string()],
+            compression: Vec::new(),
+            request_ids: true,
+            compare_and_swap: true,
+            batch_operations: true,
+        }
+    }
+
+    /// Assumed for servers that do not answer the hello: JSON only, no
+    /// optional features
+    pub fn legacy() -> Self {
+        Self {
+            protocol_version: 0,
+            codecs: vec!["json".to_string()],
+            compression: Vec::new(),
+            request_ids: false,
+            compare_and_swap: false,
+            batch_operations: false,
+        }
+    }
+
+    /// Check whether a feature is supported
+    pub fn supports(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::MessagePack => self.codecs.iter().any(|codec| codec == "msgpack"),
+            Feature::Compression => !self.compression.is_empty(),
+            Feature::RequestIds => self.request_ids,
+            Feature::CompareAndSwap => self.compare_and_swap,
+            Feature::BatchOperations => self.batch_operations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_supports_everything_but_compression() {
+        let client = Capabilities::client();
+        assert_eq!(client.protocol_version, PROTOCOL_VERSION);
+        assert!(client.supports(Feature::MessagePack));
+        assert!(client.supports(Feature::RequestIds));
+        assert!(client.supports(Feature::CompareAndSwap));
+        assert!(client.supports(Feature::BatchOperations));
+        assert!(!client.supports(Feature::Compression));
+    }
+
+    #[test]
+    fn test_legacy_supports_no_optional_feature() {
+        let legacy = Capabilities::legacy();
+        for feature in [
+            Feature::MessagePack,
+            Feature::Compression,
+            Feature::RequestIds,
+            Feature::CompareAndSwap,
+            Feature::BatchOperations,
+        ] {
+            assert!(!legacy.supports(feature), "{}", feature);
+        }
+    }
+
+    #[test]
+    fn test_missing_fields_default() {
+        let capabilities: Capabilities =
+            serde_json::from_str(r#"{"protocol_version":2,"compare_and_swap":true}"#).unwrap();
+        assert_eq!(capabilities.protocol_version, 2);
+        assert!(capabilities.supports(Feature::CompareAndSwap));
+        assert!(capabilities.codecs.is_empty());
+        assert!(!capabilities.supports(Feature::BatchOperations));
+    }
+
+    #[test]
+    fn test_feature_names() {
+        assert_eq!(Feature::CompareAndSwap.to_string(), "compare-and-swap");
+        assert_eq!(Feature::MessagePack.to_string(), "MessagePack framing");
+    }
+}
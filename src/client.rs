@@ -1,1245 +1,4002 @@
-//! Main Commy client for connecting to servers
-
-use crate::auth::{AuthContext, AuthCredentials};
-use crate::connection::{Connection, ConnectionState};
-use crate::error::{CommyError, Result};
-use crate::message::{ClientMessage, ServerMessage};
-use crate::service::Service;
-use crate::state::{create_shared_state, SharedState};
-use crate::virtual_file::VirtualVariableFile;
-use crate::watcher::VariableFileWatcher;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::RwLock;
-use uuid::Uuid;
-
-/// Main Commy client for interacting with a Commy server
-pub struct Client {
-    /// Unique client identifier
-    client_id: String,
-
-    /// Server URL (WebSocket)
-    server_url: String,
-
-    /// Active WebSocket connection
-    connection: Arc<RwLock<Option<Connection>>>,
-
-    /// Shared client state
-    state: SharedState,
-
-    /// Heartbeat interval
-    heartbeat_interval: Duration,
-
-    /// Maximum reconnection attempts
-    max_reconnect_attempts: u32,
-
-    /// Current reconnection attempt
-    reconnect_attempts: Arc<AtomicU64>,
-
-    /// Virtual variable files by service ID
-    virtual_files: Arc<RwLock<std::collections::HashMap<String, Arc<VirtualVariableFile>>>>,
-
-    /// File watcher for change detection
-    file_watcher: Arc<RwLock<Option<Arc<VariableFileWatcher>>>>,
-
-    /// Background heartbeat task handle
-    heartbeat_task: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
-}
-
-impl Client {
-    /// Initialize a fully-configured client in one call
-    ///
-    /// This is the primary entry point. It:
-    /// 1. Creates a new client
-    /// 2. Connects to the server
-    /// 3. Authenticates to the tenant
-    /// 4. Initializes the file watcher
-    /// 5. Starts file monitoring
-    ///
-    /// After this, the client is ready to use with `get_virtual_service_file()`.
-    pub async fn initialize(
-        server_url: impl Into<String>,
-        tenant_id: impl Into<String>,
-        credentials: AuthCredentials,
-    ) -> Result<Self> {
-        let client = Self::_new(server_url);
-        client._connect_impl().await?;
-        client
-            ._authenticate_impl(&tenant_id.into(), credentials)
-            .await?;
-        client._init_file_watcher_impl().await?;
-        client._start_file_monitoring_impl().await?;
-        Ok(client)
-    }
-
-    /// Create a new client (internal)
-    #[inline]
-    fn _new(server_url: impl Into<String>) -> Self {
-        let client_id = Uuid::new_v4().to_string();
-
-        Self {
-            client_id: client_id.clone(),
-            server_url: server_url.into(),
-            connection: Arc::new(RwLock::new(None)),
-            state: create_shared_state(client_id),
-            heartbeat_interval: Duration::from_secs(30),
-            max_reconnect_attempts: 5,
-            reconnect_attempts: Arc::new(AtomicU64::new(0)),
-            virtual_files: Arc::new(RwLock::new(std::collections::HashMap::new())),
-            file_watcher: Arc::new(RwLock::new(None)),
-            heartbeat_task: Arc::new(RwLock::new(None)),
-        }
-    }
-
-    /// Create a new client (public - for testing/special cases)
-    pub fn new(server_url: impl Into<String>) -> Self {
-        Self::_new(server_url)
-    }
-
-    /// Create a new client with custom ID
-    pub fn with_id(server_url: impl Into<String>, client_id: impl Into<String>) -> Self {
-        let client_id = client_id.into();
-
-        Self {
-            client_id: client_id.clone(),
-            server_url: server_url.into(),
-            connection: Arc::new(RwLock::new(None)),
-            state: create_shared_state(client_id),
-            heartbeat_interval: Duration::from_secs(30),
-            max_reconnect_attempts: 5,
-            reconnect_attempts: Arc::new(AtomicU64::new(0)),
-            virtual_files: Arc::new(RwLock::new(std::collections::HashMap::new())),
-            file_watcher: Arc::new(RwLock::new(None)),
-            heartbeat_task: Arc::new(RwLock::new(None)),
-        }
-    }
-
-    /// Get client ID
-    pub fn id(&self) -> &str {
-        &self.client_id
-    }
-
-    /// Get server URL
-    pub fn server_url(&self) -> &str {
-        &self.server_url
-    }
-
-    /// Connect to server (internal)
-    #[inline]
-    async fn _connect_impl(&self) -> Result<()> {
-        let mut state = self.state.write().await;
-        state.connection_state = ConnectionState::Connecting;
-        drop(state);
-
-        match Connection::new(&self.server_url).await {
-            Ok(conn) => {
-                let mut state = self.state.write().await;
-                state.connection_state = ConnectionState::Connected;
-                state.session_id = Some(Uuid::new_v4().to_string());
-                drop(state);
-
-                let mut conn_guard = self.connection.write().await;
-                *conn_guard = Some(conn);
-
-                // Reset reconnection attempts on successful connection
-                self.reconnect_attempts.store(0, Ordering::SeqCst);
-
-                // TODO: Re-enable background heartbeat task
-                // For now, disabled to avoid message ordering issues with concurrent operations
-                // self.start_heartbeat_task().await;
-
-                // WebSocket connection established - no Connect message needed
-                // Authentication will be the first message sent
-
-                Ok(())
-            }
-            Err(e) => {
-                let mut state = self.state.write().await;
-                state.connection_state = ConnectionState::Disconnected;
-                Err(e)
-            }
-        }
-    }
-
-    /// Connect to server (public - for testing/special cases)
-    pub async fn connect(&self) -> Result<()> {
-        self._connect_impl().await
-    }
-
-    /// Authenticate to a tenant (internal)
-    #[inline]
-    async fn _authenticate_impl(
-        &self,
-        tenant_id: &str,
-        credentials: AuthCredentials,
-    ) -> Result<AuthContext> {
-        self.send_message(ClientMessage::Authenticate {
-            tenant_id: tenant_id.to_string(),
-            client_version: env!("CARGO_PKG_VERSION").to_string(),
-            credentials: credentials.clone(),
-        })
-        .await?;
-
-        // Wait for authentication result
-        if let Some(conn) = &*self.connection.read().await {
-            match tokio::time::timeout(Duration::from_secs(10), conn.recv()).await {
-                Ok(Ok(Some(ServerMessage::AuthenticationResult {
-                    success: true,
-                    permissions,
-                    ..
-                }))) => {
-                    let auth_context =
-                        AuthContext::new(tenant_id.to_string(), permissions.unwrap_or_default());
-
-                    let mut state = self.state.write().await;
-                    state.connection_state = ConnectionState::Authenticated;
-                    state.add_auth_context(tenant_id.to_string(), auth_context.clone());
-
-                    Ok(auth_context)
-                }
-                Ok(Ok(Some(ServerMessage::AuthenticationResult { success: false, message, .. }))) => {
-                    Err(CommyError::AuthenticationFailed(message))
-                }
-                Ok(Ok(Some(ServerMessage::Error { code, .. }))) => {
-                    Err(CommyError::from(code))
-                }
-                Err(_) => Err(CommyError::Timeout),
-                _ => Err(CommyError::Timeout),
-            }
-        } else {
-            Err(CommyError::ConnectionLost(
-                "Connection not established".to_string(),
-            ))
-        }
-    }
-
-    /// Authenticate to a tenant (public - for testing/special cases)
-    pub async fn authenticate(
-        &self,
-        tenant_id: impl Into<String>,
-        credentials: AuthCredentials,
-    ) -> Result<AuthContext> {
-        self._authenticate_impl(&tenant_id.into(), credentials)
-            .await
-    }
-
-    /// Create a new service in a tenant
-    ///
-    /// Returns the service ID on success. Returns error if:
-    /// - Not authenticated to the tenant
-    /// - Service already exists
-    /// - Insufficient permissions (need create_service permission)
-    pub async fn create_service(&self, tenant_id: &str, service_name: &str) -> Result<String> {
-        // Check if authenticated to this tenant
-        let state = self.state.read().await;
-        if !state.is_authenticated_to(tenant_id) {
-            return Err(CommyError::PermissionDenied(format!(
-                "Not authenticated to tenant: {}",
-                tenant_id
-            )));
-        }
-        drop(state);
-
-        // Request service creation
-        self.send_message(ClientMessage::CreateService {
-            tenant_id: tenant_id.to_string(),
-            service_name: service_name.to_string(),
-        })
-        .await?;
-
-        // Wait for service response
-        if let Some(conn) = &*self.connection.read().await {
-            match tokio::time::timeout(Duration::from_secs(10), conn.recv()).await {
-                Ok(Ok(Some(ServerMessage::Service { service_id, .. }))) => Ok(service_id),
-                Ok(Ok(Some(ServerMessage::Error { code, .. }))) => Err(CommyError::from(code)),
-                Err(_) => Err(CommyError::Timeout),
-                _ => Err(CommyError::Timeout),
-            }
-        } else {
-            Err(CommyError::ConnectionLost(
-                "Connection lost during create_service".to_string(),
-            ))
-        }
-    }
-
-    /// Get an existing service from a tenant (read-only, no side effects)
-    ///
-    /// Returns error if:
-    /// - Not authenticated to the tenant
-    /// - Service does not exist (NotFound error)
-    /// - Insufficient permissions (need read_service permission)
-    pub async fn get_service(&self, tenant_id: &str, service_name: &str) -> Result<Service> {
-        // Check if authenticated to this tenant
-        let state = self.state.read().await;
-        if !state.is_authenticated_to(tenant_id) {
-            return Err(CommyError::PermissionDenied(format!(
-                "Not authenticated to tenant: {}",
-                tenant_id
-            )));
-        }
-        drop(state);
-
-        // Request service
-        self.send_message(ClientMessage::GetService {
-            tenant_id: tenant_id.to_string(),
-            service_name: service_name.to_string(),
-        })
-        .await?;
-
-        // Wait for service response
-        if let Some(conn) = &*self.connection.read().await {
-            match tokio::time::timeout(Duration::from_secs(10), conn.recv()).await {
-                Ok(Ok(Some(ServerMessage::Service {
-                    service_id,
-                    service_name,
-                    tenant_id: resp_tenant,
-                    file_path,
-                }))) => {
-                    let service = Service::new(service_id, service_name, resp_tenant, file_path);
-                    Ok(service)
-                }
-                Ok(Ok(Some(ServerMessage::Error { code, .. }))) => Err(CommyError::from(code)),
-                Err(_) => Err(CommyError::Timeout),
-                _ => Err(CommyError::Timeout),
-            }
-        } else {
-            Err(CommyError::ConnectionLost(
-                "Connection lost during get_service".to_string(),
-            ))
-        }
-    }
-
-    /// Delete a service from a tenant
-    ///
-    /// Returns error if:
-    /// - Not authenticated to the tenant
-    /// - Service does not exist
-    /// - Insufficient permissions (need delete_service permission, typically admin)
-    pub async fn delete_service(&self, tenant_id: &str, service_name: &str) -> Result<()> {
-        // Check if authenticated to this tenant
-        let state = self.state.read().await;
-        if !state.is_authenticated_to(tenant_id) {
-            return Err(CommyError::PermissionDenied(format!(
-                "Not authenticated to tenant: {}",
-                tenant_id
-            )));
-        }
-        drop(state);
-
-        // Request service deletion
-        self.send_message(ClientMessage::DeleteService {
-            tenant_id: tenant_id.to_string(),
-            service_name: service_name.to_string(),
-        })
-        .await?;
-
-        // Wait for result acknowledgment
-        if let Some(conn) = &*self.connection.read().await {
-            match tokio::time::timeout(Duration::from_secs(10), conn.recv()).await {
-                Ok(Ok(Some(ServerMessage::Result { success: true, .. }))) => Ok(()),
-                Ok(Ok(Some(ServerMessage::Result { success: false, message, .. }))) => {
-                    Err(CommyError::PermissionDenied(message))
-                }
-                Ok(Ok(Some(ServerMessage::Error { code, .. }))) => Err(CommyError::from(code)),
-                Err(_) => Err(CommyError::Timeout),
-                _ => Err(CommyError::Timeout),
-            }
-        } else {
-            Err(CommyError::ConnectionLost(
-                "Connection lost during delete_service".to_string(),
-            ))
-        }
-    }
-
-    /// Create a new tenant (admin operation)
-    ///
-    /// Requires admin credentials or special permissions to create tenants.
-    /// Returns the tenant ID on success.
-    ///
-    /// Returns error if:
-    /// - Not connected to server
-    /// - Tenant already exists
-    /// - Insufficient permissions (need admin role)
-    pub async fn create_tenant(&self, tenant_id: &str, tenant_name: &str) -> Result<String> {
-        // Request tenant creation
-        self.send_message(ClientMessage::CreateTenant {
-            tenant_id: tenant_id.to_string(),
-            tenant_name: tenant_name.to_string(),
-        })
-        .await?;
-
-        // Wait for result
-        if let Some(conn) = &*self.connection.read().await {
-            match tokio::time::timeout(Duration::from_secs(10), conn.recv()).await {
-                Ok(Ok(Some(ServerMessage::TenantResult {
-                    success: true,
-                    tenant_id: returned_id,
-                    ..
-                }))) => Ok(returned_id),
-                Ok(Ok(Some(ServerMessage::TenantResult { success: false, message, .. }))) => {
-                    Err(CommyError::PermissionDenied(message))
-                }
-                Ok(Ok(Some(ServerMessage::Error { code, .. }))) => Err(CommyError::from(code)),
-                Err(_) => Err(CommyError::Timeout),
-                _ => Err(CommyError::Timeout),
-            }
-        } else {
-            Err(CommyError::ConnectionLost(
-                "Connection lost during create_tenant".to_string(),
-            ))
-        }
-    }
-
-    /// Delete a tenant (admin operation)
-    ///
-    /// Removes all services and data associated with the tenant.
-    /// Requires admin credentials or special permissions.
-    ///
-    /// Returns error if:
-    /// - Not connected to server
-    /// - Tenant does not exist
-    /// - Insufficient permissions (need admin role)
-    /// - Tenant has active clients
-    pub async fn delete_tenant(&self, tenant_id: &str) -> Result<()> {
-        // Request tenant deletion
-        self.send_message(ClientMessage::DeleteTenant {
-            tenant_id: tenant_id.to_string(),
-        })
-        .await?;
-
-        // Wait for result acknowledgment
-        if let Some(conn) = &*self.connection.read().await {
-            match tokio::time::timeout(Duration::from_secs(10), conn.recv()).await {
-                Ok(Ok(Some(ServerMessage::Result { success: true, .. }))) => Ok(()),
-                Ok(Ok(Some(ServerMessage::Result { success: false, message, .. }))) => {
-                    Err(CommyError::PermissionDenied(message))
-                }
-                Ok(Ok(Some(ServerMessage::Error { code, .. }))) => Err(CommyError::from(code)),
-                Err(_) => Err(CommyError::Timeout),
-                _ => Err(CommyError::Timeout),
-            }
-        } else {
-            Err(CommyError::ConnectionLost(
-                "Connection lost during delete_tenant".to_string(),
-            ))
-        }
-    }
-
-    /// Read a variable value
-    pub async fn read_variable(&self, service_id: &str, variable_name: &str) -> Result<Vec<u8>> {
-        self.send_message(ClientMessage::ReadVariable {
-            service_id: service_id.to_string(),
-            variable_name: variable_name.to_string(),
-        })
-        .await?;
-
-        // Wait for variable data
-        if let Some(conn) = &*self.connection.read().await {
-            match tokio::time::timeout(Duration::from_secs(10), conn.recv()).await {
-                Ok(Ok(Some(ServerMessage::VariableData { data, .. }))) => Ok(data),
-                Ok(Ok(Some(ServerMessage::Error { code, .. }))) => Err(CommyError::from(code)),
-                Err(_) => Err(CommyError::Timeout),
-                _ => Err(CommyError::Timeout),
-            }
-        } else {
-            Err(CommyError::ConnectionLost(
-                "Connection lost during read_variable".to_string(),
-            ))
-        }
-    }
-
-    /// Write a variable value
-    pub async fn write_variable(
-        &self,
-        service_id: &str,
-        variable_name: &str,
-        data: Vec<u8>,
-    ) -> Result<()> {
-        self.send_message(ClientMessage::WriteVariable {
-            service_id: service_id.to_string(),
-            variable_name: variable_name.to_string(),
-            data,
-        })
-        .await?;
-
-        Ok(())
-    }
-
-    /// Subscribe to variable changes
-    pub async fn subscribe(&self, service_id: &str, variable_name: &str) -> Result<()> {
-        self.send_message(ClientMessage::Subscribe {
-            service_id: service_id.to_string(),
-            variable_name: variable_name.to_string(),
-        })
-        .await?;
-
-        Ok(())
-    }
-
-    /// Unsubscribe from variable changes
-    pub async fn unsubscribe(&self, service_id: &str, variable_name: &str) -> Result<()> {
-        self.send_message(ClientMessage::Unsubscribe {
-            service_id: service_id.to_string(),
-            variable_name: variable_name.to_string(),
-        })
-        .await?;
-
-        Ok(())
-    }
-
-    /// Send heartbeat to server
-    pub async fn heartbeat(&self) -> Result<()> {
-        self.send_message(ClientMessage::Heartbeat {
-            client_id: self.client_id.clone(),
-        })
-        .await?;
-
-        // Wait for heartbeat response from server
-        if let Some(conn) = &*self.connection.read().await {
-            match tokio::time::timeout(Duration::from_secs(10), conn.recv()).await {
-                Ok(Ok(Some(ServerMessage::Heartbeat { .. }))) => {
-                    // Heartbeat response received successfully
-                }
-                _ => {
-                    // Heartbeat response not received or wrong type, but don't fail
-                }
-            }
-        }
-
-        let mut state = self.state.write().await;
-        state.touch();
-
-        Ok(())
-    }
-
-    /// Disconnect from server
-    pub async fn disconnect(&self) -> Result<()> {
-        self.send_message(ClientMessage::Disconnect {
-            client_id: self.client_id.clone(),
-        })
-        .await?;
-
-        let mut conn_guard = self.connection.write().await;
-        *conn_guard = None;
-
-        let mut state = self.state.write().await;
-        state.reset();
-
-        Ok(())
-    }
-
-    /// Check if connected
-    pub async fn is_connected(&self) -> bool {
-        self.connection.read().await.is_some()
-    }
-
-    /// Get current connection state
-    pub async fn connection_state(&self) -> ConnectionState {
-        let state = self.state.read().await;
-        state.connection_state
-    }
-
-    /// Get authenticated tenants
-    pub async fn authenticated_tenants(&self) -> Vec<String> {
-        let state = self.state.read().await;
-        state
-            .authenticated_tenants()
-            .into_iter()
-            .map(|s| s.to_string())
-            .collect()
-    }
-
-    /// Check if authenticated to tenant
-    pub async fn is_authenticated_to(&self, tenant_id: &str) -> bool {
-        let state = self.state.read().await;
-        state.is_authenticated_to(tenant_id)
-    }
-
-    /// Get idle time in seconds
-    pub async fn idle_seconds(&self) -> u64 {
-        let state = self.state.read().await;
-        state.idle_seconds()
-    }
-
-    /// Send a message to server with automatic reconnection
-    async fn send_message(&self, msg: ClientMessage) -> Result<()> {
-        // Try sending the message
-        let result = self.send_message_once(msg.clone()).await;
-
-        // If connection was lost, attempt reconnection
-        if let Err(CommyError::ConnectionLost(_)) = result {
-            let current_attempts = self.reconnect_attempts.fetch_add(1, Ordering::SeqCst);
-
-            if current_attempts < self.max_reconnect_attempts as u64 {
-                // Exponential backoff: 1s, 2s, 4s, 8s, 16s
-                let delay = Duration::from_secs(2_u64.pow(current_attempts as u32).min(16));
-                tokio::time::sleep(delay).await;
-
-                // Attempt to reconnect
-                if let Ok(()) = self._connect_impl().await {
-                    // Retry the message after reconnection
-                    return self.send_message_once(msg).await;
-                }
-            }
-
-            return Err(CommyError::ConnectionLost(format!(
-                "Connection lost after {} reconnection attempts",
-                current_attempts + 1
-            )));
-        }
-
-        result
-    }
-
-    /// Send a message without reconnection logic (internal)
-    async fn send_message_once(&self, msg: ClientMessage) -> Result<()> {
-        let conn_guard = self.connection.read().await;
-        if let Some(conn) = conn_guard.as_ref() {
-            conn.send(msg).await?;
-
-            let mut state = self.state.write().await;
-            state.touch();
-
-            Ok(())
-        } else {
-            Err(CommyError::ConnectionLost(
-                "Connection not established".to_string(),
-            ))
-        }
-    }
-
-    /// Start background heartbeat task
-    async fn start_heartbeat_task(&self) {
-        // Stop existing heartbeat task if any
-        let mut task_guard = self.heartbeat_task.write().await;
-        if let Some(handle) = task_guard.take() {
-            handle.abort();
-        }
-
-        let interval = self.heartbeat_interval;
-        let client_id = self.client_id.clone();
-        let connection = Arc::clone(&self.connection);
-        let state = Arc::clone(&self.state);
-
-        let handle = tokio::spawn(async move {
-            let mut interval_timer = tokio::time::interval(interval);
-            interval_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-
-            loop {
-                interval_timer.tick().await;
-
-                // Check if still connected
-                let conn_guard = connection.read().await;
-                if let Some(conn) = conn_guard.as_ref() {
-                    // Send heartbeat
-                    let heartbeat_msg = ClientMessage::Heartbeat {
-                        client_id: client_id.clone(),
-                    };
-
-                    if conn.send(heartbeat_msg).await.is_ok() {
-                        // Update last activity timestamp
-                        let mut state_guard = state.write().await;
-                        state_guard.touch();
-                    } else {
-                        // Heartbeat failed, connection likely lost
-                        break;
-                    }
-                } else {
-                    // No connection, stop heartbeat task
-                    break;
-                }
-            }
-        });
-
-        *task_guard = Some(handle);
-    }
-
-    /// Initialize file watcher for hybrid mode (internal)
-    #[inline]
-    async fn _init_file_watcher_impl(&self) -> Result<()> {
-        let watcher = VariableFileWatcher::new(None).await?;
-        let watcher = Arc::new(watcher);
-        watcher.start_watching().await?;
-        *self.file_watcher.write().await = Some(watcher);
-        Ok(())
-    }
-
-    /// Initialize file watcher for hybrid mode (public - for testing/special cases)
-    pub async fn init_file_watcher(&self) -> Result<()> {
-        self._init_file_watcher_impl().await
-    }
-
-    /// Get or create a virtual variable file for a service
-    ///
-    /// This creates a virtual representation that works seamlessly for both:
-    /// - Local clients: Memory-mapped to actual service file
-    /// - Remote clients: In-memory buffer synced via WSS
-    pub async fn get_virtual_service_file(
-        &self,
-        tenant_id: &str,
-        service_name: &str,
-    ) -> Result<Arc<VirtualVariableFile>> {
-        let service_id = format!("{}_{}", tenant_id, service_name);
-
-        // Check if already loaded
-        {
-            let vfiles = self.virtual_files.read().await;
-            if let Some(vf) = vfiles.get(&service_id) {
-                return Ok(Arc::clone(vf));
-            }
-        }
-
-        // Create new virtual file
-        let vf = Arc::new(VirtualVariableFile::new(
-            service_id.clone(),
-            service_name.to_string(),
-            tenant_id.to_string(),
-        ));
-
-        // Register with watcher if available
-        if let Some(watcher_guard) = self.file_watcher.read().await.as_ref() {
-            watcher_guard
-                .register_virtual_file(service_id.clone(), Arc::clone(&vf))
-                .await?;
-        }
-
-        // Store in cache
-        let mut vfiles = self.virtual_files.write().await;
-        vfiles.insert(service_id, Arc::clone(&vf));
-
-        Ok(vf)
-    }
-
-    /// Start monitoring virtual files for changes (internal)
-    ///
-    /// This spawns a background task that watches for file changes
-    /// and automatically detects which variables have changed using SIMD
-    #[inline]
-    async fn _start_file_monitoring_impl(&self) -> Result<()> {
-        let watcher = self.file_watcher.read().await;
-        if watcher.is_some() {
-            // Already started in init_file_watcher
-            Ok(())
-        } else {
-            drop(watcher);
-            self._init_file_watcher_impl().await
-        }
-    }
-
-    /// Start monitoring virtual files for changes (public - for testing/special cases)
-    ///
-    /// This spawns a background task that watches for file changes
-    /// and automatically detects which variables have changed using SIMD
-    pub async fn start_file_monitoring(&self) -> Result<()> {
-        self._start_file_monitoring_impl().await
-    }
-
-    /// Get next file change event (blocks until a file changes)
-    pub async fn wait_for_file_change(&self) -> Result<Option<crate::watcher::FileChangeEvent>> {
-        let watcher = self.file_watcher.read().await;
-        if let Some(w) = watcher.as_ref() {
-            Ok(w.next_change().await)
-        } else {
-            Err(CommyError::InvalidState(
-                "File watcher not initialized. Call start_file_monitoring() first".to_string(),
-            ))
-        }
-    }
-
-    /// Try to get next file change event (non-blocking)
-    pub async fn try_get_file_change(&self) -> Result<Option<crate::watcher::FileChangeEvent>> {
-        let watcher = self.file_watcher.read().await;
-        if let Some(w) = watcher.as_ref() {
-            Ok(w.try_next_change().await)
-        } else {
-            Err(CommyError::InvalidState(
-                "File watcher not initialized. Call start_file_monitoring() first".to_string(),
-            ))
-        }
-    }
-
-    /// Stop file monitoring
-    pub async fn stop_file_monitoring(&self) -> Result<()> {
-        if let Some(watcher) = self.file_watcher.write().await.take() {
-            watcher.stop_watching().await?;
-        }
-        Ok(())
-    }
-
-    /// Inject a pre-built `Connection` into the client (for unit testing without a real server).
-    ///
-    /// Sets the connection field **and** advances `connection_state` to `Connected` so
-    /// subsequent permission guards behave correctly.
-    #[cfg(test)]
-    pub async fn inject_connection_for_test(&self, conn: Connection) {
-        let mut c = self.connection.write().await;
-        *c = Some(conn);
-        let mut state = self.state.write().await;
-        state.connection_state = ConnectionState::Connected;
-    }
-
-    /// Inject an authenticated tenant context (no real server auth required).
-    #[cfg(test)]
-    pub async fn inject_auth_for_test(&self, tenant_id: &str) {
-        let mut state = self.state.write().await;
-        state.add_auth_context(
-            tenant_id.to_string(),
-            AuthContext::new(
-                tenant_id.to_string(),
-                vec!["read".to_string(), "write".to_string()],
-            ),
-        );
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_client_creation() {
-        let client = Client::new("wss://localhost:9000");
-        assert!(!client.id().is_empty());
-        assert_eq!(client.server_url(), "wss://localhost:9000");
-    }
-
-    #[test]
-    fn test_client_with_custom_id() {
-        let client = Client::with_id("wss://localhost:9000", "my_client");
-        assert_eq!(client.id(), "my_client");
-    }
-
-    #[tokio::test]
-    async fn test_is_connected_initially_false() {
-        let client = Client::new("wss://localhost:9000");
-        assert!(!client.is_connected().await);
-    }
-
-    #[tokio::test]
-    async fn test_idle_seconds() {
-        let client = Client::new("wss://localhost:9000");
-        let idle = client.idle_seconds().await;
-        assert!(idle < 2);
-    }
-
-    #[tokio::test]
-    async fn test_connection_state_initially_disconnected() {
-        let client = Client::new("wss://localhost:9000");
-        assert_eq!(client.connection_state().await, ConnectionState::Disconnected);
-    }
-
-    #[tokio::test]
-    async fn test_authenticated_tenants_initially_empty() {
-        let client = Client::new("wss://localhost:9000");
-        let tenants = client.authenticated_tenants().await;
-        assert!(tenants.is_empty());
-    }
-
-    #[tokio::test]
-    async fn test_is_authenticated_to_false() {
-        let client = Client::new("wss://localhost:9000");
-        assert!(!client.is_authenticated_to("any_tenant").await);
-    }
-
-    #[test]
-    fn test_client_server_url() {
-        let client = Client::new("wss://example.com:9000");
-        assert_eq!(client.server_url(), "wss://example.com:9000");
-    }
-
-    // ─── File watcher methods ─────────────────────────────────────────────────
-
-    #[tokio::test]
-    async fn test_init_file_watcher_succeeds() {
-        let client = Client::new("wss://localhost:9000");
-        let result = client.init_file_watcher().await;
-        assert!(result.is_ok(), "init_file_watcher should succeed: {:?}", result);
-    }
-
-    #[tokio::test]
-    async fn test_start_file_monitoring_succeeds_and_is_idempotent() {
-        let client = Client::new("wss://localhost:9000");
-        // First call initialises the watcher
-        let r1 = client.start_file_monitoring().await;
-        assert!(r1.is_ok(), "first start_file_monitoring should succeed: {:?}", r1);
-        // Second call is a no-op (watcher already exists)
-        let r2 = client.start_file_monitoring().await;
-        assert!(r2.is_ok(), "second start_file_monitoring should also succeed: {:?}", r2);
-        let _ = client.stop_file_monitoring().await;
-    }
-
-    #[tokio::test]
-    async fn test_wait_for_file_change_uninitialized_returns_err() {
-        let client = Client::new("wss://localhost:9000");
-        // Watcher not initialised — should return Err(InvalidState)
-        let result = client.wait_for_file_change().await;
-        assert!(
-            matches!(result, Err(CommyError::InvalidState(_))),
-            "expected InvalidState error, got {:?}",
-            result
-        );
-    }
-
-    #[tokio::test]
-    async fn test_try_get_file_change_uninitialized_returns_err() {
-        let client = Client::new("wss://localhost:9000");
-        let result = client.try_get_file_change().await;
-        assert!(
-            matches!(result, Err(CommyError::InvalidState(_))),
-            "expected InvalidState error when watcher not initialised, got {:?}",
-            result
-        );
-    }
-
-    #[tokio::test]
-    async fn test_try_get_file_change_initialized_returns_ok_none() {
-        let client = Client::new("wss://localhost:9000");
-        client.init_file_watcher().await.unwrap();
-        // No file changes have occurred, so the channel should be empty
-        let result = client.try_get_file_change().await;
-        assert!(
-            matches!(result, Ok(None)),
-            "expected Ok(None) with no pending events, got {:?}",
-            result
-        );
-        let _ = client.stop_file_monitoring().await;
-    }
-
-    #[tokio::test]
-    async fn test_stop_file_monitoring_clears_watcher() {
-        let client = Client::new("wss://localhost:9000");
-        client.init_file_watcher().await.unwrap();
-
-        // Watcher is live — non-blocking peek should be Ok(None)
-        assert!(matches!(client.try_get_file_change().await, Ok(None)));
-
-        // Stop the watcher
-        let stop_result = client.stop_file_monitoring().await;
-        assert!(stop_result.is_ok(), "stop_file_monitoring should succeed: {:?}", stop_result);
-
-        // After stopping, the watcher slot is None ⇒ try_get returns InvalidState
-        let after = client.try_get_file_change().await;
-        assert!(
-            matches!(after, Err(CommyError::InvalidState(_))),
-            "expected InvalidState after stopping, got {:?}",
-            after
-        );
-    }
-
-    #[tokio::test]
-    async fn test_get_virtual_service_file_creates_and_caches() {
-        let client = Client::new("wss://localhost:9000");
-
-        // First call creates the virtual file
-        let vf1 = client
-            .get_virtual_service_file("tenant_a", "my_service")
-            .await
-            .expect("should create virtual service file");
-
-        // Second call with same args returns the cached instance (same Arc pointer)
-        let vf2 = client
-            .get_virtual_service_file("tenant_a", "my_service")
-            .await
-            .expect("should return cached virtual service file");
-
-        assert!(Arc::ptr_eq(&vf1, &vf2), "second call should return the cached VirtualVariableFile");
-
-        // Different service name → different file
-        let vf3 = client
-            .get_virtual_service_file("tenant_a", "other_service")
-            .await
-            .expect("should create a separate virtual service file");
-
-        assert!(!Arc::ptr_eq(&vf1, &vf3), "different service should be a separate file");
-    }
-
-    // ─────────────────────────────────────────────────────────────
-    // Auth pre-check guard tests
-    // ─────────────────────────────────────────────────────────────
-
-    #[tokio::test]
-    async fn test_create_service_not_authenticated_returns_permission_denied() {
-        let client = Client::new("wss://localhost:9999");
-        // No authentication → must fail immediately with PermissionDenied
-        let result = client.create_service("tenant_a", "my_service").await;
-        assert!(
-            result.is_err(),
-            "create_service must fail when not authenticated"
-        );
-        match result.unwrap_err() {
-            crate::error::CommyError::PermissionDenied(_) => {}
-            e => panic!("Expected PermissionDenied, got {:?}", e),
-        }
-    }
-
-    #[tokio::test]
-    async fn test_get_service_not_authenticated_returns_permission_denied() {
-        let client = Client::new("wss://localhost:9999");
-        let result = client.get_service("tenant_a", "my_service").await;
-        assert!(
-            result.is_err(),
-            "get_service must fail when not authenticated"
-        );
-        match result.unwrap_err() {
-            crate::error::CommyError::PermissionDenied(_) => {}
-            e => panic!("Expected PermissionDenied, got {:?}", e),
-        }
-    }
-
-    #[tokio::test]
-    async fn test_delete_service_not_authenticated_returns_permission_denied() {
-        let client = Client::new("wss://localhost:9999");
-        let result = client.delete_service("tenant_a", "my_service").await;
-        assert!(
-            result.is_err(),
-            "delete_service must fail when not authenticated"
-        );
-        match result.unwrap_err() {
-            crate::error::CommyError::PermissionDenied(_) => {}
-            e => panic!("Expected PermissionDenied, got {:?}", e),
-        }
-    }
-
-    // ─────────────────────────────────────────────────────────────────────────
-    // Bug #14 regression tests: server Error response must NOT be silently
-    // converted to Timeout.  These verify the match-based response handlers.
-    // ─────────────────────────────────────────────────────────────────────────
-
-    /// Helper: creates a Client with injected mock connection that immediately
-    /// returns `response` when the next recv() is called.
-    /// Returns `(client, _client_rx)` — the caller MUST keep `_client_rx` alive
-    /// for the duration of the test, otherwise the sender channel closes and
-    /// `send_message` will fail with `ChannelError`.
-    async fn setup_client_with_mock_response(
-        tenant_id: &str,
-        response: crate::message::ServerMessage,
-    ) -> (Client, tokio::sync::mpsc::UnboundedReceiver<crate::message::ClientMessage>) {
-        let client = Client::new("wss://test");
-        let (conn, server_tx, client_rx) = crate::connection::Connection::new_for_test();
-        // Pre-load the server-side response channel BEFORE injecting
-        server_tx.send(response).expect("pre-send failed");
-        client.inject_auth_for_test(tenant_id).await;
-        client.inject_connection_for_test(conn).await;
-        (client, client_rx)
-    }
-
-    /// #14: create_service receiving Error{NotFound} must return Err(NotFound),
-    /// NOT Err(Timeout) as was the bug.
-    #[tokio::test]
-    async fn test_create_service_server_error_returns_not_found_not_timeout() {
-        let err_response = crate::message::ServerMessage::Error {
-            code: crate::message::ErrorCode::NotFound,
-            message: "service creation failed: not found".to_string(),
-        };
-        let (client, _client_rx) = setup_client_with_mock_response("tenant_a", err_response).await;
-        let result = client.create_service("tenant_a", "missing_svc").await;
-        assert!(result.is_err(), "create_service must fail on Error response");
-        match result.unwrap_err() {
-            CommyError::NotFound(_) => {} // Correct: server error is properly translated
-            CommyError::Timeout => panic!(
-                "Bug #14 regressed: server Error{{NotFound}} was silently converted to Timeout"
-            ),
-            e => panic!("Unexpected error: {:?}", e),
-        }
-    }
-
-    /// #14: get_service receiving Error{PermissionDenied} must return Err(PermissionDenied).
-    #[tokio::test]
-    async fn test_get_service_server_error_returns_permission_denied_not_timeout() {
-        let err_response = crate::message::ServerMessage::Error {
-            code: crate::message::ErrorCode::PermissionDenied,
-            message: "insufficient permissions".to_string(),
-        };
-        let (client, _client_rx) = setup_client_with_mock_response("tenant_b", err_response).await;
-        let result = client.get_service("tenant_b", "svc").await;
-        assert!(result.is_err());
-        match result.unwrap_err() {
-            CommyError::PermissionDenied(_) => {}
-            CommyError::Timeout => panic!(
-                "Bug #14 regressed: server Error{{PermissionDenied}} was silently converted to Timeout"
-            ),
-            e => panic!("Unexpected error variant: {:?}", e),
-        }
-    }
-
-    /// #14: delete_service receiving Error{AlreadyExists} (a non-success error)
-    /// must return Err(AlreadyExists), NOT Err(Timeout).
-    #[tokio::test]
-    async fn test_delete_service_server_error_returns_proper_error_not_timeout() {
-        let err_response = crate::message::ServerMessage::Error {
-            code: crate::message::ErrorCode::AlreadyExists,
-            message: "unexpected".to_string(),
-        };
-        let (client, _client_rx) = setup_client_with_mock_response("tenant_c", err_response).await;
-        let result = client.delete_service("tenant_c", "svc").await;
-        assert!(result.is_err());
-        match result.unwrap_err() {
-            CommyError::AlreadyExists(_) => {}
-            CommyError::Timeout => panic!(
-                "Bug #14 regressed: server Error{{AlreadyExists}} was silently converted to Timeout"
-            ),
-            e => panic!("Unexpected error variant: {:?}", e),
-        }
-    }
-
-    // ─────────────────────────────────────────────────────────────────────────
-    // Authentication edge-case tests
-    // ─────────────────────────────────────────────────────────────────────────
-
-    /// #2 (SDK): authenticate where server responds with success=false must
-    /// return Err(AuthenticationFailed), not Err(Timeout).
-    #[tokio::test]
-    async fn test_authenticate_success_false_returns_auth_failed() {
-        let client = Client::new("wss://test");
-        let (conn, server_tx, _client_rx) = crate::connection::Connection::new_for_test();
-        server_tx
-            .send(crate::message::ServerMessage::AuthenticationResult {
-                success: false,
-                message: "invalid credentials".to_string(),
-                server_version: "0.1.0".to_string(),
-                permissions: None,
-            })
-            .expect("pre-send failed");
-        client.inject_connection_for_test(conn).await;
-
-        let creds = crate::message::AuthCredentials::ApiKey {
-            key: "bad_key".to_string(),
-        };
-        let result = client.authenticate("tenant_a", creds).await;
-
-        assert!(result.is_err(), "authenticate must fail when success=false");
-        match result.unwrap_err() {
-            CommyError::AuthenticationFailed(msg) => {
-                assert!(
-                    msg.contains("invalid credentials"),
-                    "Error message should propagate: {}",
-                    msg
-                );
-            }
-            CommyError::Timeout => panic!(
-                "authenticate success=false was silently converted to Timeout"
-            ),
-            e => panic!("Unexpected error: {:?}", e),
-        }
-    }
-
-    /// authenticate receiving Error{Unauthorized} returns Err(Unauthorized).
-    #[tokio::test]
-    async fn test_authenticate_error_response_returns_unauthorized() {
-        let client = Client::new("wss://test");
-        let (conn, server_tx, _client_rx) = crate::connection::Connection::new_for_test();
-        server_tx
-            .send(crate::message::ServerMessage::Error {
-                code: crate::message::ErrorCode::Unauthorized,
-                message: "bad token".to_string(),
-            })
-            .expect("pre-send failed");
-        client.inject_connection_for_test(conn).await;
-
-        let creds = crate::message::AuthCredentials::Jwt {
-            token: "expired-token".to_string(),
-        };
-        let result = client.authenticate("tenant_a", creds).await;
-        assert!(result.is_err());
-        assert!(
-            matches!(result.unwrap_err(), CommyError::Unauthorized(_)),
-            "Expected Unauthorized"
-        );
-    }
-
-    // ─────────────────────────────────────────────────────────────────────────
-    // Connection lifecycle tests
-    // ─────────────────────────────────────────────────────────────────────────
-
-    /// #5: disconnect() must clear both the connection and all auth contexts.
-    #[tokio::test]
-    async fn test_disconnect_clears_connection_and_auth() {
-        let client = Client::new("wss://test");
-        let (conn, _server_tx, _client_rx) = crate::connection::Connection::new_for_test();
-        client.inject_auth_for_test("tenant_a").await;
-        client.inject_connection_for_test(conn).await;
-
-        // Preconditions
-        assert!(client.is_connected().await, "must be connected before disconnect");
-        assert!(
-            client.is_authenticated_to("tenant_a").await,
-            "must be authenticated before disconnect"
-        );
-
-        let result = client.disconnect().await;
-        assert!(result.is_ok(), "disconnect should succeed: {:?}", result);
-
-        // Post-conditions
-        assert!(!client.is_connected().await, "connection must be cleared after disconnect");
-        assert!(
-            !client.is_authenticated_to("tenant_a").await,
-            "auth must be cleared after disconnect"
-        );
-        assert!(
-            client.authenticated_tenants().await.is_empty(),
-            "all auth contexts must be removed after disconnect"
-        );
-        assert_eq!(
-            client.connection_state().await,
-            crate::connection::ConnectionState::Disconnected,
-            "state must be Disconnected after disconnect"
-        );
-    }
-
-    // ─────────────────────────────────────────────────────────────────────────
-    // Bug #15 documentation test: concurrent CRUD methods share a single
-    // receiver, so interleaved responses can be mismatched.
-    // ─────────────────────────────────────────────────────────────────────────
-
-    /// #15: Demonstrates the concurrent receiver race: if two tasks call
-    /// create_service simultaneously on the SAME client instance, both share
-    /// the same Arc<RwLock<Receiver>>.  The first recv() winner gets the only
-    /// response; the loser sees Timeout or a mis-attributed response.
-    ///
-    /// This test documents the KNOWN limitation — it is NOT asserting correct
-    /// behaviour but that the race DOES produce observable mis-routing.
-    #[tokio::test]
-    async fn test_concurrent_create_service_receiver_race_documented() {
-        use std::sync::Arc;
-
-        let client = Arc::new(Client::new("wss://test"));
-        let (conn, server_tx, _client_rx) = crate::connection::Connection::new_for_test();
-
-        // Only one Service response in the channel — two tasks will race for it
-        server_tx
-            .send(crate::message::ServerMessage::Service {
-                service_id: "svc-1".to_string(),
-                service_name: "svc_name".to_string(),
-                tenant_id: "t1".to_string(),
-                file_path: None,
-            })
-            .expect("pre-send one response");
-
-        client.inject_auth_for_test("t1").await;
-        client.inject_connection_for_test(conn).await;
-
-        let c1 = Arc::clone(&client);
-        let c2 = Arc::clone(&client);
-
-        // Spawn both concurrently — only one can win the recv()
-        let (r1, r2) = tokio::join!(
-            tokio::spawn(async move { c1.create_service("t1", "svc_a").await }),
-            tokio::spawn(async move { c2.create_service("t1", "svc_b").await }),
-        );
-
-        let r1 = r1.expect("task 1 panicked");
-        let r2 = r2.expect("task 2 panicked");
-
-        // Exactly ONE task should get the single response; the other times out
-        // or gets an error.  Both succeeding simultaneously would indicate a
-        // bug (response duplication).
-        let successes = [r1.is_ok(), r2.is_ok()].iter().filter(|&&b| b).count();
-        assert!(
-            successes <= 1,
-            "Both concurrent create_service calls succeeded with a single server response — \
-             this indicates response duplication which is a serious protocol bug. \
-             Expected at most 1 success."
-        );
-    }
-}
+//! Main Commy client for connecting to servers
+
+use crate::auth::{AuthContext, AuthCredentials};
+use crate::builder::{ClientBuilder, Timeouts};
+use crate::capabilities::{Capabilities, Feature};
+use crate::connection::{Connection, ConnectionState, PUSH_CHANNEL_CAPACITY};
+use crate::error::{CommyError, Result};
+use crate::event::{ClientEvent, ClientEvents, EVENT_CHANNEL_CAPACITY};
+use crate::fault::{FaultInjectingTransport, FaultInjection};
+use crate::file_accessor::{FileAccess, FileAccessor, LocalFileAccessor, RemoteFileAccessor};
+use crate::message::{ClientMessage, ServerMessage};
+use crate::recording::Recorder;
+use crate::retry::RetryPolicy;
+use crate::service::Service;
+use crate::state::{create_shared_state, SharedState};
+use crate::subscription::{Subscription, SubscriptionRegistry, VariableUpdate};
+use crate::sync::PendingChanges;
+use crate::tls::TlsConfig;
+use crate::transport::{self, TransportFactory};
+use crate::unix_socket::{self, PeerCheck};
+use crate::virtual_file::VirtualVariableFile;
+use crate::watcher::{FileChangeEvent, VariableFileWatcher};
+use crate::wire::WireFormat;
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::{BoxStream, SelectAll, StreamExt};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex, RwLock};
+use tokio_tungstenite::tungstenite::http::Uri;
+use uuid::Uuid;
+
+/// How often the sync engine checks whether the user's client is gone
+const SYNC_OWNER_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Main Commy client for interacting with a Commy server
+pub struct Client {
+    /// Unique client identifier
+    client_id: String,
+
+    /// Server URL (WebSocket)
+    server_url: String,
+
+    /// Active WebSocket connection
+    connection: Arc<RwLock<Option<Connection>>>,
+
+    /// Shared client state
+    state: SharedState,
+
+    /// Heartbeat interval
+    heartbeat_interval: Duration,
+
+    /// Missed heartbeat replies after which the connection is considered dead
+    max_missed_heartbeats: u32,
+
+    /// Wire format offered to the server on connect
+    wire_format: WireFormat,
+
+    /// Whether capabilities are exchanged after connecting
+    capability_handshake: bool,
+
+    /// Set once the server let a capability handshake time out
+    legacy_server: Arc<AtomicBool>,
+
+    /// TLS settings for `wss://` connections
+    tls: Option<TlsConfig>,
+
+    /// Server processes accepted on `unix://` connections
+    peer_check: PeerCheck,
+
+    /// Opens connections instead of the server URL, when set
+    transport: Option<Arc<dyn TransportFactory>>,
+
+    /// Records the messages of every connection, when set
+    recorder: Option<Recorder>,
+
+    /// Faults injected into every connection, when set
+    fault_injection: Option<FaultInjection>,
+
+    /// How virtual service files reach the service's data
+    file_access: FileAccess,
+
+    /// How long the sync engine collects change events before reporting
+    sync_batch_window: Duration,
+
+    /// Maximum reconnection attempts
+    max_reconnect_attempts: u32,
+
+    /// Current reconnection attempt
+    reconnect_attempts: Arc<AtomicU64>,
+
+    /// Number of connections opened so far, identifying the current one
+    connection_generation: Arc<AtomicU64>,
+
+    /// Held while reconnecting, so concurrent failures reconnect only once
+    reconnect_lock: Arc<Mutex<()>>,
+
+    /// Delay policy between reconnection attempts
+    retry_policy: Arc<dyn RetryPolicy>,
+
+    /// Per-operation timeouts
+    timeouts: Timeouts,
+
+    /// Virtual variable files by service ID
+    virtual_files: Arc<RwLock<std::collections::HashMap<String, Arc<VirtualVariableFile>>>>,
+
+    /// Server-side service IDs of virtual files, by virtual file ID
+    service_ids: Arc<RwLock<HashMap<String, String>>>,
+
+    /// File watcher for change detection
+    file_watcher: Arc<RwLock<Option<Arc<VariableFileWatcher>>>>,
+
+    /// Background heartbeat task handle
+    heartbeat_task: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
+
+    /// Background sync engine task handle
+    sync_task: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
+
+    /// Server pushes, shared by every connection this client opens
+    pushes: broadcast::Sender<ServerMessage>,
+
+    /// Variables watched by live `Subscription` handles
+    subscriptions: SubscriptionRegistry,
+
+    /// Credentials of authenticated tenants, replayed after a reconnect
+    credentials: Arc<RwLock<HashMap<String, AuthCredentials>>>,
+
+    /// Lifecycle events
+    events: broadcast::Sender<ClientEvent>,
+
+    /// Held only by the user's client, not by background task handles
+    _owner: Option<Arc<()>>,
+
+    /// Lets background tasks notice when the user's client is dropped
+    owner_alive: Weak<()>,
+}
+
+impl Client {
+    /// Initialize a fully-configured client in one call
+    ///
+    /// This is the primary entry point. It:
+    /// 1. Creates a new client
+    /// 2. Connects to the server
+    /// 3. Authenticates to the tenant
+    /// 4. Initializes the file watcher
+    /// 5. Starts file monitoring
+    ///
+    /// After this, the client is ready to use with `get_virtual_service_file()`.
+    pub async fn initialize(
+        server_url: impl Into<String>,
+        tenant_id: impl Into<String>,
+        credentials: AuthCredentials,
+    ) -> Result<Self> {
+        let client = Self::_new(server_url);
+        client._connect_impl().await?;
+        client
+            ._authenticate_impl(&tenant_id.into(), credentials)
+            .await?;
+        client._init_file_watcher_impl().await?;
+        client._start_file_monitoring_impl().await?;
+        Ok(client)
+    }
+
+    /// Create a new client (internal)
+    #[inline]
+    fn _new(server_url: impl Into<String>) -> Self {
+        ClientBuilder::new(server_url).build()
+    }
+
+    /// Create a client from a finished builder
+    pub(crate) fn from_builder(builder: ClientBuilder) -> Self {
+        let client_id = builder
+            .client_id
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let owner = Arc::new(());
+
+        Self {
+            client_id: client_id.clone(),
+            server_url: builder.server_url,
+            connection: Arc::new(RwLock::new(None)),
+            state: create_shared_state(client_id),
+            heartbeat_interval: builder.heartbeat_interval,
+            max_missed_heartbeats: builder.max_missed_heartbeats,
+            wire_format: builder.wire_format,
+            capability_handshake: builder.capability_handshake,
+            legacy_server: Arc::new(AtomicBool::new(false)),
+            tls: builder.tls,
+            peer_check: builder.peer_check,
+            transport: builder.transport,
+            recorder: builder.recorder,
+            fault_injection: builder.fault_injection,
+            file_access: builder.file_access,
+            sync_batch_window: builder.sync_batch_window,
+            max_reconnect_attempts: builder.max_reconnect_attempts,
+            reconnect_attempts: Arc::new(AtomicU64::new(0)),
+            connection_generation: Arc::new(AtomicU64::new(0)),
+            reconnect_lock: Arc::new(Mutex::new(())),
+            retry_policy: builder.retry_policy,
+            timeouts: builder.timeouts,
+            virtual_files: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            service_ids: Arc::new(RwLock::new(HashMap::new())),
+            file_watcher: Arc::new(RwLock::new(None)),
+            heartbeat_task: Arc::new(RwLock::new(None)),
+            sync_task: Arc::new(RwLock::new(None)),
+            pushes: broadcast::channel(PUSH_CHANNEL_CAPACITY).0,
+            subscriptions: SubscriptionRegistry::default(),
+            credentials: Arc::new(RwLock::new(HashMap::new())),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            owner_alive: Arc::downgrade(&owner),
+            _owner: Some(owner),
+        }
+    }
+
+    /// Create a new client (public - for testing/special cases)
+    pub fn new(server_url: impl Into<String>) -> Self {
+        Self::_new(server_url)
+    }
+
+    /// Create a new client with custom ID
+    pub fn with_id(server_url: impl Into<String>, client_id: impl Into<String>) -> Self {
+        ClientBuilder::new(server_url).client_id(client_id).build()
+    }
+
+    /// Start configuring a client with timeouts, retry and heartbeat policies
+    pub fn builder(server_url: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(server_url)
+    }
+
+    /// Get client ID
+    pub fn id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// Get server URL
+    pub fn server_url(&self) -> &str {
+        &self.server_url
+    }
+
+    /// Connect to server (internal)
+    #[inline]
+    async fn _connect_impl(&self) -> Result<()> {
+        let mut state = self.state.write().await;
+        state.connection_state = ConnectionState::Connecting;
+        drop(state);
+        let _ = self.events.send(ClientEvent::Connecting);
+
+        let (url, pushes, format) = (&self.server_url, self.pushes.clone(), self.wire_format);
+        let connecting = async {
+            let transport = match &self.transport {
+                Some(factory) => factory.open().await?,
+                None => transport::connect(url, format, self.tls.as_ref(), &self.peer_check).await?,
+            };
+            let transport = match &self.fault_injection {
+                Some(faults) => Box::new(FaultInjectingTransport::new(transport, faults.clone())),
+                None => transport,
+            };
+            let connection = Connection::with_transport(transport, pushes);
+            if let Some(recorder) = &self.recorder {
+                connection.record(recorder);
+            }
+            Ok(connection)
+        };
+        let connected = match tokio::time::timeout(self.timeouts.connect, connecting).await {
+            Ok(result) => result,
+            Err(_) => Err(CommyError::Timeout),
+        };
+
+        match connected {
+            Ok(conn) => {
+                let mut state = self.state.write().await;
+                state.connection_state = ConnectionState::Connected;
+                state.session_id = Some(Uuid::new_v4().to_string());
+                drop(state);
+
+                let mut conn_guard = self.connection.write().await;
+                // Started under the lock so an instant close still finds this connection
+                self.watch_connection(&conn);
+                *conn_guard = Some(conn);
+                self.connection_generation.fetch_add(1, Ordering::SeqCst);
+                drop(conn_guard);
+
+                if self.capability_handshake {
+                    self.negotiate_capabilities().await;
+                }
+
+                // Reset reconnection attempts on successful connection
+                self.reconnect_attempts.store(0, Ordering::SeqCst);
+                let _ = self.events.send(ClientEvent::Connected);
+
+                // Heartbeat replies are matched by request ID, so they never
+                // compete with other request responses
+                self.start_heartbeat_task().await;
+
+                // WebSocket connection established - no Connect message needed
+                // Authentication will be the first message sent
+
+                Ok(())
+            }
+            Err(e) => {
+                let mut state = self.state.write().await;
+                state.connection_state = ConnectionState::Disconnected;
+                drop(state);
+                let _ = self
+                    .events
+                    .send(ClientEvent::Disconnected(format!("connect failed: {}", e)));
+                Err(e)
+            }
+        }
+    }
+
+    /// Exchange capabilities with the server just connected to
+    ///
+    /// A server that answers the hello with anything but `Welcome`, or not at
+    /// all, predates the handshake and gets legacy capabilities. One that
+    /// never answered is not asked again on later connections, so each
+    /// reconnect does not wait out the timeout anew.
+    async fn negotiate_capabilities(&self) {
+        if self.legacy_server.load(Ordering::SeqCst) {
+            self.state.write().await.server_capabilities = Some(Capabilities::legacy());
+            return;
+        }
+
+        let hello = ClientMessage::Hello {
+            client_version: crate::VERSION.to_string(),
+            capabilities: Capabilities::client(),
+        };
+        let response = match self.request_once(hello).await {
+            Ok(response) => await_response(response, self.timeouts.connect).await,
+            Err(e) => Err(e),
+        };
+
+        let mut state = self.state.write().await;
+        match response {
+            Ok(ServerMessage::Welcome {
+                server_version,
+                capabilities,
+            }) => {
+                state.server_version = Some(server_version);
+                state.server_capabilities = Some(capabilities);
+            }
+            Err(CommyError::Timeout) => {
+                self.legacy_server.store(true, Ordering::SeqCst);
+                state.server_capabilities = Some(Capabilities::legacy());
+            }
+            _ => state.server_capabilities = Some(Capabilities::legacy()),
+        }
+    }
+
+    /// Fail if the server announced that it lacks `feature`
+    async fn require(&self, feature: Feature) -> Result<()> {
+        let state = self.state.read().await;
+        match &state.server_capabilities {
+            Some(capabilities) if !capabilities.supports(feature) => Err(CommyError::Unsupported {
+                feature,
+                server_version: state
+                    .server_version
+                    .clone()
+                    .unwrap_or_else(|| "unknown".to_string()),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Follow a new connection until its socket closes
+    ///
+    /// Forwards `ServerMessage::Disconnected` pushes and messages of unknown
+    /// type as events, and discards replies no request is waiting for. When
+    /// the socket's reader stops while the connection is still the current
+    /// one, the connection is dropped and the state set to `Disconnected`;
+    /// the next operation then reconnects.
+    fn watch_connection(&self, conn: &Connection) {
+        let mut closed = conn.closed();
+        let unsolicited = conn.unsolicited();
+        // Weak, so this task does not keep a dropped client's connection open
+        let connection = Arc::downgrade(&self.connection);
+        let state = Arc::clone(&self.state);
+        let events = self.events.clone();
+        let mut pushes = self.pushes.subscribe();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = closed.wait_for(|closed| *closed) => break,
+                    Some(message) = async { unsolicited.write().await.recv().await } => {
+                        tracing::debug!(?message, "ignoring a server message that answers no request");
+                    }
+                    push = pushes.recv() => match push {
+                        Ok(ServerMessage::Disconnected { reason }) => {
+                            let _ = events.send(ClientEvent::ServerDisconnected(reason));
+                        }
+                        Ok(message @ ServerMessage::Unknown(_)) => {
+                            let _ = events.send(ClientEvent::UnknownMessage {
+                                message_type: message.unknown_type().unwrap_or_default().to_string(),
+                                raw: serde_json::to_string(&message).unwrap_or_default(),
+                            });
+                        }
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                }
+            }
+
+            let Some(connection) = connection.upgrade() else {
+                return;
+            };
+            let mut conn_guard = connection.write().await;
+            // A replaced or deliberately dropped connection is not news
+            if conn_guard.as_ref().is_some_and(|conn| conn.is_closed()) {
+                *conn_guard = None;
+                drop(conn_guard);
+                state.write().await.connection_state = ConnectionState::Disconnected;
+                let _ = events.send(ClientEvent::Disconnected(
+                    "connection closed by server".to_string(),
+                ));
+            }
+        });
+    }
+
+    /// Connect to server (public - for testing/special cases)
+    pub async fn connect(&self) -> Result<()> {
+        self._connect_impl().await
+    }
+
+    /// Authenticate to a tenant (internal)
+    #[inline]
+    async fn _authenticate_impl(
+        &self,
+        tenant_id: &str,
+        credentials: AuthCredentials,
+    ) -> Result<AuthContext> {
+        let response = self
+            .request(authenticate_message(tenant_id, credentials.clone()))
+            .await?;
+        self.apply_authentication_response(tenant_id, credentials, response)
+            .await
+    }
+
+    /// Record the outcome of an `Authenticate` request
+    async fn apply_authentication_response(
+        &self,
+        tenant_id: &str,
+        credentials: AuthCredentials,
+        response: ServerMessage,
+    ) -> Result<AuthContext> {
+        match response {
+            ServerMessage::AuthenticationResult {
+                success: true,
+                permissions,
+                ..
+            } => {
+                let auth_context =
+                    AuthContext::new(tenant_id.to_string(), permissions.unwrap_or_default());
+
+                let mut state = self.state.write().await;
+                state.connection_state = ConnectionState::Authenticated;
+                state.add_auth_context(tenant_id.to_string(), auth_context.clone());
+                drop(state);
+
+                self.credentials
+                    .write()
+                    .await
+                    .insert(tenant_id.to_string(), credentials);
+
+                let _ = self
+                    .events
+                    .send(ClientEvent::Authenticated(tenant_id.to_string()));
+
+                Ok(auth_context)
+            }
+            ServerMessage::AuthenticationResult { success: false, message, .. } => {
+                Err(CommyError::AuthenticationFailed(message))
+            }
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("authenticate", other)),
+        }
+    }
+
+    /// Authenticate to a tenant (public - for testing/special cases)
+    pub async fn authenticate(
+        &self,
+        tenant_id: impl Into<String>,
+        credentials: AuthCredentials,
+    ) -> Result<AuthContext> {
+        self._authenticate_impl(&tenant_id.into(), credentials)
+            .await
+    }
+
+    /// Create a new service in a tenant
+    ///
+    /// Returns the service ID on success. Returns error if:
+    /// - Not authenticated to the tenant
+    /// - Service already exists
+    /// - Insufficient permissions (need create_service permission)
+    pub async fn create_service(&self, tenant_id: &str, service_name: &str) -> Result<String> {
+        // Check if authenticated to this tenant
+        let state = self.state.read().await;
+        if !state.is_authenticated_to(tenant_id) {
+            return Err(CommyError::PermissionDenied(format!(
+                "Not authenticated to tenant: {}",
+                tenant_id
+            )));
+        }
+        drop(state);
+
+        // Request service creation
+        let response = self
+            .request(ClientMessage::CreateService {
+                tenant_id: tenant_id.to_string(),
+                service_name: service_name.to_string(),
+            })
+            .await?;
+
+        match response {
+            ServerMessage::Service { service_id, .. } => Ok(service_id),
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("create_service", other)),
+        }
+    }
+
+    /// Get an existing service from a tenant (read-only, no side effects)
+    ///
+    /// Returns error if:
+    /// - Not authenticated to the tenant
+    /// - Service does not exist (NotFound error)
+    /// - Insufficient permissions (need read_service permission)
+    pub async fn get_service(&self, tenant_id: &str, service_name: &str) -> Result<Service> {
+        // Check if authenticated to this tenant
+        let state = self.state.read().await;
+        if !state.is_authenticated_to(tenant_id) {
+            return Err(CommyError::PermissionDenied(format!(
+                "Not authenticated to tenant: {}",
+                tenant_id
+            )));
+        }
+        drop(state);
+
+        // Request service
+        let response = self
+            .request(ClientMessage::GetService {
+                tenant_id: tenant_id.to_string(),
+                service_name: service_name.to_string(),
+            })
+            .await?;
+
+        match response {
+            ServerMessage::Service {
+                service_id,
+                service_name,
+                tenant_id: resp_tenant,
+                file_path,
+            } => {
+                let service = Service::new(service_id, service_name, resp_tenant, file_path);
+                Ok(service)
+            }
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("get_service", other)),
+        }
+    }
+
+    /// Delete a service from a tenant
+    ///
+    /// Returns error if:
+    /// - Not authenticated to the tenant
+    /// - Service does not exist
+    /// - Insufficient permissions (need delete_service permission, typically admin)
+    pub async fn delete_service(&self, tenant_id: &str, service_name: &str) -> Result<()> {
+        // Check if authenticated to this tenant
+        let state = self.state.read().await;
+        if !state.is_authenticated_to(tenant_id) {
+            return Err(CommyError::PermissionDenied(format!(
+                "Not authenticated to tenant: {}",
+                tenant_id
+            )));
+        }
+        drop(state);
+
+        // Request service deletion
+        let response = self
+            .request(ClientMessage::DeleteService {
+                tenant_id: tenant_id.to_string(),
+                service_name: service_name.to_string(),
+            })
+            .await?;
+
+        match response {
+            ServerMessage::Result { success: true, .. } => Ok(()),
+            ServerMessage::Result { success: false, message, .. } => {
+                Err(CommyError::PermissionDenied(message))
+            }
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("delete_service", other)),
+        }
+    }
+
+    /// Create a new tenant (admin operation)
+    ///
+    /// Requires admin credentials or special permissions to create tenants.
+    /// Returns the tenant ID on success.
+    ///
+    /// Returns error if:
+    /// - Not connected to server
+    /// - Tenant already exists
+    /// - Insufficient permissions (need admin role)
+    pub async fn create_tenant(&self, tenant_id: &str, tenant_name: &str) -> Result<String> {
+        // Request tenant creation
+        let response = self
+            .request(ClientMessage::CreateTenant {
+                tenant_id: tenant_id.to_string(),
+                tenant_name: tenant_name.to_string(),
+            })
+            .await?;
+
+        match response {
+            ServerMessage::TenantResult {
+                success: true,
+                tenant_id: returned_id,
+                ..
+            } => Ok(returned_id),
+            ServerMessage::TenantResult { success: false, message, .. } => {
+                Err(CommyError::PermissionDenied(message))
+            }
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("create_tenant", other)),
+        }
+    }
+
+    /// Delete a tenant (admin operation)
+    ///
+    /// Removes all services and data associated with the tenant.
+    /// Requires admin credentials or special permissions.
+    ///
+    /// Returns error if:
+    /// - Not connected to server
+    /// - Tenant does not exist
+    /// - Insufficient permissions (need admin role)
+    /// - Tenant has active clients
+    pub async fn delete_tenant(&self, tenant_id: &str) -> Result<()> {
+        // Request tenant deletion
+        let response = self
+            .request(ClientMessage::DeleteTenant {
+                tenant_id: tenant_id.to_string(),
+            })
+            .await?;
+
+        match response {
+            ServerMessage::Result { success: true, .. } => Ok(()),
+            ServerMessage::Result { success: false, message, .. } => {
+                Err(CommyError::PermissionDenied(message))
+            }
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("delete_tenant", other)),
+        }
+    }
+
+    /// Allocate a new variable in a service
+    ///
+    /// Returns error if:
+    /// - Variable already exists (AlreadyExists error)
+    /// - Service does not exist (NotFound error)
+    /// - Insufficient permissions
+    pub async fn allocate_variable(
+        &self,
+        service_id: &str,
+        variable_name: &str,
+        initial_data: Vec<u8>,
+    ) -> Result<()> {
+        let response = self
+            .request(ClientMessage::AllocateVariable {
+                service_id: service_id.to_string(),
+                variable_name: variable_name.to_string(),
+                initial_data,
+            })
+            .await?;
+
+        match response {
+            ServerMessage::Result { success: true, .. } | ServerMessage::VariableData { .. } => {
+                Ok(())
+            }
+            ServerMessage::Result { success: false, message, .. } => {
+                Err(CommyError::PermissionDenied(message))
+            }
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("allocate_variable", other)),
+        }
+    }
+
+    /// Deallocate a variable, freeing its space in the service
+    ///
+    /// Returns error if:
+    /// - Variable does not exist (NotFound error)
+    /// - Insufficient permissions
+    pub async fn deallocate_variable(&self, service_id: &str, variable_name: &str) -> Result<()> {
+        let response = self
+            .request(ClientMessage::DeallocateVariable {
+                service_id: service_id.to_string(),
+                variable_name: variable_name.to_string(),
+            })
+            .await?;
+
+        match response {
+            ServerMessage::Result { success: true, .. } => Ok(()),
+            ServerMessage::Result { success: false, message, .. } => {
+                Err(CommyError::PermissionDenied(message))
+            }
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("deallocate_variable", other)),
+        }
+    }
+
+    /// Read a variable value
+    pub async fn read_variable(&self, service_id: &str, variable_name: &str) -> Result<Vec<u8>> {
+        let response = self
+            .request(ClientMessage::ReadVariable {
+                service_id: service_id.to_string(),
+                variable_name: variable_name.to_string(),
+            })
+            .await?;
+
+        match response {
+            ServerMessage::VariableData { data, .. } => Ok(data),
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("read_variable", other)),
+        }
+    }
+
+    /// Write a variable value
+    ///
+    /// Waits for the server to apply the write and returns the variable's new
+    /// version. Writes from one client are applied in the order they are sent,
+    /// so versions from concurrent writers tell which write landed last.
+    ///
+    /// Returns error if:
+    /// - Variable does not exist (NotFound error)
+    /// - Data does not fit the variable (InvalidRequest error)
+    /// - Insufficient permissions
+    pub async fn write_variable(
+        &self,
+        service_id: &str,
+        variable_name: &str,
+        data: Vec<u8>,
+    ) -> Result<u64> {
+        let response = self
+            .request(ClientMessage::WriteVariable {
+                service_id: service_id.to_string(),
+                variable_name: variable_name.to_string(),
+                data,
+            })
+            .await?;
+
+        match response {
+            ServerMessage::VariableWritten { version, .. }
+            | ServerMessage::VariableData { version, .. } => Ok(version),
+            ServerMessage::Result { success: false, message, .. } => {
+                Err(CommyError::PermissionDenied(message))
+            }
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("write_variable", other)),
+        }
+    }
+
+    /// Write a variable value only if it is still at `expected_version`
+    ///
+    /// Returns the variable's new version. If another writer got there first,
+    /// returns `CommyError::Conflict` with the variable's current version and
+    /// value, so the caller can recompute its update and try again.
+    ///
+    /// Returns error if:
+    /// - Variable has moved past `expected_version` (Conflict error)
+    /// - Variable does not exist (NotFound error)
+    /// - Insufficient permissions
+    /// - Server announced it lacks compare-and-swap (Unsupported error)
+    pub async fn compare_and_swap(
+        &self,
+        service_id: &str,
+        variable_name: &str,
+        expected_version: u64,
+        data: Vec<u8>,
+    ) -> Result<u64> {
+        self.require(Feature::CompareAndSwap).await?;
+
+        let response = self
+            .request(ClientMessage::CompareAndSwap {
+                service_id: service_id.to_string(),
+                variable_name: variable_name.to_string(),
+                expected_version,
+                data,
+            })
+            .await?;
+
+        match response {
+            ServerMessage::VariableWritten { version, .. }
+            | ServerMessage::VariableData { version, .. } => Ok(version),
+            ServerMessage::VersionConflict {
+                current_version,
+                current_data,
+                ..
+            } => Err(CommyError::Conflict {
+                current_version,
+                current_data,
+            }),
+            ServerMessage::Result { success: false, message, .. } => {
+                Err(CommyError::PermissionDenied(message))
+            }
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("compare_and_swap", other)),
+        }
+    }
+
+    /// Subscribe to variable changes
+    ///
+    /// Returns a `Subscription` stream yielding each new value of the variable.
+    /// Dropping it unsubscribes from the server once no other subscription
+    /// watches the same variable.
+    ///
+    /// Returns error if:
+    /// - Variable does not exist (NotFound error)
+    /// - Not authenticated or insufficient permissions
+    pub async fn subscribe(&self, service_id: &str, variable_name: &str) -> Result<Subscription> {
+        // Listen before subscribing so the first change cannot slip past
+        let pushes = self.pushes.subscribe();
+
+        let response = self
+            .request(ClientMessage::Subscribe {
+                service_id: service_id.to_string(),
+                variable_name: variable_name.to_string(),
+            })
+            .await?;
+        subscribe_result(response)?;
+
+        self.subscriptions.acquire(service_id, variable_name);
+        Ok(Subscription::new(
+            service_id.to_string(),
+            variable_name.to_string(),
+            pushes,
+            self.subscriptions.clone(),
+            Arc::clone(&self.connection),
+        ))
+    }
+
+    /// Unsubscribe from variable changes
+    ///
+    /// Tells the server immediately, regardless of any live `Subscription`
+    /// handles for the variable; those simply stop receiving updates, and the
+    /// variable is not subscribed to again after a reconnect.
+    pub async fn unsubscribe(&self, service_id: &str, variable_name: &str) -> Result<()> {
+        self.subscriptions.remove(service_id, variable_name);
+        self.send_message(ClientMessage::Unsubscribe {
+            service_id: service_id.to_string(),
+            variable_name: variable_name.to_string(),
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Send heartbeat to server
+    ///
+    /// Returns `CommyError::Timeout` if the server does not answer within the
+    /// heartbeat timeout. A server that does not echo request IDs cannot mark
+    /// its answer, so any message it sends within the timeout counts as one.
+    pub async fn heartbeat(&self) -> Result<()> {
+        let generation = self.connection_generation.load(Ordering::SeqCst);
+        match self.exchange_heartbeat().await {
+            Err(CommyError::ConnectionLost(_)) => {
+                self.reconnect(generation).await?;
+                self.exchange_heartbeat().await
+            }
+            result => result,
+        }
+    }
+
+    /// Send one heartbeat and wait for the server's answer to it
+    ///
+    /// Only a server echoing request IDs can tell its answer apart from a
+    /// heartbeat it pushes on its own.
+    async fn exchange_heartbeat(&self) -> Result<()> {
+        if self.require(Feature::RequestIds).await.is_err() {
+            return self.exchange_uncorrelated_heartbeat().await;
+        }
+
+        let response = self
+            .request_once(ClientMessage::Heartbeat {
+                client_id: self.client_id.clone(),
+            })
+            .await?;
+
+        match await_response(response, self.timeouts.heartbeat).await? {
+            ServerMessage::Heartbeat { .. } => Ok(()),
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("heartbeat", other)),
+        }
+    }
+
+    /// Send one heartbeat to a server without request IDs and wait for any
+    /// message from it
+    ///
+    /// Its answer looks like any heartbeat it pushes, so every message
+    /// received after sending proves the connection alive.
+    async fn exchange_uncorrelated_heartbeat(&self) -> Result<()> {
+        let (mut received, mut closed) = match self.connection.read().await.as_ref() {
+            Some(conn) => (conn.received(), conn.closed()),
+            None => {
+                return Err(CommyError::ConnectionLost(
+                    "Connection not established".to_string(),
+                ))
+            }
+        };
+        received.borrow_and_update();
+
+        self.send_message_once(ClientMessage::Heartbeat {
+            client_id: self.client_id.clone(),
+        })
+        .await?;
+
+        let answered = async {
+            tokio::select! {
+                changed = received.changed() => changed.is_ok(),
+                _ = closed.wait_for(|closed| *closed) => false,
+            }
+        };
+        match tokio::time::timeout(self.timeouts.heartbeat, answered).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(CommyError::ConnectionLost(
+                "Connection closed before the heartbeat was answered".to_string(),
+            )),
+            Err(_) => Err(CommyError::Timeout),
+        }
+    }
+
+    /// Disconnect from server
+    pub async fn disconnect(&self) -> Result<()> {
+        self.send_message(ClientMessage::Disconnect {
+            client_id: self.client_id.clone(),
+        })
+        .await?;
+
+        if let Some(handle) = self.heartbeat_task.write().await.take() {
+            handle.abort();
+        }
+
+        let mut conn_guard = self.connection.write().await;
+        *conn_guard = None;
+        drop(conn_guard);
+
+        let mut state = self.state.write().await;
+        state.reset();
+        drop(state);
+
+        self.credentials.write().await.clear();
+        let _ = self
+            .events
+            .send(ClientEvent::Disconnected("client disconnected".to_string()));
+
+        Ok(())
+    }
+
+    /// Check if connected
+    pub async fn is_connected(&self) -> bool {
+        self.connection.read().await.is_some()
+    }
+
+    /// Capabilities the server announced when connecting
+    ///
+    /// `None` before connecting or when the handshake is disabled.
+    pub async fn server_capabilities(&self) -> Option<Capabilities> {
+        self.state.read().await.server_capabilities.clone()
+    }
+
+    /// Wire format of the current connection, if connected
+    pub async fn wire_format(&self) -> Option<WireFormat> {
+        self.connection.read().await.as_ref().map(Connection::wire_format)
+    }
+
+    /// Get current connection state
+    pub async fn connection_state(&self) -> ConnectionState {
+        let state = self.state.read().await;
+        state.connection_state
+    }
+
+    /// Get authenticated tenants
+    pub async fn authenticated_tenants(&self) -> Vec<String> {
+        let state = self.state.read().await;
+        state
+            .authenticated_tenants()
+            .into_iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    /// Check if authenticated to tenant
+    pub async fn is_authenticated_to(&self, tenant_id: &str) -> bool {
+        let state = self.state.read().await;
+        state.is_authenticated_to(tenant_id)
+    }
+
+    /// Subscribe to unsolicited server pushes
+    ///
+    /// Receives `VariableChanged`, `Disconnected` and `Heartbeat` messages from
+    /// every connection the client opens, including after a reconnect.
+    pub fn subscribe_pushes(&self) -> broadcast::Receiver<ServerMessage> {
+        self.pushes.subscribe()
+    }
+
+    /// Subscribe to client lifecycle events
+    ///
+    /// Reports connection state changes, authentication, reconnect attempts
+    /// and server-initiated disconnects as they happen, so supervisors need
+    /// not poll `connection_state`.
+    pub fn events(&self) -> ClientEvents {
+        ClientEvents::new(self.events.subscribe())
+    }
+
+    /// Get idle time in seconds
+    pub async fn idle_seconds(&self) -> u64 {
+        let state = self.state.read().await;
+        state.idle_seconds()
+    }
+
+    /// Send a message to server with automatic reconnection
+    async fn send_message(&self, msg: ClientMessage) -> Result<()> {
+        // Try sending the message
+        let generation = self.connection_generation.load(Ordering::SeqCst);
+        let result = self.send_message_once(msg.clone()).await;
+
+        // If connection was lost, attempt reconnection
+        if let Err(CommyError::ConnectionLost(_)) = result {
+            self.reconnect(generation).await?;
+
+            // Retry the message after reconnection
+            return self.send_message_once(msg).await;
+        }
+
+        result
+    }
+
+    /// Send a message without reconnection logic (internal)
+    async fn send_message_once(&self, msg: ClientMessage) -> Result<()> {
+        let conn_guard = self.connection.read().await;
+        if let Some(conn) = conn_guard.as_ref() {
+            conn.send(msg).await?;
+
+            let mut state = self.state.write().await;
+            state.touch();
+
+            Ok(())
+        } else {
+            Err(CommyError::ConnectionLost(
+                "Connection not established".to_string(),
+            ))
+        }
+    }
+
+    /// Send a request with automatic reconnection and wait for its response
+    ///
+    /// The response is matched to this request by request ID, so concurrent
+    /// calls on the same client never receive each other's responses.
+    async fn request(&self, msg: ClientMessage) -> Result<ServerMessage> {
+        let timeout = self.timeouts.for_message(&msg);
+
+        // Try sending the request
+        let generation = self.connection_generation.load(Ordering::SeqCst);
+        let response = match self.request_once(msg.clone()).await {
+            Err(CommyError::ConnectionLost(_)) => {
+                self.reconnect(generation).await?;
+
+                // Retry the request after reconnection
+                self.request_once(msg).await?
+            }
+            result => result?,
+        };
+
+        await_response(response, timeout).await
+    }
+
+    /// Send a request without reconnection logic (internal)
+    async fn request_once(
+        &self,
+        msg: ClientMessage,
+    ) -> Result<tokio::sync::oneshot::Receiver<ServerMessage>> {
+        let conn_guard = self.connection.read().await;
+        if let Some(conn) = conn_guard.as_ref() {
+            let response = conn.request(msg).await?;
+
+            let mut state = self.state.write().await;
+            state.touch();
+
+            Ok(response)
+        } else {
+            Err(CommyError::ConnectionLost(
+                "Connection not established".to_string(),
+            ))
+        }
+    }
+
+    /// Reconnect after the connection was lost, waiting as the retry policy says
+    ///
+    /// `generation` is the `connection_generation` of the connection that
+    /// failed. Callers whose connection was already replaced while they
+    /// waited for another caller's reconnect return right away.
+    async fn reconnect(&self, generation: u64) -> Result<()> {
+        let _reconnecting = self.reconnect_lock.lock().await;
+        if self.connection_generation.load(Ordering::SeqCst) != generation {
+            return Ok(());
+        }
+
+        let current_attempts = self.reconnect_attempts.fetch_add(1, Ordering::SeqCst);
+
+        let delay = if current_attempts < self.max_reconnect_attempts as u64 {
+            self.retry_policy.next_delay(current_attempts as u32)
+        } else {
+            None
+        };
+
+        if let Some(delay) = delay {
+            let _ = self
+                .events
+                .send(ClientEvent::Reconnecting(current_attempts as u32 + 1));
+            tokio::time::sleep(delay).await;
+
+            // Attempt to reconnect, then restore the session on the new socket
+            if let Ok(()) = self._connect_impl().await {
+                return self.resync().await;
+            }
+        }
+
+        Err(CommyError::ConnectionLost(format!(
+            "Connection lost after {} reconnection attempts",
+            current_attempts + 1
+        )))
+    }
+
+    /// Restore authentication and subscriptions on a fresh connection
+    ///
+    /// Replays `Authenticate` for every remembered tenant and `Subscribe` for
+    /// every variable with a live `Subscription`, then emits
+    /// `ClientEvent::Resynced`. Each tenant that cannot be authenticated to
+    /// again is reported as `ClientEvent::ReauthenticationFailed`; its
+    /// credentials are forgotten only when the server rejected them. Each
+    /// subscription the server does not accept is reported as
+    /// `ClientEvent::ResubscriptionFailed` and left out of `Resynced`. A
+    /// timeout or lost connection ends the resync with that error.
+    async fn resync(&self) -> Result<()> {
+        let credentials: Vec<(String, AuthCredentials)> = self
+            .credentials
+            .read()
+            .await
+            .iter()
+            .map(|(tenant_id, credentials)| (tenant_id.clone(), credentials.clone()))
+            .collect();
+
+        let mut tenants = Vec::new();
+        for (tenant_id, credentials) in credentials {
+            let response = self
+                .request_once(authenticate_message(&tenant_id, credentials.clone()))
+                .await?;
+            let result = match await_response(response, self.timeouts.authenticate).await {
+                Ok(response) => {
+                    self.apply_authentication_response(&tenant_id, credentials, response)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+
+            let Err(e) = result else {
+                tenants.push(tenant_id);
+                continue;
+            };
+
+            let _ = self.events.send(ClientEvent::ReauthenticationFailed {
+                tenant_id: tenant_id.clone(),
+                reason: e.to_string(),
+            });
+            match e {
+                // The new connection is no better than the old one
+                CommyError::ConnectionLost(_) | CommyError::Timeout => return Err(e),
+                CommyError::AuthenticationFailed(_) | CommyError::Unauthorized(_) => {
+                    self.state.write().await.clear_auth(&tenant_id);
+                    self.credentials.write().await.remove(&tenant_id);
+                }
+                _ => self.state.write().await.clear_auth(&tenant_id),
+            }
+        }
+
+        let mut subscriptions = Vec::new();
+        for (service_id, variable_name) in self.subscriptions.active() {
+            let request = ClientMessage::Subscribe {
+                service_id: service_id.clone(),
+                variable_name: variable_name.clone(),
+            };
+            let timeout = self.timeouts.for_message(&request);
+            let response = self.request_once(request).await?;
+            let result = match await_response(response, timeout).await {
+                Ok(response) => subscribe_result(response),
+                Err(e) => Err(e),
+            };
+
+            let Err(e) = result else {
+                subscriptions.push((service_id, variable_name));
+                continue;
+            };
+
+            let _ = self.events.send(ClientEvent::ResubscriptionFailed {
+                service_id,
+                variable_name,
+                reason: e.to_string(),
+            });
+            if matches!(e, CommyError::ConnectionLost(_) | CommyError::Timeout) {
+                return Err(e);
+            }
+        }
+
+        let _ = self.events.send(ClientEvent::Resynced {
+            tenants,
+            subscriptions,
+        });
+
+        Ok(())
+    }
+
+    /// Start the background heartbeat task unless it is already running
+    ///
+    /// The task outlives individual connections: it reconnects by itself
+    /// after too many missed heartbeats, and stops when the client is
+    /// disconnected or dropped. A zero heartbeat interval disables it.
+    async fn start_heartbeat_task(&self) {
+        if self.heartbeat_interval.is_zero() {
+            return;
+        }
+
+        let mut task_guard = self.heartbeat_task.write().await;
+        if task_guard.as_ref().is_some_and(|handle| !handle.is_finished()) {
+            return;
+        }
+
+        let client = self.background_handle();
+        *task_guard = Some(tokio::spawn(client.run_heartbeat()));
+    }
+
+    /// Heartbeat loop run by the background task
+    ///
+    /// Boxed because it reconnects, and connecting starts this task again.
+    fn run_heartbeat(self) -> BoxFuture<'static, ()> {
+        async move {
+            let interval = self.heartbeat_interval;
+            let mut timer = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            let mut missed = 0;
+
+            loop {
+                timer.tick().await;
+
+                // Stop once the user's client is gone or was disconnected
+                if self.owner_alive.strong_count() == 0 || self.connection.read().await.is_none() {
+                    break;
+                }
+
+                let generation = self.connection_generation.load(Ordering::SeqCst);
+                match self.exchange_heartbeat().await {
+                    Ok(()) => missed = 0,
+                    Err(CommyError::Timeout) => missed += 1,
+                    // The connection is already unusable
+                    Err(_) => missed = self.max_missed_heartbeats,
+                }
+
+                if missed >= self.max_missed_heartbeats {
+                    let mut conn_guard = self.connection.write().await;
+                    // Someone else already replaced the dead connection
+                    if self.connection_generation.load(Ordering::SeqCst) != generation {
+                        missed = 0;
+                        continue;
+                    }
+                    *conn_guard = None;
+                    drop(conn_guard);
+                    self.state.write().await.connection_state = ConnectionState::Disconnected;
+                    let _ = self.events.send(ClientEvent::Disconnected(format!(
+                        "{} heartbeats missed",
+                        missed
+                    )));
+
+                    if !self.reconnect_until_exhausted(generation).await {
+                        break;
+                    }
+                    missed = 0;
+                }
+            }
+        }
+        .boxed()
+    }
+
+    /// Keep reconnecting until it works or the attempts run out
+    async fn reconnect_until_exhausted(&self, generation: u64) -> bool {
+        loop {
+            match self.reconnect(generation).await {
+                Ok(()) => return true,
+                Err(_)
+                    if self.reconnect_attempts.load(Ordering::SeqCst)
+                        < self.max_reconnect_attempts as u64 =>
+                {
+                    continue
+                }
+                Err(_) => return false,
+            }
+        }
+    }
+
+    /// Clone of this client for background tasks
+    ///
+    /// Shares the connection and all state with `self` but does not keep the
+    /// user's client alive; tasks check `owner_alive` to know when to stop.
+    fn background_handle(&self) -> Client {
+        Self {
+            client_id: self.client_id.clone(),
+            server_url: self.server_url.clone(),
+            connection: Arc::clone(&self.connection),
+            state: Arc::clone(&self.state),
+            heartbeat_interval: self.heartbeat_interval,
+            max_missed_heartbeats: self.max_missed_heartbeats,
+            wire_format: self.wire_format,
+            capability_handshake: self.capability_handshake,
+            legacy_server: Arc::clone(&self.legacy_server),
+            tls: self.tls.clone(),
+            peer_check: self.peer_check.clone(),
+            transport: self.transport.clone(),
+            recorder: self.recorder.clone(),
+            fault_injection: self.fault_injection.clone(),
+            file_access: self.file_access,
+            sync_batch_window: self.sync_batch_window,
+            max_reconnect_attempts: self.max_reconnect_attempts,
+            reconnect_attempts: Arc::clone(&self.reconnect_attempts),
+            connection_generation: Arc::clone(&self.connection_generation),
+            reconnect_lock: Arc::clone(&self.reconnect_lock),
+            retry_policy: Arc::clone(&self.retry_policy),
+            timeouts: self.timeouts.clone(),
+            virtual_files: Arc::clone(&self.virtual_files),
+            service_ids: Arc::clone(&self.service_ids),
+            file_watcher: Arc::clone(&self.file_watcher),
+            heartbeat_task: Arc::clone(&self.heartbeat_task),
+            sync_task: Arc::clone(&self.sync_task),
+            pushes: self.pushes.clone(),
+            subscriptions: self.subscriptions.clone(),
+            credentials: Arc::clone(&self.credentials),
+            events: self.events.clone(),
+            _owner: None,
+            owner_alive: self.owner_alive.clone(),
+        }
+    }
+
+    /// Initialize file watcher for hybrid mode (internal)
+    ///
+    /// Also starts the sync engine reporting the changes it detects.
+    #[inline]
+    async fn _init_file_watcher_impl(&self) -> Result<()> {
+        let watcher = VariableFileWatcher::new(None).await?;
+        let watcher = Arc::new(watcher);
+        watcher.start_watching().await?;
+        self.start_sync_task(watcher.subscribe_changes()).await;
+        *self.file_watcher.write().await = Some(watcher);
+        Ok(())
+    }
+
+    /// Start the sync engine over a watcher's change events, replacing the
+    /// engine started for an earlier watcher
+    async fn start_sync_task(&self, changes: broadcast::Receiver<FileChangeEvent>) {
+        let client = self.background_handle();
+        let events = self.events.subscribe();
+        let task = tokio::spawn(client.run_sync(changes, events));
+        if let Some(previous) = self.sync_task.write().await.replace(task) {
+            previous.abort();
+        }
+    }
+
+    /// Sync engine loop run by the background task
+    ///
+    /// Collects change events for one batch window, then reports them. A
+    /// `Resynced` event retries whatever a lost connection left pending.
+    async fn run_sync(
+        self,
+        mut changes: broadcast::Receiver<FileChangeEvent>,
+        mut events: broadcast::Receiver<ClientEvent>,
+    ) {
+        use broadcast::error::{RecvError, TryRecvError};
+
+        let mut pending = PendingChanges::default();
+        let mut owner_check = tokio::time::interval(SYNC_OWNER_CHECK_INTERVAL);
+
+        loop {
+            tokio::select! {
+                change = changes.recv() => match change {
+                    Ok(event) => pending.add(&event.service_id, event.changed_variables),
+                    Err(RecvError::Lagged(_)) => self.collect_marked_changes(&mut pending).await,
+                    Err(RecvError::Closed) => break,
+                },
+                event = events.recv() => match event {
+                    Ok(ClientEvent::Resynced { .. }) => {}
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+                _ = owner_check.tick() => {
+                    if self.owner_alive.strong_count() == 0 {
+                        break;
+                    }
+                    continue;
+                }
+            }
+
+            if pending.is_empty() {
+                continue;
+            }
+
+            // Let the rest of a burst arrive before reporting
+            tokio::time::sleep(self.sync_batch_window).await;
+            loop {
+                match changes.try_recv() {
+                    Ok(event) => pending.add(&event.service_id, event.changed_variables),
+                    Err(TryRecvError::Lagged(_)) => self.collect_marked_changes(&mut pending).await,
+                    Err(_) => break,
+                }
+            }
+
+            self.report_pending_changes(&mut pending).await;
+        }
+    }
+
+    /// Fall back on the variables each virtual file marks as changed, after
+    /// change events were missed
+    async fn collect_marked_changes(&self, pending: &mut PendingChanges) {
+        let files: Vec<_> = self.virtual_files.read().await.values().cloned().collect();
+        for vf in files {
+            pending.add(vf.service_id(), vf.get_changed_variables().await);
+        }
+    }
+
+    /// Report every pending batch, keeping those that may succeed after a
+    /// reconnect
+    async fn report_pending_changes(&self, pending: &mut PendingChanges) {
+        for (file_id, variables) in pending.take() {
+            match self.report_changes(&file_id, &variables).await {
+                Ok(()) => {}
+                Err(CommyError::ConnectionLost(_) | CommyError::Timeout) => {
+                    pending.add(&file_id, variables)
+                }
+                Err(e) => {
+                    let _ = self.events.send(ClientEvent::ChangesDropped {
+                        service_id: file_id,
+                        variables,
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Report a virtual file's changed variables with their current values,
+    /// and sync its shadow copy once the server acknowledges them
+    async fn report_changes(&self, file_id: &str, variables: &[String]) -> Result<()> {
+        let Some(vf) = self.virtual_files.read().await.get(file_id).cloned() else {
+            return Ok(());
+        };
+        self.require(Feature::BatchOperations).await?;
+        let service_id = self.remote_service_id(&vf).await?;
+
+        let mut new_values = Vec::with_capacity(variables.len());
+        for variable in variables {
+            new_values.push((variable.clone(), vf.read_variable_slice(variable).await?));
+        }
+
+        let request = ClientMessage::ReportVariableChanges {
+            service_id,
+            changed_variables: variables.to_vec(),
+            new_values: new_values.clone(),
+        };
+        let timeout = self.timeouts.for_message(&request);
+        let response = await_response(self.request_once(request).await?, timeout).await?;
+
+        match response {
+            ServerMessage::VariableChangesAcknowledged { .. } => {
+                vf.sync_variables(&new_values).await
+            }
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("report_variable_changes", other)),
+        }
+    }
+
+    /// Server-side ID of the service behind a virtual file, looked up once
+    async fn remote_service_id(&self, vf: &VirtualVariableFile) -> Result<String> {
+        if let Some(service_id) = self.service_ids.read().await.get(vf.service_id()) {
+            return Ok(service_id.clone());
+        }
+
+        let service = self.get_service(vf.tenant_id(), vf.service_name()).await?;
+        self.service_ids
+            .write()
+            .await
+            .insert(vf.service_id().to_string(), service.id.clone());
+        Ok(service.id)
+    }
+
+    /// Initialize file watcher for hybrid mode (public - for testing/special cases)
+    pub async fn init_file_watcher(&self) -> Result<()> {
+        self._init_file_watcher_impl().await
+    }
+
+    /// Get or create a virtual variable file for a service
+    ///
+    /// This creates a virtual representation that works seamlessly for both:
+    /// - Local clients: Memory-mapped to actual service file
+    /// - Remote clients: In-memory buffer synced via WSS
+    ///
+    /// Which one is used follows `ClientBuilder::file_access`. Local mapping
+    /// asks the server for the service's file path, so it needs a
+    /// connection. When the server keeps no file or the file cannot be
+    /// mapped, `FileAccess::Auto` uses the remote buffer instead, while
+    /// `FileAccess::Local` returns the error. A remote buffer subscribes to
+    /// each variable registered on it, and applies the server's changes.
+    pub async fn get_virtual_service_file(
+        &self,
+        tenant_id: &str,
+        service_name: &str,
+    ) -> Result<Arc<VirtualVariableFile>> {
+        let service_id = format!("{}_{}", tenant_id, service_name);
+
+        // Check if already loaded
+        {
+            let vfiles = self.virtual_files.read().await;
+            if let Some(vf) = vfiles.get(&service_id) {
+                return Ok(Arc::clone(vf));
+            }
+        }
+
+        // Create new virtual file over the mapped service file, or a synced buffer
+        let accessor = self.open_service_file(tenant_id, service_name).await?;
+        let vf = Arc::new(VirtualVariableFile::with_accessor(
+            service_id.clone(),
+            service_name.to_string(),
+            tenant_id.to_string(),
+            accessor,
+        ));
+
+        // Another caller may have loaded the file meanwhile; keep theirs
+        let mut vfiles = self.virtual_files.write().await;
+        let entry = match vfiles.entry(service_id.clone()) {
+            Entry::Occupied(entry) => return Ok(Arc::clone(entry.get())),
+            Entry::Vacant(entry) => entry,
+        };
+
+        // Register with watcher if available
+        if let Some(watcher_guard) = self.file_watcher.read().await.as_ref() {
+            watcher_guard
+                .register_virtual_file(service_id, Arc::clone(&vf))
+                .await?;
+        }
+
+        // Keep a synced buffer up to date with the server
+        if !vf.is_local() {
+            let client = self.background_handle();
+            let registrations = vf.subscribe_registrations();
+            let events = self.events.subscribe();
+            tokio::spawn(client.run_file_updates(Arc::clone(&vf), registrations, events));
+        }
+
+        // Store in cache
+        entry.insert(Arc::clone(&vf));
+
+        Ok(vf)
+    }
+
+    /// Open the accessor for a service's variable file
+    ///
+    /// Maps the file the server names for the service when it is reachable
+    /// from this host. With `FileAccess::Auto`, falls back to an in-memory
+    /// buffer synced over the connection when the server is elsewhere, keeps
+    /// no file, or names one that cannot be mapped here; with
+    /// `FileAccess::Local`, fails instead.
+    async fn open_service_file(
+        &self,
+        tenant_id: &str,
+        service_name: &str,
+    ) -> Result<Arc<dyn FileAccessor>> {
+        match self.file_access {
+            FileAccess::Local => Ok(Arc::new(
+                self.map_service_file(tenant_id, service_name).await?,
+            )),
+            FileAccess::Auto if server_is_on_this_host(&self.server_url) => {
+                match self.map_service_file(tenant_id, service_name).await {
+                    Ok(accessor) => Ok(Arc::new(accessor)),
+                    Err(_) => Ok(Arc::new(RemoteFileAccessor::new())),
+                }
+            }
+            FileAccess::Auto | FileAccess::Remote => Ok(Arc::new(RemoteFileAccessor::new())),
+        }
+    }
+
+    /// Ask the server for a service's file and map it read-write
+    ///
+    /// A file shorter than the size the server reports is extended to it, so
+    /// every variable the server placed in it is mapped. Does not reconnect:
+    /// without a connection, there is no server to ask.
+    async fn map_service_file(
+        &self,
+        tenant_id: &str,
+        service_name: &str,
+    ) -> Result<LocalFileAccessor> {
+        let request = ClientMessage::GetServiceFilePath {
+            tenant_id: tenant_id.to_string(),
+            service_name: service_name.to_string(),
+        };
+        let timeout = self.timeouts.for_message(&request);
+        let response = await_response(self.request_once(request).await?, timeout).await?;
+
+        match response {
+            ServerMessage::ServiceFilePath {
+                file_path,
+                file_size,
+                ..
+            } => {
+                let path = std::path::PathBuf::from(file_path);
+                // The server may share a host name but not a file system
+                if !path.is_file() {
+                    return Err(CommyError::FileError(std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        format!("Service file {} not found", path.display()),
+                    )));
+                }
+                let accessor = LocalFileAccessor::new(path).await?;
+                if accessor.file_size().await? < file_size {
+                    accessor.resize(file_size).await?;
+                }
+                Ok(accessor)
+            }
+            ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+            other => Err(unexpected_response("get_service_file_path", other)),
+        }
+    }
+
+    /// Update loop of a remote virtual file, run by a background task
+    ///
+    /// Subscribes to every variable registered on the file and applies the
+    /// `VariableChanged` pushes for them. Variables that could not be
+    /// subscribed to, for instance before authenticating, are tried again
+    /// once the client authenticates or resyncs.
+    async fn run_file_updates(
+        self,
+        vf: Arc<VirtualVariableFile>,
+        mut registrations: broadcast::Receiver<String>,
+        mut events: broadcast::Receiver<ClientEvent>,
+    ) {
+        use broadcast::error::RecvError;
+
+        let mut unsubscribed: Vec<String> = Vec::new();
+        let mut subscribed = HashSet::new();
+        let mut updates: SelectAll<BoxStream<'static, (String, VariableUpdate)>> = SelectAll::new();
+        let mut owner_check = tokio::time::interval(SYNC_OWNER_CHECK_INTERVAL);
+        let mut retry = true;
+
+        // Variables registered before this task started
+        for metadata in vf.list_variables().await.unwrap_or_default() {
+            unsubscribed.push(metadata.name);
+        }
+
+        loop {
+            if retry {
+                self.subscribe_variables(&vf, &mut unsubscribed, &mut subscribed, &mut updates)
+                    .await;
+                retry = false;
+            }
+
+            tokio::select! {
+                registration = registrations.recv() => match registration {
+                    Ok(name) => {
+                        unsubscribed.push(name);
+                        retry = true;
+                    }
+                    Err(RecvError::Lagged(_)) => {
+                        for metadata in vf.list_variables().await.unwrap_or_default() {
+                            unsubscribed.push(metadata.name);
+                        }
+                        retry = true;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                Some((name, update)) = updates.next(), if !updates.is_empty() => {
+                    if let Err(e) = vf.apply_remote_update(&name, &update.data).await {
+                        let _ = self.events.send(ClientEvent::RemoteUpdateFailed {
+                            service_id: vf.service_id().to_string(),
+                            variable_name: name,
+                            reason: e.to_string(),
+                        });
+                    }
+                }
+                event = events.recv() => match event {
+                    Ok(ClientEvent::Authenticated(_) | ClientEvent::Resynced { .. }) => retry = true,
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                },
+                _ = owner_check.tick() => {
+                    if self.owner_alive.strong_count() == 0 {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Subscribe to the server's changes of a remote virtual file's
+    /// variables, leaving in `names` those to try again later
+    ///
+    /// Does nothing while disconnected, so the attempt cannot start a
+    /// reconnect of its own.
+    async fn subscribe_variables(
+        &self,
+        vf: &VirtualVariableFile,
+        names: &mut Vec<String>,
+        subscribed: &mut HashSet<String>,
+        updates: &mut SelectAll<BoxStream<'static, (String, VariableUpdate)>>,
+    ) {
+        names.retain(|name| !subscribed.contains(name));
+        if names.is_empty() || !self.is_connected().await {
+            return;
+        }
+
+        let service_id = match self.remote_service_id(vf).await {
+            Ok(service_id) => service_id,
+            Err(_) => return,
+        };
+
+        let mut failed = Vec::new();
+        for name in names.drain(..) {
+            if subscribed.contains(&name) {
+                continue;
+            }
+            match self.subscribe(&service_id, &name).await {
+                Ok(subscription) => {
+                    subscribed.insert(name.clone());
+                    updates.push(
+                        subscription
+                            .map(move |update| (name.clone(), update))
+                            .boxed(),
+                    );
+                }
+                Err(_) => failed.push(name),
+            }
+        }
+        *names = failed;
+    }
+
+    /// Start monitoring virtual files for changes (internal)
+    ///
+    /// This spawns a background task that watches for file changes
+    /// and automatically detects which variables have changed using SIMD
+    #[inline]
+    async fn _start_file_monitoring_impl(&self) -> Result<()> {
+        let watcher = self.file_watcher.read().await;
+        if watcher.is_some() {
+            // Already started in init_file_watcher
+            Ok(())
+        } else {
+            drop(watcher);
+            self._init_file_watcher_impl().await
+        }
+    }
+
+    /// Start monitoring virtual files for changes (public - for testing/special cases)
+    ///
+    /// This spawns a background task that watches for file changes
+    /// and automatically detects which variables have changed using SIMD
+    pub async fn start_file_monitoring(&self) -> Result<()> {
+        self._start_file_monitoring_impl().await
+    }
+
+    /// Get next file change event (blocks until a file changes)
+    pub async fn wait_for_file_change(&self) -> Result<Option<crate::watcher::FileChangeEvent>> {
+        let watcher = self.file_watcher.read().await;
+        if let Some(w) = watcher.as_ref() {
+            Ok(w.next_change().await)
+        } else {
+            Err(CommyError::InvalidState(
+                "File watcher not initialized. Call start_file_monitoring() first".to_string(),
+            ))
+        }
+    }
+
+    /// Try to get next file change event (non-blocking)
+    pub async fn try_get_file_change(&self) -> Result<Option<crate::watcher::FileChangeEvent>> {
+        let watcher = self.file_watcher.read().await;
+        if let Some(w) = watcher.as_ref() {
+            Ok(w.try_next_change().await)
+        } else {
+            Err(CommyError::InvalidState(
+                "File watcher not initialized. Call start_file_monitoring() first".to_string(),
+            ))
+        }
+    }
+
+    /// Stop file monitoring
+    ///
+    /// Changes not yet acknowledged by the server are no longer reported.
+    pub async fn stop_file_monitoring(&self) -> Result<()> {
+        if let Some(task) = self.sync_task.write().await.take() {
+            task.abort();
+        }
+        if let Some(watcher) = self.file_watcher.write().await.take() {
+            watcher.stop_watching().await?;
+        }
+        Ok(())
+    }
+
+    /// Inject a pre-built `Connection` into the client (for unit testing without a real server).
+    ///
+    /// Sets the connection field **and** advances `connection_state` to `Connected` so
+    /// subsequent permission guards behave correctly.
+    #[cfg(test)]
+    pub async fn inject_connection_for_test(&self, conn: Connection) {
+        let mut c = self.connection.write().await;
+        *c = Some(conn);
+        let mut state = self.state.write().await;
+        state.connection_state = ConnectionState::Connected;
+    }
+
+    /// Inject an in-memory connection wired to the client's push channel.
+    ///
+    /// Returns the mock server's ends: a sender for server messages and a
+    /// receiver for what the client sends.
+    #[cfg(test)]
+    pub async fn connect_mock_for_test(
+        &self,
+    ) -> (
+        tokio::sync::mpsc::UnboundedSender<crate::message::ServerEnvelope>,
+        tokio::sync::mpsc::UnboundedReceiver<crate::message::ClientEnvelope>,
+    ) {
+        let (transport, peer) = crate::transport::ChannelTransport::pair();
+        let conn = Connection::with_transport(Box::new(transport), self.pushes.clone());
+        self.inject_connection_for_test(conn).await;
+        (peer.to_client, peer.from_client)
+    }
+
+    /// Inject an authenticated tenant context (no real server auth required).
+    #[cfg(test)]
+    pub async fn inject_auth_for_test(&self, tenant_id: &str) {
+        let mut state = self.state.write().await;
+        state.add_auth_context(
+            tenant_id.to_string(),
+            AuthContext::new(
+                tenant_id.to_string(),
+                vec!["read".to_string(), "write".to_string()],
+            ),
+        );
+    }
+}
+
+/// Build the `Authenticate` message for a tenant
+fn authenticate_message(tenant_id: &str, credentials: AuthCredentials) -> ClientMessage {
+    ClientMessage::Authenticate {
+        tenant_id: tenant_id.to_string(),
+        client_version: env!("CARGO_PKG_VERSION").to_string(),
+        credentials,
+    }
+}
+
+/// Wait for the response to a request sent with `Connection::request`
+async fn await_response(
+    response: tokio::sync::oneshot::Receiver<ServerMessage>,
+    timeout: Duration,
+) -> Result<ServerMessage> {
+    match tokio::time::timeout(timeout, response).await {
+        Ok(Ok(message)) => Ok(message),
+        Ok(Err(_)) => Err(CommyError::ConnectionLost(
+            "Connection closed before the response arrived".to_string(),
+        )),
+        Err(_) => Err(CommyError::Timeout),
+    }
+}
+
+/// Interpret the server's answer to a `Subscribe`
+fn subscribe_result(response: ServerMessage) -> Result<()> {
+    match response {
+        ServerMessage::Result { success: true, .. } | ServerMessage::VariableData { .. } => Ok(()),
+        ServerMessage::Result { success: false, message, .. } => {
+            Err(CommyError::PermissionDenied(message))
+        }
+        ServerMessage::Error { code, .. } => Err(CommyError::from(code)),
+        other => Err(unexpected_response("subscribe", other)),
+    }
+}
+
+/// Whether a server URL points at this host, so its service files can be
+/// mapped
+fn server_is_on_this_host(server_url: &str) -> bool {
+    if unix_socket::socket_path(server_url).is_some() {
+        return true;
+    }
+
+    let Ok(uri) = server_url.parse::<Uri>() else {
+        return false;
+    };
+    match uri.host() {
+        Some(host) => {
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            host.eq_ignore_ascii_case("localhost")
+                || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+        }
+        None => false,
+    }
+}
+
+/// Build the error returned when the server answers a request with the wrong message
+fn unexpected_response(operation: &str, response: ServerMessage) -> CommyError {
+    CommyError::InvalidMessage(format!(
+        "Unexpected response to {}: {:?}",
+        operation, response
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_creation() {
+        let client = Client::new("wss://localhost:9000");
+        assert!(!client.id().is_empty());
+        assert_eq!(client.server_url(), "wss://localhost:9000");
+    }
+
+    #[test]
+    fn test_client_with_custom_id() {
+        let client = Client::with_id("wss://localhost:9000", "my_client");
+        assert_eq!(client.id(), "my_client");
+    }
+
+    #[tokio::test]
+    async fn test_is_connected_initially_false() {
+        let client = Client::new("wss://localhost:9000");
+        assert!(!client.is_connected().await);
+    }
+
+    #[tokio::test]
+    async fn test_idle_seconds() {
+        let client = Client::new("wss://localhost:9000");
+        let idle = client.idle_seconds().await;
+        assert!(idle < 2);
+    }
+
+    #[tokio::test]
+    async fn test_connection_state_initially_disconnected() {
+        let client = Client::new("wss://localhost:9000");
+        assert_eq!(client.connection_state().await, ConnectionState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_tenants_initially_empty() {
+        let client = Client::new("wss://localhost:9000");
+        let tenants = client.authenticated_tenants().await;
+        assert!(tenants.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_is_authenticated_to_false() {
+        let client = Client::new("wss://localhost:9000");
+        assert!(!client.is_authenticated_to("any_tenant").await);
+    }
+
+    #[test]
+    fn test_client_server_url() {
+        let client = Client::new("wss://example.com:9000");
+        assert_eq!(client.server_url(), "wss://example.com:9000");
+    }
+
+    // ─── File watcher methods ─────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_init_file_watcher_succeeds() {
+        let client = Client::new("wss://localhost:9000");
+        let result = client.init_file_watcher().await;
+        assert!(result.is_ok(), "init_file_watcher should succeed: {:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_start_file_monitoring_succeeds_and_is_idempotent() {
+        let client = Client::new("wss://localhost:9000");
+        // First call initialises the watcher
+        let r1 = client.start_file_monitoring().await;
+        assert!(r1.is_ok(), "first start_file_monitoring should succeed: {:?}", r1);
+        // Second call is a no-op (watcher already exists)
+        let r2 = client.start_file_monitoring().await;
+        assert!(r2.is_ok(), "second start_file_monitoring should also succeed: {:?}", r2);
+        let _ = client.stop_file_monitoring().await;
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_file_change_uninitialized_returns_err() {
+        let client = Client::new("wss://localhost:9000");
+        // Watcher not initialised — should return Err(InvalidState)
+        let result = client.wait_for_file_change().await;
+        assert!(
+            matches!(result, Err(CommyError::InvalidState(_))),
+            "expected InvalidState error, got {:?}",
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn test_try_get_file_change_uninitialized_returns_err() {
+        let client = Client::new("wss://localhost:9000");
+        let result = client.try_get_file_change().await;
+        assert!(
+            matches!(result, Err(CommyError::InvalidState(_))),
+            "expected InvalidState error when watcher not initialised, got {:?}",
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn test_try_get_file_change_initialized_returns_ok_none() {
+        let client = Client::new("wss://localhost:9000");
+        client.init_file_watcher().await.unwrap();
+        // No file changes have occurred, so the channel should be empty
+        let result = client.try_get_file_change().await;
+        assert!(
+            matches!(result, Ok(None)),
+            "expected Ok(None) with no pending events, got {:?}",
+            result
+        );
+        let _ = client.stop_file_monitoring().await;
+    }
+
+    #[tokio::test]
+    async fn test_stop_file_monitoring_clears_watcher() {
+        let client = Client::new("wss://localhost:9000");
+        client.init_file_watcher().await.unwrap();
+
+        // Watcher is live — non-blocking peek should be Ok(None)
+        assert!(matches!(client.try_get_file_change().await, Ok(None)));
+
+        // Stop the watcher
+        let stop_result = client.stop_file_monitoring().await;
+        assert!(stop_result.is_ok(), "stop_file_monitoring should succeed: {:?}", stop_result);
+
+        // After stopping, the watcher slot is None ⇒ try_get returns InvalidState
+        let after = client.try_get_file_change().await;
+        assert!(
+            matches!(after, Err(CommyError::InvalidState(_))),
+            "expected InvalidState after stopping, got {:?}",
+            after
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_virtual_service_file_creates_and_caches() {
+        let client = Client::new("wss://localhost:9000");
+
+        // First call creates the virtual file
+        let vf1 = client
+            .get_virtual_service_file("tenant_a", "my_service")
+            .await
+            .expect("should create virtual service file");
+
+        // Second call with same args returns the cached instance (same Arc pointer)
+        let vf2 = client
+            .get_virtual_service_file("tenant_a", "my_service")
+            .await
+            .expect("should return cached virtual service file");
+
+        assert!(Arc::ptr_eq(&vf1, &vf2), "second call should return the cached VirtualVariableFile");
+
+        // Different service name → different file
+        let vf3 = client
+            .get_virtual_service_file("tenant_a", "other_service")
+            .await
+            .expect("should create a separate virtual service file");
+
+        assert!(!Arc::ptr_eq(&vf1, &vf3), "different service should be a separate file");
+    }
+
+    // ─────────────────────────────────────────────────────────────
+    // Auth pre-check guard tests
+    // ─────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_create_service_not_authenticated_returns_permission_denied() {
+        let client = Client::new("wss://localhost:9999");
+        // No authentication → must fail immediately with PermissionDenied
+        let result = client.create_service("tenant_a", "my_service").await;
+        assert!(
+            result.is_err(),
+            "create_service must fail when not authenticated"
+        );
+        match result.unwrap_err() {
+            crate::error::CommyError::PermissionDenied(_) => {}
+            e => panic!("Expected PermissionDenied, got {:?}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_service_not_authenticated_returns_permission_denied() {
+        let client = Client::new("wss://localhost:9999");
+        let result = client.get_service("tenant_a", "my_service").await;
+        assert!(
+            result.is_err(),
+            "get_service must fail when not authenticated"
+        );
+        match result.unwrap_err() {
+            crate::error::CommyError::PermissionDenied(_) => {}
+            e => panic!("Expected PermissionDenied, got {:?}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delete_service_not_authenticated_returns_permission_denied() {
+        let client = Client::new("wss://localhost:9999");
+        let result = client.delete_service("tenant_a", "my_service").await;
+        assert!(
+            result.is_err(),
+            "delete_service must fail when not authenticated"
+        );
+        match result.unwrap_err() {
+            crate::error::CommyError::PermissionDenied(_) => {}
+            e => panic!("Expected PermissionDenied, got {:?}", e),
+        }
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Bug #14 regression tests: server Error response must NOT be silently
+    // converted to Timeout.  These verify the match-based response handlers.
+    // ─────────────────────────────────────────────────────────────────────────
+
+    /// Helper: a connection over an in-process channel, with the mock
+    /// server's ends (a sender for server messages and a receiver for what
+    /// the client sends).
+    fn in_memory_connection() -> (
+        Connection,
+        tokio::sync::mpsc::UnboundedSender<crate::message::ServerEnvelope>,
+        tokio::sync::mpsc::UnboundedReceiver<crate::message::ClientEnvelope>,
+    ) {
+        let (transport, peer) = crate::transport::ChannelTransport::pair();
+        (Connection::from_transport(Box::new(transport)), peer.to_client, peer.from_client)
+    }
+
+    /// Helper: answers the next request the client sends with `response`,
+    /// echoing the request ID the way a real server would.
+    fn reply_to_next_request(
+        mut client_rx: tokio::sync::mpsc::UnboundedReceiver<crate::message::ClientEnvelope>,
+        server_tx: tokio::sync::mpsc::UnboundedSender<crate::message::ServerEnvelope>,
+        response: crate::message::ServerMessage,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            if let Some(request) = client_rx.recv().await {
+                let _ = server_tx.send(crate::message::ServerEnvelope {
+                    request_id: request.request_id,
+                    message: response,
+                });
+            }
+        })
+    }
+
+    /// Helper: creates a Client with injected mock connection that answers
+    /// the next request with `response`.
+    /// Returns `(client, _responder)` — the responder task owns the mock
+    /// server side of the connection.
+    async fn setup_client_with_mock_response(
+        tenant_id: &str,
+        response: crate::message::ServerMessage,
+    ) -> (Client, tokio::task::JoinHandle<()>) {
+        let client = Client::new("wss://test");
+        let (conn, server_tx, client_rx) = in_memory_connection();
+        let responder = reply_to_next_request(client_rx, server_tx, response);
+        client.inject_auth_for_test(tenant_id).await;
+        client.inject_connection_for_test(conn).await;
+        (client, responder)
+    }
+
+    /// #14: create_service receiving Error{NotFound} must return Err(NotFound),
+    /// NOT Err(Timeout) as was the bug.
+    #[tokio::test]
+    async fn test_create_service_server_error_returns_not_found_not_timeout() {
+        let err_response = crate::message::ServerMessage::Error {
+            code: crate::message::ErrorCode::NotFound,
+            message: "service creation failed: not found".to_string(),
+        };
+        let (client, _responder) = setup_client_with_mock_response("tenant_a", err_response).await;
+        let result = client.create_service("tenant_a", "missing_svc").await;
+        assert!(result.is_err(), "create_service must fail on Error response");
+        match result.unwrap_err() {
+            CommyError::NotFound(_) => {} // Correct: server error is properly translated
+            CommyError::Timeout => panic!(
+                "Bug #14 regressed: server Error{{NotFound}} was silently converted to Timeout"
+            ),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    /// #14: get_service receiving Error{PermissionDenied} must return Err(PermissionDenied).
+    #[tokio::test]
+    async fn test_get_service_server_error_returns_permission_denied_not_timeout() {
+        let err_response = crate::message::ServerMessage::Error {
+            code: crate::message::ErrorCode::PermissionDenied,
+            message: "insufficient permissions".to_string(),
+        };
+        let (client, _responder) = setup_client_with_mock_response("tenant_b", err_response).await;
+        let result = client.get_service("tenant_b", "svc").await;
+        assert!(result.is_err());
+        match result.unwrap_err() {
+            CommyError::PermissionDenied(_) => {}
+            CommyError::Timeout => panic!(
+                "Bug #14 regressed: server Error{{PermissionDenied}} was silently converted to Timeout"
+            ),
+            e => panic!("Unexpected error variant: {:?}", e),
+        }
+    }
+
+    /// #14: delete_service receiving Error{AlreadyExists} (a non-success error)
+    /// must return Err(AlreadyExists), NOT Err(Timeout).
+    #[tokio::test]
+    async fn test_delete_service_server_error_returns_proper_error_not_timeout() {
+        let err_response = crate::message::ServerMessage::Error {
+            code: crate::message::ErrorCode::AlreadyExists,
+            message: "unexpected".to_string(),
+        };
+        let (client, _responder) = setup_client_with_mock_response("tenant_c", err_response).await;
+        let result = client.delete_service("tenant_c", "svc").await;
+        assert!(result.is_err());
+        match result.unwrap_err() {
+            CommyError::AlreadyExists(_) => {}
+            CommyError::Timeout => panic!(
+                "Bug #14 regressed: server Error{{AlreadyExists}} was silently converted to Timeout"
+            ),
+            e => panic!("Unexpected error variant: {:?}", e),
+        }
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Variable allocation
+    // ─────────────────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_allocate_variable_sends_initial_data() {
+        let client = Client::new("wss://test");
+        let (conn, server_tx, mut client_rx) = in_memory_connection();
+        client.inject_connection_for_test(conn).await;
+
+        let server = tokio::spawn(async move {
+            let request = client_rx.recv().await.expect("client closed");
+            server_tx
+                .send(crate::message::ServerEnvelope::reply(
+                    request.request_id.clone().expect("request must carry an ID"),
+                    crate::message::ServerMessage::Result {
+                        request_id: String::new(),
+                        success: true,
+                        message: "allocated".to_string(),
+                    },
+                ))
+                .unwrap();
+            request.message
+        });
+
+        client
+            .allocate_variable("svc", "counter", vec![0, 0, 0, 1])
+            .await
+            .unwrap();
+        match server.await.unwrap() {
+            ClientMessage::AllocateVariable {
+                service_id,
+                variable_name,
+                initial_data,
+            } => {
+                assert_eq!(service_id, "svc");
+                assert_eq!(variable_name, "counter");
+                assert_eq!(initial_data, vec![0, 0, 0, 1]);
+            }
+            other => panic!("Expected AllocateVariable, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_allocate_variable_accepts_variable_data_reply() {
+        let response = crate::message::ServerMessage::VariableData {
+            service_id: "svc".to_string(),
+            variable_name: "counter".to_string(),
+            data: vec![7],
+            version: 0,
+        };
+        let (client, _responder) = setup_client_with_mock_response("tenant_a", response).await;
+        assert!(client.allocate_variable("svc", "counter", vec![7]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_allocate_variable_existing_returns_already_exists() {
+        let err_response = crate::message::ServerMessage::Error {
+            code: crate::message::ErrorCode::AlreadyExists,
+            message: "variable exists".to_string(),
+        };
+        let (client, _responder) = setup_client_with_mock_response("tenant_a", err_response).await;
+        match client.allocate_variable("svc", "counter", vec![]).await {
+            Err(CommyError::AlreadyExists(_)) => {}
+            other => panic!("Expected AlreadyExists, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deallocate_variable_missing_returns_not_found() {
+        let err_response = crate::message::ServerMessage::Error {
+            code: crate::message::ErrorCode::NotFound,
+            message: "no such variable".to_string(),
+        };
+        let (client, _responder) = setup_client_with_mock_response("tenant_a", err_response).await;
+        match client.deallocate_variable("svc", "counter").await {
+            Err(CommyError::NotFound(_)) => {}
+            other => panic!("Expected NotFound, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deallocate_variable_rejected_returns_permission_denied() {
+        let response = crate::message::ServerMessage::Result {
+            request_id: String::new(),
+            success: false,
+            message: "read-only service".to_string(),
+        };
+        let (client, _responder) = setup_client_with_mock_response("tenant_a", response).await;
+        match client.deallocate_variable("svc", "counter").await {
+            Err(CommyError::PermissionDenied(message)) => assert_eq!(message, "read-only service"),
+            other => panic!("Expected PermissionDenied, got {:?}", other),
+        }
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Acknowledged writes
+    // ─────────────────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_write_variable_returns_new_version() {
+        let response = crate::message::ServerMessage::VariableWritten {
+            service_id: "svc".to_string(),
+            variable_name: "counter".to_string(),
+            version: 12,
+        };
+        let (client, _responder) = setup_client_with_mock_response("tenant_a", response).await;
+        let version = client.write_variable("svc", "counter", vec![1]).await.unwrap();
+        assert_eq!(version, 12);
+    }
+
+    #[tokio::test]
+    async fn test_write_variable_surfaces_server_error() {
+        let err_response = crate::message::ServerMessage::Error {
+            code: crate::message::ErrorCode::InvalidRequest,
+            message: "size mismatch".to_string(),
+        };
+        let (client, _responder) = setup_client_with_mock_response("tenant_a", err_response).await;
+        match client.write_variable("svc", "counter", vec![1, 2, 3]).await {
+            Err(CommyError::InvalidRequest(_)) => {}
+            other => panic!("Expected InvalidRequest, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_variable_without_version_is_invalid_message() {
+        let response = crate::message::ServerMessage::Result {
+            request_id: String::new(),
+            success: true,
+            message: "ok".to_string(),
+        };
+        let (client, _responder) = setup_client_with_mock_response("tenant_a", response).await;
+        match client.write_variable("svc", "counter", vec![1]).await {
+            Err(CommyError::InvalidMessage(_)) => {}
+            other => panic!("Expected InvalidMessage, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compare_and_swap_sends_expected_version() {
+        let client = Client::new("wss://test");
+        let (conn, server_tx, mut client_rx) = in_memory_connection();
+        client.inject_connection_for_test(conn).await;
+
+        let server = tokio::spawn(async move {
+            let request = client_rx.recv().await.expect("client closed");
+            server_tx
+                .send(crate::message::ServerEnvelope::reply(
+                    request.request_id.clone().expect("request must carry an ID"),
+                    crate::message::ServerMessage::VariableWritten {
+                        service_id: "svc".to_string(),
+                        variable_name: "counter".to_string(),
+                        version: 6,
+                    },
+                ))
+                .unwrap();
+            request.message
+        });
+
+        let version = client
+            .compare_and_swap("svc", "counter", 5, vec![2])
+            .await
+            .unwrap();
+        assert_eq!(version, 6);
+        match server.await.unwrap() {
+            ClientMessage::CompareAndSwap {
+                expected_version,
+                data,
+                ..
+            } => {
+                assert_eq!(expected_version, 5);
+                assert_eq!(data, vec![2]);
+            }
+            other => panic!("Expected CompareAndSwap, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compare_and_swap_conflict_returns_current_value() {
+        let response = crate::message::ServerMessage::VersionConflict {
+            service_id: "svc".to_string(),
+            variable_name: "counter".to_string(),
+            current_version: 8,
+            current_data: vec![4, 4],
+        };
+        let (client, _responder) = setup_client_with_mock_response("tenant_a", response).await;
+        match client.compare_and_swap("svc", "counter", 5, vec![2]).await {
+            Err(CommyError::Conflict {
+                current_version,
+                current_data,
+            }) => {
+                assert_eq!(current_version, 8);
+                assert_eq!(current_data, vec![4, 4]);
+            }
+            other => panic!("Expected Conflict, got {:?}", other),
+        }
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Resync after reconnect
+    // ─────────────────────────────────────────────────────────────────────────
+
+    fn authentication_result(success: bool) -> ServerMessage {
+        ServerMessage::AuthenticationResult {
+            success,
+            message: if success { "ok" } else { "key revoked" }.to_string(),
+            server_version: "0.1.0".to_string(),
+            permissions: Some(vec!["read".to_string()]),
+        }
+    }
+
+    /// Authenticate `client` to `tenant_id` over a fresh mock connection
+    async fn authenticate_over_mock(client: &Client, tenant_id: &str) {
+        let (server_tx, client_rx) = client.connect_mock_for_test().await;
+        let _responder = reply_to_next_request(client_rx, server_tx, authentication_result(true));
+        client
+            .authenticate(tenant_id, crate::auth::api_key(format!("{}_key", tenant_id)))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resync_replays_authentication_and_subscriptions() {
+        use futures::StreamExt;
+
+        let client = Client::new("wss://test");
+        authenticate_over_mock(&client, "tenant_a").await;
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        let (subscription, _) = tokio::join!(
+            client.subscribe("svc", "counter"),
+            acknowledge_next_request(&mut client_rx, &server_tx)
+        );
+        let _subscription = subscription.unwrap();
+
+        // A new connection knows nothing about the old session
+        let mut events = client.events();
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        let server = tokio::spawn(async move {
+            let auth = client_rx.recv().await.expect("client closed");
+            match &auth.message {
+                ClientMessage::Authenticate {
+                    tenant_id,
+                    credentials: AuthCredentials::ApiKey { key },
+                    ..
+                } => {
+                    assert_eq!(tenant_id, "tenant_a");
+                    assert_eq!(key, "tenant_a_key");
+                }
+                other => panic!("Expected Authenticate, got {:?}", other),
+            }
+            server_tx
+                .send(crate::message::ServerEnvelope::reply(
+                    auth.request_id.unwrap(),
+                    authentication_result(true),
+                ))
+                .unwrap();
+
+            acknowledge_next_request(&mut client_rx, &server_tx)
+                .await
+                .message
+        });
+
+        client.resync().await.unwrap();
+        match server.await.unwrap() {
+            ClientMessage::Subscribe {
+                service_id,
+                variable_name,
+            } => {
+                assert_eq!(service_id, "svc");
+                assert_eq!(variable_name, "counter");
+            }
+            other => panic!("Expected Subscribe, got {:?}", other),
+        }
+        assert_eq!(
+            events.next().await,
+            Some(ClientEvent::Authenticated("tenant_a".to_string()))
+        );
+        assert_eq!(
+            events.next().await,
+            Some(ClientEvent::Resynced {
+                tenants: vec!["tenant_a".to_string()],
+                subscriptions: vec![("svc".to_string(), "counter".to_string())],
+            })
+        );
+        assert!(client.is_authenticated_to("tenant_a").await);
+    }
+
+    #[tokio::test]
+    async fn test_resync_forgets_tenant_whose_credentials_are_rejected() {
+        let client = Client::new("wss://test");
+        authenticate_over_mock(&client, "tenant_a").await;
+
+        let (server_tx, client_rx) = client.connect_mock_for_test().await;
+        let _responder = reply_to_next_request(client_rx, server_tx, authentication_result(false));
+
+        client.resync().await.unwrap();
+        assert!(!client.is_authenticated_to("tenant_a").await);
+        assert!(client.credentials.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resync_keeps_credentials_after_server_error() {
+        use futures::StreamExt;
+
+        let client = Client::new("wss://test");
+        authenticate_over_mock(&client, "tenant_a").await;
+
+        let mut events = client.events();
+        let (server_tx, client_rx) = client.connect_mock_for_test().await;
+        let response = ServerMessage::Error {
+            code: crate::message::ErrorCode::InternalError,
+            message: "database unavailable".to_string(),
+        };
+        let _responder = reply_to_next_request(client_rx, server_tx, response);
+
+        client.resync().await.unwrap();
+        assert!(matches!(
+            events.next().await,
+            Some(ClientEvent::ReauthenticationFailed { ref tenant_id, .. }) if tenant_id == "tenant_a"
+        ));
+        assert!(!client.is_authenticated_to("tenant_a").await);
+        assert_eq!(client.credentials.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resync_reports_only_accepted_subscriptions() {
+        use futures::StreamExt;
+
+        let client = Client::new("wss://test");
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        let mut subscriptions = Vec::new();
+        for variable in ["open", "secret"] {
+            let (subscription, _) = tokio::join!(
+                client.subscribe("svc", variable),
+                acknowledge_next_request(&mut client_rx, &server_tx)
+            );
+            subscriptions.push(subscription.unwrap());
+        }
+
+        let mut events = client.events();
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        tokio::spawn(async move {
+            while let Some(request) = client_rx.recv().await {
+                let ClientMessage::Subscribe { variable_name, .. } = &request.message else {
+                    continue;
+                };
+                let request_id = request.request_id.clone().unwrap_or_default();
+                let _ = server_tx.send(crate::message::ServerEnvelope::reply(
+                    request_id.clone(),
+                    ServerMessage::Result {
+                        request_id,
+                        success: variable_name == "open",
+                        message: String::new(),
+                    },
+                ));
+            }
+        });
+
+        client.resync().await.unwrap();
+        assert!(matches!(
+            events.next().await,
+            Some(ClientEvent::ResubscriptionFailed { ref variable_name, .. }) if variable_name == "secret"
+        ));
+        assert_eq!(
+            events.next().await,
+            Some(ClientEvent::Resynced {
+                tenants: vec![],
+                subscriptions: vec![("svc".to_string(), "open".to_string())],
+            })
+        );
+        // Still wanted, so the next reconnect tries again
+        assert_eq!(client.subscriptions.active().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_resync_stops_on_timeout_and_keeps_credentials() {
+        let client = Client::builder("wss://test")
+            .authenticate_timeout(Duration::from_millis(50))
+            .build();
+        authenticate_over_mock(&client, "tenant_a").await;
+
+        let (_server_tx, _client_rx) = client.connect_mock_for_test().await;
+        let result = client.resync().await;
+        assert!(matches!(result, Err(CommyError::Timeout)), "{:?}", result);
+        assert_eq!(client.credentials.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_forgets_credentials() {
+        let client = Client::new("wss://test");
+        authenticate_over_mock(&client, "tenant_a").await;
+        assert_eq!(client.credentials.read().await.len(), 1);
+
+        let (_server_tx, _client_rx) = client.connect_mock_for_test().await;
+        client.disconnect().await.unwrap();
+        assert!(client.credentials.read().await.is_empty());
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Builder policies
+    // ─────────────────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_read_timeout_from_builder_is_applied() {
+        let client = Client::builder("wss://test")
+            .read_timeout(Duration::from_millis(50))
+            .build();
+        let (_server_tx, _client_rx) = client.connect_mock_for_test().await;
+
+        let started = std::time::Instant::now();
+        let result = client.read_variable("svc", "counter").await;
+        assert!(matches!(result, Err(CommyError::Timeout)), "{:?}", result);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_policy_fails_without_reconnecting() {
+        let client = Client::builder("wss://127.0.0.1:1")
+            .retry_policy(crate::retry::NoRetry)
+            .build();
+
+        let started = std::time::Instant::now();
+        let result = client.read_variable("svc", "counter").await;
+        assert!(matches!(result, Err(CommyError::ConnectionLost(_))), "{:?}", result);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_zero_reconnect_attempts_fails_without_reconnecting() {
+        let client = Client::builder("wss://127.0.0.1:1")
+            .retry_policy(crate::retry::FixedDelay::new(Duration::from_secs(30)))
+            .max_reconnect_attempts(0)
+            .build();
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(1),
+            client.read_variable("svc", "counter"),
+        )
+        .await
+        .expect("must not wait for the retry delay");
+        assert!(matches!(result, Err(CommyError::ConnectionLost(_))), "{:?}", result);
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Capability handshake
+    // ─────────────────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_handshake_stores_server_capabilities() {
+        let client = Client::new("wss://test");
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        let capabilities = Capabilities {
+            compare_and_swap: false,
+            ..Capabilities::client()
+        };
+        let welcome = ServerMessage::Welcome {
+            server_version: "2.0.0".to_string(),
+            capabilities: capabilities.clone(),
+        };
+        let server = tokio::spawn(async move {
+            let hello = client_rx.recv().await.expect("client closed");
+            assert!(matches!(hello.message, ClientMessage::Hello { .. }));
+            server_tx
+                .send(crate::message::ServerEnvelope::reply(
+                    hello.request_id.unwrap(),
+                    welcome,
+                ))
+                .unwrap();
+            client_rx
+        });
+
+        client.negotiate_capabilities().await;
+        let _client_rx = server.await.unwrap();
+
+        assert_eq!(client.server_capabilities().await, Some(capabilities));
+        assert_eq!(
+            client.state.read().await.server_version.as_deref(),
+            Some("2.0.0")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejected_handshake_means_legacy_server() {
+        let client = Client::new("wss://test");
+        let (server_tx, client_rx) = client.connect_mock_for_test().await;
+        let _responder = reply_to_next_request(
+            client_rx,
+            server_tx,
+            ServerMessage::Error {
+                code: crate::message::ErrorCode::InvalidRequest,
+                message: "unknown message type".to_string(),
+            },
+        );
+
+        client.negotiate_capabilities().await;
+
+        assert_eq!(client.server_capabilities().await, Some(Capabilities::legacy()));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_feature_fails_before_sending() {
+        let client = Client::new("wss://test");
+        let (_server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        {
+            let mut state = client.state.write().await;
+            state.server_version = Some("1.4.0".to_string());
+            state.server_capabilities = Some(Capabilities::legacy());
+        }
+
+        let result = client.compare_and_swap("svc", "counter", 1, vec![2]).await;
+
+        match result {
+            Err(err @ CommyError::Unsupported { .. }) => {
+                assert_eq!(
+                    err.to_string(),
+                    "Server 1.4.0 does not support compare-and-swap"
+                );
+            }
+            other => panic!("Expected Unsupported, got {:?}", other),
+        }
+        assert!(client_rx.try_recv().is_err(), "nothing should be sent");
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_handshake_is_not_repeated() {
+        let client = Client::builder("wss://test")
+            .connect_timeout(Duration::from_millis(50))
+            .build();
+        let (_server_tx, mut client_rx) = client.connect_mock_for_test().await;
+
+        client.negotiate_capabilities().await;
+        let hello = client_rx.recv().await.expect("client closed");
+        assert!(matches!(hello.message, ClientMessage::Hello { .. }));
+        assert_eq!(client.server_capabilities().await, Some(Capabilities::legacy()));
+
+        client.state.write().await.server_capabilities = None;
+        client.negotiate_capabilities().await;
+        assert!(client_rx.try_recv().is_err(), "no second hello");
+        assert_eq!(client.server_capabilities().await, Some(Capabilities::legacy()));
+    }
+
+    #[tokio::test]
+    async fn test_legacy_server_gates_every_feature_the_client_uses() {
+        let (client, vf, _server_tx, mut client_rx) = client_with_changed_variable().await;
+        client.state.write().await.server_capabilities = Some(Capabilities::legacy());
+
+        assert!(matches!(
+            client.report_changes(vf.service_id(), &["v".to_string()]).await,
+            Err(CommyError::Unsupported {
+                feature: Feature::BatchOperations,
+                ..
+            })
+        ));
+        assert!(client_rx.try_recv().is_err(), "nothing should be sent");
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_forgets_server_capabilities() {
+        let client = Client::new("wss://test");
+        let (_server_tx, _client_rx) = client.connect_mock_for_test().await;
+        client.state.write().await.server_capabilities = Some(Capabilities::legacy());
+
+        client.disconnect().await.unwrap();
+
+        assert_eq!(client.server_capabilities().await, None);
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Custom transports
+    // ─────────────────────────────────────────────────────────────────────────
+
+    fn channel_client() -> (Client, tokio::sync::mpsc::UnboundedReceiver<crate::transport::ChannelPeer>) {
+        let (factory, peers) = crate::transport::ChannelFactory::new();
+        let client = Client::builder("memory://test")
+            .transport(factory)
+            .capability_handshake(false)
+            .heartbeat_interval(Duration::ZERO)
+            .build();
+        (client, peers)
+    }
+
+    #[tokio::test]
+    async fn test_connect_through_transport_factory() {
+        let (client, mut peers) = channel_client();
+        client.connect().await.unwrap();
+        assert!(client.is_connected().await);
+
+        let mut peer = peers.recv().await.unwrap();
+        tokio::spawn(async move {
+            while let Some(request) = peer.from_client.recv().await {
+                let _ = peer.to_client.send(crate::message::ServerEnvelope {
+                    request_id: request.request_id,
+                    message: ServerMessage::Heartbeat {
+                        timestamp: "now".to_string(),
+                    },
+                });
+            }
+        });
+
+        client.heartbeat().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_each_connect_opens_a_new_transport() {
+        let (client, mut peers) = channel_client();
+        let mut events = client.events();
+
+        client.connect().await.unwrap();
+        drop(peers.recv().await.unwrap());
+        assert_eq!(next_event(&mut events).await, ClientEvent::Connecting);
+        assert_eq!(next_event(&mut events).await, ClientEvent::Connected);
+        assert_eq!(
+            next_event(&mut events).await,
+            ClientEvent::Disconnected("connection closed by server".to_string())
+        );
+
+        client.connect().await.unwrap();
+        assert!(peers.recv().await.is_some());
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Lifecycle events
+    // ─────────────────────────────────────────────────────────────────────────
+
+    async fn next_event(events: &mut ClientEvents) -> ClientEvent {
+        use futures::StreamExt;
+
+        tokio::time::timeout(Duration::from_secs(2), events.next())
+            .await
+            .expect("event should arrive")
+            .expect("event stream ended")
+    }
+
+    #[tokio::test]
+    async fn test_failed_connect_reports_connecting_then_disconnected() {
+        let client = Client::builder("wss://127.0.0.1:1")
+            .connect_timeout(Duration::from_secs(1))
+            .build();
+        let mut events = client.events();
+
+        assert!(client.connect().await.is_err());
+        assert_eq!(next_event(&mut events).await, ClientEvent::Connecting);
+        match next_event(&mut events).await {
+            ClientEvent::Disconnected(reason) => assert!(reason.starts_with("connect failed")),
+            other => panic!("Expected Disconnected, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_reports_attempt_number() {
+        let client = Client::builder("wss://127.0.0.1:1")
+            .retry_policy(crate::retry::FixedDelay::new(Duration::from_millis(1)))
+            .max_reconnect_attempts(1)
+            .build();
+        let mut events = client.events();
+
+        assert!(client.read_variable("svc", "counter").await.is_err());
+        assert_eq!(next_event(&mut events).await, ClientEvent::Reconnecting(1));
+        assert_eq!(next_event(&mut events).await, ClientEvent::Connecting);
+    }
+
+    #[tokio::test]
+    async fn test_closed_socket_marks_client_disconnected() {
+        let client = Client::new("wss://test");
+        let (server_tx, _client_rx) = client.connect_mock_for_test().await;
+        client.watch_connection(client.connection.read().await.as_ref().unwrap());
+        let mut events = client.events();
+
+        drop(server_tx);
+
+        assert_eq!(
+            next_event(&mut events).await,
+            ClientEvent::Disconnected("connection closed by server".to_string())
+        );
+        assert!(!client.is_connected().await);
+        assert_eq!(client.connection_state().await, ConnectionState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn test_server_disconnect_push_is_reported() {
+        let client = Client::new("wss://test");
+        let (server_tx, _client_rx) = client.connect_mock_for_test().await;
+        client.watch_connection(client.connection.read().await.as_ref().unwrap());
+        let mut events = client.events();
+
+        server_tx
+            .send(
+                ServerMessage::Disconnected {
+                    reason: "server shutdown".to_string(),
+                }
+                .into(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            next_event(&mut events).await,
+            ClientEvent::ServerDisconnected("server shutdown".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_message_is_reported() {
+        let client = Client::new("wss://test");
+        let (server_tx, _client_rx) = client.connect_mock_for_test().await;
+        client.watch_connection(client.connection.read().await.as_ref().unwrap());
+        let mut events = client.events();
+
+        let raw = serde_json::json!({ "type": "QuotaWarning", "data": { "used": 95 } });
+        server_tx
+            .send(ServerMessage::Unknown(raw.clone()).into())
+            .unwrap();
+
+        match next_event(&mut events).await {
+            ClientEvent::UnknownMessage { message_type, raw: text } => {
+                assert_eq!(message_type, "QuotaWarning");
+                assert_eq!(serde_json::from_str::<serde_json::Value>(&text).unwrap(), raw);
+            }
+            other => panic!("Expected UnknownMessage, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replaced_connection_closing_is_not_reported() {
+        let client = Client::new("wss://test");
+        let (old_server_tx, _old_client_rx) = client.connect_mock_for_test().await;
+        client.watch_connection(client.connection.read().await.as_ref().unwrap());
+        let (_server_tx, _client_rx) = client.connect_mock_for_test().await;
+
+        drop(old_server_tx);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(client.is_connected().await);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_reports_event() {
+        let client = Client::new("wss://test");
+        let (_server_tx, _client_rx) = client.connect_mock_for_test().await;
+        let mut events = client.events();
+
+        client.disconnect().await.unwrap();
+
+        assert_eq!(
+            next_event(&mut events).await,
+            ClientEvent::Disconnected("client disconnected".to_string())
+        );
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Background heartbeat
+    // ─────────────────────────────────────────────────────────────────────────
+
+    fn heartbeat_test_client() -> Client {
+        Client::builder("wss://127.0.0.1:1")
+            .heartbeat_interval(Duration::from_millis(20))
+            .heartbeat_timeout(Duration::from_millis(20))
+            .max_missed_heartbeats(2)
+            .retry_policy(crate::retry::FixedDelay::new(Duration::from_millis(1)))
+            .max_reconnect_attempts(1)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_missed_heartbeats_mark_connection_dead_and_reconnect() {
+        let client = heartbeat_test_client();
+        let (_server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        client.start_heartbeat_task().await;
+
+        // The silent server sees the heartbeats but never answers them
+        for _ in 0..2 {
+            let envelope = tokio::time::timeout(Duration::from_secs(2), client_rx.recv())
+                .await
+                .expect("heartbeat should be sent")
+                .unwrap();
+            assert!(matches!(envelope.message, ClientMessage::Heartbeat { .. }));
+        }
+
+        // The only reconnect attempt fails, so the task gives up and ends
+        let task = client.heartbeat_task.write().await.take().unwrap();
+        tokio::time::timeout(Duration::from_secs(2), task)
+            .await
+            .expect("heartbeat task should give up")
+            .unwrap();
+
+        assert_eq!(client.reconnect_attempts.load(Ordering::SeqCst), 1);
+        assert!(!client.is_connected().await);
+        assert_eq!(client.connection_state().await, ConnectionState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn test_answered_heartbeats_keep_connection() {
+        let client = heartbeat_test_client();
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        client.start_heartbeat_task().await;
+
+        for _ in 0..5 {
+            let envelope = tokio::time::timeout(Duration::from_secs(2), client_rx.recv())
+                .await
+                .expect("heartbeat should be sent")
+                .unwrap();
+            assert!(matches!(envelope.message, ClientMessage::Heartbeat { .. }));
+            server_tx
+                .send(crate::message::ServerEnvelope {
+                    request_id: envelope.request_id,
+                    message: ServerMessage::Heartbeat {
+                        timestamp: "now".to_string(),
+                    },
+                })
+                .unwrap();
+        }
+
+        assert!(client.is_connected().await);
+        assert_eq!(client.reconnect_attempts.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_zero_heartbeat_interval_disables_task() {
+        let client = Client::builder("wss://test")
+            .heartbeat_interval(Duration::ZERO)
+            .build();
+        client.start_heartbeat_task().await;
+        assert!(client.heartbeat_task.read().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dropping_client_stops_heartbeat_task() {
+        let client = heartbeat_test_client();
+        let (_server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        client.start_heartbeat_task().await;
+        let task = client.heartbeat_task.write().await.take().unwrap();
+        drop(client);
+
+        tokio::time::timeout(Duration::from_secs(2), task)
+            .await
+            .expect("heartbeat task should stop")
+            .unwrap();
+        // Nothing keeps the connection alive any more
+        while client_rx.recv().await.is_some() {}
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Authentication edge-case tests
+    // ─────────────────────────────────────────────────────────────────────────
+
+    /// #2 (SDK): authenticate where server responds with success=false must
+    /// return Err(AuthenticationFailed), not Err(Timeout).
+    #[tokio::test]
+    async fn test_authenticate_success_false_returns_auth_failed() {
+        let client = Client::new("wss://test");
+        let (conn, server_tx, client_rx) = in_memory_connection();
+        let _responder = reply_to_next_request(
+            client_rx,
+            server_tx,
+            crate::message::ServerMessage::AuthenticationResult {
+                success: false,
+                message: "invalid credentials".to_string(),
+                server_version: "0.1.0".to_string(),
+                permissions: None,
+            },
+        );
+        client.inject_connection_for_test(conn).await;
+
+        let creds = crate::message::AuthCredentials::ApiKey {
+            key: "bad_key".to_string(),
+        };
+        let result = client.authenticate("tenant_a", creds).await;
+
+        assert!(result.is_err(), "authenticate must fail when success=false");
+        match result.unwrap_err() {
+            CommyError::AuthenticationFailed(msg) => {
+                assert!(
+                    msg.contains("invalid credentials"),
+                    "Error message should propagate: {}",
+                    msg
+                );
+            }
+            CommyError::Timeout => panic!(
+                "authenticate success=false was silently converted to Timeout"
+            ),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    /// authenticate receiving Error{Unauthorized} returns Err(Unauthorized).
+    #[tokio::test]
+    async fn test_authenticate_error_response_returns_unauthorized() {
+        let client = Client::new("wss://test");
+        let (conn, server_tx, client_rx) = in_memory_connection();
+        let _responder = reply_to_next_request(
+            client_rx,
+            server_tx,
+            crate::message::ServerMessage::Error {
+                code: crate::message::ErrorCode::Unauthorized,
+                message: "bad token".to_string(),
+            },
+        );
+        client.inject_connection_for_test(conn).await;
+
+        let creds = crate::message::AuthCredentials::Jwt {
+            token: "expired-token".to_string(),
+        };
+        let result = client.authenticate("tenant_a", creds).await;
+        assert!(result.is_err());
+        assert!(
+            matches!(result.unwrap_err(), CommyError::Unauthorized(_)),
+            "Expected Unauthorized"
+        );
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Connection lifecycle tests
+    // ─────────────────────────────────────────────────────────────────────────
+
+    /// #5: disconnect() must clear both the connection and all auth contexts.
+    #[tokio::test]
+    async fn test_disconnect_clears_connection_and_auth() {
+        let client = Client::new("wss://test");
+        let (conn, _server_tx, _client_rx) = in_memory_connection();
+        client.inject_auth_for_test("tenant_a").await;
+        client.inject_connection_for_test(conn).await;
+
+        // Preconditions
+        assert!(client.is_connected().await, "must be connected before disconnect");
+        assert!(
+            client.is_authenticated_to("tenant_a").await,
+            "must be authenticated before disconnect"
+        );
+
+        let result = client.disconnect().await;
+        assert!(result.is_ok(), "disconnect should succeed: {:?}", result);
+
+        // Post-conditions
+        assert!(!client.is_connected().await, "connection must be cleared after disconnect");
+        assert!(
+            !client.is_authenticated_to("tenant_a").await,
+            "auth must be cleared after disconnect"
+        );
+        assert!(
+            client.authenticated_tenants().await.is_empty(),
+            "all auth contexts must be removed after disconnect"
+        );
+        assert_eq!(
+            client.connection_state().await,
+            crate::connection::ConnectionState::Disconnected,
+            "state must be Disconnected after disconnect"
+        );
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Bug #15 regression test: concurrent CRUD methods on one client must each
+    // receive their own response, even when the server answers out of order.
+    // ─────────────────────────────────────────────────────────────────────────
+
+    /// #15: two tasks call create_service simultaneously on the SAME client
+    /// instance. The mock server answers in reverse order; the request IDs must
+    /// still route each response to the task that asked for it.
+    #[tokio::test]
+    async fn test_concurrent_create_service_responses_are_correlated() {
+        use std::sync::Arc;
+
+        let client = Arc::new(Client::new("wss://test"));
+        let (conn, server_tx, mut client_rx) = in_memory_connection();
+
+        client.inject_auth_for_test("t1").await;
+        client.inject_connection_for_test(conn).await;
+
+        // Mock server: collect both requests, then answer the second one first
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            while requests.len() < 2 {
+                requests.push(client_rx.recv().await.expect("client closed"));
+            }
+            for request in requests.into_iter().rev() {
+                let service_name = match request.message {
+                    crate::message::ClientMessage::CreateService { service_name, .. } => {
+                        service_name
+                    }
+                    other => panic!("Unexpected request: {:?}", other),
+                };
+                server_tx
+                    .send(crate::message::ServerEnvelope {
+                        request_id: request.request_id,
+                        message: crate::message::ServerMessage::Service {
+                            service_id: format!("id-{}", service_name),
+                            service_name,
+                            tenant_id: "t1".to_string(),
+                            file_path: None,
+                        },
+                    })
+                    .expect("send response");
+            }
+        });
+
+        let c1 = Arc::clone(&client);
+        let c2 = Arc::clone(&client);
+
+        let (r1, r2) = tokio::join!(
+            tokio::spawn(async move { c1.create_service("t1", "svc_a").await }),
+            tokio::spawn(async move { c2.create_service("t1", "svc_b").await }),
+        );
+        server.await.expect("mock server panicked");
+
+        assert_eq!(r1.expect("task 1 panicked").unwrap(), "id-svc_a");
+        assert_eq!(r2.expect("task 2 panicked").unwrap(), "id-svc_b");
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Push routing
+    // ─────────────────────────────────────────────────────────────────────────
+
+    /// A change notification arriving before the reply must not be consumed
+    /// by read_variable, and must still reach push subscribers.
+    #[tokio::test]
+    async fn test_read_variable_ignores_interleaved_variable_changed_push() {
+        let client = Client::new("wss://test");
+        let mut pushes = client.subscribe_pushes();
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+
+        let server = tokio::spawn(async move {
+            let request = client_rx.recv().await.expect("client closed");
+            server_tx
+                .send(
+                    crate::message::ServerMessage::VariableChanged {
+                        service_id: "svc".to_string(),
+                        variable_name: "other".to_string(),
+                        data: vec![9],
+                        version: 3,
+                    }
+                    .into(),
+                )
+                .unwrap();
+            server_tx
+                .send(
+                    crate::message::ServerMessage::VariableData {
+                        service_id: "svc".to_string(),
+                        variable_name: "counter".to_string(),
+                        data: vec![1, 2],
+                        version: 1,
+                    }
+                    .into(),
+                )
+                .unwrap();
+            request
+        });
+
+        let data = client.read_variable("svc", "counter").await.unwrap();
+        assert_eq!(data, vec![1, 2]);
+        server.await.unwrap();
+
+        match pushes.recv().await.unwrap() {
+            crate::message::ServerMessage::VariableChanged { variable_name, .. } => {
+                assert_eq!(variable_name, "other")
+            }
+            other => panic!("Expected VariableChanged push, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_completes_on_its_answer() {
+        let client = Client::new("wss://test");
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+
+        tokio::spawn(async move {
+            if let Some(request) = client_rx.recv().await {
+                let _ = server_tx.send(crate::message::ServerEnvelope {
+                    request_id: request.request_id,
+                    message: ServerMessage::Heartbeat {
+                        timestamp: "now".to_string(),
+                    },
+                });
+            }
+        });
+
+        let result =
+            tokio::time::timeout(Duration::from_secs(2), client.heartbeat()).await;
+        assert!(
+            matches!(result, Ok(Ok(()))),
+            "heartbeat should complete as soon as the server answers: {:?}",
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_heartbeat_times_out() {
+        let client = Client::builder("wss://test")
+            .heartbeat_timeout(Duration::from_millis(50))
+            .build();
+        let (server_tx, _client_rx) = client.connect_mock_for_test().await;
+
+        // A heartbeat pushed by the server answers no request
+        server_tx
+            .send(
+                ServerMessage::Heartbeat {
+                    timestamp: "now".to_string(),
+                }
+                .into(),
+            )
+            .unwrap();
+
+        let result = client.heartbeat().await;
+        assert!(matches!(result, Err(CommyError::Timeout)), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_to_legacy_server_accepts_any_message() {
+        let client = Client::builder("wss://test")
+            .heartbeat_timeout(Duration::from_millis(50))
+            .build();
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        client.state.write().await.server_capabilities = Some(Capabilities::legacy());
+
+        // Unanswered, the heartbeat still times out
+        let result = client.heartbeat().await;
+        assert!(matches!(result, Err(CommyError::Timeout)), "{:?}", result);
+        assert!(matches!(
+            next_request(&mut client_rx).await.message,
+            ClientMessage::Heartbeat { .. }
+        ));
+
+        // A legacy server answers without a request ID
+        tokio::spawn(async move {
+            while let Some(request) = client_rx.recv().await {
+                assert!(request.request_id.is_none());
+                let _ = server_tx.send(
+                    ServerMessage::Heartbeat {
+                        timestamp: "now".to_string(),
+                    }
+                    .into(),
+                );
+            }
+        });
+        client.heartbeat().await.unwrap();
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Subscriptions
+    // ─────────────────────────────────────────────────────────────────────────
+
+    /// Answer the next request with a successful `Result`, returning the request
+    async fn acknowledge_next_request(
+        client_rx: &mut tokio::sync::mpsc::UnboundedReceiver<crate::message::ClientEnvelope>,
+        server_tx: &tokio::sync::mpsc::UnboundedSender<crate::message::ServerEnvelope>,
+    ) -> crate::message::ClientEnvelope {
+        let request = client_rx.recv().await.expect("client closed");
+        server_tx
+            .send(crate::message::ServerEnvelope {
+                request_id: request.request_id.clone(),
+                message: ServerMessage::Result {
+                    request_id: request.request_id.clone().unwrap_or_default(),
+                    success: true,
+                    message: "ok".to_string(),
+                },
+            })
+            .unwrap();
+        request
+    }
+
+    #[tokio::test]
+    async fn test_subscription_yields_updates_for_its_variable() {
+        use futures::StreamExt;
+
+        let client = Client::new("wss://test");
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+
+        let (subscription, sent) = tokio::join!(
+            client.subscribe("svc", "counter"),
+            acknowledge_next_request(&mut client_rx, &server_tx)
+        );
+        let mut subscription = subscription.unwrap();
+        assert!(matches!(
+            sent.message,
+            ClientMessage::Subscribe { ref variable_name, .. } if variable_name == "counter"
+        ));
+
+        for (variable_name, version) in [("other", 1), ("counter", 2)] {
+            server_tx
+                .send(
+                    ServerMessage::VariableChanged {
+                        service_id: "svc".to_string(),
+                        variable_name: variable_name.to_string(),
+                        data: vec![version as u8],
+                        version,
+                    }
+                    .into(),
+                )
+                .unwrap();
+        }
+
+        let update = tokio::time::timeout(Duration::from_secs(2), subscription.next())
+            .await
+            .expect("no update delivered")
+            .expect("subscription ended");
+        assert_eq!(update.version, 2);
+        assert_eq!(update.data, vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_subscription_is_an_error() {
+        let response = ServerMessage::Error {
+            code: crate::message::ErrorCode::NotFound,
+            message: "variable counter".to_string(),
+        };
+        let (client, _responder) = setup_client_with_mock_response("tenant_a", response).await;
+
+        let result = client.subscribe("svc", "counter").await;
+        assert!(matches!(result, Err(CommyError::NotFound(_))), "{:?}", result);
+        assert!(client.subscriptions.active().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_is_not_undone_by_resync() {
+        let client = Client::new("wss://test");
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        let (subscription, _) = tokio::join!(
+            client.subscribe("svc", "counter"),
+            acknowledge_next_request(&mut client_rx, &server_tx)
+        );
+        let subscription = subscription.unwrap();
+
+        client.unsubscribe("svc", "counter").await.unwrap();
+        assert!(client.subscriptions.active().is_empty());
+
+        // Dropping the handle sends no second unsubscribe
+        client_rx.recv().await.expect("client closed");
+        drop(subscription);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(client_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dropping_last_subscription_sends_unsubscribe() {
+        let client = Client::new("wss://test");
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+
+        let mut handles = Vec::new();
+        for _ in 0..2 {
+            let (subscription, _) = tokio::join!(
+                client.subscribe("svc", "counter"),
+                acknowledge_next_request(&mut client_rx, &server_tx)
+            );
+            handles.push(subscription.unwrap());
+        }
+        let second = handles.pop().unwrap();
+        let first = handles.pop().unwrap();
+
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(
+            client_rx.try_recv().is_err(),
+            "another subscription still watches the variable"
+        );
+
+        drop(second);
+        let sent = tokio::time::timeout(Duration::from_secs(2), client_rx.recv())
+            .await
+            .expect("no unsubscribe sent")
+            .expect("client closed");
+        match sent.message {
+            ClientMessage::Unsubscribe {
+                service_id,
+                variable_name,
+            } => {
+                assert_eq!(service_id, "svc");
+                assert_eq!(variable_name, "counter");
+            }
+            other => panic!("Expected Unsubscribe, got {:?}", other),
+        }
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Hybrid file selection
+    // ─────────────────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_server_is_on_this_host() {
+        for url in [
+            "ws://localhost:9000",
+            "wss://LOCALHOST",
+            "ws://127.0.0.1:9000",
+            "ws://127.8.0.1",
+            "ws://[::1]:9000",
+            "unix:///run/commy.sock",
+        ] {
+            assert!(server_is_on_this_host(url), "{} is local", url);
+        }
+        for url in [
+            "wss://commy.example.com:9000",
+            "ws://10.0.0.5:9000",
+            "ws://[2001:db8::1]",
+            "memory://test",
+            "not a url",
+        ] {
+            assert!(!server_is_on_this_host(url), "{} is remote", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unconnected_client_uses_remote_file() {
+        let client = Client::new("ws://localhost:9000");
+
+        let vf = client.get_virtual_service_file("t1", "svc").await.unwrap();
+        assert!(!vf.is_local());
+    }
+
+    #[tokio::test]
+    async fn test_explicit_local_access_reports_mapping_error() {
+        let client = Client::builder("ws://localhost:9000")
+            .file_access(FileAccess::Local)
+            .build();
+
+        let result = client.get_virtual_service_file("t1", "svc").await;
+        assert!(
+            matches!(result, Err(CommyError::ConnectionLost(_))),
+            "{:?}",
+            result
+        );
+        assert!(client.virtual_files.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mapped_file_is_extended_to_reported_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("svc.mem");
+        std::fs::write(&path, [1, 2, 3, 4]).unwrap();
+
+        let client = Client::builder("ws://localhost:9000")
+            .file_access(FileAccess::Local)
+            .build();
+        let (server_tx, client_rx) = client.connect_mock_for_test().await;
+        let response = ServerMessage::ServiceFilePath {
+            service_id: "svc-1".to_string(),
+            file_path: path.display().to_string(),
+            file_size: 16,
+        };
+        let _responder = reply_to_next_request(client_rx, server_tx, response);
+
+        let vf = client.get_virtual_service_file("t1", "svc").await.unwrap();
+        assert!(vf.is_local());
+        assert_eq!(vf.accessor().file_size().await.unwrap(), 16);
+        assert_eq!(&vf.bytes().await.unwrap()[..4], &[1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_opens_share_one_virtual_file() {
+        let client = Client::builder("wss://test")
+            .file_access(FileAccess::Remote)
+            .build();
+
+        let (first, second) = tokio::join!(
+            client.get_virtual_service_file("t1", "svc"),
+            client.get_virtual_service_file("t1", "svc")
+        );
+        assert!(Arc::ptr_eq(&first.unwrap(), &second.unwrap()));
+        assert_eq!(client.virtual_files.read().await.len(), 1);
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Sync engine
+    // ─────────────────────────────────────────────────────────────────────────
+
+    /// Client over a mock connection, with a changed variable `v` in the
+    /// virtual file of service `config`
+    ///
+    /// The file is added to the cache directly, so no update task sends
+    /// requests of its own.
+    async fn client_with_changed_variable() -> (
+        Client,
+        Arc<VirtualVariableFile>,
+        tokio::sync::mpsc::UnboundedSender<crate::message::ServerEnvelope>,
+        tokio::sync::mpsc::UnboundedReceiver<crate::message::ClientEnvelope>,
+    ) {
+        let client = Client::builder("wss://test")
+            .file_access(FileAccess::Remote)
+            .sync_batch_window(Duration::from_millis(20))
+            .build();
+        let (server_tx, client_rx) = client.connect_mock_for_test().await;
+        client.inject_auth_for_test("t").await;
+
+        let vf = Arc::new(VirtualVariableFile::new(
+            "t_config".to_string(),
+            "config".to_string(),
+            "t".to_string(),
+        ));
+        client
+            .virtual_files
+            .write()
+            .await
+            .insert("t_config".to_string(), Arc::clone(&vf));
+        vf.register_variable(crate::virtual_file::VariableMetadata::new(
+            "v".to_string(),
+            0,
+            4,
+            1,
+        ))
+        .await
+        .unwrap();
+        vf.write_variable("v", &[1, 2, 3, 4]).await.unwrap();
+        (client, vf, server_tx, client_rx)
+    }
+
+    fn change_event(vf: &VirtualVariableFile, variables: &[&str]) -> FileChangeEvent {
+        FileChangeEvent {
+            file_path: std::path::PathBuf::from("service.mem"),
+            service_id: vf.service_id().to_string(),
+            changed_variables: variables.iter().map(|v| v.to_string()).collect(),
+            byte_ranges: vec![],
+        }
+    }
+
+    async fn next_request(
+        client_rx: &mut tokio::sync::mpsc::UnboundedReceiver<crate::message::ClientEnvelope>,
+    ) -> crate::message::ClientEnvelope {
+        tokio::time::timeout(Duration::from_secs(2), client_rx.recv())
+            .await
+            .expect("request sent")
+            .expect("connection open")
+    }
+
+    async fn wait_for_shadow(vf: &VirtualVariableFile, expected: &[u8]) {
+        for _ in 0..100 {
+            if vf.shadow_bytes().await == expected {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("shadow never became {:?}", expected);
+    }
+
+    #[tokio::test]
+    async fn test_sync_engine_reports_batch_and_syncs_shadow_on_ack() {
+        let (client, vf, server_tx, mut client_rx) = client_with_changed_variable().await;
+        let (changes, rx) = broadcast::channel(16);
+        client.start_sync_task(rx).await;
+
+        // A burst of events for the same variable becomes one report
+        changes.send(change_event(&vf, &["v"])).unwrap();
+        changes.send(change_event(&vf, &["v"])).unwrap();
+
+        let request = next_request(&mut client_rx).await;
+        assert!(matches!(request.message, ClientMessage::GetService { .. }));
+        server_tx
+            .send(crate::message::ServerEnvelope {
+                request_id: request.request_id,
+                message: ServerMessage::Service {
+                    service_id: "svc-1".to_string(),
+                    service_name: "config".to_string(),
+                    tenant_id: "t".to_string(),
+                    file_path: None,
+                },
+            })
+            .unwrap();
+
+        let request = next_request(&mut client_rx).await;
+        match &request.message {
+            ClientMessage::ReportVariableChanges {
+                service_id,
+                changed_variables,
+                new_values,
+            } => {
+                assert_eq!(service_id, "svc-1");
+                assert_eq!(changed_variables, &vec!["v".to_string()]);
+                assert_eq!(new_values, &vec![("v".to_string(), vec![1, 2, 3, 4])]);
+            }
+            other => panic!("expected ReportVariableChanges, got {:?}", other),
+        }
+
+        // Nothing is synced until the server acknowledges
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(vf.shadow_bytes().await, vec![0; 4]);
+
+        server_tx
+            .send(crate::message::ServerEnvelope {
+                request_id: request.request_id,
+                message: ServerMessage::VariableChangesAcknowledged {
+                    service_id: "svc-1".to_string(),
+                    changed_variables: vec!["v".to_string()],
+                },
+            })
+            .unwrap();
+        wait_for_shadow(&vf, &[1, 2, 3, 4]).await;
+        assert!(vf.get_changed_variables().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_engine_keeps_writes_made_before_the_ack() {
+        let (client, vf, server_tx, mut client_rx) = client_with_changed_variable().await;
+        let (changes, rx) = broadcast::channel(16);
+        client.start_sync_task(rx).await;
+        changes.send(change_event(&vf, &["v"])).unwrap();
+
+        let request = next_request(&mut client_rx).await;
+        server_tx
+            .send(crate::message::ServerEnvelope {
+                request_id: request.request_id,
+                message: ServerMessage::Service {
+                    service_id: "svc-1".to_string(),
+                    service_name: "config".to_string(),
+                    tenant_id: "t".to_string(),
+                    file_path: None,
+                },
+            })
+            .unwrap();
+        let request = next_request(&mut client_rx).await;
+        assert!(matches!(
+            request.message,
+            ClientMessage::ReportVariableChanges { .. }
+        ));
+
+        // Written after the report went out, so the server has not seen it
+        vf.write_variable("v", &[5, 6, 7, 8]).await.unwrap();
+        server_tx
+            .send(crate::message::ServerEnvelope {
+                request_id: request.request_id,
+                message: ServerMessage::VariableChangesAcknowledged {
+                    service_id: "svc-1".to_string(),
+                    changed_variables: vec!["v".to_string()],
+                },
+            })
+            .unwrap();
+
+        wait_for_shadow(&vf, &[1, 2, 3, 4]).await;
+        assert_eq!(vf.get_changed_variables().await, vec!["v".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_sync_engine_reports_refused_changes_as_event() {
+        let (client, vf, server_tx, mut client_rx) = client_with_changed_variable().await;
+        let mut events = client.events();
+        let (changes, rx) = broadcast::channel(16);
+        client.start_sync_task(rx).await;
+        changes.send(change_event(&vf, &["v"])).unwrap();
+
+        let request = next_request(&mut client_rx).await;
+        server_tx
+            .send(crate::message::ServerEnvelope {
+                request_id: request.request_id,
+                message: ServerMessage::Service {
+                    service_id: "svc-1".to_string(),
+                    service_name: "config".to_string(),
+                    tenant_id: "t".to_string(),
+                    file_path: None,
+                },
+            })
+            .unwrap();
+        let request = next_request(&mut client_rx).await;
+        server_tx
+            .send(crate::message::ServerEnvelope {
+                request_id: request.request_id,
+                message: ServerMessage::Error {
+                    code: crate::message::ErrorCode::PermissionDenied,
+                    message: "read only".to_string(),
+                },
+            })
+            .unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(2), events.next())
+            .await
+            .expect("event emitted");
+        match event {
+            Some(ClientEvent::ChangesDropped {
+                service_id,
+                variables,
+                ..
+            }) => {
+                assert_eq!(service_id, vf.service_id());
+                assert_eq!(variables, vec!["v".to_string()]);
+            }
+            other => panic!("expected ChangesDropped, got {:?}", other),
+        }
+        assert_eq!(vf.get_changed_variables().await, vec!["v".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_sync_engine_retries_unacknowledged_batch_after_resync() {
+        let (client, vf, server_tx, mut client_rx) = client_with_changed_variable().await;
+        let (changes, rx) = broadcast::channel(16);
+        client.start_sync_task(rx).await;
+        changes.send(change_event(&vf, &["v"])).unwrap();
+
+        let request = next_request(&mut client_rx).await;
+        server_tx
+            .send(crate::message::ServerEnvelope {
+                request_id: request.request_id,
+                message: ServerMessage::Service {
+                    service_id: "svc-1".to_string(),
+                    service_name: "config".to_string(),
+                    tenant_id: "t".to_string(),
+                    file_path: None,
+                },
+            })
+            .unwrap();
+        let request = next_request(&mut client_rx).await;
+        assert!(matches!(
+            request.message,
+            ClientMessage::ReportVariableChanges { .. }
+        ));
+
+        // The connection drops before the acknowledgement arrives
+        drop(server_tx);
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(vf.shadow_bytes().await, vec![0; 4]);
+
+        let _ = client.events.send(ClientEvent::Resynced {
+            tenants: vec!["t".to_string()],
+            subscriptions: vec![],
+        });
+        let request = next_request(&mut client_rx).await;
+        match &request.message {
+            ClientMessage::ReportVariableChanges { service_id, .. } => {
+                assert_eq!(service_id, "svc-1", "service ID is remembered")
+            }
+            other => panic!("expected ReportVariableChanges, got {:?}", other),
+        }
+        server_tx
+            .send(crate::message::ServerEnvelope {
+                request_id: request.request_id,
+                message: ServerMessage::VariableChangesAcknowledged {
+                    service_id: "svc-1".to_string(),
+                    changed_variables: vec!["v".to_string()],
+                },
+            })
+            .unwrap();
+        wait_for_shadow(&vf, &[1, 2, 3, 4]).await;
+    }
+
+    #[tokio::test]
+    async fn test_stop_file_monitoring_stops_sync_engine() {
+        let client = Client::new("wss://test");
+        client.init_file_watcher().await.unwrap();
+        assert!(client.sync_task.read().await.is_some());
+
+        client.stop_file_monitoring().await.unwrap();
+        assert!(client.sync_task.read().await.is_none());
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Remote virtual file updates
+    // ─────────────────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_remote_file_subscribes_and_applies_pushes() {
+        let client = Client::builder("wss://test")
+            .file_access(FileAccess::Remote)
+            .build();
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        client.inject_auth_for_test("t").await;
+        let mut events = client.events();
+
+        let vf = client
+            .get_virtual_service_file("t", "config")
+            .await
+            .unwrap();
+        vf.register_variable(crate::virtual_file::VariableMetadata::new(
+            "v".to_string(),
+            4,
+            2,
+            1,
+        ))
+        .await
+        .unwrap();
+
+        let request = next_request(&mut client_rx).await;
+        assert!(matches!(request.message, ClientMessage::GetService { .. }));
+        server_tx
+            .send(crate::message::ServerEnvelope {
+                request_id: request.request_id,
+                message: ServerMessage::Service {
+                    service_id: "svc-1".to_string(),
+                    service_name: "config".to_string(),
+                    tenant_id: "t".to_string(),
+                    file_path: None,
+                },
+            })
+            .unwrap();
+
+        let request = next_request(&mut client_rx).await;
+        match &request.message {
+            ClientMessage::Subscribe {
+                service_id,
+                variable_name,
+            } => {
+                assert_eq!(service_id, "svc-1");
+                assert_eq!(variable_name, "v");
+            }
+            other => panic!("expected Subscribe, got {:?}", other),
+        }
+        server_tx
+            .send(crate::message::ServerEnvelope {
+                request_id: request.request_id.clone(),
+                message: ServerMessage::Result {
+                    request_id: request.request_id.unwrap_or_default(),
+                    success: true,
+                    message: "ok".to_string(),
+                },
+            })
+            .unwrap();
+
+        server_tx
+            .send(crate::message::ServerEnvelope {
+                request_id: None,
+                message: ServerMessage::VariableChanged {
+                    service_id: "svc-1".to_string(),
+                    variable_name: "v".to_string(),
+                    data: vec![5, 6],
+                    version: 2,
+                },
+            })
+            .unwrap();
+        wait_for_shadow(&vf, &[0, 0, 0, 0, 5, 6]).await;
+        assert_eq!(vf.read_variable_slice("v").await.unwrap(), vec![5, 6]);
+        assert!(vf.get_changed_variables().await.is_empty());
+
+        // A push of the wrong size is reported instead of applied
+        server_tx
+            .send(crate::message::ServerEnvelope {
+                request_id: None,
+                message: ServerMessage::VariableChanged {
+                    service_id: "svc-1".to_string(),
+                    variable_name: "v".to_string(),
+                    data: vec![7, 8, 9],
+                    version: 3,
+                },
+            })
+            .unwrap();
+        let failure = tokio::time::timeout(Duration::from_secs(2), async {
+            while let Some(event) = events.next().await {
+                if let ClientEvent::RemoteUpdateFailed { variable_name, .. } = event {
+                    return Some(variable_name);
+                }
+            }
+            None
+        })
+        .await
+        .expect("failure reported");
+        assert_eq!(failure.as_deref(), Some("v"));
+        assert_eq!(vf.read_variable_slice("v").await.unwrap(), vec![5, 6]);
+    }
+
+    #[tokio::test]
+    async fn test_remote_file_subscribes_after_authenticating() {
+        let client = Client::builder("wss://test")
+            .file_access(FileAccess::Remote)
+            .build();
+        let (_server_tx, mut client_rx) = client.connect_mock_for_test().await;
+
+        let vf = client
+            .get_virtual_service_file("t", "config")
+            .await
+            .unwrap();
+        vf.register_variable(crate::virtual_file::VariableMetadata::new(
+            "v".to_string(),
+            0,
+            2,
+            1,
+        ))
+        .await
+        .unwrap();
+
+        // Not authenticated to the tenant, so nothing can be asked yet
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(client_rx.try_recv().is_err());
+
+        client.inject_auth_for_test("t").await;
+        let _ = client
+            .events
+            .send(ClientEvent::Authenticated("t".to_string()));
+        let request = next_request(&mut client_rx).await;
+        assert!(matches!(request.message, ClientMessage::GetService { .. }));
+    }
+}
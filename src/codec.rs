@@ -0,0 +1,131 @@
+//! Value codecs for typed variables
+//!
+//! A codec turns a serde value into the raw bytes stored in a variable and
+//! back. All clients sharing a variable must agree on its codec.
+
+use crate::error::Result;
+use crate::fixed_layout;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Encoding used to store typed values in variables
+pub trait Codec: Clone + Send + Sync + Unpin + 'static {
+    /// Encode a value into variable bytes
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>>;
+
+    /// Decode a value from variable bytes
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T>;
+}
+
+/// JSON text encoding
+///
+/// Human-readable and tolerant of added fields; the largest encoding.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// MessagePack binary encoding
+///
+/// Structs are written as maps keyed by field name, so fields may be
+/// reordered or added without breaking existing readers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePackCodec;
+
+impl Codec for MessagePackCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(rmp_serde::to_vec_named(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+}
+
+/// Raw fixed-layout binary encoding
+///
+/// Little-endian fields back to back, as described in `fixed_layout`. The
+/// most compact encoding, and the only one where a struct of fixed-width
+/// fields always has the same size; field order is part of the format.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FixedLayoutCodec;
+
+impl Codec for FixedLayoutCodec {
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(fixed_layout::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T> {
+        Ok(fixed_layout::from_slice(bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CommyError;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Settings {
+        name: String,
+        retries: u32,
+        ratio: f64,
+    }
+
+    fn sample() -> Settings {
+        Settings {
+            name: "primary".to_string(),
+            retries: 3,
+            ratio: 0.25,
+        }
+    }
+
+    fn round_trip<C: Codec>(codec: C) -> Settings {
+        let bytes = codec.encode(&sample()).unwrap();
+        codec.decode(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_all_codecs_round_trip() {
+        assert_eq!(round_trip(JsonCodec), sample());
+        assert_eq!(round_trip(MessagePackCodec), sample());
+        assert_eq!(round_trip(FixedLayoutCodec), sample());
+    }
+
+    #[test]
+    fn test_json_codec_writes_text() {
+        let bytes = JsonCodec.encode(&sample()).unwrap();
+        assert!(String::from_utf8(bytes).unwrap().contains("\"retries\":3"));
+    }
+
+    #[test]
+    fn test_message_pack_codec_keeps_field_names() {
+        let bytes = MessagePackCodec.encode(&sample()).unwrap();
+        assert!(bytes.windows(7).any(|w| w == b"retries"));
+    }
+
+    #[test]
+    fn test_decode_errors_map_to_codec_specific_variants() {
+        assert!(matches!(
+            JsonCodec.decode::<Settings>(b"{"),
+            Err(CommyError::SerializationError(_))
+        ));
+        assert!(matches!(
+            MessagePackCodec.decode::<Settings>(&[0xc1]),
+            Err(CommyError::MessagePackDecodeError(_))
+        ));
+        assert!(matches!(
+            FixedLayoutCodec.decode::<Settings>(&[1, 2]),
+            Err(CommyError::FixedLayoutError(_))
+        ));
+    }
+}
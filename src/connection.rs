@@ -1,175 +1,943 @@
-//! WebSocket connection management
-
-use crate::error::{CommyError, Result};
-use crate::message::{ClientMessage, ServerMessage};
-use futures::{SinkExt, StreamExt};
-use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
-
-/// Connection state
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ConnectionState {
-    Disconnected,
-    Connecting,
-    Connected,
-    Authenticated,
-    Closing,
-}
-
-/// Manages WebSocket connection
-pub struct Connection {
-    state: Arc<RwLock<ConnectionState>>,
-    tx: mpsc::UnboundedSender<ClientMessage>,
-    rx: Arc<RwLock<mpsc::UnboundedReceiver<ServerMessage>>>,
-}
-
-impl Connection {
-    /// Create a new connection
-    pub async fn new(url: &str) -> Result<Self> {
-        let (ws_stream, _) = connect_async(url)
-            .await
-            .map_err(|e| CommyError::WebSocketError(e.to_string()))?;
-
-        let (mut write, mut read) = ws_stream.split();
-        let (tx, mut rx) = mpsc::unbounded_channel::<ClientMessage>();
-        let (server_tx, server_rx) = mpsc::unbounded_channel::<ServerMessage>();
-
-        // Spawn tasks to handle message routing
-        tokio::spawn(async move {
-            while let Some(msg) = rx.recv().await {
-                if let Ok(serialized) = serde_json::to_string(&msg) {
-                    let _ = write.send(Message::Text(serialized)).await;
-                }
-            }
-        });
-
-        tokio::spawn(async move {
-            while let Some(Ok(msg)) = read.next().await {
-                if let Message::Text(text) = msg {
-                    match serde_json::from_str::<ServerMessage>(&text) {
-                        Ok(server_msg) => {
-                            let _ = server_tx.send(server_msg);
-                        }
-                        Err(e) => {
-                            eprintln!("[Client] Failed to deserialize ServerMessage: {}", e);
-                            eprintln!("[Client] Raw message: {}", text);
-                        }
-                    }
-                }
-            }
-        });
-
-        Ok(Self {
-            state: Arc::new(RwLock::new(ConnectionState::Connected)),
-            tx,
-            rx: Arc::new(RwLock::new(server_rx)),
-        })
-    }
-
-    /// Send a message to the server
-    pub async fn send(&self, message: ClientMessage) -> Result<()> {
-        self.tx
-            .send(message)
-            .map_err(|e| CommyError::ChannelError(format!("Failed to send message: {}", e)))?;
-        Ok(())
-    }
-
-    /// Receive a message from the server
-    pub async fn recv(&self) -> Result<Option<ServerMessage>> {
-        let mut rx = self.rx.write().await;
-        Ok(rx.recv().await)
-    }
-
-    /// Get current connection state
-    pub async fn state(&self) -> ConnectionState {
-        *self.state.read().await
-    }
-
-    /// Set connection state
-    pub async fn set_state(&self, state: ConnectionState) {
-        *self.state.write().await = state;
-    }
-
-    /// Check if connected
-    pub async fn is_connected(&self) -> bool {
-        matches!(
-            *self.state.read().await,
-            ConnectionState::Connected | ConnectionState::Authenticated
-        )
-    }
-
-    /// Create a mock connection for testing that uses in-memory channels.
-    ///
-    /// Returns `(conn, server_tx, client_rx)` where:
-    /// - `conn` is the mock `Connection` ready to be injected into a `Client`
-    /// - `server_tx` is used by the test to **inject** `ServerMessage` responses
-    /// - `client_rx` is used by the test to **observe** `ClientMessage`s sent by the client
-    #[cfg(test)]
-    pub fn new_for_test()
-    -> (Self, mpsc::UnboundedSender<ServerMessage>, mpsc::UnboundedReceiver<ClientMessage>)
-    {
-        let (server_inbound_tx, server_inbound_rx) =
-            mpsc::unbounded_channel::<ServerMessage>();
-        let (client_outbound_tx, client_outbound_rx) =
-            mpsc::unbounded_channel::<ClientMessage>();
-
-        let conn = Self {
-            state: Arc::new(RwLock::new(ConnectionState::Connected)),
-            tx: client_outbound_tx,
-            rx: Arc::new(RwLock::new(server_inbound_rx)),
-        };
-
-        (conn, server_inbound_tx, client_outbound_rx)
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_connection_state() {
-        let state = ConnectionState::Connected;
-        assert!(matches!(state, ConnectionState::Connected));
-    }
-
-    #[test]
-    fn test_connection_state_disconnected() {
-        let state = ConnectionState::Disconnected;
-        assert!(matches!(state, ConnectionState::Disconnected));
-        assert_ne!(state, ConnectionState::Connected);
-    }
-
-    #[test]
-    fn test_connection_state_is_connected_pattern() {
-        // Only Connected and Authenticated are considered "is_connected"
-        assert!(matches!(
-            ConnectionState::Connected,
-            ConnectionState::Connected | ConnectionState::Authenticated
-        ));
-        assert!(matches!(
-            ConnectionState::Authenticated,
-            ConnectionState::Connected | ConnectionState::Authenticated
-        ));
-        assert!(!matches!(
-            ConnectionState::Disconnected,
-            ConnectionState::Connected | ConnectionState::Authenticated
-        ));
-        assert!(!matches!(
-            ConnectionState::Connecting,
-            ConnectionState::Connected | ConnectionState::Authenticated
-        ));
-        assert!(!matches!(
-            ConnectionState::Closing,
-            ConnectionState::Connected | ConnectionState::Authenticated
-        ));
-    }
-
-    #[test]
-    fn test_connection_state_equality() {
-        assert_eq!(ConnectionState::Connected, ConnectionState::Connected);
-        assert_ne!(ConnectionState::Connected, ConnectionState::Disconnected);
-        assert_ne!(ConnectionState::Authenticated, ConnectionState::Connected);
-    }
-}
+//! Connection management
+
+use crate::error::{CommyError, Result};
+use crate::message::{ClientEnvelope, ClientMessage, ServerEnvelope, ServerMessage};
+use crate::recording::{Recorder, Session};
+use crate::tls::TlsConfig;
+use crate::transport::{self, Transport};
+use crate::unix_socket::PeerCheck;
+use crate::wire::WireFormat;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc, oneshot, watch, RwLock};
+
+/// Number of server pushes buffered per push subscriber before it starts lagging
+pub const PUSH_CHANNEL_CAPACITY: usize = 256;
+
+/// Number of messages that were neither a reply nor a push kept for
+/// `Connection::recv`; further ones are dropped until it catches up
+pub const UNSOLICITED_CHANNEL_CAPACITY: usize = 64;
+
+/// Connection state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Disconnected,
+    Connecting,
+    Connected,
+    Authenticated,
+    Closing,
+}
+
+/// A request waiting for its response
+struct PendingRequest {
+    request_id: String,
+    reply: oneshot::Sender<ServerMessage>,
+}
+
+/// Routes incoming server messages to the requests waiting for them
+///
+/// Responses carrying a `request_id` go to the matching request. Until the
+/// server has echoed a request ID once, responses without one are assumed to
+/// come from a server that predates request IDs and go to the oldest pending
+/// request, which matches the order such servers reply in.
+///
+/// Server pushes (see `ServerMessage::is_push`) never take a reply slot unless
+/// they carry the request ID of a pending request; they are broadcast to push
+/// subscribers instead. Anything left over is forwarded to the unsolicited
+/// channel read by `Connection::recv`, or counted and dropped once that
+/// channel is full.
+#[derive(Clone)]
+struct Dispatcher {
+    pending: Arc<Mutex<VecDeque<PendingRequest>>>,
+    unsolicited: mpsc::Sender<ServerMessage>,
+    dropped: Arc<AtomicU64>,
+    pushes: broadcast::Sender<ServerMessage>,
+    server_echoes_ids: Arc<AtomicBool>,
+    closed: Arc<watch::Sender<bool>>,
+    received: Arc<watch::Sender<u64>>,
+}
+
+impl Dispatcher {
+    fn new(
+        unsolicited: mpsc::Sender<ServerMessage>,
+        pushes: broadcast::Sender<ServerMessage>,
+    ) -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(VecDeque::new())),
+            unsolicited,
+            dropped: Arc::new(AtomicU64::new(0)),
+            pushes,
+            server_echoes_ids: Arc::new(AtomicBool::new(false)),
+            closed: Arc::new(watch::channel(false).0),
+            received: Arc::new(watch::channel(0).0),
+        }
+    }
+
+    /// Register a request and return the receiver for its response
+    fn register(&self, request_id: String) -> oneshot::Receiver<ServerMessage> {
+        let (reply, rx) = oneshot::channel();
+        let mut pending = self.pending.lock().unwrap();
+        // Callers that timed out dropped their receivers; forget them
+        pending.retain(|p| !p.reply.is_closed());
+        pending.push_back(PendingRequest { request_id, reply });
+        rx
+    }
+
+    /// Forget a request whose message could not be sent
+    fn cancel(&self, request_id: &str) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|p| p.request_id != request_id);
+    }
+
+    /// Route one incoming message
+    fn dispatch(&self, envelope: ServerEnvelope) {
+        self.received.send_modify(|count| *count += 1);
+
+        let ServerEnvelope {
+            request_id,
+            message,
+        } = envelope;
+
+        let waiter = {
+            let mut pending = self.pending.lock().unwrap();
+            match request_id {
+                Some(id) => {
+                    self.server_echoes_ids.store(true, Ordering::Relaxed);
+                    pending
+                        .iter()
+                        .position(|p| p.request_id == id)
+                        .and_then(|idx| pending.remove(idx))
+                }
+                None if message.is_push() => None,
+                None if self.server_echoes_ids.load(Ordering::Relaxed) => None,
+                None => {
+                    while pending.front().is_some_and(|p| p.reply.is_closed()) {
+                        pending.pop_front();
+                    }
+                    pending.pop_front()
+                }
+            }
+        };
+
+        match waiter {
+            Some(waiter) => {
+                // The caller may have given up in the meantime; nothing to do then
+                let _ = waiter.reply.send(message);
+            }
+            None if message.is_push() => {
+                // No subscribers is not an error; the push is simply dropped
+                let _ = self.pushes.send(message);
+            }
+            None => {
+                if self.unsolicited.try_send(message).is_err() {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    tracing::debug!("dropped a server message that answers no request");
+                }
+            }
+        }
+    }
+
+    /// Fail every pending request and signal that the connection is gone
+    fn close(&self) {
+        // Signal first, so a request registered after the clear sees it
+        self.closed.send_replace(true);
+        self.pending.lock().unwrap().clear();
+    }
+
+    /// Whether the connection is gone
+    fn is_closed(&self) -> bool {
+        *self.closed.borrow()
+    }
+}
+
+/// Manages the connection to a server over a `Transport`
+pub struct Connection {
+    state: Arc<RwLock<ConnectionState>>,
+    tx: mpsc::UnboundedSender<ClientEnvelope>,
+    rx: Arc<RwLock<mpsc::Receiver<ServerMessage>>>,
+    dispatcher: Dispatcher,
+    next_request_id: AtomicU64,
+    wire_format: WireFormat,
+    recording: Arc<Mutex<Option<Session>>>,
+}
+
+impl Connection {
+    /// Create a new connection
+    pub async fn new(url: &str) -> Result<Self> {
+        let (pushes, _) = broadcast::channel(PUSH_CHANNEL_CAPACITY);
+        Self::with_push_channel(url, pushes).await
+    }
+
+    /// Create a new connection that broadcasts server pushes on `pushes`
+    ///
+    /// Passing the same sender on every reconnect keeps existing push
+    /// subscribers attached across connections.
+    pub async fn with_push_channel(
+        url: &str,
+        pushes: broadcast::Sender<ServerMessage>,
+    ) -> Result<Self> {
+        Self::with_wire_format(url, pushes, WireFormat::default()).await
+    }
+
+    /// Create a new connection offering `preferred` as its wire format
+    ///
+    /// The server's subprotocol answer decides the format actually used. A
+    /// server that refuses the offer gets a second, plain handshake and JSON.
+    pub async fn with_wire_format(
+        url: &str,
+        pushes: broadcast::Sender<ServerMessage>,
+        preferred: WireFormat,
+    ) -> Result<Self> {
+        Self::open(url, pushes, preferred, None, &PeerCheck::default()).await
+    }
+
+    /// Create a new connection using the given TLS settings for `wss://`
+    pub async fn with_tls(
+        url: &str,
+        pushes: broadcast::Sender<ServerMessage>,
+        preferred: WireFormat,
+        tls: &TlsConfig,
+    ) -> Result<Self> {
+        Self::open(url, pushes, preferred, Some(tls), &PeerCheck::default()).await
+    }
+
+    /// Create a new connection to a `unix://` URL, accepting only server
+    /// processes that pass `peer_check`
+    ///
+    /// Other URLs connect as usual and ignore the check.
+    pub async fn with_peer_check(
+        url: &str,
+        pushes: broadcast::Sender<ServerMessage>,
+        preferred: WireFormat,
+        peer_check: &PeerCheck,
+    ) -> Result<Self> {
+        Self::open(url, pushes, preferred, None, peer_check).await
+    }
+
+    /// Open the transport for `url` and start the reader and writer tasks
+    async fn open(
+        url: &str,
+        pushes: broadcast::Sender<ServerMessage>,
+        preferred: WireFormat,
+        tls: Option<&TlsConfig>,
+        peer_check: &PeerCheck,
+    ) -> Result<Self> {
+        let transport = transport::connect(url, preferred, tls, peer_check).await?;
+        Ok(Self::with_transport(transport, pushes))
+    }
+
+    /// Create a connection over an already open transport
+    pub fn from_transport(transport: Box<dyn Transport>) -> Self {
+        let (pushes, _) = broadcast::channel(PUSH_CHANNEL_CAPACITY);
+        Self::with_transport(transport, pushes)
+    }
+
+    /// Create a connection over an already open transport, broadcasting
+    /// server pushes on `pushes`
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn with_transport(
+        transport: Box<dyn Transport>,
+        pushes: broadcast::Sender<ServerMessage>,
+    ) -> Self {
+        let transport: Arc<dyn Transport> = Arc::from(transport);
+        let wire_format = transport.wire_format();
+        let (tx, mut rx) = mpsc::unbounded_channel::<ClientEnvelope>();
+        let (server_tx, server_rx) = mpsc::channel::<ServerMessage>(UNSOLICITED_CHANNEL_CAPACITY);
+        let dispatcher = Dispatcher::new(server_tx, pushes);
+        let recording: Arc<Mutex<Option<Session>>> = Arc::new(Mutex::new(None));
+
+        // Spawn tasks to handle message routing
+        let writer = Arc::clone(&transport);
+        let sent = Arc::clone(&recording);
+        let writer_dispatcher = dispatcher.clone();
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if let Some(session) = sent.lock().unwrap().as_ref() {
+                    session.sent(&msg);
+                }
+                if let Err(e) = writer.send(msg).await {
+                    // Nothing sent from now on would arrive; fail the waiters now
+                    tracing::debug!(error = %e, "failed to send a message, closing the connection");
+                    writer_dispatcher.close();
+                    break;
+                }
+            }
+            // The connection was dropped or broke; close the transport so the
+            // reader ends too
+            writer.close().await;
+        });
+
+        let state = Arc::new(RwLock::new(ConnectionState::Connected));
+        let reader_state = Arc::clone(&state);
+        let reader = dispatcher.clone();
+        let received_by = Arc::clone(&recording);
+        tokio::spawn(async move {
+            while let Some(received) = transport.recv().await {
+                match received {
+                    Ok(envelope) => {
+                        if let Some(session) = received_by.lock().unwrap().as_ref() {
+                            session.received(&envelope);
+                        }
+                        reader.dispatch(envelope);
+                    }
+                    Err(e) => {
+                        tracing::warn!(error = %e, "failed to decode server message");
+                    }
+                }
+            }
+            if let Some(session) = received_by.lock().unwrap().take() {
+                session.closed();
+            }
+            *reader_state.write().await = ConnectionState::Disconnected;
+            reader.close();
+        });
+
+        Self {
+            state,
+            tx,
+            rx: Arc::new(RwLock::new(server_rx)),
+            dispatcher,
+            next_request_id: AtomicU64::new(1),
+            wire_format,
+            recording,
+        }
+    }
+
+    /// Record every message sent or received from now on with `recorder`
+    ///
+    /// Replaces any earlier recorder. Each call starts a new connection in
+    /// the recording.
+    pub fn record(&self, recorder: &Recorder) {
+        *self.recording.lock().unwrap() = Some(recorder.session());
+    }
+
+    /// Stop recording this connection's messages
+    pub fn stop_recording(&self) {
+        self.recording.lock().unwrap().take();
+    }
+
+    /// Send a message to the server
+    ///
+    /// Returns `CommyError::ConnectionLost` once the connection is closed.
+    pub async fn send(&self, message: ClientMessage) -> Result<()> {
+        if self.dispatcher.is_closed() {
+            return Err(connection_closed());
+        }
+        self.tx
+            .send(message.into())
+            .map_err(|e| CommyError::ChannelError(format!("Failed to send message: {}", e)))?;
+        Ok(())
+    }
+
+    /// Send a request and return a receiver for its correlated response
+    ///
+    /// The receiver resolves with the server message carrying the same request
+    /// ID. It errors if the connection closes before the response arrives.
+    /// Returns `CommyError::ConnectionLost` once the connection is closed.
+    pub async fn request(&self, message: ClientMessage) -> Result<oneshot::Receiver<ServerMessage>> {
+        let request_id = self.next_request_id.fetch_add(1, Ordering::Relaxed).to_string();
+        let response = self.dispatcher.register(request_id.clone());
+        // Checked after registering, so a concurrent close either fails this
+        // request or is seen here
+        if self.dispatcher.is_closed() {
+            self.dispatcher.cancel(&request_id);
+            return Err(connection_closed());
+        }
+
+        if let Err(e) = self.tx.send(ClientEnvelope::request(request_id.clone(), message)) {
+            self.dispatcher.cancel(&request_id);
+            return Err(CommyError::ChannelError(format!("Failed to send message: {}", e)));
+        }
+
+        Ok(response)
+    }
+
+    /// Subscribe to unsolicited server pushes
+    ///
+    /// Pushes (`VariableChanged`, `Disconnected`, `Heartbeat`) are delivered
+    /// here instead of being handed to a pending request.
+    pub fn subscribe_pushes(&self) -> broadcast::Receiver<ServerMessage> {
+        self.dispatcher.pushes.subscribe()
+    }
+
+    /// Receive the next message that was neither a reply nor a push
+    pub async fn recv(&self) -> Result<Option<ServerMessage>> {
+        let mut rx = self.rx.write().await;
+        Ok(rx.recv().await)
+    }
+
+    /// Number of messages dropped because nobody read them with `recv`
+    pub fn dropped_messages(&self) -> u64 {
+        self.dispatcher.dropped.load(Ordering::Relaxed)
+    }
+
+    /// The receiving end behind `recv`, for draining it in the background
+    pub(crate) fn unsolicited(&self) -> Arc<RwLock<mpsc::Receiver<ServerMessage>>> {
+        Arc::clone(&self.rx)
+    }
+
+    /// Get current connection state
+    pub async fn state(&self) -> ConnectionState {
+        *self.state.read().await
+    }
+
+    /// Set connection state
+    pub async fn set_state(&self, state: ConnectionState) {
+        *self.state.write().await = state;
+    }
+
+    /// Wire format agreed with the server
+    pub fn wire_format(&self) -> WireFormat {
+        self.wire_format
+    }
+
+    /// Watch for the connection closing
+    ///
+    /// The value turns `true` once the socket's reader has stopped, whether
+    /// the server closed it or the connection was dropped, or a message could
+    /// not be sent.
+    pub fn closed(&self) -> watch::Receiver<bool> {
+        self.dispatcher.closed.subscribe()
+    }
+
+    /// Watch the number of messages received from the server
+    ///
+    /// Every decoded message counts, whether it is a reply, a push or neither.
+    pub fn received(&self) -> watch::Receiver<u64> {
+        self.dispatcher.received.subscribe()
+    }
+
+    /// Check whether the connection is closed, as signalled by `closed`
+    pub fn is_closed(&self) -> bool {
+        self.dispatcher.is_closed()
+    }
+
+    /// Check if connected
+    pub async fn is_connected(&self) -> bool {
+        matches!(
+            *self.state.read().await,
+            ConnectionState::Connected | ConnectionState::Authenticated
+        )
+    }
+}
+
+/// Error for using a connection that is already closed
+fn connection_closed() -> CommyError {
+    CommyError::ConnectionLost("Connection closed".to_string())
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // Requests still waiting on this connection will never be answered
+        self.dispatcher.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use tokio::io::{AsyncRead, AsyncWrite};
+    use tokio_tungstenite::tungstenite::http::header::{HeaderValue, SEC_WEBSOCKET_PROTOCOL};
+
+    #[test]
+    fn test_connection_state() {
+        let state = ConnectionState::Connected;
+        assert!(matches!(state, ConnectionState::Connected));
+    }
+
+    #[test]
+    fn test_connection_state_disconnected() {
+        let state = ConnectionState::Disconnected;
+        assert!(matches!(state, ConnectionState::Disconnected));
+        assert_ne!(state, ConnectionState::Connected);
+    }
+
+    #[test]
+    fn test_connection_state_is_connected_pattern() {
+        // Only Connected and Authenticated are considered "is_connected"
+        assert!(matches!(
+            ConnectionState::Connected,
+            ConnectionState::Connected | ConnectionState::Authenticated
+        ));
+        assert!(matches!(
+            ConnectionState::Authenticated,
+            ConnectionState::Connected | ConnectionState::Authenticated
+        ));
+        assert!(!matches!(
+            ConnectionState::Disconnected,
+            ConnectionState::Connected | ConnectionState::Authenticated
+        ));
+        assert!(!matches!(
+            ConnectionState::Connecting,
+            ConnectionState::Connected | ConnectionState::Authenticated
+        ));
+        assert!(!matches!(
+            ConnectionState::Closing,
+            ConnectionState::Connected | ConnectionState::Authenticated
+        ));
+    }
+
+    #[test]
+    fn test_connection_state_equality() {
+        assert_eq!(ConnectionState::Connected, ConnectionState::Connected);
+        assert_ne!(ConnectionState::Connected, ConnectionState::Disconnected);
+        assert_ne!(ConnectionState::Authenticated, ConnectionState::Connected);
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Request/response correlation
+    // ─────────────────────────────────────────────────────────────────────────
+
+    /// A connection over an in-process channel, with the server's ends: a
+    /// sender to inject server messages and a receiver observing what the
+    /// client sends
+    fn in_memory() -> (
+        Connection,
+        mpsc::UnboundedSender<ServerEnvelope>,
+        mpsc::UnboundedReceiver<ClientEnvelope>,
+    ) {
+        let (transport, peer) = crate::transport::ChannelTransport::pair();
+        (Connection::from_transport(Box::new(transport)), peer.to_client, peer.from_client)
+    }
+
+    fn result_reply(message: &str) -> ServerMessage {
+        ServerMessage::Result {
+            request_id: String::new(),
+            success: true,
+            message: message.to_string(),
+        }
+    }
+
+    fn delete_tenant_request() -> ClientMessage {
+        ClientMessage::DeleteTenant {
+            tenant_id: "t1".to_string(),
+        }
+    }
+
+    fn reply_message(response: ServerMessage) -> String {
+        match response {
+            ServerMessage::Result { message, .. } => message,
+            other => panic!("Unexpected response: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_responses_routed_by_request_id() {
+        let (conn, server_tx, mut client_rx) = in_memory();
+
+        let first = conn.request(delete_tenant_request()).await.unwrap();
+        let second = conn.request(delete_tenant_request()).await.unwrap();
+
+        let first_id = client_rx.recv().await.unwrap().request_id.unwrap();
+        let second_id = client_rx.recv().await.unwrap().request_id.unwrap();
+        assert_ne!(first_id, second_id, "request IDs must be unique");
+
+        // Answer out of order
+        server_tx
+            .send(ServerEnvelope::reply(second_id, result_reply("second")))
+            .unwrap();
+        server_tx
+            .send(ServerEnvelope::reply(first_id, result_reply("first")))
+            .unwrap();
+
+        assert_eq!(reply_message(first.await.unwrap()), "first");
+        assert_eq!(reply_message(second.await.unwrap()), "second");
+    }
+
+    #[tokio::test]
+    async fn test_untagged_response_goes_to_oldest_pending_request() {
+        let (conn, server_tx, _client_rx) = in_memory();
+
+        let first = conn.request(delete_tenant_request()).await.unwrap();
+        let second = conn.request(delete_tenant_request()).await.unwrap();
+
+        // A server without request ID support answers in order
+        server_tx.send(result_reply("first").into()).unwrap();
+        server_tx.send(result_reply("second").into()).unwrap();
+
+        assert_eq!(reply_message(first.await.unwrap()), "first");
+        assert_eq!(reply_message(second.await.unwrap()), "second");
+    }
+
+    #[tokio::test]
+    async fn test_untagged_message_is_unsolicited_once_server_echoes_ids() {
+        let (conn, server_tx, mut client_rx) = in_memory();
+
+        let first = conn.request(delete_tenant_request()).await.unwrap();
+        let first_id = client_rx.recv().await.unwrap().request_id.unwrap();
+        server_tx
+            .send(ServerEnvelope::reply(first_id, result_reply("first")))
+            .unwrap();
+        first.await.unwrap();
+
+        // The server is now known to echo IDs, so an untagged message must not
+        // be handed to an unrelated pending request
+        let pending = conn.request(delete_tenant_request()).await.unwrap();
+        server_tx.send(result_reply("stray").into()).unwrap();
+
+        match conn.recv().await.unwrap() {
+            Some(message) => assert_eq!(reply_message(message), "stray"),
+            None => panic!("Expected unsolicited message"),
+        }
+        drop(pending);
+    }
+
+    #[tokio::test]
+    async fn test_unread_unsolicited_messages_are_bounded() {
+        let (conn, server_tx, mut client_rx) = in_memory();
+
+        let first = conn.request(delete_tenant_request()).await.unwrap();
+        let first_id = client_rx.recv().await.unwrap().request_id.unwrap();
+        server_tx
+            .send(ServerEnvelope::reply(first_id, result_reply("first")))
+            .unwrap();
+        first.await.unwrap();
+
+        let extra = 5;
+        for _ in 0..UNSOLICITED_CHANNEL_CAPACITY + extra {
+            server_tx.send(result_reply("stray").into()).unwrap();
+        }
+        // A round trip makes sure every stray message was dispatched
+        let last = conn.request(delete_tenant_request()).await.unwrap();
+        let last_id = client_rx.recv().await.unwrap().request_id.unwrap();
+        server_tx
+            .send(ServerEnvelope::reply(last_id, result_reply("last")))
+            .unwrap();
+        last.await.unwrap();
+
+        assert_eq!(conn.dropped_messages(), extra as u64);
+        assert_eq!(reply_message(conn.recv().await.unwrap().unwrap()), "stray");
+    }
+
+    #[tokio::test]
+    async fn test_send_has_no_request_id() {
+        let (conn, _server_tx, mut client_rx) = in_memory();
+        conn.send(delete_tenant_request()).await.unwrap();
+        let envelope = client_rx.recv().await.unwrap();
+        assert!(envelope.request_id.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pending_requests_fail_when_connection_closes() {
+        let (conn, server_tx, _client_rx) = in_memory();
+        let response = conn.request(delete_tenant_request()).await.unwrap();
+
+        drop(server_tx);
+
+        assert!(
+            response.await.is_err(),
+            "pending request must fail once the connection is gone"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_send_fails_the_request_and_closes() {
+        let (conn, _server_tx, client_rx) = in_memory();
+        drop(client_rx);
+
+        let response = conn.request(delete_tenant_request()).await.unwrap();
+        let result = tokio::time::timeout(std::time::Duration::from_secs(2), response)
+            .await
+            .expect("waiter failed without a timeout");
+        assert!(result.is_err());
+
+        conn.closed().wait_for(|closed| *closed).await.unwrap();
+        assert!(matches!(
+            conn.request(delete_tenant_request()).await,
+            Err(CommyError::ConnectionLost(_))
+        ));
+        assert!(matches!(
+            conn.send(delete_tenant_request()).await,
+            Err(CommyError::ConnectionLost(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_closed_signal_and_state_follow_reader() {
+        let (conn, server_tx, _client_rx) = in_memory();
+        let mut closed = conn.closed();
+        assert!(!conn.is_closed());
+
+        drop(server_tx);
+
+        closed.wait_for(|closed| *closed).await.unwrap();
+        assert!(conn.is_closed());
+        assert_eq!(conn.state().await, ConnectionState::Disconnected);
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Wire format negotiation
+    // ─────────────────────────────────────────────────────────────────────────
+
+    /// Serve WebSocket connections on an ephemeral port, answering every
+    /// request frame with a `Result` reply in the same frame type
+    ///
+    /// With `accept_message_pack`, the MessagePack subprotocol is selected
+    /// when offered; otherwise the subprotocol header is ignored, like a
+    /// server that predates negotiation. Each request frame is reported on
+    /// the returned channel.
+    async fn serve_replies(
+        accept_message_pack: bool,
+    ) -> (String, mpsc::UnboundedReceiver<tokio_tungstenite::tungstenite::Message>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (frames_tx, frames_rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(reply_to_requests(stream, accept_message_pack, frames_tx.clone()));
+            }
+        });
+
+        (url, frames_rx)
+    }
+
+    /// Like `serve_replies`, on a Unix domain socket in `dir`
+    #[cfg(unix)]
+    async fn serve_replies_unix(
+        dir: &std::path::Path,
+    ) -> (String, mpsc::UnboundedReceiver<tokio_tungstenite::tungstenite::Message>) {
+        let path = dir.join("commy.sock");
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        let url = format!("unix://{}", path.display());
+        let (frames_tx, frames_rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(reply_to_requests(stream, true, frames_tx.clone()));
+            }
+        });
+
+        (url, frames_rx)
+    }
+
+    /// Accept one WebSocket connection and answer its requests
+    async fn reply_to_requests<S>(
+        stream: S,
+        accept_message_pack: bool,
+        frames_tx: mpsc::UnboundedSender<tokio_tungstenite::tungstenite::Message>,
+    ) where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+        use tokio_tungstenite::tungstenite::Message;
+
+        // The callback signature is fixed by tungstenite
+        #[allow(clippy::result_large_err)]
+        let select = |request: &Request, mut response: Response| {
+            let offered = request
+                .headers()
+                .get(SEC_WEBSOCKET_PROTOCOL)
+                .is_some_and(|value| value.to_str().unwrap().contains("commy.msgpack"));
+            if accept_message_pack && offered {
+                response.headers_mut().insert(
+                    SEC_WEBSOCKET_PROTOCOL,
+                    HeaderValue::from_static("commy.msgpack"),
+                );
+            }
+            Ok(response)
+        };
+        let Ok(mut ws) = tokio_tungstenite::accept_hdr_async(stream, select).await else {
+            return;
+        };
+
+        while let Some(Ok(frame)) = ws.next().await {
+            let reply = match &frame {
+                Message::Text(text) => {
+                    let request: ClientEnvelope = serde_json::from_str(text).unwrap();
+                    let reply = ServerEnvelope::reply(request.request_id.unwrap(), result_reply("json"));
+                    Message::Text(serde_json::to_string(&reply).unwrap())
+                }
+                Message::Binary(bytes) => {
+                    let request: ClientEnvelope = rmp_serde::from_slice(bytes).unwrap();
+                    let reply =
+                        ServerEnvelope::reply(request.request_id.unwrap(), result_reply("msgpack"));
+                    Message::Binary(rmp_serde::to_vec_named(&reply).unwrap())
+                }
+                _ => continue,
+            };
+            let _ = frames_tx.send(frame);
+            ws.send(reply).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_message_pack_is_used_when_server_accepts_it() {
+        let (url, mut frames) = serve_replies(true).await;
+        let (pushes, _) = broadcast::channel(PUSH_CHANNEL_CAPACITY);
+        let conn = Connection::with_wire_format(&url, pushes, WireFormat::MessagePack)
+            .await
+            .unwrap();
+        assert_eq!(conn.wire_format(), WireFormat::MessagePack);
+
+        let response = conn.request(delete_tenant_request()).await.unwrap();
+        assert_eq!(reply_message(response.await.unwrap()), "msgpack");
+        assert!(frames.recv().await.unwrap().is_binary());
+    }
+
+    #[tokio::test]
+    async fn test_json_fallback_when_server_ignores_offer() {
+        let (url, mut frames) = serve_replies(false).await;
+        let (pushes, _) = broadcast::channel(PUSH_CHANNEL_CAPACITY);
+        let conn = Connection::with_wire_format(&url, pushes, WireFormat::MessagePack)
+            .await
+            .unwrap();
+        assert_eq!(conn.wire_format(), WireFormat::Json);
+
+        let response = conn.request(delete_tenant_request()).await.unwrap();
+        assert_eq!(reply_message(response.await.unwrap()), "json");
+        assert!(frames.recv().await.unwrap().is_text());
+    }
+
+    #[tokio::test]
+    async fn test_json_preference_skips_offer() {
+        let (url, mut frames) = serve_replies(true).await;
+        let (pushes, _) = broadcast::channel(PUSH_CHANNEL_CAPACITY);
+        let conn = Connection::with_wire_format(&url, pushes, WireFormat::Json)
+            .await
+            .unwrap();
+        assert_eq!(conn.wire_format(), WireFormat::Json);
+
+        conn.request(delete_tenant_request()).await.unwrap().await.unwrap();
+        assert!(frames.recv().await.unwrap().is_text());
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Unix domain sockets
+    // ─────────────────────────────────────────────────────────────────────────
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_requests_over_unix_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let (url, mut frames) = serve_replies_unix(dir.path()).await;
+
+        let conn = Connection::new(&url).await.unwrap();
+        assert_eq!(conn.wire_format(), WireFormat::MessagePack);
+
+        let response = conn.request(delete_tenant_request()).await.unwrap();
+        assert_eq!(reply_message(response.await.unwrap()), "msgpack");
+        assert!(frames.recv().await.unwrap().is_binary());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_socket_peer_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let (url, _frames) = serve_replies_unix(dir.path()).await;
+
+        let (pushes, _) = broadcast::channel(PUSH_CHANNEL_CAPACITY);
+        let result = Connection::with_peer_check(
+            &url,
+            pushes,
+            WireFormat::Json,
+            &PeerCheck::Uids(Vec::new()),
+        )
+        .await;
+        assert!(matches!(result, Err(CommyError::PermissionDenied(_))));
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Push routing
+    // ─────────────────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_push_does_not_take_pending_reply_slot() {
+        let (conn, server_tx, _client_rx) = in_memory();
+        let mut pushes = conn.subscribe_pushes();
+
+        let response = conn.request(delete_tenant_request()).await.unwrap();
+
+        // An untagged change notification arrives before the reply
+        server_tx
+            .send(
+                ServerMessage::VariableChanged {
+                    service_id: "svc".to_string(),
+                    variable_name: "counter".to_string(),
+                    data: vec![1],
+                    version: 2,
+                }
+                .into(),
+            )
+            .unwrap();
+        server_tx.send(result_reply("done").into()).unwrap();
+
+        assert_eq!(reply_message(response.await.unwrap()), "done");
+        assert!(matches!(
+            pushes.recv().await.unwrap(),
+            ServerMessage::VariableChanged { version: 2, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_untagged_message_does_not_take_reply_slot() {
+        let (conn, server_tx, _client_rx) = in_memory();
+        let mut pushes = conn.subscribe_pushes();
+        let response = conn.request(delete_tenant_request()).await.unwrap();
+
+        server_tx
+            .send(ServerMessage::Unknown(serde_json::json!({ "type": "QuotaWarning" })).into())
+            .unwrap();
+        server_tx.send(result_reply("done").into()).unwrap();
+
+        assert_eq!(
+            pushes.recv().await.unwrap().unknown_type(),
+            Some("QuotaWarning")
+        );
+        assert_eq!(reply_message(response.await.unwrap()), "done");
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_and_disconnected_are_pushes() {
+        let (conn, server_tx, _client_rx) = in_memory();
+        let mut pushes = conn.subscribe_pushes();
+
+        server_tx
+            .send(
+                ServerMessage::Heartbeat {
+                    timestamp: "now".to_string(),
+                }
+                .into(),
+            )
+            .unwrap();
+        server_tx
+            .send(
+                ServerMessage::Disconnected {
+                    reason: "shutdown".to_string(),
+                }
+                .into(),
+            )
+            .unwrap();
+
+        assert!(matches!(pushes.recv().await.unwrap(), ServerMessage::Heartbeat { .. }));
+        assert!(matches!(pushes.recv().await.unwrap(), ServerMessage::Disconnected { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_tagged_heartbeat_answers_its_request() {
+        let (conn, server_tx, mut client_rx) = in_memory();
+        let mut pushes = conn.subscribe_pushes();
+
+        let response = conn
+            .request(ClientMessage::Heartbeat {
+                client_id: "c1".to_string(),
+            })
+            .await
+            .unwrap();
+        let request_id = client_rx.recv().await.unwrap().request_id.unwrap();
+        server_tx
+            .send(ServerEnvelope::reply(
+                request_id,
+                ServerMessage::Heartbeat {
+                    timestamp: "now".to_string(),
+                },
+            ))
+            .unwrap();
+
+        assert!(matches!(response.await.unwrap(), ServerMessage::Heartbeat { .. }));
+        assert!(pushes.try_recv().is_err(), "a correlated reply is not a push");
+    }
+}
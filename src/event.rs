@@ -0,0 +1,173 @@
+//! Client lifecycle events
+
+use futures::stream::{BoxStream, Stream, StreamExt};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::broadcast;
+
+/// Capacity of the client event channel
+///
+/// A consumer falling further behind than this misses the oldest events.
+pub const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Something that happened to the client's connection or session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientEvent {
+    /// A connection attempt started
+    Connecting,
+
+    /// The connection was established
+    Connected,
+
+    /// Authentication to the given tenant succeeded
+    Authenticated(String),
+
+    /// The connection was lost, closed or could not be opened
+    Disconnected(String),
+
+    /// Reconnection attempt number (starting at 1) is about to be made
+    Reconnecting(u32),
+
+    /// The server announced it is closing the connection
+    ServerDisconnected(String),
+
+    /// The server sent a message this client does not understand
+    UnknownMessage {
+        /// The message's `type` field, or empty if it had none
+        message_type: String,
+
+        /// The message as JSON text
+        raw: String,
+    },
+
+    /// Authentication and subscriptions were restored after a reconnect
+    Resynced {
+        /// Tenants the client is authenticated to again
+        tenants: Vec<String>,
+
+        /// `(service_id, variable_name)` pairs the server accepted again
+        subscriptions: Vec<(String, String)>,
+    },
+
+    /// Authenticating to a tenant again after a reconnect failed
+    ///
+    /// The tenant's credentials are only forgotten when the server rejected
+    /// them; after any other failure the next reconnect tries again.
+    ReauthenticationFailed {
+        /// Tenant the client is not authenticated to on the new connection
+        tenant_id: String,
+
+        /// Why authentication failed
+        reason: String,
+    },
+
+    /// Subscribing to a variable again after a reconnect failed
+    ///
+    /// The variable stays subscribed on the client side, so the next
+    /// reconnect tries again.
+    ResubscriptionFailed {
+        /// Service the variable belongs to
+        service_id: String,
+
+        /// Variable that receives no updates on the new connection
+        variable_name: String,
+
+        /// Why subscribing failed
+        reason: String,
+    },
+
+    /// The server refused a batch of local variable changes
+    ///
+    /// The variables stay marked as changed in their virtual file.
+    ChangesDropped {
+        /// Service whose virtual file the changes were made to
+        service_id: String,
+
+        /// Variables the batch reported
+        variables: Vec<String>,
+
+        /// Why the report failed
+        reason: String,
+    },
+
+    /// A pushed variable change could not be applied to a remote virtual file
+    RemoteUpdateFailed {
+        /// Service whose virtual file the change was for
+        service_id: String,
+
+        /// Variable the change was for
+        variable_name: String,
+
+        /// Why the change could not be applied
+        reason: String,
+    },
+}
+
+/// Stream of client events
+///
+/// Created by `Client::events`. Only events emitted after it was created are
+/// delivered.
+pub struct ClientEvents {
+    events: BoxStream<'static, ClientEvent>,
+}
+
+impl ClientEvents {
+    /// Create a stream reading from the client's event channel
+    pub(crate) fn new(receiver: broadcast::Receiver<ClientEvent>) -> Self {
+        let events = futures::stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+        .boxed();
+
+        Self { events }
+    }
+}
+
+impl Stream for ClientEvents {
+    type Item = ClientEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.events.poll_next_unpin(cx)
+    }
+}
+
+impl std::fmt::Debug for ClientEvents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientEvents").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_events_skip_lagged_and_end_on_close() {
+        let (tx, rx) = broadcast::channel(1);
+        let mut events = ClientEvents::new(rx);
+
+        for tenant in ["a", "b"] {
+            tx.send(ClientEvent::Resynced {
+                tenants: vec![tenant.to_string()],
+                subscriptions: vec![],
+            })
+            .unwrap();
+        }
+        drop(tx);
+
+        assert_eq!(
+            events.next().await,
+            Some(ClientEvent::Resynced {
+                tenants: vec!["b".to_string()],
+                subscriptions: vec![],
+            })
+        );
+        assert_eq!(events.next().await, None);
+    }
+}
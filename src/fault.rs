@@ -0,0 +1,429 @@
+//! Fault injection for chaos testing
+//!
+//! A `FaultInjectingTransport` wraps another transport and makes it
+//! misbehave: messages are delayed, reordered or lost, the connection drops
+//! after a number of requests, and replies turn into server errors. Set on a
+//! client with `ClientBuilder::fault_injection`, it applies to every
+//! connection, so the client's reconnection logic gets exercised too:
+//!
+//! ```no_run
+//! use commy_sdk_rust::fault::FaultInjection;
+//! use commy_sdk_rust::message::ErrorCode;
+//! use commy_sdk_rust::Client;
+//! use std::time::Duration;
+//!
+//! let client = Client::builder("ws://localhost:9000")
+//!     .fault_injection(
+//!         FaultInjection::new()
+//!             .with_latency(Duration::from_millis(20))
+//!             .with_jitter(Duration::from_millis(50))
+//!             .with_drop_probability(0.01)
+//!             .with_disconnect_after(100)
+//!             .with_errors(ErrorCode::InternalError, 0.05)
+//!             .with_seed(7),
+//!     )
+//!     .build();
+//! ```
+
+use crate::error::{CommyError, Result};
+use crate::message::{ClientEnvelope, ErrorCode, ServerEnvelope, ServerMessage};
+use crate::transport::Transport;
+use crate::wire::WireFormat;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+/// Message text of substituted errors
+pub const INJECTED_ERROR_MESSAGE: &str = "injected fault";
+
+/// What goes wrong on a fault-injecting connection
+///
+/// Every fault is off by default. Probabilities range from 0.0 to 1.0 and
+/// apply to each message independently.
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjection {
+    /// Delay added to every message, in both directions
+    pub latency: Duration,
+
+    /// Largest random delay added on top of `latency`
+    ///
+    /// Received messages are delayed independently, so they can overtake
+    /// each other.
+    pub jitter: Duration,
+
+    /// Chance that a message, in either direction, is silently lost
+    pub drop_probability: f64,
+
+    /// Number of messages sent before the connection drops; the next send
+    /// closes it instead of going out
+    pub disconnect_after: Option<u32>,
+
+    /// Error code replacing replies, chosen with `error_probability`
+    pub error_code: Option<ErrorCode>,
+
+    /// Chance that a reply is replaced by `error_code`
+    pub error_probability: f64,
+
+    /// Seed making the random choices repeatable; each connection starts
+    /// from the same seed
+    pub seed: Option<u64>,
+}
+
+impl FaultInjection {
+    /// Inject no faults until configured
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the delay added to every message
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Set the largest random delay added on top of the latency
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the chance of losing each message, clamped to 0.0 to 1.0
+    pub fn with_drop_probability(mut self, probability: f64) -> Self {
+        self.drop_probability = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Drop each connection once it has sent `messages` messages
+    pub fn with_disconnect_after(mut self, messages: u32) -> Self {
+        self.disconnect_after = Some(messages);
+        self
+    }
+
+    /// Replace replies with `code` at the given chance, clamped to 0.0 to 1.0
+    ///
+    /// Pushes are never replaced, only messages answering a request.
+    pub fn with_errors(mut self, code: ErrorCode, probability: f64) -> Self {
+        self.error_code = Some(code);
+        self.error_probability = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Make the random choices repeatable
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    fn rng(&self) -> fastrand::Rng {
+        match self.seed {
+            Some(seed) => fastrand::Rng::with_seed(seed),
+            None => fastrand::Rng::new(),
+        }
+    }
+}
+
+/// Random choices shared by the sending and receiving sides
+struct Dice {
+    faults: FaultInjection,
+    rng: Mutex<fastrand::Rng>,
+}
+
+impl Dice {
+    fn drops(&self) -> bool {
+        self.faults.drop_probability > 0.0
+            && self.rng.lock().unwrap().f64() < self.faults.drop_probability
+    }
+
+    fn delay(&self) -> Duration {
+        let jitter = self.faults.jitter.mul_f64(self.rng.lock().unwrap().f64());
+        self.faults.latency + jitter
+    }
+
+    fn error(&self, envelope: &ServerEnvelope) -> Option<ErrorCode> {
+        let code = self.faults.error_code?;
+        let replaced = envelope.request_id.is_some()
+            && self.rng.lock().unwrap().f64() < self.faults.error_probability;
+        replaced.then_some(code)
+    }
+}
+
+/// Wraps a transport and injects the configured faults
+///
+/// Must be created from within a Tokio runtime.
+pub struct FaultInjectingTransport {
+    inner: Arc<dyn Transport>,
+    dice: Arc<Dice>,
+    sent: AtomicU32,
+    incoming: tokio::sync::Mutex<mpsc::UnboundedReceiver<Result<ServerEnvelope>>>,
+    closed: watch::Sender<bool>,
+    pump: JoinHandle<()>,
+}
+
+impl FaultInjectingTransport {
+    /// Inject `faults` into the messages crossing `inner`
+    pub fn new(inner: Box<dyn Transport>, faults: FaultInjection) -> Self {
+        let inner: Arc<dyn Transport> = Arc::from(inner);
+        let dice = Arc::new(Dice {
+            rng: Mutex::new(faults.rng()),
+            faults,
+        });
+        let (deliver, incoming) = mpsc::unbounded_channel();
+        let pump = tokio::spawn(pump(Arc::clone(&inner), Arc::clone(&dice), deliver));
+
+        Self {
+            inner,
+            dice,
+            sent: AtomicU32::new(0),
+            incoming: tokio::sync::Mutex::new(incoming),
+            closed: watch::channel(false).0,
+            pump,
+        }
+    }
+}
+
+/// Read from the wrapped transport, delivering each message after its delay
+///
+/// Delayed messages are delivered by their own tasks, which hold a sender
+/// each; the channel ends once the last of them has delivered.
+async fn pump(
+    inner: Arc<dyn Transport>,
+    dice: Arc<Dice>,
+    deliver: mpsc::UnboundedSender<Result<ServerEnvelope>>,
+) {
+    while let Some(received) = inner.recv().await {
+        let mut envelope = match received {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                let _ = deliver.send(Err(e));
+                continue;
+            }
+        };
+
+        if dice.drops() {
+            continue;
+        }
+        if let Some(code) = dice.error(&envelope) {
+            envelope.message = ServerMessage::Error {
+                code,
+                message: INJECTED_ERROR_MESSAGE.to_string(),
+            };
+        }
+
+        let delay = dice.delay();
+        if delay.is_zero() {
+            let _ = deliver.send(Ok(envelope));
+        } else {
+            let deliver = deliver.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                let _ = deliver.send(Ok(envelope));
+            });
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for FaultInjectingTransport {
+    async fn send(&self, envelope: ClientEnvelope) -> Result<()> {
+        if *self.closed.borrow() {
+            return Err(CommyError::ConnectionLost("transport closed".to_string()));
+        }
+
+        let sent = self.sent.fetch_add(1, Ordering::Relaxed);
+        if let Some(limit) = self.dice.faults.disconnect_after {
+            if sent >= limit {
+                self.close().await;
+                return Err(CommyError::ConnectionLost(format!(
+                    "{} after {} messages",
+                    INJECTED_ERROR_MESSAGE, limit
+                )));
+            }
+        }
+
+        let delay = self.dice.delay();
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        if self.dice.drops() {
+            return Ok(());
+        }
+        self.inner.send(envelope).await
+    }
+
+    async fn recv(&self) -> Option<Result<ServerEnvelope>> {
+        let mut closed = self.closed.subscribe();
+        let mut incoming = self.incoming.lock().await;
+        tokio::select! {
+            received = incoming.recv() => received,
+            _ = closed.wait_for(|closed| *closed) => None,
+        }
+    }
+
+    async fn close(&self) {
+        self.closed.send_replace(true);
+        self.inner.close().await;
+    }
+
+    fn wire_format(&self) -> WireFormat {
+        self.inner.wire_format()
+    }
+}
+
+impl Drop for FaultInjectingTransport {
+    fn drop(&mut self) {
+        self.pump.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::ClientMessage;
+    use crate::transport::{ChannelPeer, ChannelTransport};
+    use std::time::Instant;
+
+    fn heartbeat() -> ClientEnvelope {
+        ClientMessage::Heartbeat {
+            client_id: "c1".to_string(),
+        }
+        .into()
+    }
+
+    fn reply(request_id: &str) -> ServerEnvelope {
+        ServerEnvelope::reply(
+            request_id,
+            ServerMessage::Heartbeat {
+                timestamp: "now".to_string(),
+            },
+        )
+    }
+
+    fn faulty(faults: FaultInjection) -> (FaultInjectingTransport, ChannelPeer) {
+        let (transport, peer) = ChannelTransport::pair();
+        (
+            FaultInjectingTransport::new(Box::new(transport), faults),
+            peer,
+        )
+    }
+
+    #[test]
+    fn test_probabilities_are_clamped() {
+        let faults = FaultInjection::new()
+            .with_drop_probability(1.5)
+            .with_errors(ErrorCode::InternalError, -1.0);
+        assert_eq!(faults.drop_probability, 1.0);
+        assert_eq!(faults.error_probability, 0.0);
+        assert!(faults.disconnect_after.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_no_faults_passes_messages_through() {
+        let (transport, mut peer) = faulty(FaultInjection::new());
+
+        transport.send(heartbeat()).await.unwrap();
+        assert!(peer.from_client.recv().await.is_some());
+        peer.to_client.send(reply("1")).unwrap();
+        let received = transport.recv().await.unwrap().unwrap();
+        assert_eq!(received.request_id.as_deref(), Some("1"));
+    }
+
+    #[tokio::test]
+    async fn test_latency_delays_both_directions() {
+        let latency = Duration::from_millis(40);
+        let (transport, mut peer) = faulty(FaultInjection::new().with_latency(latency));
+
+        let started = Instant::now();
+        transport.send(heartbeat()).await.unwrap();
+        peer.from_client.recv().await.unwrap();
+        assert!(started.elapsed() >= latency);
+
+        let started = Instant::now();
+        peer.to_client.send(reply("1")).unwrap();
+        transport.recv().await.unwrap().unwrap();
+        assert!(started.elapsed() >= latency);
+    }
+
+    #[tokio::test]
+    async fn test_jitter_reorders_received_messages() {
+        let faults = FaultInjection::new()
+            .with_jitter(Duration::from_millis(50))
+            .with_seed(3);
+        let (transport, peer) = faulty(faults);
+
+        for id in 0..20 {
+            peer.to_client.send(reply(&id.to_string())).unwrap();
+        }
+        drop(peer);
+
+        let mut ids = Vec::new();
+        while let Some(received) = transport.recv().await {
+            ids.push(
+                received
+                    .unwrap()
+                    .request_id
+                    .unwrap()
+                    .parse::<u32>()
+                    .unwrap(),
+            );
+        }
+        assert_ne!(ids, (0..20).collect::<Vec<_>>());
+        ids.sort();
+        assert_eq!(ids, (0..20).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_certain_drop_loses_every_message() {
+        let (transport, mut peer) = faulty(FaultInjection::new().with_drop_probability(1.0));
+
+        transport.send(heartbeat()).await.unwrap();
+        peer.to_client.send(reply("1")).unwrap();
+        drop(peer.to_client);
+
+        assert!(transport.recv().await.is_none());
+        transport.close().await;
+        assert!(peer.from_client.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_after_closes_instead_of_sending() {
+        let (transport, mut peer) = faulty(FaultInjection::new().with_disconnect_after(2));
+
+        transport.send(heartbeat()).await.unwrap();
+        transport.send(heartbeat()).await.unwrap();
+        assert!(matches!(
+            transport.send(heartbeat()).await,
+            Err(CommyError::ConnectionLost(_))
+        ));
+
+        assert!(transport.recv().await.is_none());
+        assert!(peer.from_client.recv().await.is_some());
+        assert!(peer.from_client.recv().await.is_some());
+        assert!(peer.from_client.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_errors_replace_replies_but_not_pushes() {
+        let faults = FaultInjection::new().with_errors(ErrorCode::InternalError, 1.0);
+        let (transport, peer) = faulty(faults);
+
+        peer.to_client.send(reply("1")).unwrap();
+        let replaced = transport.recv().await.unwrap().unwrap();
+        assert_eq!(replaced.request_id.as_deref(), Some("1"));
+        assert!(matches!(
+            replaced.message,
+            ServerMessage::Error {
+                code: ErrorCode::InternalError,
+                ..
+            }
+        ));
+
+        let push = ServerMessage::Disconnected {
+            reason: "bye".to_string(),
+        };
+        peer.to_client.send(ServerEnvelope::from(push)).unwrap();
+        let kept = transport.recv().await.unwrap().unwrap();
+        assert!(matches!(kept.message, ServerMessage::Disconnected { .. }));
+    }
+}
@@ -1,721 +1,1209 @@
-//! Message types for Commy client protocol
-
-use serde::{Deserialize, Serialize};
-
-/// Messages sent from client to server
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type", content = "data")]
-pub enum ClientMessage {
-    /// Authenticate with a tenant
-    Authenticate {
-        tenant_id: String,
-        client_version: String,
-        #[serde(flatten)]
-        credentials: AuthCredentials,
-    },
-
-    /// Create a new tenant (admin operation)
-    CreateTenant {
-        tenant_id: String,
-        tenant_name: String,
-    },
-
-    /// Delete a tenant (admin operation)
-    DeleteTenant { tenant_id: String },
-
-    /// Create a new service
-    CreateService {
-        tenant_id: String,
-        service_name: String,
-    },
-
-    /// Get existing service (errors if not found)
-    GetService {
-        tenant_id: String,
-        service_name: String,
-    },
-
-    /// Delete a service
-    DeleteService {
-        tenant_id: String,
-        service_name: String,
-    },
-
-    /// Allocate a variable in a service
-    AllocateVariable {
-        service_id: String,
-        variable_name: String,
-        initial_data: Vec<u8>,
-    },
-
-    /// Read variable data
-    ReadVariable {
-        service_id: String,
-        variable_name: String,
-    },
-
-    /// Write variable data
-    WriteVariable {
-        service_id: String,
-        variable_name: String,
-        data: Vec<u8>,
-    },
-
-    /// Deallocate a variable
-    DeallocateVariable {
-        service_id: String,
-        variable_name: String,
-    },
-
-    /// Subscribe to variable changes
-    Subscribe {
-        service_id: String,
-        variable_name: String,
-    },
-
-    /// Unsubscribe from variable changes
-    Unsubscribe {
-        service_id: String,
-        variable_name: String,
-    },
-
-    /// Heartbeat/keepalive
-    Heartbeat { client_id: String },
-
-    /// Disconnect gracefully
-    Disconnect { client_id: String },
-
-    /// Request service file path for memory mapping (local clients)
-    GetServiceFilePath {
-        tenant_id: String,
-        service_name: String,
-    },
-
-    /// Notify server of variable changes detected locally
-    ReportVariableChanges {
-        service_id: String,
-        changed_variables: Vec<String>,
-        new_values: Vec<(String, Vec<u8>)>,
-    },
-}
-
-/// Messages received from server
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type", content = "data")]
-pub enum ServerMessage {
-    /// Authentication result
-    AuthenticationResult {
-        success: bool,
-        message: String,
-        server_version: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        permissions: Option<Vec<String>>,
-    },
-
-    /// Service created or retrieved
-    Service {
-        service_id: String,
-        service_name: String,
-        tenant_id: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        file_path: Option<String>,
-    },
-
-    /// Tenant created or retrieved
-    Tenant {
-        tenant_id: String,
-        tenant_name: String,
-    },
-
-    /// Tenant operation result (create/delete)
-    TenantResult {
-        success: bool,
-        tenant_id: String,
-        message: String,
-    },
-
-    /// Variable data response
-    VariableData {
-        service_id: String,
-        variable_name: String,
-        data: Vec<u8>,
-        version: u64,
-    },
-
-    /// Variable change notification
-    VariableChanged {
-        service_id: String,
-        variable_name: String,
-        data: Vec<u8>,
-        version: u64,
-    },
-
-    /// Operation result
-    Result {
-        request_id: String,
-        success: bool,
-        message: String,
-    },
-
-    /// Error response with explicit error type
-    Error { code: ErrorCode, message: String },
-
-    /// Server disconnecting
-    Disconnected { reason: String },
-
-    /// Service file path response (for local memory mapping)
-    ServiceFilePath {
-        service_id: String,
-        file_path: String,
-        file_size: u64,
-    },
-
-    /// Acknowledgment of variable changes
-    VariableChangesAcknowledged {
-        service_id: String,
-        changed_variables: Vec<String>,
-    },
-
-    /// Heartbeat response (keep-alive)
-    Heartbeat { timestamp: String },
-}
-
-/// Explicit error codes for API responses
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-pub enum ErrorCode {
-    /// Resource not found (e.g., service doesn't exist)
-    NotFound,
-    /// Permission denied (unauthorized for this resource)
-    PermissionDenied,
-    /// Invalid authentication credentials
-    Unauthorized,
-    /// Resource already exists
-    AlreadyExists,
-    /// Invalid request parameters
-    InvalidRequest,
-    /// Internal server error
-    InternalError,
-    /// Connection lost or disconnected
-    ConnectionLost,
-    /// Operation timed out
-    Timeout,
-}
-
-/// Authentication credentials
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "method")]
-pub enum AuthCredentials {
-    /// API key authentication
-    #[serde(rename = "api_key")]
-    ApiKey { key: String },
-
-    /// JWT token authentication
-    #[serde(rename = "jwt")]
-    Jwt { token: String },
-
-    /// Username/password authentication
-    #[serde(rename = "basic")]
-    Basic { username: String, password: String },
-
-    /// Custom authentication
-    #[serde(rename = "custom")]
-    Custom { data: serde_json::Value },
-}
-
-/// Service metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ServiceMetadata {
-    pub service_id: String,
-    pub service_name: String,
-    pub tenant_id: String,
-    pub created_at: chrono::DateTime<chrono::Utc>,
-    pub file_path: Option<String>,
-}
-
-/// Variable metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VariableMetadata {
-    pub name: String,
-    pub service_id: String,
-    pub offset: u64,
-    pub size: u64,
-    pub version: u64,
-    pub created_at: chrono::DateTime<chrono::Utc>,
-}
-
-/// Permission set
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub enum Permission {
-    Read,
-    Write,
-    Admin,
-    Execute,
-}
-
-/// Convert ErrorCode to CommyError
-impl From<ErrorCode> for crate::error::CommyError {
-    fn from(code: ErrorCode) -> Self {
-        match code {
-            ErrorCode::NotFound => {
-                crate::error::CommyError::NotFound("Resource not found".to_string())
-            }
-            ErrorCode::PermissionDenied => {
-                crate::error::CommyError::PermissionDenied("Permission denied".to_string())
-            }
-            ErrorCode::Unauthorized => {
-                crate::error::CommyError::Unauthorized("Unauthorized".to_string())
-            }
-            ErrorCode::AlreadyExists => {
-                crate::error::CommyError::AlreadyExists("Resource already exists".to_string())
-            }
-            ErrorCode::InvalidRequest => {
-                crate::error::CommyError::InvalidRequest("Invalid request".to_string())
-            }
-            ErrorCode::InternalError => {
-                crate::error::CommyError::Other("Internal server error".to_string())
-            }
-            ErrorCode::ConnectionLost => {
-                crate::error::CommyError::ConnectionLost("Connection lost".to_string())
-            }
-            ErrorCode::Timeout => crate::error::CommyError::Timeout,
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::error::CommyError;
-
-    #[test]
-    fn test_error_code_all_variants_convert_correctly() {
-        // NotFound
-        let e: CommyError = ErrorCode::NotFound.into();
-        assert!(matches!(e, CommyError::NotFound(_)));
-
-        // PermissionDenied
-        let e: CommyError = ErrorCode::PermissionDenied.into();
-        assert!(matches!(e, CommyError::PermissionDenied(_)));
-
-        // Unauthorized
-        let e: CommyError = ErrorCode::Unauthorized.into();
-        assert!(matches!(e, CommyError::Unauthorized(_)));
-
-        // AlreadyExists
-        let e: CommyError = ErrorCode::AlreadyExists.into();
-        assert!(matches!(e, CommyError::AlreadyExists(_)));
-
-        // InvalidRequest
-        let e: CommyError = ErrorCode::InvalidRequest.into();
-        assert!(matches!(e, CommyError::InvalidRequest(_)));
-
-        // InternalError -> Other
-        let e: CommyError = ErrorCode::InternalError.into();
-        assert!(matches!(e, CommyError::Other(_)));
-
-        // ConnectionLost
-        let e: CommyError = ErrorCode::ConnectionLost.into();
-        assert!(matches!(e, CommyError::ConnectionLost(_)));
-
-        // Timeout
-        let e: CommyError = ErrorCode::Timeout.into();
-        assert!(matches!(e, CommyError::Timeout));
-    }
-
-    #[test]
-    fn test_error_code_serialization_round_trip() {
-        let codes = [
-            ErrorCode::NotFound,
-            ErrorCode::PermissionDenied,
-            ErrorCode::Unauthorized,
-            ErrorCode::AlreadyExists,
-            ErrorCode::InvalidRequest,
-            ErrorCode::InternalError,
-            ErrorCode::ConnectionLost,
-            ErrorCode::Timeout,
-        ];
-        for code in codes {
-            let json = serde_json::to_string(&code).unwrap();
-            let decoded: ErrorCode = serde_json::from_str(&json).unwrap();
-            assert_eq!(decoded, code);
-        }
-    }
-
-    #[test]
-    fn test_client_message_authenticate_serialization() {
-        let msg = ClientMessage::Authenticate {
-            tenant_id: "tenant1".to_string(),
-            client_version: "0.1.0".to_string(),
-            credentials: AuthCredentials::ApiKey { key: "secret".to_string() },
-        };
-        let json = serde_json::to_string(&msg).unwrap();
-        let decoded: ClientMessage = serde_json::from_str(&json).unwrap();
-        assert!(matches!(decoded, ClientMessage::Authenticate { .. }));
-    }
-
-    #[test]
-    fn test_client_message_heartbeat_serialization() {
-        let msg = ClientMessage::Heartbeat { client_id: "c1".to_string() };
-        let json = serde_json::to_string(&msg).unwrap();
-        let decoded: ClientMessage = serde_json::from_str(&json).unwrap();
-        assert!(matches!(decoded, ClientMessage::Heartbeat { .. }));
-    }
-
-    #[test]
-    fn test_server_message_authentication_result_serialization() {
-        let msg = ServerMessage::AuthenticationResult {
-            success: true,
-            message: "OK".to_string(),
-            server_version: "0.1.0".to_string(),
-            permissions: Some(vec!["read".to_string()]),
-        };
-        let json = serde_json::to_string(&msg).unwrap();
-        let decoded: ServerMessage = serde_json::from_str(&json).unwrap();
-        assert!(matches!(decoded, ServerMessage::AuthenticationResult { success: true, .. }));
-    }
-
-    #[test]
-    fn test_server_message_error_serialization() {
-        let msg = ServerMessage::Error {
-            code: ErrorCode::NotFound,
-            message: "Not found".to_string(),
-        };
-        let json = serde_json::to_string(&msg).unwrap();
-        let decoded: ServerMessage = serde_json::from_str(&json).unwrap();
-        match decoded {
-            ServerMessage::Error { code, .. } => assert_eq!(code, ErrorCode::NotFound),
-            _ => panic!("Expected ServerMessage::Error"),
-        }
-    }
-
-    #[test]
-    fn test_auth_credentials_variants_serialization() {
-        let jwt = AuthCredentials::Jwt { token: "tok".to_string() };
-        let json = serde_json::to_string(&jwt).unwrap();
-        assert!(json.contains("jwt"));
-
-        let basic = AuthCredentials::Basic {
-            username: "user".to_string(),
-            password: "pass".to_string(),
-        };
-        let json = serde_json::to_string(&basic).unwrap();
-        assert!(json.contains("basic"));
-
-        let custom = AuthCredentials::Custom {
-            data: serde_json::json!({"key": "val"}),
-        };
-        let json = serde_json::to_string(&custom).unwrap();
-        assert!(json.contains("custom"));
-    }
-
-    // ─────────────────────────────────────────────────────────────
-    // ClientMessage round-trip tests (untested variants)
-    // ─────────────────────────────────────────────────────────────
-
-    fn round_trip_client(msg: ClientMessage) -> ClientMessage {
-        let json = serde_json::to_string(&msg).unwrap();
-        serde_json::from_str(&json).unwrap()
-    }
-
-    fn round_trip_server(msg: ServerMessage) -> ServerMessage {
-        let json = serde_json::to_string(&msg).unwrap();
-        serde_json::from_str(&json).unwrap()
-    }
-
-    #[test]
-    fn test_client_message_create_tenant_round_trip() {
-        let msg = ClientMessage::CreateTenant {
-            tenant_id: "t1".to_string(),
-            tenant_name: "Tenant One".to_string(),
-        };
-        match round_trip_client(msg) {
-            ClientMessage::CreateTenant { tenant_id, tenant_name } => {
-                assert_eq!(tenant_id, "t1");
-                assert_eq!(tenant_name, "Tenant One");
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_client_message_delete_tenant_round_trip() {
-        let msg = ClientMessage::DeleteTenant { tenant_id: "t2".to_string() };
-        match round_trip_client(msg) {
-            ClientMessage::DeleteTenant { tenant_id } => assert_eq!(tenant_id, "t2"),
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_client_message_allocate_variable_round_trip() {
-        let initial = vec![1u8, 2, 3, 4];
-        let msg = ClientMessage::AllocateVariable {
-            service_id: "svc1".to_string(),
-            variable_name: "counter".to_string(),
-            initial_data: initial.clone(),
-        };
-        match round_trip_client(msg) {
-            ClientMessage::AllocateVariable { service_id, variable_name, initial_data } => {
-                assert_eq!(service_id, "svc1");
-                assert_eq!(variable_name, "counter");
-                assert_eq!(initial_data, initial);
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_client_message_read_variable_round_trip() {
-        let msg = ClientMessage::ReadVariable {
-            service_id: "svc1".to_string(),
-            variable_name: "counter".to_string(),
-        };
-        match round_trip_client(msg) {
-            ClientMessage::ReadVariable { service_id, variable_name } => {
-                assert_eq!(service_id, "svc1");
-                assert_eq!(variable_name, "counter");
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_client_message_write_variable_round_trip() {
-        let data = vec![9u8, 8, 7];
-        let msg = ClientMessage::WriteVariable {
-            service_id: "svc2".to_string(),
-            variable_name: "temp".to_string(),
-            data: data.clone(),
-        };
-        match round_trip_client(msg) {
-            ClientMessage::WriteVariable { service_id, variable_name, data: d } => {
-                assert_eq!(service_id, "svc2");
-                assert_eq!(variable_name, "temp");
-                assert_eq!(d, data);
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_client_message_deallocate_variable_round_trip() {
-        let msg = ClientMessage::DeallocateVariable {
-            service_id: "svc3".to_string(),
-            variable_name: "old_var".to_string(),
-        };
-        match round_trip_client(msg) {
-            ClientMessage::DeallocateVariable { service_id, variable_name } => {
-                assert_eq!(service_id, "svc3");
-                assert_eq!(variable_name, "old_var");
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_client_message_subscribe_round_trip() {
-        let msg = ClientMessage::Subscribe {
-            service_id: "svc4".to_string(),
-            variable_name: "level".to_string(),
-        };
-        match round_trip_client(msg) {
-            ClientMessage::Subscribe { service_id, variable_name } => {
-                assert_eq!(service_id, "svc4");
-                assert_eq!(variable_name, "level");
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_client_message_unsubscribe_round_trip() {
-        let msg = ClientMessage::Unsubscribe {
-            service_id: "svc5".to_string(),
-            variable_name: "level".to_string(),
-        };
-        match round_trip_client(msg) {
-            ClientMessage::Unsubscribe { service_id, variable_name } => {
-                assert_eq!(service_id, "svc5");
-                assert_eq!(variable_name, "level");
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_client_message_disconnect_round_trip() {
-        let msg = ClientMessage::Disconnect { client_id: "cli_99".to_string() };
-        match round_trip_client(msg) {
-            ClientMessage::Disconnect { client_id } => assert_eq!(client_id, "cli_99"),
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_client_message_get_service_file_path_round_trip() {
-        let msg = ClientMessage::GetServiceFilePath {
-            tenant_id: "t1".to_string(),
-            service_name: "cache".to_string(),
-        };
-        match round_trip_client(msg) {
-            ClientMessage::GetServiceFilePath { tenant_id, service_name } => {
-                assert_eq!(tenant_id, "t1");
-                assert_eq!(service_name, "cache");
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_client_message_report_variable_changes_round_trip() {
-        let new_values = vec![("v1".to_string(), vec![1u8, 2, 3])];
-        let msg = ClientMessage::ReportVariableChanges {
-            service_id: "svc6".to_string(),
-            changed_variables: vec!["v1".to_string()],
-            new_values: new_values.clone(),
-        };
-        match round_trip_client(msg) {
-            ClientMessage::ReportVariableChanges {
-                service_id,
-                changed_variables,
-                new_values: nv,
-            } => {
-                assert_eq!(service_id, "svc6");
-                assert_eq!(changed_variables, vec!["v1".to_string()]);
-                assert_eq!(nv, new_values);
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    // ─────────────────────────────────────────────────────────────
-    // ServerMessage round-trip tests (untested variants)
-    // ─────────────────────────────────────────────────────────────
-
-    #[test]
-    fn test_server_message_tenant_round_trip() {
-        let msg = ServerMessage::Tenant {
-            tenant_id: "t1".to_string(),
-            tenant_name: "Alpha Corp".to_string(),
-        };
-        match round_trip_server(msg) {
-            ServerMessage::Tenant { tenant_id, tenant_name } => {
-                assert_eq!(tenant_id, "t1");
-                assert_eq!(tenant_name, "Alpha Corp");
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_server_message_tenant_result_round_trip() {
-        let msg = ServerMessage::TenantResult {
-            success: true,
-            tenant_id: "t2".to_string(),
-            message: "Tenant created".to_string(),
-        };
-        match round_trip_server(msg) {
-            ServerMessage::TenantResult { success, tenant_id, message } => {
-                assert!(success);
-                assert_eq!(tenant_id, "t2");
-                assert_eq!(message, "Tenant created");
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_server_message_variable_data_round_trip() {
-        let data = vec![5u8, 6, 7, 8];
-        let msg = ServerMessage::VariableData {
-            service_id: "svc1".to_string(),
-            variable_name: "x".to_string(),
-            data: data.clone(),
-            version: 42,
-        };
-        match round_trip_server(msg) {
-            ServerMessage::VariableData { service_id, variable_name, data: d, version } => {
-                assert_eq!(service_id, "svc1");
-                assert_eq!(variable_name, "x");
-                assert_eq!(d, data);
-                assert_eq!(version, 42);
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_server_message_variable_changed_round_trip() {
-        let data = vec![11u8, 22];
-        let msg = ServerMessage::VariableChanged {
-            service_id: "svc2".to_string(),
-            variable_name: "y".to_string(),
-            data: data.clone(),
-            version: 7,
-        };
-        match round_trip_server(msg) {
-            ServerMessage::VariableChanged { service_id, variable_name, data: d, version } => {
-                assert_eq!(service_id, "svc2");
-                assert_eq!(variable_name, "y");
-                assert_eq!(d, data);
-                assert_eq!(version, 7);
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_server_message_disconnected_round_trip() {
-        let msg = ServerMessage::Disconnected { reason: "server shutdown".to_string() };
-        match round_trip_server(msg) {
-            ServerMessage::Disconnected { reason } => {
-                assert_eq!(reason, "server shutdown");
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_server_message_service_file_path_round_trip() {
-        let msg = ServerMessage::ServiceFilePath {
-            service_id: "svc7".to_string(),
-            file_path: "/var/commy/service.mem".to_string(),
-            file_size: 1_048_576,
-        };
-        match round_trip_server(msg) {
-            ServerMessage::ServiceFilePath { service_id, file_path, file_size } => {
-                assert_eq!(service_id, "svc7");
-                assert_eq!(file_path, "/var/commy/service.mem");
-                assert_eq!(file_size, 1_048_576);
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_server_message_variable_changes_acknowledged_round_trip() {
-        let msg = ServerMessage::VariableChangesAcknowledged {
-            service_id: "svc8".to_string(),
-            changed_variables: vec!["a".to_string(), "b".to_string()],
-        };
-        match round_trip_server(msg) {
-            ServerMessage::VariableChangesAcknowledged { service_id, changed_variables } => {
-                assert_eq!(service_id, "svc8");
-                assert_eq!(changed_variables, vec!["a".to_string(), "b".to_string()]);
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-
-    #[test]
-    fn test_server_message_heartbeat_round_trip() {
-        let msg = ServerMessage::Heartbeat { timestamp: "2025-01-01T00:00:00Z".to_string() };
-        match round_trip_server(msg) {
-            ServerMessage::Heartbeat { timestamp } => {
-                assert_eq!(timestamp, "2025-01-01T00:00:00Z");
-            }
-            _ => panic!("Wrong variant"),
-        }
-    }
-}
+//! Message types for Commy client protocol
+
+use crate::capabilities::Capabilities;
+use serde::{Deserialize, Serialize};
+
+/// Messages sent from client to server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum ClientMessage {
+    /// Open the session by exchanging capabilities; answered by `Welcome`
+    Hello {
+        client_version: String,
+        capabilities: Capabilities,
+    },
+
+    /// Authenticate with a tenant
+    Authenticate {
+        tenant_id: String,
+        client_version: String,
+        #[serde(flatten)]
+        credentials: AuthCredentials,
+    },
+
+    /// Create a new tenant (admin operation)
+    CreateTenant {
+        tenant_id: String,
+        tenant_name: String,
+    },
+
+    /// Delete a tenant (admin operation)
+    DeleteTenant { tenant_id: String },
+
+    /// Create a new service
+    CreateService {
+        tenant_id: String,
+        service_name: String,
+    },
+
+    /// Get existing service (errors if not found)
+    GetService {
+        tenant_id: String,
+        service_name: String,
+    },
+
+    /// Delete a service
+    DeleteService {
+        tenant_id: String,
+        service_name: String,
+    },
+
+    /// Allocate a variable in a service
+    AllocateVariable {
+        service_id: String,
+        variable_name: String,
+        #[serde(with = "serde_bytes")]
+        initial_data: Vec<u8>,
+    },
+
+    /// Read variable data
+    ReadVariable {
+        service_id: String,
+        variable_name: String,
+    },
+
+    /// Write variable data
+    WriteVariable {
+        service_id: String,
+        variable_name: String,
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+    },
+
+    /// Write variable data only if it is still at `expected_version`
+    CompareAndSwap {
+        service_id: String,
+        variable_name: String,
+        expected_version: u64,
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+    },
+
+    /// Deallocate a variable
+    DeallocateVariable {
+        service_id: String,
+        variable_name: String,
+    },
+
+    /// Subscribe to variable changes
+    Subscribe {
+        service_id: String,
+        variable_name: String,
+    },
+
+    /// Unsubscribe from variable changes
+    Unsubscribe {
+        service_id: String,
+        variable_name: String,
+    },
+
+    /// Heartbeat/keepalive
+    Heartbeat { client_id: String },
+
+    /// Disconnect gracefully
+    Disconnect { client_id: String },
+
+    /// Request service file path for memory mapping (local clients)
+    GetServiceFilePath {
+        tenant_id: String,
+        service_name: String,
+    },
+
+    /// Notify server of variable changes detected locally
+    ReportVariableChanges {
+        service_id: String,
+        changed_variables: Vec<String>,
+        new_values: Vec<(String, Vec<u8>)>,
+    },
+}
+
+/// Messages received from server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum ServerMessage {
+    /// Answer to `Hello` with the server's capabilities
+    Welcome {
+        server_version: String,
+        capabilities: Capabilities,
+    },
+
+    /// Authentication result
+    AuthenticationResult {
+        success: bool,
+        message: String,
+        server_version: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        permissions: Option<Vec<String>>,
+    },
+
+    /// Service created or retrieved
+    Service {
+        service_id: String,
+        service_name: String,
+        tenant_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file_path: Option<String>,
+    },
+
+    /// Tenant created or retrieved
+    Tenant {
+        tenant_id: String,
+        tenant_name: String,
+    },
+
+    /// Tenant operation result (create/delete)
+    TenantResult {
+        success: bool,
+        tenant_id: String,
+        message: String,
+    },
+
+    /// Variable data response
+    VariableData {
+        service_id: String,
+        variable_name: String,
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+        version: u64,
+    },
+
+    /// Write acknowledgment carrying the variable's new version
+    VariableWritten {
+        service_id: String,
+        variable_name: String,
+        version: u64,
+    },
+
+    /// Compare-and-swap rejected because the variable moved on
+    VersionConflict {
+        service_id: String,
+        variable_name: String,
+        current_version: u64,
+        #[serde(with = "serde_bytes")]
+        current_data: Vec<u8>,
+    },
+
+    /// Variable change notification
+    VariableChanged {
+        service_id: String,
+        variable_name: String,
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+        version: u64,
+    },
+
+    /// Operation result
+    Result {
+        request_id: String,
+        success: bool,
+        message: String,
+    },
+
+    /// Error response with explicit error type
+    Error { code: ErrorCode, message: String },
+
+    /// Server disconnecting
+    Disconnected { reason: String },
+
+    /// Service file path response (for local memory mapping)
+    ServiceFilePath {
+        service_id: String,
+        file_path: String,
+        file_size: u64,
+    },
+
+    /// Acknowledgment of variable changes
+    VariableChangesAcknowledged {
+        service_id: String,
+        changed_variables: Vec<String>,
+    },
+
+    /// Heartbeat response (keep-alive)
+    Heartbeat { timestamp: String },
+
+    /// A message this client does not understand, kept as the raw JSON
+    /// object (`type` and `data` fields)
+    ///
+    /// Newer servers may send message types this client predates; they decode
+    /// to this variant instead of failing, and re-serialize unchanged. Binary
+    /// data sent over MessagePack has no JSON counterpart and is kept as an
+    /// array of byte values instead. A known `type` with a malformed payload
+    /// is still a decoding error.
+    #[serde(untagged, deserialize_with = "deserialize_unknown")]
+    Unknown(serde_json::Value),
+}
+
+/// `type` tags of every `ServerMessage` variant except `Unknown`
+const KNOWN_SERVER_MESSAGE_TYPES: &[&str] = &[
+    "Welcome",
+    "AuthenticationResult",
+    "Service",
+    "Tenant",
+    "TenantResult",
+    "VariableData",
+    "VariableWritten",
+    "VersionConflict",
+    "VariableChanged",
+    "Result",
+    "Error",
+    "Disconnected",
+    "ServiceFilePath",
+    "VariableChangesAcknowledged",
+    "Heartbeat",
+];
+
+/// Decode `ServerMessage::Unknown`, refusing messages whose `type` is known
+///
+/// Serde only tries the untagged variant after the tagged ones failed, so a
+/// known tag reaching this point means its payload did not match.
+fn deserialize_unknown<'de, D>(deserializer: D) -> std::result::Result<serde_json::Value, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw = deserializer.deserialize_any(RawValueVisitor)?;
+    match raw.get("type").and_then(|t| t.as_str()) {
+        Some(kind) if KNOWN_SERVER_MESSAGE_TYPES.contains(&kind) => Err(serde::de::Error::custom(
+            format!("malformed {} message", kind),
+        )),
+        _ => Ok(raw),
+    }
+}
+
+/// Builds the JSON value of an unknown message from any self-describing
+/// format, turning binary data into an array of byte values
+struct RawValueVisitor;
+
+/// A value decoded by `RawValueVisitor`, for nested values
+struct RawValue(serde_json::Value);
+
+impl<'de> Deserialize<'de> for RawValue {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(RawValueVisitor).map(RawValue)
+    }
+}
+
+impl<'de> serde::de::Visitor<'de> for RawValueVisitor {
+    type Value = serde_json::Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> std::result::Result<Self::Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_i64<E>(self, v: i64) -> std::result::Result<Self::Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_u64<E>(self, v: u64) -> std::result::Result<Self::Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_f64<E>(self, v: f64) -> std::result::Result<Self::Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E> {
+        Ok(v.into())
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> std::result::Result<Self::Value, E> {
+        Ok(v.iter().copied().map(serde_json::Value::from).collect())
+    }
+
+    fn visit_none<E>(self) -> std::result::Result<Self::Value, E> {
+        Ok(serde_json::Value::Null)
+    }
+
+    fn visit_unit<E>(self) -> std::result::Result<Self::Value, E> {
+        Ok(serde_json::Value::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        let mut values = Vec::new();
+        while let Some(RawValue(value)) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(values.into())
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let mut object = serde_json::Map::new();
+        while let Some((key, RawValue(value))) = map.next_entry::<String, RawValue>()? {
+            object.insert(key, value);
+        }
+        Ok(object.into())
+    }
+}
+
+/// Client message tagged with an optional request ID
+///
+/// The request ID is flattened next to the `type`/`data` fields, so servers
+/// that do not know about request IDs still see a regular `ClientMessage`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientEnvelope {
+    /// Correlation ID echoed back by the server on the matching response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+
+    /// The wrapped message
+    #[serde(flatten)]
+    pub message: ClientMessage,
+}
+
+impl ClientEnvelope {
+    /// Wrap a message that expects a correlated response
+    pub fn request(request_id: impl Into<String>, message: ClientMessage) -> Self {
+        Self {
+            request_id: Some(request_id.into()),
+            message,
+        }
+    }
+}
+
+impl From<ClientMessage> for ClientEnvelope {
+    fn from(message: ClientMessage) -> Self {
+        Self {
+            request_id: None,
+            message,
+        }
+    }
+}
+
+/// Server message tagged with the request ID it answers, if any
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerEnvelope {
+    /// Request ID copied from the `ClientEnvelope` this message answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+
+    /// The wrapped message
+    #[serde(flatten)]
+    pub message: ServerMessage,
+}
+
+impl ServerEnvelope {
+    /// Wrap a response to the given request
+    pub fn reply(request_id: impl Into<String>, message: ServerMessage) -> Self {
+        Self {
+            request_id: Some(request_id.into()),
+            message,
+        }
+    }
+}
+
+impl From<ServerMessage> for ServerEnvelope {
+    fn from(message: ServerMessage) -> Self {
+        Self {
+            request_id: None,
+            message,
+        }
+    }
+}
+
+impl ServerMessage {
+    /// Whether the server sends this message on its own rather than as a reply
+    ///
+    /// Pushes are routed to push subscribers so they never take the place of
+    /// the response a pending request is waiting for. Unknown messages count
+    /// as pushes for the same reason: a server that omits request IDs must not
+    /// have one answer a request it was not meant for.
+    pub fn is_push(&self) -> bool {
+        matches!(
+            self,
+            ServerMessage::VariableChanged { .. }
+                | ServerMessage::Disconnected { .. }
+                | ServerMessage::Heartbeat { .. }
+                | ServerMessage::Unknown(_)
+        )
+    }
+
+    /// The `type` field of an unknown message, if present
+    pub fn unknown_type(&self) -> Option<&str> {
+        match self {
+            ServerMessage::Unknown(raw) => raw.get("type").and_then(|t| t.as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// Explicit error codes for API responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// Resource not found (e.g., service doesn't exist)
+    NotFound,
+    /// Permission denied (unauthorized for this resource)
+    PermissionDenied,
+    /// Invalid authentication credentials
+    Unauthorized,
+    /// Resource already exists
+    AlreadyExists,
+    /// Invalid request parameters
+    InvalidRequest,
+    /// Internal server error
+    InternalError,
+    /// Connection lost or disconnected
+    ConnectionLost,
+    /// Operation timed out
+    Timeout,
+    /// A code this client does not know
+    ///
+    /// Newer servers may send codes this client predates. The code itself is
+    /// not kept; the error's message still describes the failure.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Authentication credentials
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method")]
+pub enum AuthCredentials {
+    /// API key authentication
+    #[serde(rename = "api_key")]
+    ApiKey { key: String },
+
+    /// JWT token authentication
+    #[serde(rename = "jwt")]
+    Jwt { token: String },
+
+    /// Username/password authentication
+    #[serde(rename = "basic")]
+    Basic { username: String, password: String },
+
+    /// Custom authentication
+    #[serde(rename = "custom")]
+    Custom { data: serde_json::Value },
+}
+
+/// Service metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceMetadata {
+    pub service_id: String,
+    pub service_name: String,
+    pub tenant_id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub file_path: Option<String>,
+}
+
+/// Variable metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariableMetadata {
+    pub name: String,
+    pub service_id: String,
+    pub offset: u64,
+    pub size: u64,
+    pub version: u64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Permission set
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Permission {
+    Read,
+    Write,
+    Admin,
+    Execute,
+}
+
+/// Convert ErrorCode to CommyError
+impl From<ErrorCode> for crate::error::CommyError {
+    fn from(code: ErrorCode) -> Self {
+        match code {
+            ErrorCode::NotFound => {
+                crate::error::CommyError::NotFound("Resource not found".to_string())
+            }
+            ErrorCode::PermissionDenied => {
+                crate::error::CommyError::PermissionDenied("Permission denied".to_string())
+            }
+            ErrorCode::Unauthorized => {
+                crate::error::CommyError::Unauthorized("Unauthorized".to_string())
+            }
+            ErrorCode::AlreadyExists => {
+                crate::error::CommyError::AlreadyExists("Resource already exists".to_string())
+            }
+            ErrorCode::InvalidRequest => {
+                crate::error::CommyError::InvalidRequest("Invalid request".to_string())
+            }
+            ErrorCode::InternalError => {
+                crate::error::CommyError::Other("Internal server error".to_string())
+            }
+            ErrorCode::ConnectionLost => {
+                crate::error::CommyError::ConnectionLost("Connection lost".to_string())
+            }
+            ErrorCode::Timeout => crate::error::CommyError::Timeout,
+            ErrorCode::Unknown => crate::error::CommyError::Other("Server error".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CommyError;
+
+    #[test]
+    fn test_error_code_all_variants_convert_correctly() {
+        // NotFound
+        let e: CommyError = ErrorCode::NotFound.into();
+        assert!(matches!(e, CommyError::NotFound(_)));
+
+        // PermissionDenied
+        let e: CommyError = ErrorCode::PermissionDenied.into();
+        assert!(matches!(e, CommyError::PermissionDenied(_)));
+
+        // Unauthorized
+        let e: CommyError = ErrorCode::Unauthorized.into();
+        assert!(matches!(e, CommyError::Unauthorized(_)));
+
+        // AlreadyExists
+        let e: CommyError = ErrorCode::AlreadyExists.into();
+        assert!(matches!(e, CommyError::AlreadyExists(_)));
+
+        // InvalidRequest
+        let e: CommyError = ErrorCode::InvalidRequest.into();
+        assert!(matches!(e, CommyError::InvalidRequest(_)));
+
+        // InternalError -> Other
+        let e: CommyError = ErrorCode::InternalError.into();
+        assert!(matches!(e, CommyError::Other(_)));
+
+        // ConnectionLost
+        let e: CommyError = ErrorCode::ConnectionLost.into();
+        assert!(matches!(e, CommyError::ConnectionLost(_)));
+
+        // Timeout
+        let e: CommyError = ErrorCode::Timeout.into();
+        assert!(matches!(e, CommyError::Timeout));
+
+        // Unknown -> Other
+        let e: CommyError = ErrorCode::Unknown.into();
+        assert!(matches!(e, CommyError::Other(_)));
+    }
+
+    #[test]
+    fn test_unknown_error_code_decodes() {
+        let code: ErrorCode = serde_json::from_str("\"QUOTA_EXCEEDED\"").unwrap();
+        assert_eq!(code, ErrorCode::Unknown);
+
+        let known: ErrorCode = serde_json::from_str("\"NOT_FOUND\"").unwrap();
+        assert_eq!(known, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn test_unknown_server_message_keeps_raw_json() {
+        let json = r#"{"type":"QuotaWarning","data":{"used":95}}"#;
+        let msg: ServerMessage = serde_json::from_str(json).unwrap();
+
+        assert_eq!(msg.unknown_type(), Some("QuotaWarning"));
+        match &msg {
+            ServerMessage::Unknown(raw) => assert_eq!(raw["data"]["used"], 95),
+            other => panic!("Expected Unknown, got {:?}", other),
+        }
+        let reencoded: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&msg).unwrap()).unwrap();
+        assert_eq!(reencoded, serde_json::from_str::<serde_json::Value>(json).unwrap());
+    }
+
+    #[test]
+    fn test_malformed_known_server_message_is_an_error() {
+        let json = r#"{"type":"Heartbeat","data":{"wrong":1}}"#;
+        assert!(serde_json::from_str::<ServerMessage>(json).is_err());
+        assert!(serde_json::from_str::<ServerEnvelope>(json).is_err());
+
+        let missing_data = r#"{"type":"VariableChanged"}"#;
+        assert!(serde_json::from_str::<ServerMessage>(missing_data).is_err());
+    }
+
+    #[test]
+    fn test_unknown_message_in_envelope_keeps_request_id() {
+        let json = r#"{"request_id":"4","type":"QuotaWarning","data":{}}"#;
+        let envelope: ServerEnvelope = serde_json::from_str(json).unwrap();
+        assert_eq!(envelope.request_id.as_deref(), Some("4"));
+        assert_eq!(envelope.message.unknown_type(), Some("QuotaWarning"));
+    }
+
+    #[test]
+    fn test_error_code_serialization_round_trip() {
+        let codes = [
+            ErrorCode::NotFound,
+            ErrorCode::PermissionDenied,
+            ErrorCode::Unauthorized,
+            ErrorCode::AlreadyExists,
+            ErrorCode::InvalidRequest,
+            ErrorCode::InternalError,
+            ErrorCode::ConnectionLost,
+            ErrorCode::Timeout,
+        ];
+        for code in codes {
+            let json = serde_json::to_string(&code).unwrap();
+            let decoded: ErrorCode = serde_json::from_str(&json).unwrap();
+            assert_eq!(decoded, code);
+        }
+    }
+
+    #[test]
+    fn test_client_message_authenticate_serialization() {
+        let msg = ClientMessage::Authenticate {
+            tenant_id: "tenant1".to_string(),
+            client_version: "0.1.0".to_string(),
+            credentials: AuthCredentials::ApiKey { key: "secret".to_string() },
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        let decoded: ClientMessage = serde_json::from_str(&json).unwrap();
+        assert!(matches!(decoded, ClientMessage::Authenticate { .. }));
+    }
+
+    #[test]
+    fn test_client_message_heartbeat_serialization() {
+        let msg = ClientMessage::Heartbeat { client_id: "c1".to_string() };
+        let json = serde_json::to_string(&msg).unwrap();
+        let decoded: ClientMessage = serde_json::from_str(&json).unwrap();
+        assert!(matches!(decoded, ClientMessage::Heartbeat { .. }));
+    }
+
+    #[test]
+    fn test_server_message_authentication_result_serialization() {
+        let msg = ServerMessage::AuthenticationResult {
+            success: true,
+            message: "OK".to_string(),
+            server_version: "0.1.0".to_string(),
+            permissions: Some(vec!["read".to_string()]),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        let decoded: ServerMessage = serde_json::from_str(&json).unwrap();
+        assert!(matches!(decoded, ServerMessage::AuthenticationResult { success: true, .. }));
+    }
+
+    #[test]
+    fn test_server_message_error_serialization() {
+        let msg = ServerMessage::Error {
+            code: ErrorCode::NotFound,
+            message: "Not found".to_string(),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        let decoded: ServerMessage = serde_json::from_str(&json).unwrap();
+        match decoded {
+            ServerMessage::Error { code, .. } => assert_eq!(code, ErrorCode::NotFound),
+            _ => panic!("Expected ServerMessage::Error"),
+        }
+    }
+
+    #[test]
+    fn test_auth_credentials_variants_serialization() {
+        let jwt = AuthCredentials::Jwt { token: "tok".to_string() };
+        let json = serde_json::to_string(&jwt).unwrap();
+        assert!(json.contains("jwt"));
+
+        let basic = AuthCredentials::Basic {
+            username: "user".to_string(),
+            password: "pass".to_string(),
+        };
+        let json = serde_json::to_string(&basic).unwrap();
+        assert!(json.contains("basic"));
+
+        let custom = AuthCredentials::Custom {
+            data: serde_json::json!({"key": "val"}),
+        };
+        let json = serde_json::to_string(&custom).unwrap();
+        assert!(json.contains("custom"));
+    }
+
+    // ─────────────────────────────────────────────────────────────
+    // ClientMessage round-trip tests (untested variants)
+    // ─────────────────────────────────────────────────────────────
+
+    fn round_trip_client(msg: ClientMessage) -> ClientMessage {
+        let json = serde_json::to_string(&msg).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn round_trip_server(msg: ServerMessage) -> ServerMessage {
+        let json = serde_json::to_string(&msg).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_client_message_create_tenant_round_trip() {
+        let msg = ClientMessage::CreateTenant {
+            tenant_id: "t1".to_string(),
+            tenant_name: "Tenant One".to_string(),
+        };
+        match round_trip_client(msg) {
+            ClientMessage::CreateTenant { tenant_id, tenant_name } => {
+                assert_eq!(tenant_id, "t1");
+                assert_eq!(tenant_name, "Tenant One");
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_client_message_delete_tenant_round_trip() {
+        let msg = ClientMessage::DeleteTenant { tenant_id: "t2".to_string() };
+        match round_trip_client(msg) {
+            ClientMessage::DeleteTenant { tenant_id } => assert_eq!(tenant_id, "t2"),
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_client_message_allocate_variable_round_trip() {
+        let initial = vec![1u8, 2, 3, 4];
+        let msg = ClientMessage::AllocateVariable {
+            service_id: "svc1".to_string(),
+            variable_name: "counter".to_string(),
+            initial_data: initial.clone(),
+        };
+        match round_trip_client(msg) {
+            ClientMessage::AllocateVariable { service_id, variable_name, initial_data } => {
+                assert_eq!(service_id, "svc1");
+                assert_eq!(variable_name, "counter");
+                assert_eq!(initial_data, initial);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_client_message_read_variable_round_trip() {
+        let msg = ClientMessage::ReadVariable {
+            service_id: "svc1".to_string(),
+            variable_name: "counter".to_string(),
+        };
+        match round_trip_client(msg) {
+            ClientMessage::ReadVariable { service_id, variable_name } => {
+                assert_eq!(service_id, "svc1");
+                assert_eq!(variable_name, "counter");
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_client_message_write_variable_round_trip() {
+        let data = vec![9u8, 8, 7];
+        let msg = ClientMessage::WriteVariable {
+            service_id: "svc2".to_string(),
+            variable_name: "temp".to_string(),
+            data: data.clone(),
+        };
+        match round_trip_client(msg) {
+            ClientMessage::WriteVariable { service_id, variable_name, data: d } => {
+                assert_eq!(service_id, "svc2");
+                assert_eq!(variable_name, "temp");
+                assert_eq!(d, data);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_client_message_deallocate_variable_round_trip() {
+        let msg = ClientMessage::DeallocateVariable {
+            service_id: "svc3".to_string(),
+            variable_name: "old_var".to_string(),
+        };
+        match round_trip_client(msg) {
+            ClientMessage::DeallocateVariable { service_id, variable_name } => {
+                assert_eq!(service_id, "svc3");
+                assert_eq!(variable_name, "old_var");
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_client_message_hello_round_trip() {
+        let msg = ClientMessage::Hello {
+            client_version: "0.1.0".to_string(),
+            capabilities: Capabilities::client(),
+        };
+        match round_trip_client(msg) {
+            ClientMessage::Hello { client_version, capabilities } => {
+                assert_eq!(client_version, "0.1.0");
+                assert_eq!(capabilities, Capabilities::client());
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_client_message_compare_and_swap_round_trip() {
+        let msg = ClientMessage::CompareAndSwap {
+            service_id: "svc3".to_string(),
+            variable_name: "counter".to_string(),
+            expected_version: 5,
+            data: vec![1, 2],
+        };
+        match round_trip_client(msg) {
+            ClientMessage::CompareAndSwap { service_id, variable_name, expected_version, data } => {
+                assert_eq!(service_id, "svc3");
+                assert_eq!(variable_name, "counter");
+                assert_eq!(expected_version, 5);
+                assert_eq!(data, vec![1, 2]);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_client_message_subscribe_round_trip() {
+        let msg = ClientMessage::Subscribe {
+            service_id: "svc4".to_string(),
+            variable_name: "level".to_string(),
+        };
+        match round_trip_client(msg) {
+            ClientMessage::Subscribe { service_id, variable_name } => {
+                assert_eq!(service_id, "svc4");
+                assert_eq!(variable_name, "level");
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_client_message_unsubscribe_round_trip() {
+        let msg = ClientMessage::Unsubscribe {
+            service_id: "svc5".to_string(),
+            variable_name: "level".to_string(),
+        };
+        match round_trip_client(msg) {
+            ClientMessage::Unsubscribe { service_id, variable_name } => {
+                assert_eq!(service_id, "svc5");
+                assert_eq!(variable_name, "level");
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_client_message_disconnect_round_trip() {
+        let msg = ClientMessage::Disconnect { client_id: "cli_99".to_string() };
+        match round_trip_client(msg) {
+            ClientMessage::Disconnect { client_id } => assert_eq!(client_id, "cli_99"),
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_client_message_get_service_file_path_round_trip() {
+        let msg = ClientMessage::GetServiceFilePath {
+            tenant_id: "t1".to_string(),
+            service_name: "cache".to_string(),
+        };
+        match round_trip_client(msg) {
+            ClientMessage::GetServiceFilePath { tenant_id, service_name } => {
+                assert_eq!(tenant_id, "t1");
+                assert_eq!(service_name, "cache");
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_client_message_report_variable_changes_round_trip() {
+        let new_values = vec![("v1".to_string(), vec![1u8, 2, 3])];
+        let msg = ClientMessage::ReportVariableChanges {
+            service_id: "svc6".to_string(),
+            changed_variables: vec!["v1".to_string()],
+            new_values: new_values.clone(),
+        };
+        match round_trip_client(msg) {
+            ClientMessage::ReportVariableChanges {
+                service_id,
+                changed_variables,
+                new_values: nv,
+            } => {
+                assert_eq!(service_id, "svc6");
+                assert_eq!(changed_variables, vec!["v1".to_string()]);
+                assert_eq!(nv, new_values);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    // ─────────────────────────────────────────────────────────────
+    // ServerMessage round-trip tests (untested variants)
+    // ─────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_server_message_tenant_round_trip() {
+        let msg = ServerMessage::Tenant {
+            tenant_id: "t1".to_string(),
+            tenant_name: "Alpha Corp".to_string(),
+        };
+        match round_trip_server(msg) {
+            ServerMessage::Tenant { tenant_id, tenant_name } => {
+                assert_eq!(tenant_id, "t1");
+                assert_eq!(tenant_name, "Alpha Corp");
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_server_message_tenant_result_round_trip() {
+        let msg = ServerMessage::TenantResult {
+            success: true,
+            tenant_id: "t2".to_string(),
+            message: "Tenant created".to_string(),
+        };
+        match round_trip_server(msg) {
+            ServerMessage::TenantResult { success, tenant_id, message } => {
+                assert!(success);
+                assert_eq!(tenant_id, "t2");
+                assert_eq!(message, "Tenant created");
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_server_message_variable_data_round_trip() {
+        let data = vec![5u8, 6, 7, 8];
+        let msg = ServerMessage::VariableData {
+            service_id: "svc1".to_string(),
+            variable_name: "x".to_string(),
+            data: data.clone(),
+            version: 42,
+        };
+        match round_trip_server(msg) {
+            ServerMessage::VariableData { service_id, variable_name, data: d, version } => {
+                assert_eq!(service_id, "svc1");
+                assert_eq!(variable_name, "x");
+                assert_eq!(d, data);
+                assert_eq!(version, 42);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_server_message_variable_written_round_trip() {
+        let msg = ServerMessage::VariableWritten {
+            service_id: "svc1".to_string(),
+            variable_name: "x".to_string(),
+            version: 43,
+        };
+        match round_trip_server(msg) {
+            ServerMessage::VariableWritten { service_id, variable_name, version } => {
+                assert_eq!(service_id, "svc1");
+                assert_eq!(variable_name, "x");
+                assert_eq!(version, 43);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_server_message_version_conflict_round_trip() {
+        let msg = ServerMessage::VersionConflict {
+            service_id: "svc1".to_string(),
+            variable_name: "x".to_string(),
+            current_version: 9,
+            current_data: vec![3, 4],
+        };
+        match round_trip_server(msg) {
+            ServerMessage::VersionConflict { service_id, variable_name, current_version, current_data } => {
+                assert_eq!(service_id, "svc1");
+                assert_eq!(variable_name, "x");
+                assert_eq!(current_version, 9);
+                assert_eq!(current_data, vec![3, 4]);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_server_message_variable_changed_round_trip() {
+        let data = vec![11u8, 22];
+        let msg = ServerMessage::VariableChanged {
+            service_id: "svc2".to_string(),
+            variable_name: "y".to_string(),
+            data: data.clone(),
+            version: 7,
+        };
+        match round_trip_server(msg) {
+            ServerMessage::VariableChanged { service_id, variable_name, data: d, version } => {
+                assert_eq!(service_id, "svc2");
+                assert_eq!(variable_name, "y");
+                assert_eq!(d, data);
+                assert_eq!(version, 7);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_server_message_disconnected_round_trip() {
+        let msg = ServerMessage::Disconnected { reason: "server shutdown".to_string() };
+        match round_trip_server(msg) {
+            ServerMessage::Disconnected { reason } => {
+                assert_eq!(reason, "server shutdown");
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_server_message_service_file_path_round_trip() {
+        let msg = ServerMessage::ServiceFilePath {
+            service_id: "svc7".to_string(),
+            file_path: "/var/commy/service.mem".to_string(),
+            file_size: 1_048_576,
+        };
+        match round_trip_server(msg) {
+            ServerMessage::ServiceFilePath { service_id, file_path, file_size } => {
+                assert_eq!(service_id, "svc7");
+                assert_eq!(file_path, "/var/commy/service.mem");
+                assert_eq!(file_size, 1_048_576);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_server_message_variable_changes_acknowledged_round_trip() {
+        let msg = ServerMessage::VariableChangesAcknowledged {
+            service_id: "svc8".to_string(),
+            changed_variables: vec!["a".to_string(), "b".to_string()],
+        };
+        match round_trip_server(msg) {
+            ServerMessage::VariableChangesAcknowledged { service_id, changed_variables } => {
+                assert_eq!(service_id, "svc8");
+                assert_eq!(changed_variables, vec!["a".to_string(), "b".to_string()]);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_server_message_heartbeat_round_trip() {
+        let msg = ServerMessage::Heartbeat { timestamp: "2025-01-01T00:00:00Z".to_string() };
+        match round_trip_server(msg) {
+            ServerMessage::Heartbeat { timestamp } => {
+                assert_eq!(timestamp, "2025-01-01T00:00:00Z");
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_server_message_welcome_round_trip() {
+        let msg = ServerMessage::Welcome {
+            server_version: "2.0.0".to_string(),
+            capabilities: Capabilities::legacy(),
+        };
+        match round_trip_server(msg) {
+            ServerMessage::Welcome { server_version, capabilities } => {
+                assert_eq!(server_version, "2.0.0");
+                assert_eq!(capabilities, Capabilities::legacy());
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    // ─────────────────────────────────────────────────────────────
+    // Envelope tests
+    // ─────────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_client_envelope_flattens_request_id() {
+        let envelope = ClientEnvelope::request(
+            "42",
+            ClientMessage::GetService {
+                tenant_id: "t1".to_string(),
+                service_name: "config".to_string(),
+            },
+        );
+        let json: serde_json::Value = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(json["request_id"], "42");
+        assert_eq!(json["type"], "GetService");
+        assert_eq!(json["data"]["service_name"], "config");
+
+        let decoded: ClientEnvelope = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.request_id.as_deref(), Some("42"));
+        assert!(matches!(decoded.message, ClientMessage::GetService { .. }));
+    }
+
+    #[test]
+    fn test_client_envelope_without_request_id_matches_plain_message() {
+        let msg = ClientMessage::Heartbeat { client_id: "c1".to_string() };
+        let plain = serde_json::to_value(&msg).unwrap();
+        let wrapped = serde_json::to_value(ClientEnvelope::from(msg)).unwrap();
+        assert_eq!(plain, wrapped);
+    }
+
+    #[test]
+    fn test_server_envelope_decodes_message_without_request_id() {
+        let json = r#"{"type":"Heartbeat","data":{"timestamp":"now"}}"#;
+        let envelope: ServerEnvelope = serde_json::from_str(json).unwrap();
+        assert!(envelope.request_id.is_none());
+        assert!(matches!(envelope.message, ServerMessage::Heartbeat { .. }));
+    }
+
+    #[test]
+    fn test_server_envelope_round_trip_with_request_id() {
+        let envelope = ServerEnvelope::reply(
+            "7",
+            ServerMessage::Error {
+                code: ErrorCode::NotFound,
+                message: "missing".to_string(),
+            },
+        );
+        let json = serde_json::to_string(&envelope).unwrap();
+        let decoded: ServerEnvelope = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.request_id.as_deref(), Some("7"));
+        assert!(matches!(
+            decoded.message,
+            ServerMessage::Error { code: ErrorCode::NotFound, .. }
+        ));
+    }
+
+    #[test]
+    fn test_is_push_classification() {
+        assert!(ServerMessage::Heartbeat { timestamp: "t".to_string() }.is_push());
+        assert!(ServerMessage::Disconnected { reason: "bye".to_string() }.is_push());
+        assert!(ServerMessage::VariableChanged {
+            service_id: "s".to_string(),
+            variable_name: "v".to_string(),
+            data: vec![],
+            version: 1,
+        }
+        .is_push());
+        assert!(ServerMessage::Unknown(serde_json::json!({ "type": "New" })).is_push());
+
+        assert!(!ServerMessage::VariableData {
+            service_id: "s".to_string(),
+            variable_name: "v".to_string(),
+            data: vec![],
+            version: 1,
+        }
+        .is_push());
+        assert!(!ServerMessage::Error {
+            code: ErrorCode::NotFound,
+            message: "x".to_string(),
+        }
+        .is_push());
+    }
+}
+
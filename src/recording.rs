@@ -0,0 +1,645 @@
+//! Recording protocol sessions and replaying them
+//!
+//! A `Recorder` attached to a client writes every message it exchanges with
+//! the server to a JSON-lines file, one `RecordedMessage` per line. A
+//! `Replay` of that file plays the server's part back to a client, so a
+//! session seen in production can be turned into a deterministic test:
+//!
+//! ```no_run
+//! use commy_sdk_rust::recording::{Recorder, Replay};
+//! use commy_sdk_rust::Client;
+//! use std::time::Duration;
+//!
+//! # async fn example() -> commy_sdk_rust::Result<()> {
+//! // In production
+//! let client = Client::builder("wss://commy.example.com:9000")
+//!     .recorder(Recorder::create("session.jsonl")?)
+//!     .build();
+//!
+//! // In a test, with the same calls made in the same order
+//! let replay = Replay::load("session.jsonl")?;
+//! let client = Client::builder("wss://commy.example.com:9000")
+//!     .transport(replay.clone())
+//!     .heartbeat_interval(Duration::ZERO)
+//!     .build();
+//! client.connect().await?;
+//! assert_eq!(replay.divergence(), None);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Replay follows the recorded order, not its timing: each recorded server
+//! message is delivered as soon as the client has sent everything that
+//! preceded it. Background heartbeats are not deterministic, so replaying
+//! clients should disable them.
+//!
+//! Credentials are never written: every field of the `AuthCredentials` an
+//! `Authenticate` carries is recorded as `REDACTED`.
+
+use crate::error::{CommyError, Result};
+use crate::message::{AuthCredentials, ClientEnvelope, ClientMessage, ServerEnvelope};
+use crate::transport::{Transport, TransportFactory};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use tokio::sync::{watch, Notify};
+
+/// Recorded in place of every credential field
+pub const REDACTED: &str = "[redacted]";
+
+/// One line of a recorded session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedMessage {
+    /// When the message was sent or received
+    pub timestamp: DateTime<Utc>,
+
+    /// Which of the recorder's connections it belongs to, counting from 0
+    pub connection: u64,
+
+    /// What happened
+    #[serde(flatten)]
+    pub event: RecordedEvent,
+}
+
+/// A message crossing the connection, or the connection ending
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "direction", rename_all = "snake_case")]
+pub enum RecordedEvent {
+    /// The client sent a message
+    Sent { message: ClientEnvelope },
+
+    /// The client received a message
+    Received { message: ServerEnvelope },
+
+    /// The server closed the connection, or it was lost
+    Closed,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Recording
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Writes the messages of one or more connections as JSON lines
+///
+/// Clones share the same output. A background thread does the writing, so
+/// recording never blocks the connection's tasks; it flushes whenever it has
+/// written every line queued so far, and `flush` waits for it to get there.
+#[derive(Clone)]
+pub struct Recorder {
+    commands: mpsc::Sender<WriterCommand>,
+    connections: Arc<AtomicU64>,
+}
+
+/// Work for a recorder's writer thread
+enum WriterCommand {
+    /// Write one line
+    Line(String),
+
+    /// Flush, then signal that every earlier line is written
+    Flush(mpsc::Sender<()>),
+}
+
+impl Recorder {
+    /// Record to a new file at `path`, replacing any existing one
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::create(path)?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+
+    /// Record to any writer
+    ///
+    /// The writer is moved to a background thread, which ends once the last
+    /// clone of the recorder is dropped.
+    pub fn new(out: impl Write + Send + 'static) -> Self {
+        let (commands, queue) = mpsc::channel();
+        std::thread::spawn(move || write_lines(out, queue));
+        Self {
+            commands,
+            connections: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Wait until every message recorded so far is written and flushed
+    ///
+    /// Blocks the calling thread.
+    pub fn flush(&self) -> Result<()> {
+        let (done, written) = mpsc::channel();
+        self.commands
+            .send(WriterCommand::Flush(done))
+            .map_err(|_| writer_gone())?;
+        written.recv().map_err(|_| writer_gone())
+    }
+
+    /// Start recording a new connection
+    pub(crate) fn session(&self) -> Session {
+        Session {
+            recorder: self.clone(),
+            connection: self.connections.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    fn write(&self, message: &RecordedMessage) -> Result<()> {
+        let line = serde_json::to_string(message)?;
+        self.commands
+            .send(WriterCommand::Line(line))
+            .map_err(|_| writer_gone())
+    }
+}
+
+/// Writer thread of a `Recorder`: write each queued line, flushing whenever
+/// the queue runs empty
+fn write_lines(mut out: impl Write, queue: mpsc::Receiver<WriterCommand>) {
+    while let Ok(command) = queue.recv() {
+        let mut next = Some(command);
+        while let Some(command) = next {
+            match command {
+                WriterCommand::Line(line) => {
+                    if let Err(e) = writeln!(out, "{}", line) {
+                        tracing::warn!(error = %e, "failed to record message");
+                    }
+                }
+                WriterCommand::Flush(done) => {
+                    flush_recording(&mut out);
+                    let _ = done.send(());
+                }
+            }
+            next = queue.try_recv().ok();
+        }
+        flush_recording(&mut out);
+    }
+}
+
+fn flush_recording(out: &mut impl Write) {
+    if let Err(e) = out.flush() {
+        tracing::warn!(error = %e, "failed to flush recording");
+    }
+}
+
+fn writer_gone() -> CommyError {
+    CommyError::ChannelError("recording writer stopped".to_string())
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("connections", &self.connections.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Records the messages of a single connection
+pub(crate) struct Session {
+    recorder: Recorder,
+    connection: u64,
+}
+
+impl Session {
+    pub(crate) fn sent(&self, message: &ClientEnvelope) {
+        self.record(RecordedEvent::Sent {
+            message: redact(message),
+        });
+    }
+
+    pub(crate) fn received(&self, message: &ServerEnvelope) {
+        self.record(RecordedEvent::Received {
+            message: message.clone(),
+        });
+    }
+
+    pub(crate) fn closed(&self) {
+        self.record(RecordedEvent::Closed);
+    }
+
+    fn record(&self, event: RecordedEvent) {
+        let message = RecordedMessage {
+            timestamp: Utc::now(),
+            connection: self.connection,
+            event,
+        };
+        if let Err(e) = self.recorder.write(&message) {
+            tracing::warn!(error = %e, "failed to record message");
+        }
+    }
+}
+
+/// Copy of a client message with any credentials replaced by `REDACTED`
+fn redact(envelope: &ClientEnvelope) -> ClientEnvelope {
+    let mut envelope = envelope.clone();
+    if let ClientMessage::Authenticate { credentials, .. } = &mut envelope.message {
+        *credentials = match credentials {
+            AuthCredentials::ApiKey { .. } => AuthCredentials::ApiKey {
+                key: REDACTED.to_string(),
+            },
+            AuthCredentials::Jwt { .. } => AuthCredentials::Jwt {
+                token: REDACTED.to_string(),
+            },
+            AuthCredentials::Basic { .. } => AuthCredentials::Basic {
+                username: REDACTED.to_string(),
+                password: REDACTED.to_string(),
+            },
+            AuthCredentials::Custom { .. } => AuthCredentials::Custom {
+                data: serde_json::Value::String(REDACTED.to_string()),
+            },
+        };
+    }
+    envelope
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Replay
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A recorded session, played back as the server
+///
+/// As a `TransportFactory`, each connect opens the next recorded connection.
+/// Clones share their progress and divergence.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    connections: Arc<Vec<Vec<RecordedEvent>>>,
+    next: Arc<AtomicUsize>,
+    divergence: Arc<Mutex<Option<String>>>,
+}
+
+impl Replay {
+    /// Load a session written by a `Recorder`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Read a session in the recorder's JSON-lines format
+    pub fn from_reader(reader: impl BufRead) -> Result<Self> {
+        let mut messages = Vec::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message = serde_json::from_str(&line).map_err(|e| {
+                CommyError::InvalidMessage(format!("recording line {}: {}", number + 1, e))
+            })?;
+            messages.push(message);
+        }
+        Ok(Self::from_messages(messages))
+    }
+
+    /// Replay the given messages, grouped by connection in the order the
+    /// connections first appear
+    pub fn from_messages(messages: impl IntoIterator<Item = RecordedMessage>) -> Self {
+        let mut order: Vec<u64> = Vec::new();
+        let mut connections: Vec<Vec<RecordedEvent>> = Vec::new();
+        for message in messages {
+            let index = match order.iter().position(|&c| c == message.connection) {
+                Some(index) => index,
+                None => {
+                    order.push(message.connection);
+                    connections.push(Vec::new());
+                    order.len() - 1
+                }
+            };
+            connections[index].push(message.event);
+        }
+
+        Self {
+            connections: Arc::new(connections),
+            next: Arc::new(AtomicUsize::new(0)),
+            divergence: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Number of recorded connections
+    pub fn connections(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Transport replaying the recorded connection at `index`
+    pub fn transport(&self, index: usize) -> Option<ReplayTransport> {
+        let events = self.connections.get(index)?;
+        Some(ReplayTransport {
+            script: Mutex::new(events.iter().cloned().collect()),
+            progress: Notify::new(),
+            closed: watch::channel(false).0,
+            divergence: Arc::clone(&self.divergence),
+        })
+    }
+
+    /// How the client first strayed from the recording, if it did
+    pub fn divergence(&self) -> Option<String> {
+        self.divergence.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl TransportFactory for Replay {
+    async fn open(&self) -> Result<Box<dyn Transport>> {
+        let index = self.next.fetch_add(1, Ordering::Relaxed);
+        let transport = self.transport(index).ok_or_else(|| {
+            CommyError::WebSocketError(format!("recording has no connection {} to replay", index))
+        })?;
+        Ok(Box::new(transport))
+    }
+}
+
+/// Plays one recorded connection back to a client
+///
+/// Sent messages are checked against the recording by type and request ID;
+/// their contents may differ, as they often hold client IDs or timestamps.
+/// On the first mismatch the divergence is noted and the transport closes.
+pub struct ReplayTransport {
+    script: Mutex<VecDeque<RecordedEvent>>,
+    progress: Notify,
+    closed: watch::Sender<bool>,
+    divergence: Arc<Mutex<Option<String>>>,
+}
+
+impl ReplayTransport {
+    fn diverge(&self, description: String) -> CommyError {
+        self.divergence
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| description.clone());
+        self.closed.send_replace(true);
+        CommyError::InvalidRequest(description)
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for ReplayTransport {
+    async fn send(&self, envelope: ClientEnvelope) -> Result<()> {
+        if *self.closed.borrow() {
+            return Err(CommyError::ConnectionLost("replay closed".to_string()));
+        }
+
+        let expected = {
+            let mut script = self.script.lock().unwrap();
+            // Server messages not yet read by the client may come first
+            let position = script.iter().position(|event| match event {
+                RecordedEvent::Sent { .. } | RecordedEvent::Closed => true,
+                RecordedEvent::Received { .. } => false,
+            });
+            match position.map(|position| (position, &script[position])) {
+                Some((position, RecordedEvent::Sent { message }))
+                    if same_request(message, &envelope) =>
+                {
+                    script.remove(position);
+                    None
+                }
+                Some((_, RecordedEvent::Sent { message })) => Some(describe(message)),
+                _ => Some("the end of the connection".to_string()),
+            }
+        };
+
+        match expected {
+            None => {
+                self.progress.notify_waiters();
+                Ok(())
+            }
+            Some(expected) => Err(self.diverge(format!(
+                "client sent {} where the recording has {}",
+                describe(&envelope),
+                expected
+            ))),
+        }
+    }
+
+    async fn recv(&self) -> Option<Result<ServerEnvelope>> {
+        let mut closed = self.closed.subscribe();
+        loop {
+            let progress = self.progress.notified();
+            if *closed.borrow() {
+                return None;
+            }
+            {
+                let mut script = self.script.lock().unwrap();
+                match script.front() {
+                    Some(RecordedEvent::Received { .. }) => {
+                        if let Some(RecordedEvent::Received { message }) = script.pop_front() {
+                            return Some(Ok(message));
+                        }
+                    }
+                    Some(RecordedEvent::Closed) => return None,
+                    // Wait for the client to send what came first, or, past
+                    // the end of the recording, for the client to close
+                    Some(RecordedEvent::Sent { .. }) | None => {}
+                }
+            }
+            tokio::select! {
+                _ = progress => {}
+                _ = closed.wait_for(|closed| *closed) => return None,
+            }
+        }
+    }
+
+    async fn close(&self) {
+        self.closed.send_replace(true);
+    }
+}
+
+/// Whether two client messages are the same request, ignoring contents
+fn same_request(recorded: &ClientEnvelope, sent: &ClientEnvelope) -> bool {
+    recorded.request_id == sent.request_id && message_type(recorded) == message_type(sent)
+}
+
+fn message_type(envelope: &ClientEnvelope) -> String {
+    serde_json::to_value(&envelope.message)
+        .ok()
+        .and_then(|value| value.get("type")?.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn describe(envelope: &ClientEnvelope) -> String {
+    match &envelope.request_id {
+        Some(request_id) => format!("{} (request {})", message_type(envelope), request_id),
+        None => message_type(envelope),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::ServerMessage;
+
+    fn heartbeat(request_id: &str) -> ClientEnvelope {
+        ClientEnvelope::request(
+            request_id,
+            ClientMessage::Heartbeat {
+                client_id: "c1".to_string(),
+            },
+        )
+    }
+
+    fn pong(request_id: &str) -> ServerEnvelope {
+        ServerEnvelope::reply(
+            request_id,
+            ServerMessage::Heartbeat {
+                timestamp: "now".to_string(),
+            },
+        )
+    }
+
+    /// Records the given events on one connection and reads them back
+    fn round_trip(events: impl FnOnce(&Session)) -> (String, Replay) {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let recorder = Recorder::create(file.path()).unwrap();
+        events(&recorder.session());
+        recorder.flush().unwrap();
+        let text = std::fs::read_to_string(file.path()).unwrap();
+        (text, Replay::load(file.path()).unwrap())
+    }
+
+    #[test]
+    fn test_recording_is_one_json_object_per_line() {
+        let (text, replay) = round_trip(|session| {
+            session.sent(&heartbeat("1"));
+            session.received(&pong("1"));
+            session.closed();
+        });
+
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["direction"], "sent");
+        assert_eq!(lines[0]["connection"], 0);
+        assert_eq!(lines[0]["message"]["type"], "Heartbeat");
+        assert_eq!(lines[1]["direction"], "received");
+        assert_eq!(lines[1]["message"]["request_id"], "1");
+        assert_eq!(lines[2]["direction"], "closed");
+        assert!(lines[0]["timestamp"].is_string());
+        assert_eq!(replay.connections(), 1);
+    }
+
+    #[test]
+    fn test_credentials_are_not_recorded() {
+        let credentials = [
+            crate::auth::api_key("secret-key".to_string()),
+            crate::auth::jwt("secret-token".to_string()),
+            crate::auth::basic("alice".to_string(), "secret-password".to_string()),
+            AuthCredentials::Custom {
+                data: serde_json::json!({ "secret": "value" }),
+            },
+        ];
+        let (text, replay) = round_trip(|session| {
+            for (number, credentials) in credentials.into_iter().enumerate() {
+                session.sent(&ClientEnvelope::request(
+                    number.to_string(),
+                    ClientMessage::Authenticate {
+                        tenant_id: "acme".to_string(),
+                        client_version: "1.0.0".to_string(),
+                        credentials,
+                    },
+                ));
+            }
+        });
+
+        for secret in ["secret", "alice"] {
+            assert!(!text.contains(secret), "{} recorded in {}", secret, text);
+        }
+        assert_eq!(text.matches(REDACTED).count(), 5);
+        assert_eq!(replay.connections(), 1);
+    }
+
+    #[test]
+    fn test_sessions_are_numbered_per_connection() {
+        let recorder = Recorder::new(Vec::new());
+        assert_eq!(recorder.session().connection, 0);
+        assert_eq!(recorder.clone().session().connection, 1);
+    }
+
+    #[test]
+    fn test_recorder_writes_off_the_calling_thread() {
+        /// Records which thread each write happens on
+        struct ThreadLog(Arc<Mutex<Vec<std::thread::ThreadId>>>);
+
+        impl Write for ThreadLog {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().push(std::thread::current().id());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let threads = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Recorder::new(ThreadLog(Arc::clone(&threads)));
+        recorder.session().sent(&heartbeat("1"));
+        recorder.flush().unwrap();
+
+        let threads = threads.lock().unwrap();
+        assert!(!threads.is_empty());
+        assert!(threads.iter().all(|id| *id != std::thread::current().id()));
+    }
+
+    #[test]
+    fn test_bad_line_is_reported_with_its_number() {
+        let input = "\n{\"not\": \"a message\"}\n";
+        match Replay::from_reader(input.as_bytes()) {
+            Err(CommyError::InvalidMessage(e)) => assert!(e.contains("line 2"), "{}", e),
+            other => panic!("Expected InvalidMessage, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_answers_after_the_recorded_request() {
+        let (_, replay) = round_trip(|session| {
+            session.sent(&heartbeat("1"));
+            session.received(&pong("1"));
+            session.closed();
+        });
+        let transport = replay.transport(0).unwrap();
+
+        // The reply waits for its request
+        let early = tokio::time::timeout(std::time::Duration::from_millis(50), transport.recv());
+        assert!(early.await.is_err());
+
+        transport.send(heartbeat("1")).await.unwrap();
+        let reply = transport.recv().await.unwrap().unwrap();
+        assert_eq!(reply.request_id.as_deref(), Some("1"));
+        assert!(transport.recv().await.is_none());
+        assert_eq!(replay.divergence(), None);
+    }
+
+    #[tokio::test]
+    async fn test_replay_notes_divergence_and_closes() {
+        let (_, replay) = round_trip(|session| {
+            session.sent(&heartbeat("1"));
+            session.received(&pong("1"));
+        });
+        let transport = replay.transport(0).unwrap();
+
+        let strayed = transport.send(heartbeat("2")).await;
+        assert!(matches!(strayed, Err(CommyError::InvalidRequest(_))));
+        assert!(transport.recv().await.is_none());
+
+        let divergence = replay.divergence().unwrap();
+        assert!(divergence.contains("request 2"), "{}", divergence);
+        assert!(divergence.contains("request 1"), "{}", divergence);
+    }
+
+    #[tokio::test]
+    async fn test_replay_factory_opens_recorded_connections_in_order() {
+        let recorder = Recorder::new(Vec::new());
+        let first = recorder.session();
+        let second = recorder.session();
+        let messages = [(&second, "2"), (&first, "1")].map(|(session, id)| RecordedMessage {
+            timestamp: Utc::now(),
+            connection: session.connection,
+            event: RecordedEvent::Received { message: pong(id) },
+        });
+        let replay = Replay::from_messages(messages);
+
+        let opened = replay.open().await.unwrap();
+        let reply = opened.recv().await.unwrap().unwrap();
+        assert_eq!(reply.request_id.as_deref(), Some("2"));
+        assert!(replay.open().await.is_ok());
+        assert!(replay.open().await.is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! Reconnection retry policies
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// Decides how long to wait before each reconnection attempt
+pub trait RetryPolicy: Debug + Send + Sync {
+    /// Delay before reconnection attempt `attempt` (starting at 0)
+    ///
+    /// Returns `None` to stop retrying.
+    fn next_delay(&self, attempt: u32) -> Option<Duration>;
+}
+
+/// Exponential backoff with random jitter
+///
+/// The base delay doubles with each attempt from `initial_delay` up to
+/// `max_delay`. Jitter then subtracts a random share of up to `jitter` (0.0 to
+/// 1.0) of the base delay, so clients that lost the same server do not all
+/// come back at once.
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoff {
+    /// Delay before the first attempt
+    pub initial_delay: Duration,
+
+    /// Upper bound on the base delay
+    pub max_delay: Duration,
+
+    /// Largest share of the base delay removed at random
+    ///
+    /// Values outside 0.0 to 1.0 are clamped and a non-finite share counts as
+    /// no jitter.
+    pub jitter: f64,
+}
+
+impl ExponentialBackoff {
+    /// Create an exponential backoff with the given bounds and no jitter
+    pub fn new(initial_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            initial_delay,
+            max_delay,
+            jitter: 0.0,
+        }
+    }
+
+    /// Set the jitter share, clamped to 0.0 to 1.0
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = clamp_jitter(jitter);
+        self
+    }
+
+    /// Base delay for an attempt, before jitter
+    fn base_delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.checked_pow(attempt).unwrap_or(u32::MAX);
+        self.initial_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+impl Default for ExponentialBackoff {
+    /// 1s, 2s, 4s, 8s, then 16s, each shortened by up to half at random
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), Duration::from_secs(16)).with_jitter(0.5)
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn next_delay(&self, attempt: u32) -> Option<Duration> {
+        let base = self.base_delay(attempt);
+        Some(base.mul_f64(1.0 - clamp_jitter(self.jitter) * fastrand::f64()))
+    }
+}
+
+/// Clamp a jitter share to 0.0 to 1.0, treating NaN and infinities as 0.0
+fn clamp_jitter(jitter: f64) -> f64 {
+    if jitter.is_finite() {
+        jitter.clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// The same delay before every attempt
+#[derive(Debug, Clone)]
+pub struct FixedDelay {
+    /// Delay before each attempt
+    pub delay: Duration,
+}
+
+impl FixedDelay {
+    /// Create a fixed-delay policy
+    pub fn new(delay: Duration) -> Self {
+        Self { delay }
+    }
+}
+
+impl RetryPolicy for FixedDelay {
+    fn next_delay(&self, _attempt: u32) -> Option<Duration> {
+        Some(self.delay)
+    }
+}
+
+/// Never reconnect; a lost connection fails the operation immediately
+#[derive(Debug, Clone, Default)]
+pub struct NoRetry;
+
+impl RetryPolicy for NoRetry {
+    fn next_delay(&self, _attempt: u32) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exponential_backoff_doubles_up_to_max() {
+        let policy = ExponentialBackoff::new(Duration::from_secs(1), Duration::from_secs(16));
+        let delays: Vec<u64> = (0..7)
+            .map(|attempt| policy.next_delay(attempt).unwrap().as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 16, 16]);
+    }
+
+    #[test]
+    fn test_exponential_backoff_survives_huge_attempt_numbers() {
+        let policy = ExponentialBackoff::new(Duration::from_secs(1), Duration::from_secs(30));
+        assert_eq!(policy.next_delay(u32::MAX), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_exponential_backoff_jitter_stays_within_share() {
+        let policy = ExponentialBackoff::new(Duration::from_secs(8), Duration::from_secs(8))
+            .with_jitter(0.25);
+        for _ in 0..100 {
+            let delay = policy.next_delay(0).unwrap();
+            assert!(delay <= Duration::from_secs(8));
+            assert!(delay >= Duration::from_secs(6));
+        }
+    }
+
+    #[test]
+    fn test_jitter_is_clamped() {
+        assert_eq!(ExponentialBackoff::default().with_jitter(3.0).jitter, 1.0);
+        assert_eq!(ExponentialBackoff::default().with_jitter(-1.0).jitter, 0.0);
+        assert_eq!(
+            ExponentialBackoff::default().with_jitter(f64::NAN).jitter,
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_jitter_field_does_not_panic() {
+        let base = ExponentialBackoff::new(Duration::from_secs(8), Duration::from_secs(8));
+        for jitter in [1.5, -2.0, f64::NAN, f64::INFINITY] {
+            let policy = ExponentialBackoff {
+                jitter,
+                ..base.clone()
+            };
+            for _ in 0..100 {
+                assert!(policy.next_delay(0).unwrap() <= Duration::from_secs(8));
+            }
+        }
+        let nan = ExponentialBackoff {
+            jitter: f64::NAN,
+            ..base
+        };
+        assert_eq!(nan.next_delay(0), Some(Duration::from_secs(8)));
+    }
+
+    #[test]
+    fn test_fixed_delay_and_no_retry() {
+        let fixed = FixedDelay::new(Duration::from_millis(250));
+        assert_eq!(fixed.next_delay(0), Some(Duration::from_millis(250)));
+        assert_eq!(fixed.next_delay(9), Some(Duration::from_millis(250)));
+        assert_eq!(NoRetry.next_delay(0), None);
+    }
+}
@@ -0,0 +1,254 @@
+//! Variable change subscriptions
+
+use crate::connection::Connection;
+use crate::message::{ClientMessage, ServerMessage};
+use futures::stream::{BoxStream, Stream, StreamExt};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::{broadcast, RwLock};
+
+/// A new value of a subscribed variable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableUpdate {
+    /// New variable data
+    pub data: Vec<u8>,
+
+    /// Version of the variable after the change
+    pub version: u64,
+}
+
+/// Reference counts of the variables the client is subscribed to
+///
+/// Several `Subscription` handles may watch the same variable; the server is
+/// only told to unsubscribe once the last of them is dropped.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SubscriptionRegistry {
+    counts: Arc<Mutex<HashMap<(String, String), usize>>>,
+}
+
+impl SubscriptionRegistry {
+    /// Record a new handle for a variable
+    pub(crate) fn acquire(&self, service_id: &str, variable_name: &str) {
+        let mut counts = self.counts.lock().unwrap();
+        *counts
+            .entry((service_id.to_string(), variable_name.to_string()))
+            .or_insert(0) += 1;
+    }
+
+    /// Drop a handle for a variable, returning true if it was the last one
+    pub(crate) fn release(&self, service_id: &str, variable_name: &str) -> bool {
+        let mut counts = self.counts.lock().unwrap();
+        let key = (service_id.to_string(), variable_name.to_string());
+        match counts.get_mut(&key) {
+            Some(count) if *count > 1 => {
+                *count -= 1;
+                false
+            }
+            Some(_) => {
+                counts.remove(&key);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget a variable and all of its handles
+    ///
+    /// Handles dropped afterwards no longer unsubscribe from the server.
+    pub(crate) fn remove(&self, service_id: &str, variable_name: &str) {
+        let mut counts = self.counts.lock().unwrap();
+        counts.remove(&(service_id.to_string(), variable_name.to_string()));
+    }
+
+    /// List the `(service_id, variable_name)` pairs with at least one handle
+    pub(crate) fn active(&self) -> Vec<(String, String)> {
+        self.counts.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+/// Stream of changes to one variable
+///
+/// Created by `Client::subscribe`. Yields a `VariableUpdate` for every
+/// `VariableChanged` push the server sends for the variable. If the consumer
+/// falls more than `PUSH_CHANNEL_CAPACITY` pushes behind, the oldest updates
+/// are skipped. Dropping the subscription unsubscribes from the server once no
+/// other subscription watches the same variable.
+pub struct Subscription {
+    service_id: String,
+    variable_name: String,
+    updates: BoxStream<'static, VariableUpdate>,
+    registry: SubscriptionRegistry,
+    connection: Arc<RwLock<Option<Connection>>>,
+}
+
+impl Subscription {
+    /// Create a subscription reading from the client's push channel
+    pub(crate) fn new(
+        service_id: String,
+        variable_name: String,
+        pushes: broadcast::Receiver<ServerMessage>,
+        registry: SubscriptionRegistry,
+        connection: Arc<RwLock<Option<Connection>>>,
+    ) -> Self {
+        let updates = variable_updates(pushes, service_id.clone(), variable_name.clone()).boxed();
+
+        Self {
+            service_id,
+            variable_name,
+            updates,
+            registry,
+            connection,
+        }
+    }
+
+    /// Get the service ID
+    pub fn service_id(&self) -> &str {
+        &self.service_id
+    }
+
+    /// Get the variable name
+    pub fn variable_name(&self) -> &str {
+        &self.variable_name
+    }
+}
+
+impl Stream for Subscription {
+    type Item = VariableUpdate;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.updates.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if !self.registry.release(&self.service_id, &self.variable_name) {
+            return;
+        }
+
+        // Sending needs the async connection lock; without a runtime (e.g. the
+        // runtime is shutting down) the server drops the subscription with the
+        // connection anyway
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let connection = Arc::clone(&self.connection);
+        let message = ClientMessage::Unsubscribe {
+            service_id: self.service_id.clone(),
+            variable_name: self.variable_name.clone(),
+        };
+        runtime.spawn(async move {
+            if let Some(conn) = connection.read().await.as_ref() {
+                let _ = conn.send(message).await;
+            }
+        });
+    }
+}
+
+impl std::fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscription")
+            .field("service_id", &self.service_id)
+            .field("variable_name", &self.variable_name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Filter the push channel down to changes of one variable
+fn variable_updates(
+    pushes: broadcast::Receiver<ServerMessage>,
+    service_id: String,
+    variable_name: String,
+) -> impl Stream<Item = VariableUpdate> + Send + 'static {
+    futures::stream::unfold(pushes, move |mut pushes| {
+        let service_id = service_id.clone();
+        let variable_name = variable_name.clone();
+        async move {
+            loop {
+                match pushes.recv().await {
+                    Ok(ServerMessage::VariableChanged {
+                        service_id: changed_service,
+                        variable_name: changed_variable,
+                        data,
+                        version,
+                    }) if changed_service == service_id && changed_variable == variable_name => {
+                        return Some((VariableUpdate { data, version }, pushes));
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_release_reports_last_handle() {
+        let registry = SubscriptionRegistry::default();
+        registry.acquire("svc", "v");
+        registry.acquire("svc", "v");
+
+        assert!(!registry.release("svc", "v"));
+        assert_eq!(registry.active(), vec![("svc".to_string(), "v".to_string())]);
+        assert!(registry.release("svc", "v"));
+        assert!(registry.active().is_empty());
+    }
+
+    #[test]
+    fn test_registry_release_unknown_is_noop() {
+        let registry = SubscriptionRegistry::default();
+        assert!(!registry.release("svc", "missing"));
+    }
+
+    #[test]
+    fn test_registry_remove_forgets_every_handle() {
+        let registry = SubscriptionRegistry::default();
+        registry.acquire("svc", "v");
+        registry.acquire("svc", "v");
+
+        registry.remove("svc", "v");
+        assert!(registry.active().is_empty());
+        assert!(!registry.release("svc", "v"));
+    }
+
+    #[tokio::test]
+    async fn test_variable_updates_filters_other_variables() {
+        let (tx, rx) = broadcast::channel(16);
+        let mut updates = variable_updates(rx, "svc".to_string(), "counter".to_string()).boxed();
+
+        tx.send(ServerMessage::VariableChanged {
+            service_id: "svc".to_string(),
+            variable_name: "other".to_string(),
+            data: vec![0],
+            version: 1,
+        })
+        .unwrap();
+        tx.send(ServerMessage::Heartbeat {
+            timestamp: "now".to_string(),
+        })
+        .unwrap();
+        tx.send(ServerMessage::VariableChanged {
+            service_id: "svc".to_string(),
+            variable_name: "counter".to_string(),
+            data: vec![4, 2],
+            version: 9,
+        })
+        .unwrap();
+        drop(tx);
+
+        assert_eq!(
+            updates.next().await,
+            Some(VariableUpdate {
+                data: vec![4, 2],
+                version: 9
+            })
+        );
+        assert_eq!(updates.next().await, None);
+    }
+}
@@ -0,0 +1,92 @@
+//! Upload of locally detected variable changes
+//!
+//! While file monitoring runs, a sync engine task turns the watcher's change
+//! events into `ReportVariableChanges` requests. Events arriving within one
+//! batch window are merged per virtual file, so a burst of writes costs a
+//! single round trip. A file's shadow copy only moves forward once the
+//! server acknowledges the batch; batches that could not be delivered stay
+//! pending and are reported again after the client reconnects.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How long the sync engine collects change events before reporting them,
+/// unless set with `ClientBuilder::sync_batch_window`
+pub const DEFAULT_SYNC_BATCH_WINDOW: Duration = Duration::from_millis(50);
+
+/// Changed variables not yet acknowledged by the server, by virtual file
+#[derive(Debug, Default)]
+pub(crate) struct PendingChanges {
+    files: BTreeMap<String, Vec<String>>,
+}
+
+impl PendingChanges {
+    /// Add changed variables of a virtual file, keeping each name once
+    pub(crate) fn add(&mut self, file_id: &str, variables: impl IntoIterator<Item = String>) {
+        let mut variables = variables.into_iter().peekable();
+        if variables.peek().is_none() {
+            return;
+        }
+
+        let pending = self.files.entry(file_id.to_string()).or_default();
+        for variable in variables {
+            if !pending.contains(&variable) {
+                pending.push(variable);
+            }
+        }
+    }
+
+    /// Check whether nothing is waiting to be reported
+    pub(crate) fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Take every pending batch, one per virtual file
+    pub(crate) fn take(&mut self) -> Vec<(String, Vec<String>)> {
+        std::mem::take(&mut self.files).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_changes_merge_per_file() {
+        let mut pending = PendingChanges::default();
+        assert!(pending.is_empty());
+
+        pending.add("t_config", names(&["a", "b"]));
+        pending.add("t_config", names(&["b", "c"]));
+        pending.add("t_other", names(&["a"]));
+        pending.add("t_empty", Vec::new());
+
+        assert_eq!(
+            pending.take(),
+            vec![
+                ("t_config".to_string(), names(&["a", "b", "c"])),
+                ("t_other".to_string(), names(&["a"])),
+            ]
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_failed_batch_can_be_added_back() {
+        let mut pending = PendingChanges::default();
+        pending.add("t_config", names(&["a"]));
+
+        let (file_id, variables) = pending.take().remove(0);
+        pending.add("t_config", names(&["b"]));
+        pending.add(&file_id, variables);
+
+        assert_eq!(
+            pending.take(),
+            vec![("t_config".to_string(), names(&["b", "a"]))]
+        );
+    }
+}
@@ -0,0 +1,1018 @@
+//! In-process mock server for tests
+//!
+//! `MockServer` speaks the full client protocol on an ephemeral local
+//! WebSocket port, keeping tenants, services and variables in memory, so
+//! code built on `Client` can be tested without a `commy` binary. It is only
+//! built with the `testing` cargo feature, typically enabled for
+//! dev-dependencies:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! commy-sdk-rust = { version = "0.1", features = ["testing"] }
+//! ```
+//!
+//! ```
+//! use commy_sdk_rust::testing::{Fault, MockServer};
+//! use commy_sdk_rust::message::ErrorCode;
+//! use commy_sdk_rust::{auth, Client, CommyError};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> commy_sdk_rust::Result<()> {
+//! let server = MockServer::start().await?;
+//! server.add_api_key("acme", "secret", &["admin"]);
+//!
+//! let client = Client::new(server.url());
+//! client.connect().await?;
+//! client.authenticate("acme", auth::api_key("secret".to_string())).await?;
+//! let service_id = client.create_service("acme", "config").await?;
+//! client.allocate_variable(&service_id, "limit", vec![1]).await?;
+//!
+//! server.fail_next("WriteVariable", Fault::Error(ErrorCode::InternalError));
+//! let write = client.write_variable(&service_id, "limit", vec![2]).await;
+//! assert!(matches!(write, Err(CommyError::Other(_))));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Permissions
+//!
+//! Credentials are registered per tenant with a list of permissions:
+//!
+//! | Permission       | Allows                                          |
+//! | ---------------- | ----------------------------------------------- |
+//! | `create_service` | `CreateService`                                 |
+//! | `read_service`   | `GetService`                                    |
+//! | `delete_service` | `DeleteService`                                 |
+//! | `read`           | `ReadVariable`, `Subscribe`                     |
+//! | `write`          | `AllocateVariable`, `WriteVariable`, `CompareAndSwap`, `DeallocateVariable`, `ReportVariableChanges` |
+//! | `admin`          | all of the above, plus `CreateTenant` and `DeleteTenant` |
+//!
+//! Requests for a tenant the connection has not authenticated to fail with
+//! `ErrorCode::Unauthorized`; missing permissions fail with
+//! `ErrorCode::PermissionDenied`.
+
+use crate::capabilities::Capabilities;
+use crate::error::Result;
+use crate::message::{
+    AuthCredentials, ClientEnvelope, ClientMessage, ErrorCode, ServerEnvelope, ServerMessage,
+};
+use futures::{SinkExt, StreamExt};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::http::header::{HeaderValue, SEC_WEBSOCKET_PROTOCOL};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Version the mock server reports in `Welcome` and authentication results
+pub const MOCK_SERVER_VERSION: &str = "mock";
+
+/// What the server does in place of handling a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Answer with `ServerMessage::Error` carrying this code
+    Error(ErrorCode),
+
+    /// Send no answer, so the client times out
+    NoReply,
+
+    /// Handle the request normally, but only after this delay
+    ///
+    /// Only this request waits: later requests on the same connection are
+    /// answered in the meantime, so replies can arrive out of order.
+    Delay(Duration),
+
+    /// Send `ServerMessage::Disconnected` and close the connection
+    Disconnect,
+}
+
+/// A fault waiting for a matching request
+#[derive(Debug)]
+struct ScriptedFault {
+    message_type: String,
+    fault: Fault,
+    remaining: usize,
+}
+
+/// A Commy server living in the test process
+///
+/// Dropping the server stops accepting connections and closes the open ones.
+pub struct MockServer {
+    url: String,
+    state: Arc<Mutex<State>>,
+    acceptor: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Start listening on an ephemeral port on 127.0.0.1
+    pub async fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("ws://{}", listener.local_addr()?);
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let accepting = Arc::clone(&state);
+        let acceptor = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(stream, Arc::clone(&accepting)));
+            }
+        });
+
+        Ok(Self {
+            url,
+            state,
+            acceptor,
+        })
+    }
+
+    /// URL to pass to `Client::new`
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Create a tenant
+    pub fn add_tenant(&self, tenant_id: &str, tenant_name: &str) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .tenants
+            .insert(tenant_id.to_string(), tenant_name.to_string());
+    }
+
+    /// Accept an API key for a tenant, creating the tenant if needed
+    pub fn add_api_key(&self, tenant_id: &str, key: &str, permissions: &[&str]) {
+        self.add_credentials(
+            tenant_id,
+            &AuthCredentials::ApiKey {
+                key: key.to_string(),
+            },
+            permissions,
+        );
+    }
+
+    /// Accept any kind of credentials for a tenant, creating the tenant if
+    /// needed
+    ///
+    /// JWTs match on the token, basic credentials on username and password,
+    /// and custom credentials on their JSON value.
+    pub fn add_credentials(
+        &self,
+        tenant_id: &str,
+        credentials: &AuthCredentials,
+        permissions: &[&str],
+    ) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .tenants
+            .entry(tenant_id.to_string())
+            .or_insert_with(|| tenant_id.to_string());
+        state.credentials.insert(
+            (tenant_id.to_string(), secret(credentials)),
+            permissions.iter().map(|p| p.to_string()).collect(),
+        );
+    }
+
+    /// Apply `fault` to the next request of `message_type` (such as
+    /// `"WriteVariable"`, or `"*"` for any request)
+    pub fn fail_next(&self, message_type: &str, fault: Fault) {
+        self.fail(message_type, fault, 1);
+    }
+
+    /// Apply `fault` to the next `times` requests of `message_type`
+    ///
+    /// Faults are matched in the order they were scripted.
+    pub fn fail(&self, message_type: &str, fault: Fault, times: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.faults.push_back(ScriptedFault {
+            message_type: message_type.to_string(),
+            fault,
+            remaining: times,
+        });
+    }
+
+    /// Offer `path` as the service's memory-mapped file to clients asking
+    /// for it
+    ///
+    /// Returns `false` if the service does not exist. Without a file, such
+    /// requests are refused as by a server that keeps none.
+    pub fn set_service_file(
+        &self,
+        tenant_id: &str,
+        service_name: &str,
+        path: impl Into<PathBuf>,
+    ) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(service_id) = state.service_id(tenant_id, service_name) else {
+            return false;
+        };
+        if let Some(service) = state.services.get_mut(&service_id) {
+            service.file_path = Some(path.into());
+        }
+        true
+    }
+
+    /// Write a variable as another client would, notifying subscribers
+    ///
+    /// Returns the new version, or `None` if the service or variable does
+    /// not exist.
+    pub fn set_variable(
+        &self,
+        tenant_id: &str,
+        service_name: &str,
+        variable_name: &str,
+        data: Vec<u8>,
+    ) -> Option<u64> {
+        let mut state = self.state.lock().unwrap();
+        let service_id = state.service_id(tenant_id, service_name)?;
+        state.write(&service_id, variable_name, data).ok()
+    }
+
+    /// Current value and version of a variable
+    pub fn variable(
+        &self,
+        tenant_id: &str,
+        service_name: &str,
+        variable_name: &str,
+    ) -> Option<(Vec<u8>, u64)> {
+        let state = self.state.lock().unwrap();
+        let service_id = state.service_id(tenant_id, service_name)?;
+        let variable = state.services[&service_id].variables.get(variable_name)?;
+        Some((variable.data.clone(), variable.version))
+    }
+
+    /// Every message received so far, in arrival order
+    pub fn received(&self) -> Vec<ClientMessage> {
+        self.state.lock().unwrap().received.clone()
+    }
+
+    /// Number of open client connections
+    pub fn connections(&self) -> usize {
+        self.state.lock().unwrap().sessions.len()
+    }
+
+    /// Send `ServerMessage::Disconnected` to every client and close their
+    /// connections
+    pub fn disconnect_all(&self, reason: &str) {
+        let state = self.state.lock().unwrap();
+        for session in state.sessions.values() {
+            session.disconnect(reason);
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.acceptor.abort();
+        self.disconnect_all("mock server stopped");
+    }
+}
+
+impl std::fmt::Debug for MockServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockServer")
+            .field("url", &self.url)
+            .finish()
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Server state
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Default)]
+struct State {
+    /// Tenant ID to tenant name
+    tenants: HashMap<String, String>,
+
+    /// (tenant ID, credential secret) to permissions
+    credentials: HashMap<(String, String), Vec<String>>,
+
+    /// Service ID to service
+    services: HashMap<String, MockService>,
+
+    sessions: HashMap<u64, Session>,
+    next_session: u64,
+    faults: VecDeque<ScriptedFault>,
+    received: Vec<ClientMessage>,
+}
+
+struct MockService {
+    tenant_id: String,
+    name: String,
+    variables: HashMap<String, MockVariable>,
+
+    /// Handed to clients asking for the service file
+    file_path: Option<PathBuf>,
+}
+
+struct MockVariable {
+    data: Vec<u8>,
+    version: u64,
+}
+
+/// One client connection
+struct Session {
+    outgoing: mpsc::UnboundedSender<Outgoing>,
+
+    /// Tenant ID to the permissions granted on authentication
+    tenants: HashMap<String, Vec<String>>,
+
+    /// (service ID, variable name) pairs
+    subscriptions: HashSet<(String, String)>,
+}
+
+enum Outgoing {
+    Message(ServerEnvelope),
+    Close,
+}
+
+impl Session {
+    fn push(&self, message: ServerMessage) {
+        let _ = self.outgoing.send(Outgoing::Message(message.into()));
+    }
+
+    fn disconnect(&self, reason: &str) {
+        self.push(ServerMessage::Disconnected {
+            reason: reason.to_string(),
+        });
+        let _ = self.outgoing.send(Outgoing::Close);
+    }
+}
+
+/// A request outcome: the reply, or the error to send instead
+type Handled = std::result::Result<ServerMessage, ServerMessage>;
+
+/// A permission check: passes, or yields the error to send
+type Check = std::result::Result<(), ServerMessage>;
+
+fn error(code: ErrorCode, message: impl Into<String>) -> ServerMessage {
+    ServerMessage::Error {
+        code,
+        message: message.into(),
+    }
+}
+
+fn secret(credentials: &AuthCredentials) -> String {
+    match credentials {
+        AuthCredentials::ApiKey { key } => key.clone(),
+        AuthCredentials::Jwt { token } => token.clone(),
+        AuthCredentials::Basic { username, password } => format!("{}:{}", username, password),
+        AuthCredentials::Custom { data } => data.to_string(),
+    }
+}
+
+/// The `type` tag of a client message, as faults are scripted by
+fn message_type(message: &ClientMessage) -> String {
+    serde_json::to_value(message)
+        .ok()
+        .and_then(|value| value.get("type")?.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+impl State {
+    /// Take the next scripted fault matching `message`, if any
+    fn take_fault(&mut self, message: &ClientMessage) -> Option<Fault> {
+        let message_type = message_type(message);
+        let index = self
+            .faults
+            .iter()
+            .position(|f| f.message_type == "*" || f.message_type == message_type)?;
+        let scripted = &mut self.faults[index];
+        scripted.remaining -= 1;
+        let fault = scripted.fault.clone();
+        if scripted.remaining == 0 {
+            self.faults.remove(index);
+        }
+        Some(fault)
+    }
+
+    fn service_id(&self, tenant_id: &str, service_name: &str) -> Option<String> {
+        self.services
+            .iter()
+            .find(|(_, s)| s.tenant_id == tenant_id && s.name == service_name)
+            .map(|(id, _)| id.clone())
+    }
+
+    /// Check that a session may perform an operation on a tenant
+    fn authorize(&self, session: u64, tenant_id: &str, permission: &str) -> Check {
+        let granted = self.sessions[&session]
+            .tenants
+            .get(tenant_id)
+            .ok_or_else(|| {
+                error(
+                    ErrorCode::Unauthorized,
+                    format!("not authenticated to tenant {}", tenant_id),
+                )
+            })?;
+        if granted.iter().any(|p| p == permission || p == "admin") {
+            Ok(())
+        } else {
+            Err(error(
+                ErrorCode::PermissionDenied,
+                format!("missing {} permission", permission),
+            ))
+        }
+    }
+
+    /// Check a permission on the tenant owning a service
+    fn authorize_service(&self, session: u64, service_id: &str, permission: &str) -> Check {
+        let service = self
+            .services
+            .get(service_id)
+            .ok_or_else(|| error(ErrorCode::NotFound, format!("service {}", service_id)))?;
+        self.authorize(session, &service.tenant_id, permission)
+    }
+
+    fn variable_mut(
+        &mut self,
+        service_id: &str,
+        variable_name: &str,
+    ) -> std::result::Result<&mut MockVariable, ServerMessage> {
+        self.services
+            .get_mut(service_id)
+            .and_then(|s| s.variables.get_mut(variable_name))
+            .ok_or_else(|| error(ErrorCode::NotFound, format!("variable {}", variable_name)))
+    }
+
+    /// Store a new value, bump the version and notify subscribers
+    fn write(
+        &mut self,
+        service_id: &str,
+        variable_name: &str,
+        data: Vec<u8>,
+    ) -> std::result::Result<u64, ServerMessage> {
+        let variable = self.variable_mut(service_id, variable_name)?;
+        variable.data = data.clone();
+        variable.version += 1;
+        let version = variable.version;
+
+        let key = (service_id.to_string(), variable_name.to_string());
+        for session in self.sessions.values() {
+            if session.subscriptions.contains(&key) {
+                session.push(ServerMessage::VariableChanged {
+                    service_id: service_id.to_string(),
+                    variable_name: variable_name.to_string(),
+                    data: data.clone(),
+                    version,
+                });
+            }
+        }
+        Ok(version)
+    }
+
+    /// Handle one request; `None` means no reply is sent
+    fn handle(
+        &mut self,
+        session: u64,
+        request_id: &str,
+        message: ClientMessage,
+    ) -> Option<ServerMessage> {
+        let done = |message: String| ServerMessage::Result {
+            request_id: request_id.to_string(),
+            success: true,
+            message,
+        };
+
+        let handled: Handled = match message {
+            ClientMessage::Hello { .. } => Ok(ServerMessage::Welcome {
+                server_version: MOCK_SERVER_VERSION.to_string(),
+                capabilities: Capabilities::client(),
+            }),
+
+            ClientMessage::Authenticate {
+                tenant_id,
+                credentials,
+                ..
+            } => Ok(self.authenticate(session, tenant_id, &credentials)),
+
+            ClientMessage::CreateTenant {
+                tenant_id,
+                tenant_name,
+            } => self.admin(session).and_then(|()| {
+                if self.tenants.contains_key(&tenant_id) {
+                    return Err(error(
+                        ErrorCode::AlreadyExists,
+                        format!("tenant {}", tenant_id),
+                    ));
+                }
+                self.tenants.insert(tenant_id.clone(), tenant_name);
+                Ok(ServerMessage::TenantResult {
+                    success: true,
+                    tenant_id,
+                    message: "tenant created".to_string(),
+                })
+            }),
+
+            ClientMessage::DeleteTenant { tenant_id } => self.admin(session).and_then(|()| {
+                self.tenants
+                    .remove(&tenant_id)
+                    .ok_or_else(|| error(ErrorCode::NotFound, format!("tenant {}", tenant_id)))?;
+                self.services.retain(|_, s| s.tenant_id != tenant_id);
+                self.credentials
+                    .retain(|(tenant, _), _| *tenant != tenant_id);
+                Ok(done(format!("tenant {} deleted", tenant_id)))
+            }),
+
+            ClientMessage::CreateService {
+                tenant_id,
+                service_name,
+            } => self
+                .authorize(session, &tenant_id, "create_service")
+                .and_then(|()| {
+                    if self.service_id(&tenant_id, &service_name).is_some() {
+                        return Err(error(
+                            ErrorCode::AlreadyExists,
+                            format!("service {}", service_name),
+                        ));
+                    }
+                    let service_id = uuid::Uuid::new_v4().to_string();
+                    self.services.insert(
+                        service_id.clone(),
+                        MockService {
+                            tenant_id: tenant_id.clone(),
+                            name: service_name.clone(),
+                            variables: HashMap::new(),
+                            file_path: None,
+                        },
+                    );
+                    Ok(ServerMessage::Service {
+                        service_id,
+                        service_name,
+                        tenant_id,
+                        file_path: None,
+                    })
+                }),
+
+            ClientMessage::GetService {
+                tenant_id,
+                service_name,
+            } => self
+                .authorize(session, &tenant_id, "read_service")
+                .and_then(|()| {
+                    let service_id =
+                        self.service_id(&tenant_id, &service_name).ok_or_else(|| {
+                            error(ErrorCode::NotFound, format!("service {}", service_name))
+                        })?;
+                    Ok(ServerMessage::Service {
+                        service_id,
+                        service_name,
+                        tenant_id,
+                        file_path: None,
+                    })
+                }),
+
+            ClientMessage::DeleteService {
+                tenant_id,
+                service_name,
+            } => self
+                .authorize(session, &tenant_id, "delete_service")
+                .and_then(|()| {
+                    let service_id =
+                        self.service_id(&tenant_id, &service_name).ok_or_else(|| {
+                            error(ErrorCode::NotFound, format!("service {}", service_name))
+                        })?;
+                    self.services.remove(&service_id);
+                    Ok(done(format!("service {} deleted", service_name)))
+                }),
+
+            ClientMessage::AllocateVariable {
+                service_id,
+                variable_name,
+                initial_data,
+            } => self
+                .authorize_service(session, &service_id, "write")
+                .and_then(|()| {
+                    let variables = &mut self.services.get_mut(&service_id).unwrap().variables;
+                    if variables.contains_key(&variable_name) {
+                        return Err(error(
+                            ErrorCode::AlreadyExists,
+                            format!("variable {}", variable_name),
+                        ));
+                    }
+                    variables.insert(
+                        variable_name.clone(),
+                        MockVariable {
+                            data: initial_data.clone(),
+                            version: 1,
+                        },
+                    );
+                    Ok(ServerMessage::VariableData {
+                        service_id,
+                        variable_name,
+                        data: initial_data,
+                        version: 1,
+                    })
+                }),
+
+            ClientMessage::ReadVariable {
+                service_id,
+                variable_name,
+            } => self
+                .authorize_service(session, &service_id, "read")
+                .and_then(|()| {
+                    let variable = self.variable_mut(&service_id, &variable_name)?;
+                    Ok(ServerMessage::VariableData {
+                        data: variable.data.clone(),
+                        version: variable.version,
+                        service_id,
+                        variable_name,
+                    })
+                }),
+
+            ClientMessage::WriteVariable {
+                service_id,
+                variable_name,
+                data,
+            } => self
+                .authorize_service(session, &service_id, "write")
+                .and_then(|()| {
+                    let version = self.write(&service_id, &variable_name, data)?;
+                    Ok(ServerMessage::VariableWritten {
+                        service_id,
+                        variable_name,
+                        version,
+                    })
+                }),
+
+            ClientMessage::CompareAndSwap {
+                service_id,
+                variable_name,
+                expected_version,
+                data,
+            } => self
+                .authorize_service(session, &service_id, "write")
+                .and_then(|()| {
+                    let variable = self.variable_mut(&service_id, &variable_name)?;
+                    if variable.version != expected_version {
+                        return Err(ServerMessage::VersionConflict {
+                            current_version: variable.version,
+                            current_data: variable.data.clone(),
+                            service_id,
+                            variable_name,
+                        });
+                    }
+                    let version = self.write(&service_id, &variable_name, data)?;
+                    Ok(ServerMessage::VariableWritten {
+                        service_id,
+                        variable_name,
+                        version,
+                    })
+                }),
+
+            ClientMessage::DeallocateVariable {
+                service_id,
+                variable_name,
+            } => self
+                .authorize_service(session, &service_id, "write")
+                .and_then(|()| {
+                    self.services
+                        .get_mut(&service_id)
+                        .unwrap()
+                        .variables
+                        .remove(&variable_name)
+                        .ok_or_else(|| {
+                            error(ErrorCode::NotFound, format!("variable {}", variable_name))
+                        })?;
+                    Ok(done(format!("variable {} deallocated", variable_name)))
+                }),
+
+            ClientMessage::Subscribe {
+                service_id,
+                variable_name,
+            } => self
+                .authorize_service(session, &service_id, "read")
+                .and_then(|()| {
+                    if !self.services[&service_id]
+                        .variables
+                        .contains_key(&variable_name)
+                    {
+                        return Err(error(
+                            ErrorCode::NotFound,
+                            format!("variable {}", variable_name),
+                        ));
+                    }
+                    let session = self.sessions.get_mut(&session).unwrap();
+                    session
+                        .subscriptions
+                        .insert((service_id, variable_name.clone()));
+                    Ok(done(format!("subscribed to {}", variable_name)))
+                }),
+
+            ClientMessage::Unsubscribe {
+                service_id,
+                variable_name,
+            } => {
+                let session = self.sessions.get_mut(&session).unwrap();
+                session
+                    .subscriptions
+                    .remove(&(service_id, variable_name.clone()));
+                Ok(done(format!("unsubscribed from {}", variable_name)))
+            }
+
+            ClientMessage::Heartbeat { .. } => Ok(ServerMessage::Heartbeat {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+            }),
+
+            ClientMessage::Disconnect { .. } => {
+                let _ = self.sessions[&session].outgoing.send(Outgoing::Close);
+                return None;
+            }
+
+            ClientMessage::GetServiceFilePath {
+                tenant_id,
+                service_name,
+            } => self
+                .authorize(session, &tenant_id, "read_service")
+                .and_then(|()| {
+                    let service_id =
+                        self.service_id(&tenant_id, &service_name).ok_or_else(|| {
+                            error(ErrorCode::NotFound, format!("service {}", service_name))
+                        })?;
+                    let file_path =
+                        self.services[&service_id]
+                            .file_path
+                            .clone()
+                            .ok_or_else(|| {
+                                error(
+                                    ErrorCode::InvalidRequest,
+                                    "the mock server keeps no file for this service",
+                                )
+                            })?;
+                    let file_size = std::fs::metadata(&file_path).map_or(0, |m| m.len());
+                    Ok(ServerMessage::ServiceFilePath {
+                        service_id,
+                        file_path: file_path.to_string_lossy().into_owned(),
+                        file_size,
+                    })
+                }),
+
+            ClientMessage::ReportVariableChanges {
+                service_id,
+                changed_variables,
+                new_values,
+            } => self
+                .authorize_service(session, &service_id, "write")
+                .and_then(|()| {
+                    for (variable_name, data) in new_values {
+                        self.write(&service_id, &variable_name, data)?;
+                    }
+                    Ok(ServerMessage::VariableChangesAcknowledged {
+                        service_id,
+                        changed_variables,
+                    })
+                }),
+        };
+
+        Some(handled.unwrap_or_else(|error| error))
+    }
+
+    fn authenticate(
+        &mut self,
+        session: u64,
+        tenant_id: String,
+        credentials: &AuthCredentials,
+    ) -> ServerMessage {
+        let rejected = |message: &str| ServerMessage::AuthenticationResult {
+            success: false,
+            message: message.to_string(),
+            server_version: MOCK_SERVER_VERSION.to_string(),
+            permissions: None,
+        };
+
+        if !self.tenants.contains_key(&tenant_id) {
+            return rejected("unknown tenant");
+        }
+        let Some(permissions) = self
+            .credentials
+            .get(&(tenant_id.clone(), secret(credentials)))
+        else {
+            return rejected("invalid credentials");
+        };
+
+        let permissions = permissions.clone();
+        let session = self.sessions.get_mut(&session).unwrap();
+        session.tenants.insert(tenant_id, permissions.clone());
+        ServerMessage::AuthenticationResult {
+            success: true,
+            message: "authenticated".to_string(),
+            server_version: MOCK_SERVER_VERSION.to_string(),
+            permissions: Some(permissions),
+        }
+    }
+
+    /// Check that a session holds `admin` on at least one tenant
+    fn admin(&self, session: u64) -> Check {
+        let is_admin = self.sessions[&session]
+            .tenants
+            .values()
+            .any(|permissions| permissions.iter().any(|p| p == "admin"));
+        if is_admin {
+            Ok(())
+        } else {
+            Err(error(
+                ErrorCode::PermissionDenied,
+                "missing admin permission",
+            ))
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Connections
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Run one client connection until either side closes it
+async fn serve_connection(stream: TcpStream, state: Arc<Mutex<State>>) {
+    let mut message_pack = false;
+    // The callback signature is fixed by tungstenite
+    #[allow(clippy::result_large_err)]
+    let select = |request: &Request, mut response: Response| {
+        message_pack = request
+            .headers()
+            .get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|offer| offer.split(',').any(|p| p.trim() == "commy.msgpack"));
+        if message_pack {
+            response.headers_mut().insert(
+                SEC_WEBSOCKET_PROTOCOL,
+                HeaderValue::from_static("commy.msgpack"),
+            );
+        }
+        Ok(response)
+    };
+    let Ok(ws) = tokio_tungstenite::accept_hdr_async(stream, select).await else {
+        return;
+    };
+    let (mut write, mut read) = ws.split();
+
+    let (outgoing, mut queued) = mpsc::unbounded_channel();
+    let session_id = {
+        let mut state = state.lock().unwrap();
+        state.next_session += 1;
+        let session_id = state.next_session;
+        state.sessions.insert(
+            session_id,
+            Session {
+                outgoing: outgoing.clone(),
+                tenants: HashMap::new(),
+                subscriptions: HashSet::new(),
+            },
+        );
+        session_id
+    };
+
+    let writer = tokio::spawn(async move {
+        while let Some(Outgoing::Message(envelope)) = queued.recv().await {
+            let frame = if message_pack {
+                match rmp_serde::to_vec_named(&envelope) {
+                    Ok(bytes) => Message::Binary(bytes),
+                    Err(_) => continue,
+                }
+            } else {
+                match serde_json::to_string(&envelope) {
+                    Ok(text) => Message::Text(text),
+                    Err(_) => continue,
+                }
+            };
+            if write.send(frame).await.is_err() {
+                break;
+            }
+        }
+        let _ = write.close().await;
+    });
+
+    while let Some(Ok(frame)) = read.next().await {
+        let envelope = match frame {
+            Message::Text(text) => serde_json::from_str::<ClientEnvelope>(&text).ok(),
+            Message::Binary(bytes) => rmp_serde::from_slice::<ClientEnvelope>(&bytes).ok(),
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let Some(ClientEnvelope {
+            request_id,
+            message,
+        }) = envelope
+        else {
+            let _ = outgoing.send(Outgoing::Message(
+                error(ErrorCode::InvalidRequest, "undecodable message").into(),
+            ));
+            continue;
+        };
+
+        let fault = {
+            let mut state = state.lock().unwrap();
+            state.received.push(message.clone());
+            state.take_fault(&message)
+        };
+        let reply = match fault {
+            Some(Fault::Error(code)) => Some(error(code, "injected fault")),
+            Some(Fault::NoReply) => None,
+            Some(Fault::Disconnect) => {
+                state.lock().unwrap().sessions[&session_id].disconnect("injected fault");
+                None
+            }
+            Some(Fault::Delay(delay)) => {
+                let state = Arc::clone(&state);
+                let outgoing = outgoing.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    let reply = {
+                        let mut state = state.lock().unwrap();
+                        // The connection closed while the reply was held back
+                        if !state.sessions.contains_key(&session_id) {
+                            return;
+                        }
+                        let id = request_id.clone().unwrap_or_default();
+                        state.handle(session_id, &id, message)
+                    };
+                    send_reply(&outgoing, request_id, reply);
+                });
+                continue;
+            }
+            None => {
+                let request_id = request_id.clone().unwrap_or_default();
+                state
+                    .lock()
+                    .unwrap()
+                    .handle(session_id, &request_id, message.clone())
+            }
+        };
+        send_reply(&outgoing, request_id, reply);
+    }
+
+    state.lock().unwrap().sessions.remove(&session_id);
+    drop(outgoing);
+    let _ = writer.await;
+}
+
+/// Queue the reply to a request, if it has one
+///
+/// Like the real server, messages sent without a request ID are answered
+/// too, just without one.
+fn send_reply(
+    outgoing: &mpsc::UnboundedSender<Outgoing>,
+    request_id: Option<String>,
+    reply: Option<ServerMessage>,
+) {
+    let Some(reply) = reply else { return };
+    let envelope = match request_id {
+        Some(request_id) => ServerEnvelope::reply(request_id, reply),
+        None => reply.into(),
+    };
+    let _ = outgoing.send(Outgoing::Message(envelope));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_type_is_serde_tag() {
+        let message = ClientMessage::WriteVariable {
+            service_id: "s".to_string(),
+            variable_name: "v".to_string(),
+            data: vec![],
+        };
+        assert_eq!(message_type(&message), "WriteVariable");
+    }
+
+    #[test]
+    fn test_faults_are_used_up_in_order() {
+        let mut state = State::default();
+        let heartbeat = ClientMessage::Heartbeat {
+            client_id: "c".to_string(),
+        };
+        state.faults.push_back(ScriptedFault {
+            message_type: "Heartbeat".to_string(),
+            fault: Fault::NoReply,
+            remaining: 2,
+        });
+        state.faults.push_back(ScriptedFault {
+            message_type: "*".to_string(),
+            fault: Fault::Disconnect,
+            remaining: 1,
+        });
+
+        assert_eq!(state.take_fault(&heartbeat), Some(Fault::NoReply));
+        assert_eq!(state.take_fault(&heartbeat), Some(Fault::NoReply));
+        assert_eq!(state.take_fault(&heartbeat), Some(Fault::Disconnect));
+        assert_eq!(state.take_fault(&heartbeat), None);
+    }
+
+    #[test]
+    fn test_credential_secrets() {
+        let basic = AuthCredentials::Basic {
+            username: "ann".to_string(),
+            password: "pw".to_string(),
+        };
+        assert_eq!(secret(&basic), "ann:pw");
+        assert_eq!(
+            secret(&AuthCredentials::Jwt {
+                token: "t".to_string()
+            }),
+            "t"
+        );
+    }
+}
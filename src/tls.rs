@@ -0,0 +1,474 @@
+//! TLS settings for `wss://` connections
+//!
+//! By default the server certificate is checked against the operating
+//! system's trusted roots. `TlsConfig` adds private CAs (such as the one that
+//! signed a development server's self-signed certificate), client
+//! certificates for mutual TLS, public key pinning, and an explicit insecure
+//! mode for local development.
+
+use crate::error::{CommyError, Result};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// TLS settings for a client
+///
+/// ```no_run
+/// use commy_sdk_rust::tls::TlsConfig;
+/// use commy_sdk_rust::Client;
+///
+/// # fn main() -> commy_sdk_rust::Result<()> {
+/// let tls = TlsConfig::new()
+///     .add_root_pem_file("certs/ca.pem")?
+///     .client_cert_pem_files("certs/client.pem", "certs/client.key")?;
+/// let client = Client::builder("wss://commy.internal:9000").tls(tls).build();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct TlsConfig {
+    native_roots: bool,
+    extra_roots: Vec<CertificateDer<'static>>,
+    client_auth: Option<(Vec<CertificateDer<'static>>, Arc<PrivateKeyDer<'static>>)>,
+    spki_pins: Vec<[u8; 32]>,
+    insecure: bool,
+}
+
+impl TlsConfig {
+    /// Trust the operating system's roots, with no client certificate
+    pub fn new() -> Self {
+        Self {
+            native_roots: true,
+            extra_roots: Vec::new(),
+            client_auth: None,
+            spki_pins: Vec::new(),
+            insecure: false,
+        }
+    }
+
+    /// Trust only the roots added with `add_root_pem`, not the system's
+    pub fn without_native_roots(mut self) -> Self {
+        self.native_roots = false;
+        self
+    }
+
+    /// Also trust the CA certificates in a PEM bundle
+    pub fn add_root_pem(mut self, pem: &[u8]) -> Result<Self> {
+        let certs = parse_certs(pem)?;
+        if certs.is_empty() {
+            return Err(CommyError::TlsError(
+                "no certificates in root PEM".to_string(),
+            ));
+        }
+        self.extra_roots.extend(certs);
+        Ok(self)
+    }
+
+    /// Also trust the CA certificates in a PEM file
+    pub fn add_root_pem_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let pem = std::fs::read(path)?;
+        self.add_root_pem(&pem)
+    }
+
+    /// Present a client certificate chain and its private key (mutual TLS)
+    pub fn client_cert_pem(mut self, cert_pem: &[u8], key_pem: &[u8]) -> Result<Self> {
+        let chain = parse_certs(cert_pem)?;
+        if chain.is_empty() {
+            return Err(CommyError::TlsError(
+                "no certificates in client certificate PEM".to_string(),
+            ));
+        }
+        let key = rustls_pemfile::private_key(&mut &key_pem[..])
+            .map_err(|e| CommyError::TlsError(format!("invalid private key PEM: {}", e)))?
+            .ok_or_else(|| CommyError::TlsError("no private key in key PEM".to_string()))?;
+        self.client_auth = Some((chain, Arc::new(key)));
+        Ok(self)
+    }
+
+    /// Present a client certificate and key read from PEM files
+    pub fn client_cert_pem_files(
+        self,
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let cert_pem = std::fs::read(cert_path)?;
+        let key_pem = std::fs::read(key_path)?;
+        self.client_cert_pem(&cert_pem, &key_pem)
+    }
+
+    /// Only accept servers whose public key has this SHA-256 digest
+    ///
+    /// The digest is taken over the DER-encoded SubjectPublicKeyInfo, as in
+    /// `openssl x509 -pubkey -noout | openssl pkey -pubin -outform der |
+    /// openssl dgst -sha256`. With several pins, any of them matches.
+    pub fn pin_spki_sha256(mut self, digest: [u8; 32]) -> Self {
+        self.spki_pins.push(digest);
+        self
+    }
+
+    /// Pin the public keys of the certificates in a PEM bundle
+    ///
+    /// A bundle without certificates is an error rather than no pin.
+    pub fn pin_certificate_pem(mut self, pem: &[u8]) -> Result<Self> {
+        let certs = parse_certs(pem)?;
+        if certs.is_empty() {
+            return Err(CommyError::TlsError(
+                "no certificates in pinned PEM".to_string(),
+            ));
+        }
+        for cert in certs {
+            self.spki_pins.push(spki_sha256(&cert)?);
+        }
+        Ok(self)
+    }
+
+    /// Accept any server certificate without checking its chain or name
+    ///
+    /// For local development against self-signed certificates only: it
+    /// removes all protection against impersonation. Pins, if any, are
+    /// still enforced, which makes pinning a self-signed certificate safe.
+    pub fn danger_accept_invalid_certs(mut self) -> Self {
+        self.insecure = true;
+        self
+    }
+
+    /// Build the rustls configuration for a connection
+    pub(crate) fn client_config(&self) -> Result<Arc<ClientConfig>> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?;
+
+        let chain_verifier = if self.insecure {
+            None
+        } else {
+            Some(
+                WebPkiServerVerifier::builder_with_provider(
+                    Arc::new(self.root_store()?),
+                    Arc::clone(&provider),
+                )
+                .build()
+                .map_err(|e| CommyError::TlsError(e.to_string()))?,
+            )
+        };
+
+        let builder = match (chain_verifier, self.spki_pins.is_empty()) {
+            (Some(verifier), true) => builder.with_webpki_verifier(verifier),
+            (chain, _) => builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(PinningVerifier {
+                    chain,
+                    pins: self.spki_pins.clone(),
+                    provider,
+                })),
+        };
+
+        let config = match &self.client_auth {
+            Some((chain, key)) => builder
+                .with_client_auth_cert(chain.clone(), key.clone_key())
+                .map_err(tls_error)?,
+            None => builder.with_no_client_auth(),
+        };
+
+        Ok(Arc::new(config))
+    }
+
+    /// Roots to verify the server's chain against
+    fn root_store(&self) -> Result<RootCertStore> {
+        let mut roots = RootCertStore::empty();
+        if self.native_roots {
+            // Unreadable system certificates are skipped, as browsers do
+            let native = rustls_native_certs::load_native_certs().unwrap_or_default();
+            roots.add_parsable_certificates(native);
+        }
+        for cert in &self.extra_roots {
+            roots.add(cert.clone()).map_err(tls_error)?;
+        }
+        Ok(roots)
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConfig")
+            .field("native_roots", &self.native_roots)
+            .field("extra_roots", &self.extra_roots.len())
+            .field("client_auth", &self.client_auth.is_some())
+            .field("spki_pins", &self.spki_pins.len())
+            .field("insecure", &self.insecure)
+            .finish()
+    }
+}
+
+/// SHA-256 digest of a certificate's DER-encoded SubjectPublicKeyInfo
+pub fn spki_sha256(cert: &CertificateDer<'_>) -> Result<[u8; 32]> {
+    let cert = webpki::EndEntityCert::try_from(cert)
+        .map_err(|e| CommyError::TlsError(format!("invalid certificate: {}", e)))?;
+    Ok(Sha256::digest(cert.subject_public_key_info().as_ref()).into())
+}
+
+/// Read every certificate from a PEM bundle
+fn parse_certs(pem: &[u8]) -> Result<Vec<CertificateDer<'static>>> {
+    rustls_pemfile::certs(&mut &pem[..])
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| CommyError::TlsError(format!("invalid certificate PEM: {}", e)))
+}
+
+fn tls_error(e: rustls::Error) -> CommyError {
+    CommyError::TlsError(e.to_string())
+}
+
+/// Checks the chain (unless insecure) and then the pinned public keys
+#[derive(Debug)]
+struct PinningVerifier {
+    chain: Option<Arc<WebPkiServerVerifier>>,
+    pins: Vec<[u8; 32]>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        if let Some(chain) = &self.chain {
+            chain.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)?;
+        }
+
+        if !self.pins.is_empty() {
+            let digest =
+                spki_sha256(end_entity).map_err(|e| rustls::Error::General(e.to_string()))?;
+            if !self.pins.contains(&digest) {
+                return Err(rustls::Error::General(
+                    "server public key does not match any pin".to_string(),
+                ));
+            }
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! The fixtures in `tests/fixtures/tls` are a test CA and a server
+    //! (`localhost`, 127.0.0.1) and client certificate it signed, all P-256
+    //! and valid for 100 years, generated with `openssl req` / `openssl x509`.
+
+    use super::*;
+    use crate::connection::{Connection, PUSH_CHANNEL_CAPACITY};
+    use crate::wire::WireFormat;
+    use futures::StreamExt;
+    use tokio::sync::broadcast;
+
+    const CA_PEM: &[u8] = include_bytes!("../tests/fixtures/tls/ca.pem");
+    const SERVER_PEM: &[u8] = include_bytes!("../tests/fixtures/tls/server.pem");
+    const SERVER_KEY: &[u8] = include_bytes!("../tests/fixtures/tls/server.key");
+    const CLIENT_PEM: &[u8] = include_bytes!("../tests/fixtures/tls/client.pem");
+    const CLIENT_KEY: &[u8] = include_bytes!("../tests/fixtures/tls/client.key");
+
+    /// Serve TLS WebSocket connections with the fixture server certificate
+    async fn serve_tls(require_client_cert: bool) -> String {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = rustls::ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()
+            .unwrap();
+        let builder = if require_client_cert {
+            let mut roots = RootCertStore::empty();
+            roots.add(parse_certs(CA_PEM).unwrap().remove(0)).unwrap();
+            let verifier = rustls::server::WebPkiClientVerifier::builder_with_provider(
+                Arc::new(roots),
+                provider,
+            )
+            .build()
+            .unwrap();
+            builder.with_client_cert_verifier(verifier)
+        } else {
+            builder.with_no_client_auth()
+        };
+        let key = rustls_pemfile::private_key(&mut &SERVER_KEY[..])
+            .unwrap()
+            .unwrap();
+        let config = builder
+            .with_single_cert(parse_certs(SERVER_PEM).unwrap(), key)
+            .unwrap();
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("wss://localhost:{}", listener.local_addr().unwrap().port());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(tls) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let Ok(mut ws) = tokio_tungstenite::accept_async(tls).await else {
+                        return;
+                    };
+                    while let Some(Ok(_)) = ws.next().await {}
+                });
+            }
+        });
+
+        url
+    }
+
+    async fn connect(url: &str, tls: TlsConfig) -> Result<Connection> {
+        let (pushes, _) = broadcast::channel(PUSH_CHANNEL_CAPACITY);
+        Connection::with_tls(url, pushes, WireFormat::Json, &tls).await
+    }
+
+    fn private_ca() -> TlsConfig {
+        TlsConfig::new()
+            .without_native_roots()
+            .add_root_pem(CA_PEM)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_private_root_is_trusted() {
+        let url = serve_tls(false).await;
+        assert!(connect(&url, private_ca()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_issuer_is_rejected() {
+        let url = serve_tls(false).await;
+        assert!(connect(&url, TlsConfig::new()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_certificate_for_mutual_tls() {
+        let url = serve_tls(true).await;
+
+        let with_cert = private_ca()
+            .client_cert_pem(CLIENT_PEM, CLIENT_KEY)
+            .unwrap();
+        assert!(connect(&url, with_cert).await.is_ok());
+        assert!(connect(&url, private_ca()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pinned_public_key() {
+        let url = serve_tls(false).await;
+
+        let pinned = private_ca().pin_certificate_pem(SERVER_PEM).unwrap();
+        assert!(connect(&url, pinned).await.is_ok());
+
+        let wrong_pin = private_ca().pin_certificate_pem(CLIENT_PEM).unwrap();
+        assert!(connect(&url, wrong_pin).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_insecure_mode_still_enforces_pins() {
+        let url = serve_tls(false).await;
+
+        let insecure = TlsConfig::new()
+            .without_native_roots()
+            .danger_accept_invalid_certs();
+        assert!(connect(&url, insecure.clone()).await.is_ok());
+
+        let wrong_pin = insecure.pin_certificate_pem(CLIENT_PEM).unwrap();
+        assert!(connect(&url, wrong_pin).await.is_err());
+    }
+
+    #[test]
+    fn test_no_roots_is_a_configuration_error() {
+        let result = TlsConfig::new().without_native_roots().client_config();
+        assert!(matches!(result, Err(CommyError::TlsError(_))));
+    }
+
+    #[test]
+    fn test_invalid_pem_is_rejected() {
+        assert!(matches!(
+            TlsConfig::new().add_root_pem(b"not a certificate"),
+            Err(CommyError::TlsError(_))
+        ));
+        assert!(matches!(
+            TlsConfig::new().client_cert_pem(CLIENT_PEM, b""),
+            Err(CommyError::TlsError(_))
+        ));
+    }
+
+    #[test]
+    fn test_pinning_an_empty_pem_is_rejected() {
+        for pem in [&b""[..], b"not a certificate", CLIENT_KEY] {
+            assert!(matches!(
+                TlsConfig::new().pin_certificate_pem(pem),
+                Err(CommyError::TlsError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_pin_is_sha256_of_public_key() {
+        let cert = parse_certs(SERVER_PEM).unwrap().remove(0);
+        let pin = spki_sha256(&cert).unwrap();
+        assert_eq!(pin, spki_sha256(&cert).unwrap());
+        assert_ne!(
+            pin,
+            spki_sha256(&parse_certs(CLIENT_PEM).unwrap()[0]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_debug_hides_private_key() {
+        let tls = TlsConfig::new()
+            .client_cert_pem(CLIENT_PEM, CLIENT_KEY)
+            .unwrap();
+        let debug = format!("{:?}", tls);
+        assert!(debug.contains("client_auth: true"));
+        assert!(!debug.contains("PRIVATE"));
+    }
+}
@@ -0,0 +1,415 @@
+//! Transports carrying protocol messages between client and server
+//!
+//! A `Connection` exchanges `ClientEnvelope`s and `ServerEnvelope`s (protocol
+//! messages tagged with their request IDs) over a `Transport`. The SDK ships
+//! a WebSocket transport, used for `ws://`, `wss://` and `unix://` URLs, and
+//! an in-process channel transport for testing code built on `Client`
+//! without a server:
+//!
+//! ```
+//! use commy_sdk_rust::message::{ServerEnvelope, ServerMessage};
+//! use commy_sdk_rust::transport::ChannelFactory;
+//! use commy_sdk_rust::Client;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> commy_sdk_rust::Result<()> {
+//! let (factory, mut peers) = ChannelFactory::new();
+//! let client = Client::builder("memory://test")
+//!     .transport(factory)
+//!     .capability_handshake(false)
+//!     .build();
+//! client.connect().await?;
+//!
+//! // Play the server's part
+//! let mut peer = peers.recv().await.unwrap();
+//! tokio::spawn(async move {
+//!     while let Some(request) = peer.from_client.recv().await {
+//!         let reply = ServerMessage::Heartbeat { timestamp: "now".to_string() };
+//!         let _ = peer.to_client.send(ServerEnvelope {
+//!             request_id: request.request_id,
+//!             message: reply,
+//!         });
+//!     }
+//! });
+//!
+//! client.heartbeat().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{CommyError, Result};
+use crate::message::{ClientEnvelope, ServerEnvelope};
+use crate::tls::TlsConfig;
+use crate::unix_socket::{self, PeerCheck};
+use crate::wire::{self, WireFormat};
+use futures::stream::{SplitSink, SplitStream};
+use futures::{SinkExt, StreamExt};
+use std::fmt::Debug;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::error::{Error as WsError, ProtocolError};
+use tokio_tungstenite::tungstenite::http::header::{HeaderValue, SEC_WEBSOCKET_PROTOCOL};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{
+    connect_async_tls_with_config, Connector, MaybeTlsStream, WebSocketStream,
+};
+
+/// A bidirectional message channel to a server
+///
+/// `send` and `recv` are called concurrently from separate tasks, so
+/// implementations must not hold a lock shared by both while waiting.
+#[async_trait::async_trait]
+pub trait Transport: Send + Sync {
+    /// Send one message to the server
+    async fn send(&self, envelope: ClientEnvelope) -> Result<()>;
+
+    /// Receive the next message from the server
+    ///
+    /// Returns `None` once the transport is closed. An error reports a single
+    /// message that could not be decoded; the transport stays usable.
+    async fn recv(&self) -> Option<Result<ServerEnvelope>>;
+
+    /// Close the transport; `recv` returns `None` afterwards
+    async fn close(&self);
+
+    /// Wire format agreed with the server
+    fn wire_format(&self) -> WireFormat {
+        WireFormat::Json
+    }
+}
+
+/// Opens a fresh transport each time a client connects or reconnects
+#[async_trait::async_trait]
+pub trait TransportFactory: Debug + Send + Sync {
+    /// Open a transport to the server
+    async fn open(&self) -> Result<Box<dyn Transport>>;
+}
+
+/// Open the transport for a `ws://`, `wss://` or `unix://` URL
+///
+/// `tls` applies to `wss://` URLs and `peer_check` to `unix://` URLs.
+pub async fn connect(
+    url: &str,
+    preferred: WireFormat,
+    tls: Option<&TlsConfig>,
+    peer_check: &PeerCheck,
+) -> Result<Box<dyn Transport>> {
+    if let Some(path) = unix_socket::socket_path(url) {
+        #[cfg(unix)]
+        {
+            let transport = WebSocketTransport::connect_unix(path, preferred, peer_check).await?;
+            return Ok(Box::new(transport));
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = (path, peer_check);
+            return Err(CommyError::InvalidRequest(
+                "unix:// URLs are only supported on Unix platforms".to_string(),
+            ));
+        }
+    }
+
+    let transport = match tls {
+        Some(tls) => WebSocketTransport::connect_tls(url, preferred, tls).await?,
+        None => WebSocketTransport::connect(url, preferred).await?,
+    };
+    Ok(Box::new(transport))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// WebSocket
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Protocol messages in WebSocket frames, encoded in the negotiated format
+pub struct WebSocketTransport<S> {
+    sink: Mutex<SplitSink<WebSocketStream<S>, Message>>,
+    stream: Mutex<SplitStream<WebSocketStream<S>>>,
+    wire_format: WireFormat,
+}
+
+impl<S> WebSocketTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Wrap a WebSocket whose handshake already settled on `wire_format`
+    pub fn new(ws_stream: WebSocketStream<S>, wire_format: WireFormat) -> Self {
+        let (sink, stream) = ws_stream.split();
+        Self {
+            sink: Mutex::new(sink),
+            stream: Mutex::new(stream),
+            wire_format,
+        }
+    }
+}
+
+impl WebSocketTransport<MaybeTlsStream<TcpStream>> {
+    /// Connect to a `ws://` or `wss://` URL, offering `preferred` as the
+    /// wire format
+    ///
+    /// The server's subprotocol answer decides the format actually used. A
+    /// server that refuses the offer gets a second, plain handshake and JSON.
+    pub async fn connect(url: &str, preferred: WireFormat) -> Result<Self> {
+        let (ws_stream, wire_format) = open_socket(url, preferred, None).await?;
+        Ok(Self::new(ws_stream, wire_format))
+    }
+
+    /// Like `connect`, using the given TLS settings for `wss://`
+    pub async fn connect_tls(url: &str, preferred: WireFormat, tls: &TlsConfig) -> Result<Self> {
+        let connector = Connector::Rustls(tls.client_config()?);
+        let (ws_stream, wire_format) = open_socket(url, preferred, Some(connector)).await?;
+        Ok(Self::new(ws_stream, wire_format))
+    }
+}
+
+#[cfg(unix)]
+impl WebSocketTransport<tokio::net::UnixStream> {
+    /// Connect to the Unix domain socket at `path`, accepting only server
+    /// processes that pass `peer_check`
+    pub async fn connect_unix(
+        path: &str,
+        preferred: WireFormat,
+        peer_check: &PeerCheck,
+    ) -> Result<Self> {
+        let (ws_stream, wire_format) = unix_socket::open(path, preferred, peer_check).await?;
+        Ok(Self::new(ws_stream, wire_format))
+    }
+}
+
+#[async_trait::async_trait]
+impl<S> Transport for WebSocketTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    async fn send(&self, envelope: ClientEnvelope) -> Result<()> {
+        let frame = self.wire_format.encode(&envelope)?;
+        self.sink.lock().await.send(frame).await?;
+        Ok(())
+    }
+
+    async fn recv(&self) -> Option<Result<ServerEnvelope>> {
+        let mut stream = self.stream.lock().await;
+        while let Some(Ok(frame)) = stream.next().await {
+            // Control frames decode to nothing
+            if let Some(decoded) = wire::decode(frame) {
+                return Some(decoded);
+            }
+        }
+        None
+    }
+
+    async fn close(&self) {
+        let _ = self.sink.lock().await.close().await;
+    }
+
+    fn wire_format(&self) -> WireFormat {
+        self.wire_format
+    }
+}
+
+/// Open the socket, offering the preferred wire format as a subprotocol
+async fn open_socket(
+    url: &str,
+    preferred: WireFormat,
+    connector: Option<Connector>,
+) -> Result<(WebSocketStream<MaybeTlsStream<TcpStream>>, WireFormat)> {
+    if preferred == WireFormat::Json {
+        let (ws_stream, _) = connect_async_tls_with_config(url, None, false, connector).await?;
+        return Ok((ws_stream, WireFormat::Json));
+    }
+
+    let mut request = url.into_client_request()?;
+    request.headers_mut().insert(
+        SEC_WEBSOCKET_PROTOCOL,
+        HeaderValue::from_static(preferred.offer()),
+    );
+
+    match connect_async_tls_with_config(request, None, false, connector.clone()).await {
+        Ok((ws_stream, response)) => {
+            let wire_format = response
+                .headers()
+                .get(SEC_WEBSOCKET_PROTOCOL)
+                .and_then(|value| value.to_str().ok())
+                .and_then(WireFormat::from_subprotocol)
+                .unwrap_or(WireFormat::Json);
+            Ok((ws_stream, wire_format))
+        }
+        // Servers without subprotocol support refuse the offer; speak plain JSON
+        Err(WsError::Protocol(ProtocolError::SecWebSocketSubProtocolError(_))) => {
+            let (ws_stream, _) = connect_async_tls_with_config(url, None, false, connector).await?;
+            Ok((ws_stream, WireFormat::Json))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// In-process channels
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// In-process transport whose other end is held by the test playing the
+/// server
+///
+/// Messages are passed as values, without encoding.
+pub struct ChannelTransport {
+    outgoing: std::sync::Mutex<Option<mpsc::UnboundedSender<ClientEnvelope>>>,
+    incoming: Mutex<mpsc::UnboundedReceiver<ServerEnvelope>>,
+    closed: watch::Sender<bool>,
+}
+
+/// The server's ends of a `ChannelTransport`
+#[derive(Debug)]
+pub struct ChannelPeer {
+    /// Delivers messages to the client
+    pub to_client: mpsc::UnboundedSender<ServerEnvelope>,
+
+    /// Yields what the client sends; ends when the client closes the transport
+    pub from_client: mpsc::UnboundedReceiver<ClientEnvelope>,
+}
+
+impl ChannelTransport {
+    /// Create a transport and the server's ends of it
+    ///
+    /// Dropping `ChannelPeer::to_client` closes the transport, as a server
+    /// closing the socket would.
+    pub fn pair() -> (Self, ChannelPeer) {
+        let (to_client, incoming) = mpsc::unbounded_channel();
+        let (outgoing, from_client) = mpsc::unbounded_channel();
+        let transport = Self {
+            outgoing: std::sync::Mutex::new(Some(outgoing)),
+            incoming: Mutex::new(incoming),
+            closed: watch::channel(false).0,
+        };
+        (
+            transport,
+            ChannelPeer {
+                to_client,
+                from_client,
+            },
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for ChannelTransport {
+    async fn send(&self, envelope: ClientEnvelope) -> Result<()> {
+        let outgoing = self.outgoing.lock().unwrap().clone();
+        let outgoing =
+            outgoing.ok_or_else(|| CommyError::ConnectionLost("transport closed".to_string()))?;
+        outgoing
+            .send(envelope)
+            .map_err(|_| CommyError::ConnectionLost("server end dropped".to_string()))
+    }
+
+    async fn recv(&self) -> Option<Result<ServerEnvelope>> {
+        let mut closed = self.closed.subscribe();
+        let mut incoming = self.incoming.lock().await;
+        tokio::select! {
+            envelope = incoming.recv() => envelope.map(Ok),
+            _ = closed.wait_for(|closed| *closed) => None,
+        }
+    }
+
+    async fn close(&self) {
+        self.outgoing.lock().unwrap().take();
+        self.closed.send_replace(true);
+    }
+}
+
+/// Opens a `ChannelTransport` on every connect and hands its server ends
+/// to the receiver returned by `new`
+#[derive(Debug, Clone)]
+pub struct ChannelFactory {
+    peers: mpsc::UnboundedSender<ChannelPeer>,
+}
+
+impl ChannelFactory {
+    /// Create a factory and the receiver of the server ends it opens
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<ChannelPeer>) {
+        let (peers, accepted) = mpsc::unbounded_channel();
+        (Self { peers }, accepted)
+    }
+}
+
+#[async_trait::async_trait]
+impl TransportFactory for ChannelFactory {
+    async fn open(&self) -> Result<Box<dyn Transport>> {
+        let (transport, peer) = ChannelTransport::pair();
+        self.peers.send(peer).map_err(|_| {
+            CommyError::WebSocketError("no server accepting connections".to_string())
+        })?;
+        Ok(Box::new(transport))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{ClientMessage, ServerMessage};
+
+    fn heartbeat() -> ClientEnvelope {
+        ClientMessage::Heartbeat {
+            client_id: "c1".to_string(),
+        }
+        .into()
+    }
+
+    #[tokio::test]
+    async fn test_channel_transport_carries_messages_both_ways() {
+        let (transport, mut peer) = ChannelTransport::pair();
+
+        transport.send(heartbeat()).await.unwrap();
+        let sent = peer.from_client.recv().await.unwrap();
+        assert!(matches!(sent.message, ClientMessage::Heartbeat { .. }));
+
+        let reply = ServerMessage::Heartbeat {
+            timestamp: "now".to_string(),
+        };
+        peer.to_client
+            .send(ServerEnvelope::reply("1", reply))
+            .unwrap();
+        let received = transport.recv().await.unwrap().unwrap();
+        assert_eq!(received.request_id.as_deref(), Some("1"));
+    }
+
+    #[tokio::test]
+    async fn test_channel_transport_close_ends_both_directions() {
+        let (transport, mut peer) = ChannelTransport::pair();
+
+        transport.close().await;
+        assert!(transport.recv().await.is_none());
+        assert!(peer.from_client.recv().await.is_none());
+        assert!(matches!(
+            transport.send(heartbeat()).await,
+            Err(CommyError::ConnectionLost(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dropping_server_end_closes_channel_transport() {
+        let (transport, peer) = ChannelTransport::pair();
+        drop(peer);
+
+        assert!(transport.recv().await.is_none());
+        assert!(transport.send(heartbeat()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_channel_factory_hands_out_a_peer_per_open() {
+        let (factory, mut peers) = ChannelFactory::new();
+
+        let first = factory.open().await.unwrap();
+        let second = factory.open().await.unwrap();
+        first.send(heartbeat()).await.unwrap();
+
+        let mut first_peer = peers.recv().await.unwrap();
+        let mut second_peer = peers.recv().await.unwrap();
+        assert!(first_peer.from_client.recv().await.is_some());
+        drop(second);
+        assert!(second_peer.from_client.recv().await.is_none());
+
+        drop(peers);
+        assert!(factory.open().await.is_err());
+    }
+}
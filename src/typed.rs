@@ -0,0 +1,262 @@
+//! Typed variable handles
+
+use crate::client::Client;
+use crate::codec::{Codec, JsonCodec};
+use crate::error::Result;
+use crate::subscription::Subscription;
+use futures::stream::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A new value of a watched typed variable
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedUpdate<T> {
+    /// Decoded variable value
+    pub value: T,
+
+    /// Version of the variable after the change
+    pub version: u64,
+}
+
+/// Handle to a variable holding values of type `T`
+///
+/// Obtained from `Service::typed_variable`. Values are encoded with the codec
+/// `C` (JSON unless chosen with `Service::typed_variable_with_codec`) on top of
+/// the client's raw byte operations.
+pub struct TypedVariable<'a, T, C = JsonCodec> {
+    client: &'a Client,
+    service_id: String,
+    name: String,
+    codec: C,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<'a, T, C> TypedVariable<'a, T, C>
+where
+    T: Serialize + DeserializeOwned,
+    C: Codec,
+{
+    /// Create a handle for a variable of a service
+    pub fn new(
+        client: &'a Client,
+        service_id: impl Into<String>,
+        name: impl Into<String>,
+        codec: C,
+    ) -> Self {
+        Self {
+            client,
+            service_id: service_id.into(),
+            name: name.into(),
+            codec,
+            _value: PhantomData,
+        }
+    }
+
+    /// Get the service ID
+    pub fn service_id(&self) -> &str {
+        &self.service_id
+    }
+
+    /// Get the variable name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Read and decode the current value
+    pub async fn get(&self) -> Result<T> {
+        let data = self
+            .client
+            .read_variable(&self.service_id, &self.name)
+            .await?;
+        self.codec.decode(&data)
+    }
+
+    /// Encode and write a new value, returning the variable's new version
+    pub async fn set(&self, value: &T) -> Result<u64> {
+        let data = self.codec.encode(value)?;
+        self.client
+            .write_variable(&self.service_id, &self.name, data)
+            .await
+    }
+
+    /// Subscribe to decoded changes of the variable
+    pub async fn watch(&self) -> Result<TypedSubscription<T, C>> {
+        let inner = self.client.subscribe(&self.service_id, &self.name).await?;
+        Ok(TypedSubscription {
+            inner,
+            codec: self.codec.clone(),
+            _value: PhantomData,
+        })
+    }
+}
+
+/// Stream of decoded changes to a typed variable
+///
+/// Yields an error for any change the codec cannot decode and keeps going.
+/// Dropping it unsubscribes like a plain `Subscription`.
+pub struct TypedSubscription<T, C = JsonCodec> {
+    inner: Subscription,
+    codec: C,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T, C> Stream for TypedSubscription<T, C>
+where
+    T: DeserializeOwned,
+    C: Codec,
+{
+    type Item = Result<TypedUpdate<T>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.inner.poll_next_unpin(cx).map(|update| {
+            update.map(|update| {
+                this.codec.decode(&update.data).map(|value| TypedUpdate {
+                    value,
+                    version: update.version,
+                })
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{FixedLayoutCodec, MessagePackCodec};
+    use crate::message::{ClientMessage, ServerEnvelope, ServerMessage};
+    use crate::service::Service;
+    use serde::Deserialize;
+    use std::time::Duration;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Position {
+        x: i32,
+        y: i32,
+    }
+
+    fn service() -> Service {
+        Service::new(
+            "svc".to_string(),
+            "map".to_string(),
+            "tenant".to_string(),
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_set_encodes_with_codec() {
+        let client = Client::new("wss://test");
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        let position =
+            service().typed_variable_with_codec::<Position, _>(&client, "pos", FixedLayoutCodec);
+
+        let server = tokio::spawn(async move {
+            let request = client_rx.recv().await.expect("client closed");
+            server_tx
+                .send(ServerEnvelope::reply(
+                    request.request_id.clone().unwrap(),
+                    ServerMessage::VariableWritten {
+                        service_id: "svc".to_string(),
+                        variable_name: "pos".to_string(),
+                        version: 2,
+                    },
+                ))
+                .unwrap();
+            request.message
+        });
+
+        let version = position.set(&Position { x: 1, y: -1 }).await.unwrap();
+        assert_eq!(version, 2);
+        match server.await.unwrap() {
+            ClientMessage::WriteVariable { data, .. } => {
+                assert_eq!(data, [1i32.to_le_bytes(), (-1i32).to_le_bytes()].concat())
+            }
+            other => panic!("Expected WriteVariable, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_decodes_with_codec() {
+        let client = Client::new("wss://test");
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        let position =
+            service().typed_variable_with_codec::<Position, _>(&client, "pos", MessagePackCodec);
+
+        tokio::spawn(async move {
+            let request = client_rx.recv().await.expect("client closed");
+            let data = MessagePackCodec.encode(&Position { x: 4, y: 5 }).unwrap();
+            server_tx
+                .send(ServerEnvelope::reply(
+                    request.request_id.unwrap(),
+                    ServerMessage::VariableData {
+                        service_id: "svc".to_string(),
+                        variable_name: "pos".to_string(),
+                        data,
+                        version: 1,
+                    },
+                ))
+                .unwrap();
+        });
+
+        assert_eq!(position.get().await.unwrap(), Position { x: 4, y: 5 });
+    }
+
+    #[tokio::test]
+    async fn test_watch_yields_decoded_updates_and_decode_errors() {
+        let client = Client::new("wss://test");
+        let (server_tx, mut client_rx) = client.connect_mock_for_test().await;
+        let position = service().typed_variable::<Position>(&client, "pos");
+
+        tokio::spawn({
+            let server_tx = server_tx.clone();
+            async move {
+                let request = client_rx.recv().await.expect("client closed");
+                let request_id = request.request_id.unwrap();
+                server_tx
+                    .send(ServerEnvelope::reply(
+                        request_id.clone(),
+                        ServerMessage::Result {
+                            request_id,
+                            success: true,
+                            message: "subscribed".to_string(),
+                        },
+                    ))
+                    .unwrap();
+            }
+        });
+
+        let mut updates = position.watch().await.unwrap();
+
+        for (data, version) in [(b"not json".to_vec(), 1), (br#"{"x":7,"y":8}"#.to_vec(), 2)] {
+            server_tx
+                .send(
+                    ServerMessage::VariableChanged {
+                        service_id: "svc".to_string(),
+                        variable_name: "pos".to_string(),
+                        data,
+                        version,
+                    }
+                    .into(),
+                )
+                .unwrap();
+        }
+
+        let undecodable = tokio::time::timeout(Duration::from_secs(2), updates.next())
+            .await
+            .expect("no update delivered")
+            .expect("subscription ended");
+        assert!(undecodable.is_err());
+
+        let update = tokio::time::timeout(Duration::from_secs(2), updates.next())
+            .await
+            .expect("no update delivered")
+            .expect("subscription ended")
+            .unwrap();
+        assert_eq!(update.value, Position { x: 7, y: 8 });
+        assert_eq!(update.version, 2);
+    }
+}
@@ -0,0 +1,217 @@
+//! Unix domain socket transport for same-host clients
+//!
+//! A `unix:///run/commy/commy.sock` URL runs the usual WebSocket protocol over
+//! a Unix domain socket instead of TCP, which spares co-located processes the
+//! TCP and TLS overhead. Before the WebSocket handshake, the credentials of
+//! the process on the other end of the socket are checked against a
+//! `PeerCheck`, so whoever manages to bind the socket path cannot pose as the
+//! server.
+
+use crate::error::{CommyError, Result};
+
+/// URL scheme selecting the Unix domain socket transport
+pub const UNIX_SCHEME: &str = "unix://";
+
+/// Which server processes a Unix socket connection may talk to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PeerCheck {
+    /// The server runs as the same user as this process, or as root
+    #[default]
+    SameUserOrRoot,
+
+    /// The server runs as one of these user IDs
+    Uids(Vec<u32>),
+
+    /// The server runs with one of these group IDs
+    Gids(Vec<u32>),
+
+    /// Accept any server process
+    Any,
+}
+
+impl PeerCheck {
+    /// Check the server's user and group IDs against this policy
+    pub fn verify(&self, uid: u32, gid: u32) -> Result<()> {
+        let allowed = match self {
+            PeerCheck::SameUserOrRoot => uid == 0 || Some(uid) == current_uid(),
+            PeerCheck::Uids(uids) => uids.contains(&uid),
+            PeerCheck::Gids(gids) => gids.contains(&gid),
+            PeerCheck::Any => true,
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(CommyError::PermissionDenied(format!(
+                "socket peer uid {} gid {} rejected by {:?}",
+                uid, gid, self
+            )))
+        }
+    }
+}
+
+/// Socket path of a `unix://` URL, or `None` for other schemes
+pub fn socket_path(url: &str) -> Option<&str> {
+    url.strip_prefix(UNIX_SCHEME)
+}
+
+#[cfg(unix)]
+fn current_uid() -> Option<u32> {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    Some(unsafe { libc::geteuid() })
+}
+
+#[cfg(not(unix))]
+fn current_uid() -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+pub(crate) use platform::open;
+
+#[cfg(unix)]
+mod platform {
+    use super::PeerCheck;
+    use crate::error::{CommyError, Result};
+    use crate::wire::WireFormat;
+    use tokio::net::UnixStream;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::error::{Error as WsError, ProtocolError};
+    use tokio_tungstenite::tungstenite::handshake::client::Request;
+    use tokio_tungstenite::tungstenite::http::header::{HeaderValue, SEC_WEBSOCKET_PROTOCOL};
+    use tokio_tungstenite::{client_async, WebSocketStream};
+
+    /// Connect to the socket at `path`, check the peer, then run the
+    /// WebSocket handshake offering the preferred wire format
+    pub(crate) async fn open(
+        path: &str,
+        preferred: WireFormat,
+        peer_check: &PeerCheck,
+    ) -> Result<(WebSocketStream<UnixStream>, WireFormat)> {
+        if path.is_empty() {
+            return Err(CommyError::InvalidRequest(
+                "unix:// URL has no socket path".to_string(),
+            ));
+        }
+
+        let stream = connect(path, peer_check).await?;
+        if preferred == WireFormat::Json {
+            let (ws_stream, _) = client_async(request(None)?, stream).await?;
+            return Ok((ws_stream, WireFormat::Json));
+        }
+
+        match client_async(request(Some(preferred.offer()))?, stream).await {
+            Ok((ws_stream, response)) => {
+                let wire_format = response
+                    .headers()
+                    .get(SEC_WEBSOCKET_PROTOCOL)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(WireFormat::from_subprotocol)
+                    .unwrap_or(WireFormat::Json);
+                Ok((ws_stream, wire_format))
+            }
+            // Servers without subprotocol support refuse the offer; speak plain JSON
+            Err(WsError::Protocol(ProtocolError::SecWebSocketSubProtocolError(_))) => {
+                let stream = connect(path, peer_check).await?;
+                let (ws_stream, _) = client_async(request(None)?, stream).await?;
+                Ok((ws_stream, WireFormat::Json))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn connect(path: &str, peer_check: &PeerCheck) -> Result<UnixStream> {
+        let stream = UnixStream::connect(path).await.map_err(|e| {
+            CommyError::WebSocketError(format!("cannot connect to {}: {}", path, e))
+        })?;
+        let peer = stream.peer_cred()?;
+        peer_check.verify(peer.uid(), peer.gid())?;
+        Ok(stream)
+    }
+
+    /// Handshake request; the host is a placeholder, as the socket path
+    /// already identifies the server
+    fn request(offer: Option<&'static str>) -> Result<Request> {
+        let mut request = "ws://localhost/".into_client_request()?;
+        if let Some(offer) = offer {
+            request
+                .headers_mut()
+                .insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static(offer));
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_socket_path() {
+        assert_eq!(
+            socket_path("unix:///run/commy.sock"),
+            Some("/run/commy.sock")
+        );
+        assert_eq!(socket_path("unix://"), Some(""));
+        assert_eq!(socket_path("ws://localhost:9000"), None);
+    }
+
+    #[test]
+    fn test_explicit_uid_and_gid_lists() {
+        assert!(PeerCheck::Uids(vec![1000, 1001]).verify(1001, 5).is_ok());
+        assert!(PeerCheck::Uids(vec![1000]).verify(1001, 1000).is_err());
+        assert!(PeerCheck::Gids(vec![50]).verify(1001, 50).is_ok());
+        assert!(matches!(
+            PeerCheck::Gids(vec![50]).verify(1001, 51),
+            Err(CommyError::PermissionDenied(_))
+        ));
+        assert!(PeerCheck::Any.verify(12345, 12345).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_same_user_or_root() {
+        let me = current_uid().unwrap();
+        assert!(PeerCheck::SameUserOrRoot.verify(me, 0).is_ok());
+        assert!(PeerCheck::SameUserOrRoot.verify(0, 0).is_ok());
+        let other = if me == 4242 { 4243 } else { 4242 };
+        assert!(PeerCheck::SameUserOrRoot.verify(other, 0).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_peer_check_runs_before_handshake() {
+        use crate::wire::WireFormat;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("commy.sock");
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(tokio_tungstenite::accept_async(stream));
+            }
+        });
+        let path = path.to_str().unwrap();
+
+        let nobody = PeerCheck::Uids(Vec::new());
+        let rejected = open(path, WireFormat::Json, &nobody).await;
+        assert!(matches!(rejected, Err(CommyError::PermissionDenied(_))));
+
+        let (_, format) = open(path, WireFormat::Json, &PeerCheck::default())
+            .await
+            .unwrap();
+        assert_eq!(format, WireFormat::Json);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_missing_socket_and_empty_path() {
+        use crate::wire::WireFormat;
+
+        let missing = open("/nonexistent/commy.sock", WireFormat::Json, &PeerCheck::Any).await;
+        assert!(matches!(missing, Err(CommyError::WebSocketError(_))));
+
+        let empty = open("", WireFormat::Json, &PeerCheck::Any).await;
+        assert!(matches!(empty, Err(CommyError::InvalidRequest(_))));
+    }
+}
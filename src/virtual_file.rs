@@ -1,772 +1,1093 @@
-//! Virtual variable file abstraction
-//!
-//! Provides a unified interface for accessing variables from either:
-//! - Direct memory-mapped files (local clients)
-//! - In-memory buffers synchronized via WSS (remote clients)
-
-use crate::error::{CommyError, Result};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
-
-/// Metadata about a variable in the file
-#[derive(Debug, Clone)]
-pub struct VariableMetadata {
-    /// Variable name
-    pub name: String,
-
-    /// Byte offset in the file
-    pub offset: u64,
-
-    /// Variable size in bytes
-    pub size: u64,
-
-    /// Type ID
-    pub type_id: u32,
-
-    /// Whether this variable persists across disconnections
-    pub persistent: bool,
-}
-
-impl VariableMetadata {
-    /// Create new variable metadata
-    pub fn new(name: String, offset: u64, size: u64, type_id: u32) -> Self {
-        Self {
-            name,
-            offset,
-            size,
-            type_id,
-            persistent: false,
-        }
-    }
-
-    /// Set persistence flag
-    pub fn with_persistent(mut self, persistent: bool) -> Self {
-        self.persistent = persistent;
-        self
-    }
-}
-
-/// Virtual representation of a service's variable file
-///
-/// This abstraction allows both local memory-mapped files and remote WSS-synced files
-/// to be accessed through a unified interface. The file maintains:
-/// - Current variable data in memory
-/// - Metadata about variable locations and sizes
-/// - A shadow copy for change detection
-/// - Per-variable change tracking
-#[derive(Debug)]
-pub struct VirtualVariableFile {
-    /// Service ID
-    service_id: String,
-
-    /// Service name
-    service_name: String,
-
-    /// Tenant ID
-    tenant_id: String,
-
-    /// Variable metadata by name
-    variables: Arc<RwLock<HashMap<String, VariableMetadata>>>,
-
-    /// Current file bytes
-    current_bytes: Arc<RwLock<Vec<u8>>>,
-
-    /// Shadow copy (last known state)
-    shadow_bytes: Arc<RwLock<Vec<u8>>>,
-
-    /// Track which variables have changed
-    changed_variables: Arc<RwLock<Vec<String>>>,
-}
-
-impl VirtualVariableFile {
-    /// Create a new virtual variable file
-    pub fn new(service_id: String, service_name: String, tenant_id: String) -> Self {
-        Self {
-            service_id,
-            service_name,
-            tenant_id,
-            variables: Arc::new(RwLock::new(HashMap::new())),
-            current_bytes: Arc::new(RwLock::new(Vec::new())),
-            shadow_bytes: Arc::new(RwLock::new(Vec::new())),
-            changed_variables: Arc::new(RwLock::new(Vec::new())),
-        }
-    }
-
-    /// Get service ID
-    pub fn service_id(&self) -> &str {
-        &self.service_id
-    }
-
-    /// Get service name
-    pub fn service_name(&self) -> &str {
-        &self.service_name
-    }
-
-    /// Get tenant ID
-    pub fn tenant_id(&self) -> &str {
-        &self.tenant_id
-    }
-
-    /// Register a variable
-    pub async fn register_variable(&self, metadata: VariableMetadata) -> Result<()> {
-        let mut vars = self.variables.write().await;
-
-        // Ensure the byte buffer is large enough
-        let end = metadata.offset as usize + metadata.size as usize;
-        let mut current = self.current_bytes.write().await;
-        if current.len() < end {
-            current.resize(end, 0);
-        }
-
-        // Also resize shadow
-        let mut shadow = self.shadow_bytes.write().await;
-        if shadow.len() < end {
-            shadow.resize(end, 0);
-        }
-
-        vars.insert(metadata.name.clone(), metadata);
-        Ok(())
-    }
-
-    /// Get variable metadata by name
-    pub async fn get_variable_metadata(&self, name: &str) -> Result<VariableMetadata> {
-        let vars = self.variables.read().await;
-        vars.get(name)
-            .cloned()
-            .ok_or_else(|| CommyError::VariableNotFound(name.to_string()))
-    }
-
-    /// List all variables
-    pub async fn list_variables(&self) -> Result<Vec<VariableMetadata>> {
-        let vars = self.variables.read().await;
-        Ok(vars.values().cloned().collect())
-    }
-
-    /// Read a variable as zero-copy slice
-    pub async fn read_variable_slice(&self, name: &str) -> Result<Vec<u8>> {
-        let metadata = self.get_variable_metadata(name).await?;
-        let current = self.current_bytes.read().await;
-
-        let start = metadata.offset as usize;
-        let end = start + metadata.size as usize;
-
-        if end > current.len() {
-            return Err(CommyError::InvalidOffset(format!(
-                "Variable {} extends beyond file bounds",
-                name
-            )));
-        }
-
-        Ok(current[start..end].to_vec())
-    }
-
-    /// Write a variable
-    pub async fn write_variable(&self, name: &str, data: &[u8]) -> Result<()> {
-        let metadata = self.get_variable_metadata(name).await?;
-
-        if data.len() as u64 != metadata.size {
-            return Err(CommyError::InvalidMessage(format!(
-                "Data size {} does not match variable size {}",
-                data.len(),
-                metadata.size
-            )));
-        }
-
-        let mut current = self.current_bytes.write().await;
-        let start = metadata.offset as usize;
-        let end = start + data.len();
-
-        if end > current.len() {
-            return Err(CommyError::InvalidOffset(format!(
-                "Variable {} offset out of bounds",
-                name
-            )));
-        }
-
-        current[start..end].copy_from_slice(data);
-
-        // Mark as changed
-        let mut changed = self.changed_variables.write().await;
-        if !changed.contains(&name.to_string()) {
-            changed.push(name.to_string());
-        }
-
-        Ok(())
-    }
-
-    /// Get raw bytes (zero-copy reference to internal buffer)
-    pub async fn bytes(&self) -> Vec<u8> {
-        self.current_bytes.read().await.clone()
-    }
-
-    /// Update entire file content
-    pub async fn update_bytes(&self, data: Vec<u8>) -> Result<()> {
-        let mut current = self.current_bytes.write().await;
-        *current = data;
-        Ok(())
-    }
-
-    /// Get shadow copy
-    pub async fn shadow_bytes(&self) -> Vec<u8> {
-        self.shadow_bytes.read().await.clone()
-    }
-
-    /// Update shadow copy
-    pub async fn update_shadow_bytes(&self, data: Vec<u8>) -> Result<()> {
-        let mut shadow = self.shadow_bytes.write().await;
-        *shadow = data;
-        Ok(())
-    }
-
-    /// Get list of changed variables since last sync
-    pub async fn get_changed_variables(&self) -> Vec<String> {
-        self.changed_variables.read().await.clone()
-    }
-
-    /// Clear change tracking
-    pub async fn clear_changes(&self) {
-        self.changed_variables.write().await.clear();
-    }
-
-    /// Mark specific variables as changed
-    pub async fn mark_variables_changed(&self, names: Vec<String>) {
-        let mut changed = self.changed_variables.write().await;
-        for name in names {
-            if !changed.contains(&name) {
-                changed.push(name);
-            }
-        }
-    }
-
-    /// Compare two byte ranges using wide SIMD operations
-    ///
-    /// Returns byte offsets where differences were found
-    pub async fn compare_ranges(current: &[u8], shadow: &[u8]) -> Result<Vec<(u64, u64)>> {
-        if current.len() != shadow.len() {
-            return Err(CommyError::SimdError(
-                "Cannot compare buffers of different sizes".to_string(),
-            ));
-        }
-
-        let mut differences = Vec::new();
-        let mut i = 0;
-
-        // Try to use AVX-512 if available (64-byte chunks)
-        #[cfg(target_arch = "x86_64")]
-        {
-            use std::arch::x86_64::*;
-
-            if is_x86_feature_detected!("avx512f") {
-                while i + 64 <= current.len() {
-                    unsafe {
-                        let a = _mm512_loadu_si512(current[i..].as_ptr() as *const _);
-                        let b = _mm512_loadu_si512(shadow[i..].as_ptr() as *const _);
-                        let cmp = _mm512_cmpeq_epi8_mask(a, b);
-
-                        // If not all equal (mask != 0xFFFFFFFFFFFFFFFF)
-                        if cmp != 0xFFFFFFFFFFFFFFFF {
-                            differences.push((i as u64, (i + 64) as u64));
-                        }
-                    }
-                    i += 64;
-                }
-            }
-        }
-
-        // Fall back to AVX2 if available (32-byte chunks)
-        #[cfg(target_arch = "x86_64")]
-        {
-            use std::arch::x86_64::*;
-
-            if i == 0 && is_x86_feature_detected!("avx2") {
-                while i + 32 <= current.len() {
-                    unsafe {
-                        let a = _mm256_loadu_si256(current[i..].as_ptr() as *const _);
-                        let b = _mm256_loadu_si256(shadow[i..].as_ptr() as *const _);
-                        let cmp = _mm256_cmpeq_epi8(a, b);
-
-                        // Check if any byte differs
-                        if _mm256_movemask_epi8(cmp) != -1 {
-                            differences.push((i as u64, (i + 32) as u64));
-                        }
-                    }
-                    i += 32;
-                }
-            }
-        }
-
-        // Fall back to 8-byte (u64) comparisons
-        while i + 8 <= current.len() {
-            let current_u64 = u64::from_ne_bytes([
-                current[i],
-                current[i + 1],
-                current[i + 2],
-                current[i + 3],
-                current[i + 4],
-                current[i + 5],
-                current[i + 6],
-                current[i + 7],
-            ]);
-            let shadow_u64 = u64::from_ne_bytes([
-                shadow[i],
-                shadow[i + 1],
-                shadow[i + 2],
-                shadow[i + 3],
-                shadow[i + 4],
-                shadow[i + 5],
-                shadow[i + 6],
-                shadow[i + 7],
-            ]);
-
-            if current_u64 != shadow_u64 {
-                differences.push((i as u64, (i + 8) as u64));
-            }
-            i += 8;
-        }
-
-        // Handle remaining bytes
-        while i < current.len() {
-            if current[i] != shadow[i] {
-                differences.push((i as u64, (i + 1) as u64));
-            }
-            i += 1;
-        }
-
-        Ok(differences)
-    }
-
-    /// Find which variables changed based on byte differences
-    pub async fn find_changed_variables_from_diff(
-        &self,
-        diff_ranges: &[(u64, u64)],
-    ) -> Result<Vec<String>> {
-        let vars = self.variables.read().await;
-        let mut changed = Vec::new();
-
-        for (diff_start, diff_end) in diff_ranges {
-            for (name, metadata) in vars.iter() {
-                let var_start = metadata.offset;
-                let var_end = metadata.offset + metadata.size;
-
-                // Check if this variable overlaps with the difference
-                if *diff_start < var_end && *diff_end > var_start {
-                    if !changed.contains(&name.clone()) {
-                        changed.push(name.clone());
-                    }
-                }
-            }
-        }
-
-        Ok(changed)
-    }
-
-    /// Sync shadow with current (after sending updates to server)
-    pub async fn sync_shadow(&self) -> Result<()> {
-        let current = self.current_bytes.read().await;
-        let mut shadow = self.shadow_bytes.write().await;
-        *shadow = current.clone();
-        self.changed_variables.write().await.clear();
-        Ok(())
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[tokio::test]
-    async fn test_register_and_read_variable() {
-        let vf = VirtualVariableFile::new(
-            "svc_1".to_string(),
-            "config".to_string(),
-            "tenant_1".to_string(),
-        );
-
-        let metadata = VariableMetadata::new("my_var".to_string(), 0, 8, 1);
-        vf.register_variable(metadata).await.unwrap();
-
-        // Write data
-        vf.write_variable("my_var", &[1, 2, 3, 4, 5, 6, 7, 8])
-            .await
-            .unwrap();
-
-        // Read it back
-        let data = vf.read_variable_slice("my_var").await.unwrap();
-        assert_eq!(data, vec![1, 2, 3, 4, 5, 6, 7, 8]);
-    }
-
-    #[tokio::test]
-    async fn test_change_tracking() {
-        let vf = VirtualVariableFile::new(
-            "svc_1".to_string(),
-            "config".to_string(),
-            "tenant_1".to_string(),
-        );
-
-        let metadata = VariableMetadata::new("var1".to_string(), 0, 4, 1);
-        vf.register_variable(metadata).await.unwrap();
-
-        vf.write_variable("var1", &[1, 2, 3, 4]).await.unwrap();
-
-        let changed = vf.get_changed_variables().await;
-        assert_eq!(changed.len(), 1);
-        assert_eq!(changed[0], "var1");
-    }
-
-    #[tokio::test]
-    async fn test_simd_compare() {
-        let current = vec![1, 2, 3, 4, 5, 6, 7, 8];
-        let mut shadow = current.clone();
-        shadow[3] = 99; // Change one byte
-
-        let diffs = VirtualVariableFile::compare_ranges(&current, &shadow)
-            .await
-            .unwrap();
-
-        assert!(!diffs.is_empty());
-    }
-
-    #[tokio::test]
-    async fn test_update_and_get_bytes() {
-        let vf = VirtualVariableFile::new(
-            "svc".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-
-        vf.update_bytes(vec![1, 2, 3]).await.unwrap();
-        let b = vf.bytes().await;
-        assert_eq!(b, vec![1, 2, 3]);
-    }
-
-    #[tokio::test]
-    async fn test_shadow_bytes_and_update() {
-        let vf = VirtualVariableFile::new(
-            "svc".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-
-        vf.update_shadow_bytes(vec![9, 8, 7]).await.unwrap();
-        let s = vf.shadow_bytes().await;
-        assert_eq!(s, vec![9, 8, 7]);
-    }
-
-    #[tokio::test]
-    async fn test_clear_changes() {
-        let vf = VirtualVariableFile::new(
-            "svc".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-
-        let meta = VariableMetadata::new("x".to_string(), 0, 4, 1);
-        vf.register_variable(meta).await.unwrap();
-        vf.write_variable("x", &[0, 0, 0, 1]).await.unwrap();
-
-        assert_eq!(vf.get_changed_variables().await.len(), 1);
-        vf.clear_changes().await;
-        assert!(vf.get_changed_variables().await.is_empty());
-    }
-
-    #[tokio::test]
-    async fn test_mark_variables_changed() {
-        let vf = VirtualVariableFile::new(
-            "svc".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-
-        vf.mark_variables_changed(vec!["a".to_string(), "b".to_string()])
-            .await;
-        let changed = vf.get_changed_variables().await;
-        assert_eq!(changed.len(), 2);
-        // Calling again should not duplicate
-        vf.mark_variables_changed(vec!["a".to_string()]).await;
-        assert_eq!(vf.get_changed_variables().await.len(), 2);
-    }
-
-    #[tokio::test]
-    async fn test_compare_ranges_equal_buffers() {
-        let buf = vec![1, 2, 3, 4, 5, 6, 7, 8];
-        let diffs = VirtualVariableFile::compare_ranges(&buf, &buf).await.unwrap();
-        assert!(diffs.is_empty());
-    }
-
-    #[tokio::test]
-    async fn test_compare_ranges_different_sizes_errors() {
-        let a = vec![1, 2, 3];
-        let b = vec![1, 2];
-        let result = VirtualVariableFile::compare_ranges(&a, &b).await;
-        assert!(result.is_err());
-    }
-
-    #[tokio::test]
-    async fn test_find_changed_variables_from_diff() {
-        let vf = VirtualVariableFile::new(
-            "svc".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-
-        let meta = VariableMetadata::new("score".to_string(), 0, 4, 1);
-        vf.register_variable(meta).await.unwrap();
-
-        // Diff covers first 4 bytes — overlaps with "score"
-        let diffs = vec![(0u64, 4u64)];
-        let changed = vf.find_changed_variables_from_diff(&diffs).await.unwrap();
-        assert!(changed.contains(&"score".to_string()));
-    }
-
-    #[tokio::test]
-    async fn test_find_changed_variables_no_overlap() {
-        let vf = VirtualVariableFile::new(
-            "svc".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-
-        let meta = VariableMetadata::new("score".to_string(), 0, 4, 1);
-        vf.register_variable(meta).await.unwrap();
-
-        // Diff is outside variable range
-        let diffs = vec![(8u64, 16u64)];
-        let changed = vf.find_changed_variables_from_diff(&diffs).await.unwrap();
-        assert!(changed.is_empty());
-    }
-
-    #[tokio::test]
-    async fn test_sync_shadow_clears_changes() {
-        let vf = VirtualVariableFile::new(
-            "svc".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-
-        let meta = VariableMetadata::new("v".to_string(), 0, 4, 1);
-        vf.register_variable(meta).await.unwrap();
-        vf.write_variable("v", &[1, 2, 3, 4]).await.unwrap();
-        assert!(!vf.get_changed_variables().await.is_empty());
-
-        vf.sync_shadow().await.unwrap();
-        assert!(vf.get_changed_variables().await.is_empty());
-
-        let current = vf.bytes().await;
-        let shadow = vf.shadow_bytes().await;
-        assert_eq!(current, shadow);
-    }
-
-    #[tokio::test]
-    async fn test_write_variable_size_mismatch_errors() {
-        let vf = VirtualVariableFile::new(
-            "svc".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-
-        let meta = VariableMetadata::new("v".to_string(), 0, 4, 1);
-        vf.register_variable(meta).await.unwrap();
-
-        // Write wrong number of bytes
-        let result = vf.write_variable("v", &[1, 2, 3]).await;
-        assert!(result.is_err());
-    }
-
-    #[tokio::test]
-    async fn test_list_variables() {
-        let vf = VirtualVariableFile::new(
-            "svc".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-
-        vf.register_variable(VariableMetadata::new("a".to_string(), 0, 4, 1))
-            .await
-            .unwrap();
-        vf.register_variable(VariableMetadata::new("b".to_string(), 4, 8, 1))
-            .await
-            .unwrap();
-
-        let list = vf.list_variables().await.unwrap();
-        assert_eq!(list.len(), 2);
-    }
-
-    #[tokio::test]
-    async fn test_get_variable_metadata_not_found_errors() {
-        let vf = VirtualVariableFile::new(
-            "svc".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-
-        let result = vf.get_variable_metadata("nonexistent").await;
-        assert!(result.is_err());
-    }
-
-    #[tokio::test]
-    async fn test_read_variable_slice_out_of_bounds() {
-        let vf = VirtualVariableFile::new(
-            "svc".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-
-        // Register a variable but don't resize the buffer manually
-        let meta = VariableMetadata::new("v".to_string(), 100, 8, 1);
-        // Don't use register_variable (which auto-resizes) — directly test the
-        // out of bounds path by writing metadata manually then trying to read
-        vf.register_variable(meta).await.unwrap(); // register_variable auto-resizes
-        // Now shrink the bytes so it IS out of bounds
-        // We can't easily shrink via the public API; this just verifies happy path
-        let data = vf.read_variable_slice("v").await.unwrap();
-        assert_eq!(data.len(), 8);
-    }
-
-    #[test]
-    fn test_service_id_accessor() {
-        let vf = VirtualVariableFile::new(
-            "svc-id-42".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-        assert_eq!(vf.service_id(), "svc-id-42");
-    }
-
-    #[test]
-    fn test_service_name_accessor() {
-        let vf = VirtualVariableFile::new(
-            "id".to_string(),
-            "my-service".to_string(),
-            "t1".to_string(),
-        );
-        assert_eq!(vf.service_name(), "my-service");
-    }
-
-    #[test]
-    fn test_tenant_id_accessor() {
-        let vf = VirtualVariableFile::new(
-            "id".to_string(),
-            "svc".to_string(),
-            "tenant_xyz".to_string(),
-        );
-        assert_eq!(vf.tenant_id(), "tenant_xyz");
-    }
-
-    #[test]
-    fn test_variable_metadata_with_persistent() {
-        let meta = VariableMetadata::new("var1".to_string(), 0, 8, 1)
-            .with_persistent(true);
-        assert!(meta.persistent);
-
-        let meta2 = VariableMetadata::new("var2".to_string(), 8, 4, 2)
-            .with_persistent(false);
-        assert!(!meta2.persistent);
-    }
-
-    // ─────────────────────────────────────────────────────────────────────────
-    // Gap test #8: write to a variable at a nonzero offset must not overwrite
-    // adjacent variables.
-    // ─────────────────────────────────────────────────────────────────────────
-
-    #[tokio::test]
-    async fn test_write_variable_at_nonzero_offset_does_not_corrupt_adjacent() {
-        let vf = VirtualVariableFile::new(
-            "svc".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-
-        // Two adjacent 4-byte variables
-        vf.register_variable(VariableMetadata::new("a".to_string(), 0, 4, 1))
-            .await
-            .unwrap();
-        vf.register_variable(VariableMetadata::new("b".to_string(), 4, 4, 1))
-            .await
-            .unwrap();
-
-        // Write known bytes to both
-        vf.write_variable("a", &[0xAA, 0xBB, 0xCC, 0xDD]).await.unwrap();
-        vf.write_variable("b", &[0x11, 0x22, 0x33, 0x44]).await.unwrap();
-
-        // Now overwrite only "b"
-        vf.write_variable("b", &[0xFF, 0xFE, 0xFD, 0xFC]).await.unwrap();
-
-        // "a" must be unchanged
-        let a_bytes = vf.read_variable_slice("a").await.unwrap();
-        assert_eq!(
-            a_bytes,
-            vec![0xAA, 0xBB, 0xCC, 0xDD],
-            "Writing variable 'b' corrupted adjacent variable 'a'"
-        );
-
-        // "b" must have the new value
-        let b_bytes = vf.read_variable_slice("b").await.unwrap();
-        assert_eq!(b_bytes, vec![0xFF, 0xFE, 0xFD, 0xFC]);
-    }
-
-    // ─────────────────────────────────────────────────────────────────────────
-    // Gap test #9: compare_ranges must detect a difference in the very LAST
-    // byte of the buffer (tail detection).
-    // ─────────────────────────────────────────────────────────────────────────
-
-    #[tokio::test]
-    async fn test_compare_ranges_detects_last_byte_difference() {
-        let mut a = vec![0u8; 11];
-        let mut b = vec![0u8; 11];
-        // Only the last byte differs
-        a[10] = 0x00;
-        b[10] = 0xFF;
-
-        let diffs = VirtualVariableFile::compare_ranges(&a, &b).await.unwrap();
-        assert!(
-            !diffs.is_empty(),
-            "compare_ranges must detect a change in the last byte"
-        );
-        // The diff range must include byte index 10
-        let covers_last = diffs.iter().any(|&(start, end)| start <= 10 && 10 < end);
-        assert!(
-            covers_last,
-            "Diff ranges {:?} must cover the changed byte at index 10",
-            diffs
-        );
-    }
-
-    // ─────────────────────────────────────────────────────────────────────────
-    // Gap test #10: find_changed_variables_from_diff must report ALL variables
-    // that overlap with the diff, including when two variables share a diff
-    // range that spans both.
-    // ─────────────────────────────────────────────────────────────────────────
-
-    #[tokio::test]
-    async fn test_find_changed_variables_multiple_overlapping() {
-        let vf = VirtualVariableFile::new(
-            "svc".to_string(),
-            "cfg".to_string(),
-            "t1".to_string(),
-        );
-
-        // Two variables that overlap in the [4, 8) range of an 8-byte buffer
-        vf.register_variable(VariableMetadata::new("x".to_string(), 0, 8, 1))
-            .await
-            .unwrap();
-        vf.register_variable(VariableMetadata::new("y".to_string(), 4, 4, 1))
-            .await
-            .unwrap();
-
-        // A diff spanning exactly the overlap region [4, 8)
-        let diffs = vec![(4u64, 8u64)];
-        let changed = vf.find_changed_variables_from_diff(&diffs).await.unwrap();
-
-        assert!(
-            changed.contains(&"x".to_string()),
-            "Variable 'x' (0..8) overlaps diff [4,8) and must be reported. Got: {:?}",
-            changed
-        );
-        assert!(
-            changed.contains(&"y".to_string()),
-            "Variable 'y' (4..8) overlaps diff [4,8) and must be reported. Got: {:?}",
-            changed
-        );
-    }
-}
+//! Virtual variable file abstraction
+//!
+//! Provides a unified interface for accessing variables from either:
+//! - Direct memory-mapped files (local clients)
+//! - In-memory buffers synchronized via WSS (remote clients)
+
+use crate::error::{CommyError, Result};
+use crate::file_accessor::{FileAccessor, RemoteFileAccessor};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+
+/// Capacity of the channel behind `VirtualVariableFile::subscribe_registrations`
+const REGISTRATION_CHANNEL_CAPACITY: usize = 64;
+
+/// Metadata about a variable in the file
+#[derive(Debug, Clone)]
+pub struct VariableMetadata {
+    /// Variable name
+    pub name: String,
+
+    /// Byte offset in the file
+    pub offset: u64,
+
+    /// Variable size in bytes
+    pub size: u64,
+
+    /// Type ID
+    pub type_id: u32,
+
+    /// Whether this variable persists across disconnections
+    pub persistent: bool,
+}
+
+impl VariableMetadata {
+    /// Create new variable metadata
+    pub fn new(name: String, offset: u64, size: u64, type_id: u32) -> Self {
+        Self {
+            name,
+            offset,
+            size,
+            type_id,
+            persistent: false,
+        }
+    }
+
+    /// Set persistence flag
+    pub fn with_persistent(mut self, persistent: bool) -> Self {
+        self.persistent = persistent;
+        self
+    }
+}
+
+/// Virtual representation of a service's variable file
+///
+/// This abstraction allows both local memory-mapped files and remote WSS-synced files
+/// to be accessed through a unified interface. The file maintains:
+/// - Current variable data, held by a `FileAccessor`
+/// - Metadata about variable locations and sizes
+/// - A shadow copy for change detection
+/// - Per-variable change tracking
+///
+/// Over a `LocalFileAccessor`, reads and writes go straight to the service's
+/// shared memory; over a `RemoteFileAccessor`, to a buffer kept in sync with
+/// the server.
+pub struct VirtualVariableFile {
+    /// Service ID
+    service_id: String,
+
+    /// Service name
+    service_name: String,
+
+    /// Tenant ID
+    tenant_id: String,
+
+    /// Variable metadata by name
+    variables: Arc<RwLock<HashMap<String, VariableMetadata>>>,
+
+    /// Current file bytes
+    accessor: Arc<dyn FileAccessor>,
+
+    /// Shadow copy (last known state)
+    shadow_bytes: Arc<RwLock<Vec<u8>>>,
+
+    /// Track which variables have changed
+    changed_variables: Arc<RwLock<Vec<String>>>,
+
+    /// Names of newly registered variables
+    registrations: broadcast::Sender<String>,
+}
+
+impl VirtualVariableFile {
+    /// Create a new virtual variable file backed by an in-memory buffer
+    pub fn new(service_id: String, service_name: String, tenant_id: String) -> Self {
+        Self::with_accessor(
+            service_id,
+            service_name,
+            tenant_id,
+            Arc::new(RemoteFileAccessor::new()),
+        )
+    }
+
+    /// Create a new virtual variable file over the given accessor
+    ///
+    /// The shadow copy starts out zeroed, so variables already holding data
+    /// differ from it until the first `sync_shadow`.
+    pub fn with_accessor(
+        service_id: String,
+        service_name: String,
+        tenant_id: String,
+        accessor: Arc<dyn FileAccessor>,
+    ) -> Self {
+        Self {
+            service_id,
+            service_name,
+            tenant_id,
+            variables: Arc::new(RwLock::new(HashMap::new())),
+            accessor,
+            shadow_bytes: Arc::new(RwLock::new(Vec::new())),
+            changed_variables: Arc::new(RwLock::new(Vec::new())),
+            registrations: broadcast::channel(REGISTRATION_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// Get service ID
+    pub fn service_id(&self) -> &str {
+        &self.service_id
+    }
+
+    /// Get service name
+    pub fn service_name(&self) -> &str {
+        &self.service_name
+    }
+
+    /// Get tenant ID
+    pub fn tenant_id(&self) -> &str {
+        &self.tenant_id
+    }
+
+    /// Get the accessor holding the file's bytes
+    pub fn accessor(&self) -> &Arc<dyn FileAccessor> {
+        &self.accessor
+    }
+
+    /// Check whether the file is a local memory mapping
+    pub fn is_local(&self) -> bool {
+        self.accessor.is_local()
+    }
+
+    /// Register a variable
+    pub async fn register_variable(&self, metadata: VariableMetadata) -> Result<()> {
+        let mut vars = self.variables.write().await;
+
+        // Ensure the file is large enough
+        let end = metadata.offset as usize + metadata.size as usize;
+        if self.accessor.file_size().await? < end as u64 {
+            self.accessor.resize(end as u64).await?;
+        }
+
+        // Also resize shadow
+        let mut shadow = self.shadow_bytes.write().await;
+        if shadow.len() < end {
+            shadow.resize(end, 0);
+        }
+
+        let name = metadata.name.clone();
+        vars.insert(name.clone(), metadata);
+        let _ = self.registrations.send(name);
+        Ok(())
+    }
+
+    /// Subscribe to the names of variables registered from now on
+    pub fn subscribe_registrations(&self) -> broadcast::Receiver<String> {
+        self.registrations.subscribe()
+    }
+
+    /// Get variable metadata by name
+    pub async fn get_variable_metadata(&self, name: &str) -> Result<VariableMetadata> {
+        let vars = self.variables.read().await;
+        vars.get(name)
+            .cloned()
+            .ok_or_else(|| CommyError::VariableNotFound(name.to_string()))
+    }
+
+    /// List all variables
+    pub async fn list_variables(&self) -> Result<Vec<VariableMetadata>> {
+        let vars = self.variables.read().await;
+        Ok(vars.values().cloned().collect())
+    }
+
+    /// Read a variable as zero-copy slice
+    pub async fn read_variable_slice(&self, name: &str) -> Result<Vec<u8>> {
+        let metadata = self.get_variable_metadata(name).await?;
+
+        self.accessor
+            .read_bytes(metadata.offset, metadata.size)
+            .await
+            .map_err(|e| match e {
+                CommyError::InvalidOffset(_) => CommyError::InvalidOffset(format!(
+                    "Variable {} extends beyond file bounds",
+                    name
+                )),
+                e => e,
+            })
+    }
+
+    /// Write a variable
+    pub async fn write_variable(&self, name: &str, data: &[u8]) -> Result<()> {
+        let metadata = self.get_variable_metadata(name).await?;
+
+        if data.len() as u64 != metadata.size {
+            return Err(CommyError::InvalidMessage(format!(
+                "Data size {} does not match variable size {}",
+                data.len(),
+                metadata.size
+            )));
+        }
+
+        let end = metadata.offset + data.len() as u64;
+        if end > self.accessor.file_size().await? {
+            return Err(CommyError::InvalidOffset(format!(
+                "Variable {} offset out of bounds",
+                name
+            )));
+        }
+
+        self.accessor.write_bytes(metadata.offset, data).await?;
+
+        // Mark as changed
+        let mut changed = self.changed_variables.write().await;
+        if !changed.contains(&name.to_string()) {
+            changed.push(name.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Apply a variable value received from the server
+    ///
+    /// Writes the value into both the current bytes and the shadow copy, so
+    /// it is not mistaken for a local change.
+    pub async fn apply_remote_update(&self, name: &str, data: &[u8]) -> Result<()> {
+        let metadata = self.get_variable_metadata(name).await?;
+
+        if data.len() as u64 != metadata.size {
+            return Err(CommyError::InvalidMessage(format!(
+                "Data size {} does not match variable size {}",
+                data.len(),
+                metadata.size
+            )));
+        }
+
+        self.accessor.write_bytes(metadata.offset, data).await?;
+
+        let start = metadata.offset as usize;
+        let mut shadow = self.shadow_bytes.write().await;
+        if shadow.len() < start + data.len() {
+            shadow.resize(start + data.len(), 0);
+        }
+        shadow[start..start + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+
+    /// Get a copy of the file's bytes
+    ///
+    /// Fails if the accessor cannot be read, for example when the file shrank
+    /// while it was being read.
+    pub async fn bytes(&self) -> Result<Vec<u8>> {
+        let size = self.accessor.file_size().await?;
+        self.accessor.read_bytes(0, size).await
+    }
+
+    /// Update entire file content
+    ///
+    /// Content equal to the current bytes is not written again, so a local
+    /// file reloaded after an outside change is left untouched.
+    pub async fn update_bytes(&self, data: Vec<u8>) -> Result<()> {
+        if self.bytes().await? == data {
+            return Ok(());
+        }
+        if self.accessor.file_size().await? != data.len() as u64 {
+            self.accessor.resize(data.len() as u64).await?;
+        }
+        self.accessor.write_bytes(0, &data).await
+    }
+
+    /// Get shadow copy
+    pub async fn shadow_bytes(&self) -> Vec<u8> {
+        self.shadow_bytes.read().await.clone()
+    }
+
+    /// Update shadow copy
+    pub async fn update_shadow_bytes(&self, data: Vec<u8>) -> Result<()> {
+        let mut shadow = self.shadow_bytes.write().await;
+        *shadow = data;
+        Ok(())
+    }
+
+    /// Get list of changed variables since last sync
+    pub async fn get_changed_variables(&self) -> Vec<String> {
+        self.changed_variables.read().await.clone()
+    }
+
+    /// Clear change tracking
+    pub async fn clear_changes(&self) {
+        self.changed_variables.write().await.clear();
+    }
+
+    /// Mark specific variables as changed
+    pub async fn mark_variables_changed(&self, names: Vec<String>) {
+        let mut changed = self.changed_variables.write().await;
+        for name in names {
+            if !changed.contains(&name) {
+                changed.push(name);
+            }
+        }
+    }
+
+    /// Compare two byte ranges using wide SIMD operations
+    ///
+    /// Returns byte offsets where differences were found
+    pub async fn compare_ranges(current: &[u8], shadow: &[u8]) -> Result<Vec<(u64, u64)>> {
+        if current.len() != shadow.len() {
+            return Err(CommyError::SimdError(
+                "Cannot compare buffers of different sizes".to_string(),
+            ));
+        }
+
+        let mut differences = Vec::new();
+        let mut i = 0;
+
+        // Try to use AVX-512 if available (64-byte chunks)
+        #[cfg(target_arch = "x86_64")]
+        {
+            use std::arch::x86_64::*;
+
+            if is_x86_feature_detected!("avx512f") {
+                while i + 64 <= current.len() {
+                    unsafe {
+                        let a = _mm512_loadu_si512(current[i..].as_ptr() as *const _);
+                        let b = _mm512_loadu_si512(shadow[i..].as_ptr() as *const _);
+                        let cmp = _mm512_cmpeq_epi8_mask(a, b);
+
+                        // If not all equal (mask != 0xFFFFFFFFFFFFFFFF)
+                        if cmp != 0xFFFFFFFFFFFFFFFF {
+                            differences.push((i as u64, (i + 64) as u64));
+                        }
+                    }
+                    i += 64;
+                }
+            }
+        }
+
+        // Fall back to AVX2 if available (32-byte chunks)
+        #[cfg(target_arch = "x86_64")]
+        {
+            use std::arch::x86_64::*;
+
+            if i == 0 && is_x86_feature_detected!("avx2") {
+                while i + 32 <= current.len() {
+                    unsafe {
+                        let a = _mm256_loadu_si256(current[i..].as_ptr() as *const _);
+                        let b = _mm256_loadu_si256(shadow[i..].as_ptr() as *const _);
+                        let cmp = _mm256_cmpeq_epi8(a, b);
+
+                        // Check if any byte differs
+                        if _mm256_movemask_epi8(cmp) != -1 {
+                            differences.push((i as u64, (i + 32) as u64));
+                        }
+                    }
+                    i += 32;
+                }
+            }
+        }
+
+        // Fall back to 8-byte (u64) comparisons
+        while i + 8 <= current.len() {
+            let current_u64 = u64::from_ne_bytes([
+                current[i],
+                current[i + 1],
+                current[i + 2],
+                current[i + 3],
+                current[i + 4],
+                current[i + 5],
+                current[i + 6],
+                current[i + 7],
+            ]);
+            let shadow_u64 = u64::from_ne_bytes([
+                shadow[i],
+                shadow[i + 1],
+                shadow[i + 2],
+                shadow[i + 3],
+                shadow[i + 4],
+                shadow[i + 5],
+                shadow[i + 6],
+                shadow[i + 7],
+            ]);
+
+            if current_u64 != shadow_u64 {
+                differences.push((i as u64, (i + 8) as u64));
+            }
+            i += 8;
+        }
+
+        // Handle remaining bytes
+        while i < current.len() {
+            if current[i] != shadow[i] {
+                differences.push((i as u64, (i + 1) as u64));
+            }
+            i += 1;
+        }
+
+        Ok(differences)
+    }
+
+    /// Find which variables changed based on byte differences
+    pub async fn find_changed_variables_from_diff(
+        &self,
+        diff_ranges: &[(u64, u64)],
+    ) -> Result<Vec<String>> {
+        let vars = self.variables.read().await;
+        let mut changed = Vec::new();
+
+        for (diff_start, diff_end) in diff_ranges {
+            for (name, metadata) in vars.iter() {
+                let var_start = metadata.offset;
+                let var_end = metadata.offset + metadata.size;
+
+                // Check if this variable overlaps with the difference
+                if *diff_start < var_end && *diff_end > var_start {
+                    if !changed.contains(&name.clone()) {
+                        changed.push(name.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Sync shadow with current (after sending updates to server)
+    pub async fn sync_shadow(&self) -> Result<()> {
+        let current = self.bytes().await?;
+        let mut shadow = self.shadow_bytes.write().await;
+        *shadow = current;
+        self.changed_variables.write().await.clear();
+        Ok(())
+    }
+
+    /// Sync shadow with the variable values the server acknowledged
+    ///
+    /// Copies only the reported values into the shadow. A variable written
+    /// again since its value was read stays marked changed, so the newer
+    /// value is reported as well.
+    pub async fn sync_variables(&self, reported: &[(String, Vec<u8>)]) -> Result<()> {
+        for (name, data) in reported {
+            let metadata = self.get_variable_metadata(name).await?;
+
+            let start = metadata.offset as usize;
+            let mut shadow = self.shadow_bytes.write().await;
+            if shadow.len() < start + data.len() {
+                shadow.resize(start + data.len(), 0);
+            }
+            shadow[start..start + data.len()].copy_from_slice(data);
+            drop(shadow);
+
+            // Writers mark a variable after writing it, so holding the list
+            // while comparing cannot lose a mark
+            let mut changed = self.changed_variables.write().await;
+            if self.read_variable_slice(name).await? == *data {
+                changed.retain(|changed_name| changed_name != name);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for VirtualVariableFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VirtualVariableFile")
+            .field("service_id", &self.service_id)
+            .field("service_name", &self.service_name)
+            .field("tenant_id", &self.tenant_id)
+            .field("is_local", &self.accessor.is_local())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_accessor::LocalFileAccessor;
+
+    #[tokio::test]
+    async fn test_register_and_read_variable() {
+        let vf = VirtualVariableFile::new(
+            "svc_1".to_string(),
+            "config".to_string(),
+            "tenant_1".to_string(),
+        );
+
+        let metadata = VariableMetadata::new("my_var".to_string(), 0, 8, 1);
+        vf.register_variable(metadata).await.unwrap();
+
+        // Write data
+        vf.write_variable("my_var", &[1, 2, 3, 4, 5, 6, 7, 8])
+            .await
+            .unwrap();
+
+        // Read it back
+        let data = vf.read_variable_slice("my_var").await.unwrap();
+        assert_eq!(data, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[tokio::test]
+    async fn test_change_tracking() {
+        let vf = VirtualVariableFile::new(
+            "svc_1".to_string(),
+            "config".to_string(),
+            "tenant_1".to_string(),
+        );
+
+        let metadata = VariableMetadata::new("var1".to_string(), 0, 4, 1);
+        vf.register_variable(metadata).await.unwrap();
+
+        vf.write_variable("var1", &[1, 2, 3, 4]).await.unwrap();
+
+        let changed = vf.get_changed_variables().await;
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0], "var1");
+    }
+
+    #[tokio::test]
+    async fn test_simd_compare() {
+        let current = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let mut shadow = current.clone();
+        shadow[3] = 99; // Change one byte
+
+        let diffs = VirtualVariableFile::compare_ranges(&current, &shadow)
+            .await
+            .unwrap();
+
+        assert!(!diffs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_update_and_get_bytes() {
+        let vf = VirtualVariableFile::new(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+
+        vf.update_bytes(vec![1, 2, 3]).await.unwrap();
+        let b = vf.bytes().await.unwrap();
+        assert_eq!(b, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_shadow_bytes_and_update() {
+        let vf = VirtualVariableFile::new(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+
+        vf.update_shadow_bytes(vec![9, 8, 7]).await.unwrap();
+        let s = vf.shadow_bytes().await;
+        assert_eq!(s, vec![9, 8, 7]);
+    }
+
+    #[tokio::test]
+    async fn test_clear_changes() {
+        let vf = VirtualVariableFile::new(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+
+        let meta = VariableMetadata::new("x".to_string(), 0, 4, 1);
+        vf.register_variable(meta).await.unwrap();
+        vf.write_variable("x", &[0, 0, 0, 1]).await.unwrap();
+
+        assert_eq!(vf.get_changed_variables().await.len(), 1);
+        vf.clear_changes().await;
+        assert!(vf.get_changed_variables().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mark_variables_changed() {
+        let vf = VirtualVariableFile::new(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+
+        vf.mark_variables_changed(vec!["a".to_string(), "b".to_string()])
+            .await;
+        let changed = vf.get_changed_variables().await;
+        assert_eq!(changed.len(), 2);
+        // Calling again should not duplicate
+        vf.mark_variables_changed(vec!["a".to_string()]).await;
+        assert_eq!(vf.get_changed_variables().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_compare_ranges_equal_buffers() {
+        let buf = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let diffs = VirtualVariableFile::compare_ranges(&buf, &buf).await.unwrap();
+        assert!(diffs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_compare_ranges_different_sizes_errors() {
+        let a = vec![1, 2, 3];
+        let b = vec![1, 2];
+        let result = VirtualVariableFile::compare_ranges(&a, &b).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_find_changed_variables_from_diff() {
+        let vf = VirtualVariableFile::new(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+
+        let meta = VariableMetadata::new("score".to_string(), 0, 4, 1);
+        vf.register_variable(meta).await.unwrap();
+
+        // Diff covers first 4 bytes — overlaps with "score"
+        let diffs = vec![(0u64, 4u64)];
+        let changed = vf.find_changed_variables_from_diff(&diffs).await.unwrap();
+        assert!(changed.contains(&"score".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_find_changed_variables_no_overlap() {
+        let vf = VirtualVariableFile::new(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+
+        let meta = VariableMetadata::new("score".to_string(), 0, 4, 1);
+        vf.register_variable(meta).await.unwrap();
+
+        // Diff is outside variable range
+        let diffs = vec![(8u64, 16u64)];
+        let changed = vf.find_changed_variables_from_diff(&diffs).await.unwrap();
+        assert!(changed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_shadow_clears_changes() {
+        let vf = VirtualVariableFile::new(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+
+        let meta = VariableMetadata::new("v".to_string(), 0, 4, 1);
+        vf.register_variable(meta).await.unwrap();
+        vf.write_variable("v", &[1, 2, 3, 4]).await.unwrap();
+        assert!(!vf.get_changed_variables().await.is_empty());
+
+        vf.sync_shadow().await.unwrap();
+        assert!(vf.get_changed_variables().await.is_empty());
+
+        let current = vf.bytes().await.unwrap();
+        let shadow = vf.shadow_bytes().await;
+        assert_eq!(current, shadow);
+    }
+
+    /// An accessor whose file can no longer be read
+    struct UnreadableAccessor;
+
+    #[async_trait::async_trait]
+    impl FileAccessor for UnreadableAccessor {
+        async fn read_bytes(&self, _offset: u64, _size: u64) -> Result<Vec<u8>> {
+            Err(CommyError::ConnectionLost("file unreadable".to_string()))
+        }
+
+        async fn write_bytes(&self, _offset: u64, _data: &[u8]) -> Result<()> {
+            Err(CommyError::ConnectionLost("file unreadable".to_string()))
+        }
+
+        async fn file_size(&self) -> Result<u64> {
+            Ok(4)
+        }
+
+        fn is_local(&self) -> bool {
+            true
+        }
+
+        async fn resize(&self, _new_size: u64) -> Result<()> {
+            Err(CommyError::ConnectionLost("file unreadable".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_file_does_not_clear_shadow() {
+        let vf = VirtualVariableFile::with_accessor(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+            Arc::new(UnreadableAccessor),
+        );
+        vf.update_shadow_bytes(vec![1, 2, 3, 4]).await.unwrap();
+
+        assert!(vf.bytes().await.is_err());
+        assert!(vf.sync_shadow().await.is_err());
+        assert!(vf.update_bytes(vec![5, 6, 7, 8]).await.is_err());
+        assert_eq!(vf.shadow_bytes().await, vec![1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_write_variable_size_mismatch_errors() {
+        let vf = VirtualVariableFile::new(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+
+        let meta = VariableMetadata::new("v".to_string(), 0, 4, 1);
+        vf.register_variable(meta).await.unwrap();
+
+        // Write wrong number of bytes
+        let result = vf.write_variable("v", &[1, 2, 3]).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_variables() {
+        let vf = VirtualVariableFile::new(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+
+        vf.register_variable(VariableMetadata::new("a".to_string(), 0, 4, 1))
+            .await
+            .unwrap();
+        vf.register_variable(VariableMetadata::new("b".to_string(), 4, 8, 1))
+            .await
+            .unwrap();
+
+        let list = vf.list_variables().await.unwrap();
+        assert_eq!(list.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_variable_metadata_not_found_errors() {
+        let vf = VirtualVariableFile::new(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+
+        let result = vf.get_variable_metadata("nonexistent").await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_variable_slice_out_of_bounds() {
+        let vf = VirtualVariableFile::new(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+
+        // Register a variable but don't resize the buffer manually
+        let meta = VariableMetadata::new("v".to_string(), 100, 8, 1);
+        // Don't use register_variable (which auto-resizes) — directly test the
+        // out of bounds path by writing metadata manually then trying to read
+        vf.register_variable(meta).await.unwrap(); // register_variable auto-resizes
+        // Now shrink the bytes so it IS out of bounds
+        // We can't easily shrink via the public API; this just verifies happy path
+        let data = vf.read_variable_slice("v").await.unwrap();
+        assert_eq!(data.len(), 8);
+    }
+
+    #[test]
+    fn test_service_id_accessor() {
+        let vf = VirtualVariableFile::new(
+            "svc-id-42".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+        assert_eq!(vf.service_id(), "svc-id-42");
+    }
+
+    #[test]
+    fn test_service_name_accessor() {
+        let vf = VirtualVariableFile::new(
+            "id".to_string(),
+            "my-service".to_string(),
+            "t1".to_string(),
+        );
+        assert_eq!(vf.service_name(), "my-service");
+    }
+
+    #[test]
+    fn test_tenant_id_accessor() {
+        let vf = VirtualVariableFile::new(
+            "id".to_string(),
+            "svc".to_string(),
+            "tenant_xyz".to_string(),
+        );
+        assert_eq!(vf.tenant_id(), "tenant_xyz");
+    }
+
+    #[test]
+    fn test_variable_metadata_with_persistent() {
+        let meta = VariableMetadata::new("var1".to_string(), 0, 8, 1)
+            .with_persistent(true);
+        assert!(meta.persistent);
+
+        let meta2 = VariableMetadata::new("var2".to_string(), 8, 4, 2)
+            .with_persistent(false);
+        assert!(!meta2.persistent);
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Gap test #8: write to a variable at a nonzero offset must not overwrite
+    // adjacent variables.
+    // ─────────────────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_write_variable_at_nonzero_offset_does_not_corrupt_adjacent() {
+        let vf = VirtualVariableFile::new(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+
+        // Two adjacent 4-byte variables
+        vf.register_variable(VariableMetadata::new("a".to_string(), 0, 4, 1))
+            .await
+            .unwrap();
+        vf.register_variable(VariableMetadata::new("b".to_string(), 4, 4, 1))
+            .await
+            .unwrap();
+
+        // Write known bytes to both
+        vf.write_variable("a", &[0xAA, 0xBB, 0xCC, 0xDD]).await.unwrap();
+        vf.write_variable("b", &[0x11, 0x22, 0x33, 0x44]).await.unwrap();
+
+        // Now overwrite only "b"
+        vf.write_variable("b", &[0xFF, 0xFE, 0xFD, 0xFC]).await.unwrap();
+
+        // "a" must be unchanged
+        let a_bytes = vf.read_variable_slice("a").await.unwrap();
+        assert_eq!(
+            a_bytes,
+            vec![0xAA, 0xBB, 0xCC, 0xDD],
+            "Writing variable 'b' corrupted adjacent variable 'a'"
+        );
+
+        // "b" must have the new value
+        let b_bytes = vf.read_variable_slice("b").await.unwrap();
+        assert_eq!(b_bytes, vec![0xFF, 0xFE, 0xFD, 0xFC]);
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Gap test #9: compare_ranges must detect a difference in the very LAST
+    // byte of the buffer (tail detection).
+    // ─────────────────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_compare_ranges_detects_last_byte_difference() {
+        let mut a = vec![0u8; 11];
+        let mut b = vec![0u8; 11];
+        // Only the last byte differs
+        a[10] = 0x00;
+        b[10] = 0xFF;
+
+        let diffs = VirtualVariableFile::compare_ranges(&a, &b).await.unwrap();
+        assert!(
+            !diffs.is_empty(),
+            "compare_ranges must detect a change in the last byte"
+        );
+        // The diff range must include byte index 10
+        let covers_last = diffs.iter().any(|&(start, end)| start <= 10 && 10 < end);
+        assert!(
+            covers_last,
+            "Diff ranges {:?} must cover the changed byte at index 10",
+            diffs
+        );
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Gap test #10: find_changed_variables_from_diff must report ALL variables
+    // that overlap with the diff, including when two variables share a diff
+    // range that spans both.
+    // ─────────────────────────────────────────────────────────────────────────
+
+    #[tokio::test]
+    async fn test_find_changed_variables_multiple_overlapping() {
+        let vf = VirtualVariableFile::new(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+        );
+
+        // Two variables that overlap in the [4, 8) range of an 8-byte buffer
+        vf.register_variable(VariableMetadata::new("x".to_string(), 0, 8, 1))
+            .await
+            .unwrap();
+        vf.register_variable(VariableMetadata::new("y".to_string(), 4, 4, 1))
+            .await
+            .unwrap();
+
+        // A diff spanning exactly the overlap region [4, 8)
+        let diffs = vec![(4u64, 8u64)];
+        let changed = vf.find_changed_variables_from_diff(&diffs).await.unwrap();
+
+        assert!(
+            changed.contains(&"x".to_string()),
+            "Variable 'x' (0..8) overlaps diff [4,8) and must be reported. Got: {:?}",
+            changed
+        );
+        assert!(
+            changed.contains(&"y".to_string()),
+            "Variable 'y' (4..8) overlaps diff [4,8) and must be reported. Got: {:?}",
+            changed
+        );
+    }
+
+    // ─────────────────────────────────────────────────────────────────────────
+    // Accessor-backed files
+    // ─────────────────────────────────────────────────────────────────────────
+
+    async fn local_file(
+        size: usize,
+    ) -> (tempfile::TempDir, std::path::PathBuf, VirtualVariableFile) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("svc.mem");
+        std::fs::write(&path, vec![0u8; size]).unwrap();
+        let accessor = LocalFileAccessor::new(path.clone()).await.unwrap();
+        let vf = VirtualVariableFile::with_accessor(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+            Arc::new(accessor),
+        );
+        (dir, path, vf)
+    }
+
+    #[tokio::test]
+    async fn test_local_file_reads_shared_memory() {
+        let (_dir, path, vf) = local_file(8).await;
+        assert!(vf.is_local());
+        vf.register_variable(VariableMetadata::new("v".to_string(), 4, 4, 1))
+            .await
+            .unwrap();
+
+        // Another process writing the service file
+        let other = LocalFileAccessor::new(path).await.unwrap();
+        other.write_bytes(4, &[1, 2, 3, 4]).await.unwrap();
+
+        assert_eq!(vf.read_variable_slice("v").await.unwrap(), vec![1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_local_file_writes_reach_the_file() {
+        let (_dir, path, vf) = local_file(4).await;
+        vf.register_variable(VariableMetadata::new("v".to_string(), 0, 4, 1))
+            .await
+            .unwrap();
+
+        vf.write_variable("v", &[9, 8, 7, 6]).await.unwrap();
+
+        let reader = LocalFileAccessor::read_only(path).await.unwrap();
+        assert_eq!(reader.read_bytes(0, 4).await.unwrap(), vec![9, 8, 7, 6]);
+        assert_eq!(vf.get_changed_variables().await, vec!["v".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_registering_past_the_end_grows_the_accessor() {
+        let (_dir, path, vf) = local_file(4).await;
+        vf.register_variable(VariableMetadata::new("v".to_string(), 8, 8, 1))
+            .await
+            .unwrap();
+
+        assert_eq!(vf.accessor().file_size().await.unwrap(), 16);
+        assert_eq!(std::fs::metadata(path).unwrap().len(), 16);
+        assert_eq!(vf.shadow_bytes().await.len(), 16);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_local_file_refuses_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("svc.mem");
+        std::fs::write(&path, [5u8, 6, 7, 8]).unwrap();
+        let accessor = LocalFileAccessor::read_only(path).await.unwrap();
+        let vf = VirtualVariableFile::with_accessor(
+            "svc".to_string(),
+            "cfg".to_string(),
+            "t1".to_string(),
+            Arc::new(accessor),
+        );
+        vf.register_variable(VariableMetadata::new("v".to_string(), 0, 4, 1))
+            .await
+            .unwrap();
+
+        assert_eq!(vf.read_variable_slice("v").await.unwrap(), vec![5, 6, 7, 8]);
+        assert!(matches!(
+            vf.write_variable("v", &[0; 4]).await,
+            Err(CommyError::InvalidState(_))
+        ));
+        // Reloading unchanged content does not need to write
+        vf.update_bytes(vec![5, 6, 7, 8]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sync_shadow_copies_accessor_bytes() {
+        let (_dir, _path, vf) = local_file(4).await;
+        vf.register_variable(VariableMetadata::new("v".to_string(), 0, 4, 1))
+            .await
+            .unwrap();
+        vf.write_variable("v", &[1, 1, 2, 2]).await.unwrap();
+
+        vf.sync_shadow().await.unwrap();
+        assert_eq!(vf.shadow_bytes().await, vec![1, 1, 2, 2]);
+        assert!(vf.get_changed_variables().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_remote_update_lands_in_bytes_and_shadow() {
+        let vf = VirtualVariableFile::new(
+            "svc_1".to_string(),
+            "config".to_string(),
+            "tenant_1".to_string(),
+        );
+        let mut registrations = vf.subscribe_registrations();
+        vf.register_variable(VariableMetadata::new("a".to_string(), 0, 2, 1))
+            .await
+            .unwrap();
+        vf.register_variable(VariableMetadata::new("b".to_string(), 2, 2, 1))
+            .await
+            .unwrap();
+        assert_eq!(registrations.try_recv().unwrap(), "a");
+        assert_eq!(registrations.try_recv().unwrap(), "b");
+
+        vf.apply_remote_update("b", &[7, 8]).await.unwrap();
+        assert_eq!(vf.read_variable_slice("b").await.unwrap(), vec![7, 8]);
+        assert_eq!(vf.shadow_bytes().await, vec![0, 0, 7, 8]);
+        assert!(vf.get_changed_variables().await.is_empty());
+
+        assert!(matches!(
+            vf.apply_remote_update("b", &[1, 2, 3]).await,
+            Err(CommyError::InvalidMessage(_))
+        ));
+        assert!(matches!(
+            vf.apply_remote_update("missing", &[1]).await,
+            Err(CommyError::VariableNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sync_variables_keeps_writes_made_while_reporting() {
+        let vf = VirtualVariableFile::new(
+            "svc_1".to_string(),
+            "config".to_string(),
+            "tenant_1".to_string(),
+        );
+        for (name, offset) in [("a", 0), ("b", 2), ("c", 4)] {
+            vf.register_variable(VariableMetadata::new(name.to_string(), offset, 2, 1))
+                .await
+                .unwrap();
+        }
+        vf.write_variable("a", &[1, 1]).await.unwrap();
+        vf.write_variable("b", &[2, 2]).await.unwrap();
+        let reported = vec![
+            ("a".to_string(), vf.read_variable_slice("a").await.unwrap()),
+            ("b".to_string(), vf.read_variable_slice("b").await.unwrap()),
+        ];
+
+        // Written while the report was in flight
+        vf.write_variable("b", &[3, 3]).await.unwrap();
+        vf.write_variable("c", &[4, 4]).await.unwrap();
+
+        vf.sync_variables(&reported).await.unwrap();
+        assert_eq!(vf.shadow_bytes().await, vec![1, 1, 2, 2, 0, 0]);
+        assert_eq!(
+            vf.get_changed_variables().await,
+            vec!["b".to_string(), "c".to_string()]
+        );
+    }
+}
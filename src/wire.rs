@@ -0,0 +1,217 @@
+//! Wire formats for protocol messages
+//!
+//! The client offers its preferred format as a WebSocket subprotocol while
+//! connecting. JSON travels in text frames and MessagePack in binary frames;
+//! incoming frames are decoded by their frame type, so either side may fall
+//! back to JSON at any time.
+
+use crate::error::{CommyError, Result};
+use crate::message::{ClientEnvelope, ServerEnvelope};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Subprotocol name for JSON text frames
+pub const JSON_SUBPROTOCOL: &str = "commy.json";
+
+/// Subprotocol name for MessagePack binary frames
+pub const MESSAGE_PACK_SUBPROTOCOL: &str = "commy.msgpack";
+
+/// Encoding of protocol messages on the socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WireFormat {
+    /// JSON in text frames; understood by every server
+    Json,
+
+    /// MessagePack in binary frames; variable data is sent as raw bytes
+    #[default]
+    MessagePack,
+}
+
+impl WireFormat {
+    /// WebSocket subprotocol naming this format
+    pub fn subprotocol(self) -> &'static str {
+        match self {
+            WireFormat::Json => JSON_SUBPROTOCOL,
+            WireFormat::MessagePack => MESSAGE_PACK_SUBPROTOCOL,
+        }
+    }
+
+    /// Format selected by the server's subprotocol answer
+    pub fn from_subprotocol(subprotocol: &str) -> Option<Self> {
+        match subprotocol.trim() {
+            JSON_SUBPROTOCOL => Some(WireFormat::Json),
+            MESSAGE_PACK_SUBPROTOCOL => Some(WireFormat::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// Subprotocols to offer when this format is preferred, best first
+    pub(crate) fn offer(self) -> &'static str {
+        match self {
+            WireFormat::Json => JSON_SUBPROTOCOL,
+            // No spaces: the handshake compares the answer against each entry
+            WireFormat::MessagePack => "commy.msgpack,commy.json",
+        }
+    }
+
+    /// Encode an outgoing envelope into a frame
+    pub(crate) fn encode(self, envelope: &ClientEnvelope) -> Result<Message> {
+        match self {
+            WireFormat::Json => Ok(Message::Text(serde_json::to_string(envelope)?)),
+            WireFormat::MessagePack => Ok(Message::Binary(rmp_serde::to_vec_named(envelope)?)),
+        }
+    }
+}
+
+/// Decode an incoming frame
+///
+/// Returns `None` for control frames, which carry no message.
+pub(crate) fn decode(frame: Message) -> Option<Result<ServerEnvelope>> {
+    match frame {
+        Message::Text(text) => Some(serde_json::from_str(&text).map_err(CommyError::from)),
+        Message::Binary(bytes) => Some(rmp_serde::from_slice(&bytes).map_err(CommyError::from)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{ClientMessage, ServerMessage};
+
+    fn write_request() -> ClientEnvelope {
+        ClientEnvelope::request(
+            "7",
+            ClientMessage::WriteVariable {
+                service_id: "svc".to_string(),
+                variable_name: "blob".to_string(),
+                data: vec![255; 1024],
+            },
+        )
+    }
+
+    #[test]
+    fn test_subprotocol_names_round_trip() {
+        for format in [WireFormat::Json, WireFormat::MessagePack] {
+            assert_eq!(
+                WireFormat::from_subprotocol(format.subprotocol()),
+                Some(format)
+            );
+        }
+        assert_eq!(WireFormat::from_subprotocol("commy.cbor"), None);
+    }
+
+    #[test]
+    fn test_formats_use_matching_frame_types() {
+        let envelope = write_request();
+        assert!(matches!(
+            WireFormat::Json.encode(&envelope).unwrap(),
+            Message::Text(_)
+        ));
+        assert!(matches!(
+            WireFormat::MessagePack.encode(&envelope).unwrap(),
+            Message::Binary(_)
+        ));
+    }
+
+    #[test]
+    fn test_message_pack_sends_variable_data_as_raw_bytes() {
+        let envelope = write_request();
+        let json = WireFormat::Json.encode(&envelope).unwrap();
+        let binary = WireFormat::MessagePack.encode(&envelope).unwrap();
+
+        assert!(json.len() > 4 * 1024);
+        assert!(binary.len() < 1024 + 100, "{} bytes", binary.len());
+    }
+
+    #[test]
+    fn test_decode_follows_frame_type() {
+        let reply = ServerEnvelope::reply(
+            "7",
+            ServerMessage::VariableData {
+                service_id: "svc".to_string(),
+                variable_name: "blob".to_string(),
+                data: vec![0, 1, 254, 255],
+                version: 3,
+            },
+        );
+        let frames = [
+            Message::Text(serde_json::to_string(&reply).unwrap()),
+            Message::Binary(rmp_serde::to_vec_named(&reply).unwrap()),
+        ];
+
+        for frame in frames {
+            let decoded = decode(frame).unwrap().unwrap();
+            assert_eq!(decoded.request_id.as_deref(), Some("7"));
+            match decoded.message {
+                ServerMessage::VariableData { data, version, .. } => {
+                    assert_eq!(data, vec![0, 1, 254, 255]);
+                    assert_eq!(version, 3);
+                }
+                other => panic!("Expected VariableData, got {:?}", other),
+            }
+        }
+        assert!(decode(Message::Ping(vec![])).is_none());
+    }
+
+    #[test]
+    fn test_json_still_accepts_number_arrays() {
+        let json = r#"{"request_id":"1","type":"VariableData","data":{"service_id":"s","variable_name":"v","data":[1,2,3],"version":1}}"#;
+        let decoded = decode(Message::Text(json.to_string())).unwrap().unwrap();
+        assert!(matches!(
+            decoded.message,
+            ServerMessage::VariableData { ref data, .. } if data == &vec![1, 2, 3]
+        ));
+    }
+
+    #[test]
+    fn test_message_pack_decodes_unknown_messages_and_codes() {
+        #[derive(serde::Serialize)]
+        struct Frame<T> {
+            request_id: &'static str,
+            r#type: &'static str,
+            data: T,
+        }
+
+        #[derive(serde::Serialize)]
+        struct Snapshot {
+            blob: serde_bytes::ByteBuf,
+        }
+
+        let unknown = Frame {
+            request_id: "3",
+            r#type: "Snapshot",
+            data: Snapshot {
+                blob: serde_bytes::ByteBuf::from(vec![0, 255]),
+            },
+        };
+        let frame = Message::Binary(rmp_serde::to_vec_named(&unknown).unwrap());
+        let decoded = decode(frame).unwrap().unwrap();
+        assert_eq!(decoded.request_id.as_deref(), Some("3"));
+        assert_eq!(decoded.message.unknown_type(), Some("Snapshot"));
+        match &decoded.message {
+            ServerMessage::Unknown(raw) => {
+                assert_eq!(raw["data"]["blob"], serde_json::json!([0, 255]))
+            }
+            other => panic!("Expected Unknown, got {:?}", other),
+        }
+        // Re-encoded, the bytes travel as an array
+        let again = Message::Binary(rmp_serde::to_vec_named(&decoded).unwrap());
+        let again = decode(again).unwrap().unwrap();
+        assert_eq!(again.message.unknown_type(), Some("Snapshot"));
+
+        let error = Frame {
+            request_id: "4",
+            r#type: "Error",
+            data: serde_json::json!({ "code": "QUOTA_EXCEEDED", "message": "full" }),
+        };
+        let frame = Message::Binary(rmp_serde::to_vec_named(&error).unwrap());
+        let decoded = decode(frame).unwrap().unwrap();
+        assert!(matches!(
+            decoded.message,
+            ServerMessage::Error {
+                code: crate::message::ErrorCode::Unknown,
+                ..
+            }
+        ));
+    }
+}
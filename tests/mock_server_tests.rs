@@ -0,0 +1,835 @@
+//! Client behaviour against the in-process `MockServer`
+//!
+//! Unlike the examples in `integration_examples.rs`, these run without a
+//! `commy` binary: the mock server keeps its state in memory and listens on
+//! an ephemeral local port.
+
+use commy_sdk_rust::connection::Connection;
+use commy_sdk_rust::fault::FaultInjection;
+use commy_sdk_rust::file_accessor::{FileAccess, FileAccessor, LocalFileAccessor};
+use commy_sdk_rust::message::ErrorCode;
+use commy_sdk_rust::recording::{Recorder, Replay};
+use commy_sdk_rust::testing::{Fault, MockServer};
+use commy_sdk_rust::virtual_file::VariableMetadata;
+use commy_sdk_rust::{
+    auth, Client, ClientEvent, ClientMessage, CommyError, FixedDelay, ServerMessage, WireFormat,
+};
+use futures::StreamExt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const TENANT: &str = "acme";
+
+/// A connected client authenticated to `TENANT` with `key`
+async fn client_with_key(server: &MockServer, key: &str) -> Client {
+    let client = Client::new(server.url());
+    client.connect().await.expect("connect");
+    client
+        .authenticate(TENANT, auth::api_key(key.to_string()))
+        .await
+        .expect("authenticate");
+    client
+}
+
+/// A mock server with an all-powerful `admin` key, a client using it, and
+/// a `config` service holding a `limit` variable
+async fn server_with_variable() -> (MockServer, Client, String) {
+    let server = MockServer::start().await.unwrap();
+    server.add_api_key(TENANT, "admin", &["admin"]);
+    let client = client_with_key(&server, "admin").await;
+    let service_id = client.create_service(TENANT, "config").await.unwrap();
+    client
+        .allocate_variable(&service_id, "limit", vec![1])
+        .await
+        .unwrap();
+    (server, client, service_id)
+}
+
+async fn next_event(client_events: &mut commy_sdk_rust::ClientEvents) -> ClientEvent {
+    tokio::time::timeout(Duration::from_secs(2), client_events.next())
+        .await
+        .expect("event should arrive")
+        .expect("event stream ended")
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Authentication and permissions
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_authentication_checks_credentials() {
+    let server = MockServer::start().await.unwrap();
+    server.add_api_key(TENANT, "reader", &["read_service"]);
+
+    let client = Client::new(server.url());
+    client.connect().await.unwrap();
+
+    let context = client
+        .authenticate(TENANT, auth::api_key("reader".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(context.permissions, vec!["read_service".to_string()]);
+
+    let wrong_key = client
+        .authenticate(TENANT, auth::api_key("guess".to_string()))
+        .await;
+    assert!(matches!(
+        wrong_key,
+        Err(CommyError::AuthenticationFailed(_))
+    ));
+
+    let unknown_tenant = client
+        .authenticate("other", auth::api_key("reader".to_string()))
+        .await;
+    assert!(matches!(
+        unknown_tenant,
+        Err(CommyError::AuthenticationFailed(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_service_operations_need_their_permissions() {
+    let server = MockServer::start().await.unwrap();
+    server.add_api_key(TENANT, "creator", &["create_service", "read_service"]);
+    server.add_api_key(TENANT, "reader", &["read_service"]);
+
+    let creator = client_with_key(&server, "creator").await;
+    let reader = client_with_key(&server, "reader").await;
+
+    let service_id = creator.create_service(TENANT, "config").await.unwrap();
+    let service = reader.get_service(TENANT, "config").await.unwrap();
+    assert_eq!(service.id(), service_id);
+
+    assert!(matches!(
+        reader.create_service(TENANT, "other").await,
+        Err(CommyError::PermissionDenied(_))
+    ));
+    assert!(matches!(
+        creator.delete_service(TENANT, "config").await,
+        Err(CommyError::PermissionDenied(_))
+    ));
+    assert!(matches!(
+        creator.create_service(TENANT, "config").await,
+        Err(CommyError::AlreadyExists(_))
+    ));
+    assert!(matches!(
+        reader.get_service(TENANT, "missing").await,
+        Err(CommyError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_tenant_management_needs_admin() {
+    let server = MockServer::start().await.unwrap();
+    server.add_api_key(TENANT, "admin", &["admin"]);
+    server.add_api_key(TENANT, "reader", &["read"]);
+
+    let admin = client_with_key(&server, "admin").await;
+    let reader = client_with_key(&server, "reader").await;
+
+    assert_eq!(admin.create_tenant("beta", "Beta").await.unwrap(), "beta");
+    assert!(matches!(
+        admin.create_tenant("beta", "Beta").await,
+        Err(CommyError::AlreadyExists(_))
+    ));
+    assert!(matches!(
+        reader.delete_tenant("beta").await,
+        Err(CommyError::PermissionDenied(_))
+    ));
+    admin.delete_tenant("beta").await.unwrap();
+    assert!(matches!(
+        admin.delete_tenant("beta").await,
+        Err(CommyError::NotFound(_))
+    ));
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Variables
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_writes_bump_the_version() {
+    let (server, client, service_id) = server_with_variable().await;
+
+    assert_eq!(
+        client
+            .write_variable(&service_id, "limit", vec![2])
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        client.read_variable(&service_id, "limit").await.unwrap(),
+        vec![2]
+    );
+    assert_eq!(
+        server.variable(TENANT, "config", "limit"),
+        Some((vec![2], 2))
+    );
+
+    client
+        .deallocate_variable(&service_id, "limit")
+        .await
+        .unwrap();
+    assert!(matches!(
+        client.read_variable(&service_id, "limit").await,
+        Err(CommyError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_compare_and_swap_detects_other_writers() {
+    let (server, client, service_id) = server_with_variable().await;
+    server.set_variable(TENANT, "config", "limit", vec![7]);
+
+    match client
+        .compare_and_swap(&service_id, "limit", 1, vec![2])
+        .await
+    {
+        Err(CommyError::Conflict {
+            current_version,
+            current_data,
+        }) => {
+            assert_eq!(current_version, 2);
+            assert_eq!(current_data, vec![7]);
+        }
+        other => panic!("Expected Conflict, got {:?}", other),
+    }
+
+    assert_eq!(
+        client
+            .compare_and_swap(&service_id, "limit", 2, vec![8])
+            .await
+            .unwrap(),
+        3
+    );
+}
+
+#[tokio::test]
+async fn test_variable_access_needs_read_and_write() {
+    let (server, _admin, service_id) = server_with_variable().await;
+    server.add_api_key(TENANT, "reader", &["read"]);
+    let reader = client_with_key(&server, "reader").await;
+
+    assert_eq!(
+        reader.read_variable(&service_id, "limit").await.unwrap(),
+        vec![1]
+    );
+    assert!(matches!(
+        reader.write_variable(&service_id, "limit", vec![2]).await,
+        Err(CommyError::PermissionDenied(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_subscribers_see_other_writers() {
+    let (server, client, service_id) = server_with_variable().await;
+
+    let mut updates = client.subscribe(&service_id, "limit").await.unwrap();
+    // A round trip on the same connection makes sure the subscribe arrived
+    client.read_variable(&service_id, "limit").await.unwrap();
+
+    server.set_variable(TENANT, "config", "limit", vec![9]);
+
+    let update = tokio::time::timeout(Duration::from_secs(2), updates.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(update.data, vec![9]);
+    assert_eq!(update.version, 2);
+}
+
+#[tokio::test]
+async fn test_subscribe_checks_variable_and_permission() {
+    let (server, _admin, service_id) = server_with_variable().await;
+    server.add_api_key(TENANT, "writer", &["write"]);
+
+    // A raw connection sees the answers to messages sent without a request ID
+    let connection = Connection::new(server.url()).await.unwrap();
+    let subscribe = |variable_name: &str| ClientMessage::Subscribe {
+        service_id: service_id.clone(),
+        variable_name: variable_name.to_string(),
+    };
+    let next_error = || async {
+        match connection.recv().await.unwrap() {
+            Some(ServerMessage::Error { code, .. }) => code,
+            other => panic!("Expected an error, got {:?}", other),
+        }
+    };
+
+    connection.send(subscribe("limit")).await.unwrap();
+    assert_eq!(next_error().await, ErrorCode::Unauthorized);
+
+    connection
+        .send(ClientMessage::Authenticate {
+            tenant_id: TENANT.to_string(),
+            client_version: "test".to_string(),
+            credentials: auth::api_key("writer".to_string()),
+        })
+        .await
+        .unwrap();
+    assert!(matches!(
+        connection.recv().await.unwrap(),
+        Some(ServerMessage::AuthenticationResult { success: true, .. })
+    ));
+    connection.send(subscribe("limit")).await.unwrap();
+    assert_eq!(next_error().await, ErrorCode::PermissionDenied);
+
+    server.add_api_key(TENANT, "reader", &["read"]);
+    connection
+        .send(ClientMessage::Authenticate {
+            tenant_id: TENANT.to_string(),
+            client_version: "test".to_string(),
+            credentials: auth::api_key("reader".to_string()),
+        })
+        .await
+        .unwrap();
+    connection.recv().await.unwrap();
+    connection.send(subscribe("missing")).await.unwrap();
+    assert_eq!(next_error().await, ErrorCode::NotFound);
+
+    connection.send(subscribe("limit")).await.unwrap();
+    assert!(matches!(
+        connection.recv().await.unwrap(),
+        Some(ServerMessage::Result { success: true, .. })
+    ));
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Wire formats
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_message_pack_and_json_clients() {
+    let (server, msgpack, service_id) = server_with_variable().await;
+    assert_eq!(msgpack.wire_format().await, Some(WireFormat::MessagePack));
+
+    let json = Client::builder(server.url())
+        .wire_format(WireFormat::Json)
+        .build();
+    json.connect().await.unwrap();
+    json.authenticate(TENANT, auth::api_key("admin".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(json.wire_format().await, Some(WireFormat::Json));
+
+    msgpack
+        .write_variable(&service_id, "limit", vec![0, 255])
+        .await
+        .unwrap();
+    assert_eq!(
+        json.read_variable(&service_id, "limit").await.unwrap(),
+        vec![0, 255]
+    );
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Scripted faults
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_injected_error_codes() {
+    let (server, client, service_id) = server_with_variable().await;
+
+    server.fail("ReadVariable", Fault::Error(ErrorCode::InternalError), 2);
+    for _ in 0..2 {
+        assert!(matches!(
+            client.read_variable(&service_id, "limit").await,
+            Err(CommyError::Other(_))
+        ));
+    }
+    assert_eq!(
+        client.read_variable(&service_id, "limit").await.unwrap(),
+        vec![1]
+    );
+}
+
+#[tokio::test]
+async fn test_injected_silence_times_out() {
+    let server = MockServer::start().await.unwrap();
+    server.add_api_key(TENANT, "admin", &["admin"]);
+    let client = Client::builder(server.url())
+        .admin_timeout(Duration::from_millis(100))
+        .build();
+    client.connect().await.unwrap();
+    client
+        .authenticate(TENANT, auth::api_key("admin".to_string()))
+        .await
+        .unwrap();
+
+    server.fail_next("CreateService", Fault::NoReply);
+    assert!(matches!(
+        client.create_service(TENANT, "config").await,
+        Err(CommyError::Timeout)
+    ));
+
+    server.fail_next("CreateService", Fault::Delay(Duration::from_millis(20)));
+    assert!(client.create_service(TENANT, "config").await.is_ok());
+}
+
+#[tokio::test]
+async fn test_delayed_reply_does_not_hold_up_later_requests() {
+    let (server, client, service_id) = server_with_variable().await;
+    server.fail_next("CreateService", Fault::Delay(Duration::from_millis(300)));
+
+    let started = tokio::time::Instant::now();
+    let (created, read) = tokio::join!(client.create_service(TENANT, "other"), async {
+        // Sent after the delayed request, answered before it
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let read = client.read_variable(&service_id, "limit").await;
+        (read, started.elapsed())
+    });
+
+    let (read, read_after) = read;
+    assert!(read.is_ok(), "{:?}", read);
+    assert!(read_after < Duration::from_millis(300), "{:?}", read_after);
+    assert!(created.is_ok(), "{:?}", created);
+    assert!(started.elapsed() >= Duration::from_millis(300));
+}
+
+#[tokio::test]
+async fn test_injected_disconnect_and_recovery() {
+    let (server, client, service_id) = server_with_variable().await;
+    let mut events = client.events();
+
+    server.fail_next("*", Fault::Disconnect);
+    assert!(client.read_variable(&service_id, "limit").await.is_err());
+    assert_eq!(
+        next_event(&mut events).await,
+        ClientEvent::ServerDisconnected("injected fault".to_string())
+    );
+    assert!(matches!(
+        next_event(&mut events).await,
+        ClientEvent::Disconnected(_)
+    ));
+
+    // The next request reconnects and restores authentication
+    assert_eq!(
+        client.read_variable(&service_id, "limit").await.unwrap(),
+        vec![1]
+    );
+    assert_eq!(server.connections(), 1);
+}
+
+#[tokio::test]
+async fn test_concurrent_requests_reconnect_once() {
+    let (server, client, service_id) = server_with_variable().await;
+    let mut events = client.events();
+
+    server.disconnect_all("restart");
+    loop {
+        if let ClientEvent::Disconnected(_) = next_event(&mut events).await {
+            break;
+        }
+    }
+    let authenticated_before = server
+        .received()
+        .iter()
+        .filter(|message| matches!(message, ClientMessage::Authenticate { .. }))
+        .count();
+
+    let (first, second) = tokio::join!(
+        client.read_variable(&service_id, "limit"),
+        client.read_variable(&service_id, "limit")
+    );
+    assert_eq!(first.unwrap(), vec![1]);
+    assert_eq!(second.unwrap(), vec![1]);
+
+    let authenticated_after = server
+        .received()
+        .iter()
+        .filter(|message| matches!(message, ClientMessage::Authenticate { .. }))
+        .count();
+    assert_eq!(authenticated_after - authenticated_before, 1);
+    assert_eq!(server.connections(), 1);
+}
+
+#[tokio::test]
+async fn test_received_messages_are_recorded() {
+    let (server, client, service_id) = server_with_variable().await;
+    client
+        .write_variable(&service_id, "limit", vec![3])
+        .await
+        .unwrap();
+
+    let types: Vec<String> = server
+        .received()
+        .iter()
+        .map(|message| {
+            serde_json::to_value(message).unwrap()["type"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+    assert_eq!(
+        types,
+        [
+            "Hello",
+            "Authenticate",
+            "CreateService",
+            "AllocateVariable",
+            "WriteVariable"
+        ]
+    );
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Hybrid file selection
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A `config` service whose file on disk holds `content`
+async fn server_with_service_file(content: &[u8]) -> (MockServer, tempfile::TempDir, PathBuf) {
+    let server = MockServer::start().await.unwrap();
+    server.add_api_key(TENANT, "admin", &["admin"]);
+    let admin = client_with_key(&server, "admin").await;
+    admin.create_service(TENANT, "config").await.unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.mem");
+    std::fs::write(&path, content).unwrap();
+    assert!(server.set_service_file(TENANT, "config", &path));
+    (server, dir, path)
+}
+
+#[tokio::test]
+async fn test_local_server_file_is_memory_mapped() {
+    let (server, _dir, path) = server_with_service_file(&[1, 2, 3, 4]).await;
+    let client = client_with_key(&server, "admin").await;
+
+    let vf = client
+        .get_virtual_service_file(TENANT, "config")
+        .await
+        .unwrap();
+    assert!(vf.is_local());
+    vf.register_variable(VariableMetadata::new("v".to_string(), 0, 4, 1))
+        .await
+        .unwrap();
+    assert_eq!(vf.read_variable_slice("v").await.unwrap(), vec![1, 2, 3, 4]);
+
+    // Writes land in the shared file
+    vf.write_variable("v", &[4, 3, 2, 1]).await.unwrap();
+    let other = LocalFileAccessor::read_only(path).await.unwrap();
+    assert_eq!(other.read_bytes(0, 4).await.unwrap(), vec![4, 3, 2, 1]);
+}
+
+#[tokio::test]
+async fn test_unmappable_service_file_falls_back_to_remote() {
+    let (server, _dir, _path) = server_with_service_file(&[0; 4]).await;
+    server.set_service_file(TENANT, "config", "/nonexistent/config.mem");
+    let client = client_with_key(&server, "admin").await;
+
+    let vf = client
+        .get_virtual_service_file(TENANT, "config")
+        .await
+        .unwrap();
+    assert!(!vf.is_local());
+
+    // A server keeping no files at all
+    let other = client.create_service(TENANT, "other").await;
+    assert!(other.is_ok());
+    let vf = client
+        .get_virtual_service_file(TENANT, "other")
+        .await
+        .unwrap();
+    assert!(!vf.is_local());
+}
+
+#[tokio::test]
+async fn test_remote_file_access_does_not_ask_for_the_file() {
+    let (server, _dir, _path) = server_with_service_file(&[0; 4]).await;
+    let client = Client::builder(server.url())
+        .file_access(FileAccess::Remote)
+        .build();
+    client.connect().await.unwrap();
+    client
+        .authenticate(TENANT, auth::api_key("admin".to_string()))
+        .await
+        .unwrap();
+
+    let vf = client
+        .get_virtual_service_file(TENANT, "config")
+        .await
+        .unwrap();
+    assert!(!vf.is_local());
+    let asked = server
+        .received()
+        .iter()
+        .any(|message| matches!(message, ClientMessage::GetServiceFilePath { .. }));
+    assert!(!asked);
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Remote virtual files
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_remote_virtual_file_follows_server_writes() {
+    let (server, writer, service_id) = server_with_variable().await;
+    let client = Client::builder(server.url())
+        .file_access(FileAccess::Remote)
+        .build();
+    client.connect().await.unwrap();
+    client
+        .authenticate(TENANT, auth::api_key("admin".to_string()))
+        .await
+        .unwrap();
+
+    let vf = client
+        .get_virtual_service_file(TENANT, "config")
+        .await
+        .unwrap();
+    vf.register_variable(VariableMetadata::new("limit".to_string(), 0, 1, 1))
+        .await
+        .unwrap();
+
+    // Wait until the file's subscription reached the server
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while !server
+            .received()
+            .iter()
+            .any(|message| matches!(message, ClientMessage::Subscribe { .. }))
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("virtual file subscribes");
+
+    writer
+        .write_variable(&service_id, "limit", vec![42])
+        .await
+        .unwrap();
+
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while vf.read_variable_slice("limit").await.unwrap() != vec![42] {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("remote write applied");
+    assert_eq!(vf.shadow_bytes().await, vec![42]);
+    assert!(vf.get_changed_variables().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_remote_virtual_file_opened_before_authenticating_subscribes_later() {
+    let (server, writer, service_id) = server_with_variable().await;
+    let client = Client::builder(server.url())
+        .file_access(FileAccess::Remote)
+        .build();
+    client.connect().await.unwrap();
+
+    let vf = client
+        .get_virtual_service_file(TENANT, "config")
+        .await
+        .unwrap();
+    vf.register_variable(VariableMetadata::new("limit".to_string(), 0, 1, 1))
+        .await
+        .unwrap();
+
+    // A key that may find the service but not read it gets the subscription
+    // rejected; the file tries again once the client authenticates anew
+    server.add_api_key(TENANT, "lister", &["read_service"]);
+    client
+        .authenticate(TENANT, auth::api_key("lister".to_string()))
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while !server
+            .received()
+            .iter()
+            .any(|message| matches!(message, ClientMessage::Subscribe { .. }))
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("virtual file tries to subscribe");
+
+    client
+        .authenticate(TENANT, auth::api_key("admin".to_string()))
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(2), async {
+        // A subscription sent after the last authentication
+        while !server
+            .received()
+            .iter()
+            .rev()
+            .take_while(|message| !matches!(message, ClientMessage::Authenticate { .. }))
+            .any(|message| matches!(message, ClientMessage::Subscribe { .. }))
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("rejected subscription is retried");
+
+    writer
+        .write_variable(&service_id, "limit", vec![42])
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while vf.read_variable_slice("limit").await.unwrap() != vec![42] {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("remote write applied");
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Client-side fault injection
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_client_survives_forced_disconnects() {
+    let server = MockServer::start().await.unwrap();
+    server.add_api_key(TENANT, "admin", &["admin"]);
+    let client = Client::builder(server.url())
+        .heartbeat_interval(Duration::ZERO)
+        .retry_policy(FixedDelay::new(Duration::from_millis(5)))
+        .fault_injection(
+            FaultInjection::new()
+                .with_latency(Duration::from_millis(2))
+                .with_disconnect_after(5),
+        )
+        .build();
+    client.connect().await.unwrap();
+    client
+        .authenticate(TENANT, auth::api_key("admin".to_string()))
+        .await
+        .unwrap();
+    let service_id = client.create_service(TENANT, "config").await.unwrap();
+    client
+        .allocate_variable(&service_id, "limit", vec![0])
+        .await
+        .unwrap();
+
+    // Every few requests the socket drops under a write; the write after
+    // that reconnects and restores the session
+    let mut lost = 0;
+    for value in 1..=10u8 {
+        match client
+            .write_variable(&service_id, "limit", vec![value])
+            .await
+        {
+            Ok(_) => {}
+            Err(CommyError::ConnectionLost(_)) => lost += 1,
+            Err(e) => panic!("Unexpected error: {}", e),
+        }
+    }
+    assert!(lost > 0);
+
+    let (data, _) = server.variable(TENANT, "config", "limit").unwrap();
+    let written = client.read_variable(&service_id, "limit").await;
+    let written = match written {
+        Err(CommyError::ConnectionLost(_)) => client.read_variable(&service_id, "limit").await,
+        other => other,
+    };
+    assert_eq!(written.unwrap(), data);
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Record and replay
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// The calls of a recorded session, returning what the client saw
+async fn scripted_session(client: &Client) -> Vec<String> {
+    let mut seen = Vec::new();
+    client.connect().await.unwrap();
+    client
+        .authenticate(TENANT, auth::api_key("admin".to_string()))
+        .await
+        .unwrap();
+    let service_id = client.create_service(TENANT, "config").await.unwrap();
+    client
+        .allocate_variable(&service_id, "limit", vec![1])
+        .await
+        .unwrap();
+    seen.push(format!(
+        "{:?}",
+        client.write_variable(&service_id, "limit", vec![2]).await
+    ));
+    seen.push(format!(
+        "{:?}",
+        client
+            .compare_and_swap(&service_id, "limit", 1, vec![3])
+            .await
+    ));
+    // The server drops the connection here; the read reconnects
+    seen.push(format!(
+        "{:?}",
+        client.read_variable(&service_id, "limit").await
+    ));
+    seen.push(format!(
+        "{:?}",
+        client.read_variable(&service_id, "limit").await
+    ));
+    seen
+}
+
+#[tokio::test]
+async fn test_recorded_session_replays_without_a_server() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.jsonl");
+
+    let server = MockServer::start().await.unwrap();
+    server.add_api_key(TENANT, "admin", &["admin"]);
+    server.fail("ReadVariable", Fault::Disconnect, 1);
+    let recorder = Recorder::create(&path).unwrap();
+    let recorded = Client::builder(server.url())
+        .client_id("recorded")
+        .heartbeat_interval(Duration::ZERO)
+        .recorder(recorder.clone())
+        .build();
+    let live = scripted_session(&recorded).await;
+    drop(recorded);
+    drop(server);
+    recorder.flush().unwrap();
+
+    let replay = Replay::load(&path).unwrap();
+    assert_eq!(replay.connections(), 2);
+    let replayed = Client::builder("ws://recorded")
+        .client_id("recorded")
+        .heartbeat_interval(Duration::ZERO)
+        .transport(replay.clone())
+        .build();
+    assert_eq!(scripted_session(&replayed).await, live);
+    assert_eq!(replay.divergence(), None);
+}
+
+#[tokio::test]
+async fn test_replay_reports_a_different_call_sequence() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.jsonl");
+
+    let server = MockServer::start().await.unwrap();
+    server.add_api_key(TENANT, "admin", &["admin"]);
+    let recorder = Recorder::create(&path).unwrap();
+    let recorded = Client::builder(server.url())
+        .heartbeat_interval(Duration::ZERO)
+        .recorder(recorder.clone())
+        .build();
+    recorded.connect().await.unwrap();
+    recorded
+        .authenticate(TENANT, auth::api_key("admin".to_string()))
+        .await
+        .unwrap();
+    drop(recorded);
+    recorder.flush().unwrap();
+
+    let replay = Replay::load(&path).unwrap();
+    let replayed = Client::builder("ws://recorded")
+        .heartbeat_interval(Duration::ZERO)
+        .max_reconnect_attempts(0)
+        .transport(replay.clone())
+        .build();
+    replayed.connect().await.unwrap();
+    assert!(replayed.read_variable("service", "limit").await.is_err());
+
+    let divergence = replay.divergence().unwrap();
+    assert!(divergence.contains("ReadVariable"), "{}", divergence);
+    assert!(divergence.contains("Authenticate"), "{}", divergence);
+}
@@ -1,355 +1,355 @@
-//! Server-side CRUD behavior validation tests
-//!
-//! These tests validate the expected server behavior when handling CRUD operations.
-//! They test the message protocol and error handling without requiring a live connection.
-
-use commy_sdk_rust::message::{ClientMessage, ErrorCode, ServerMessage};
-
-#[test]
-fn test_server_response_format_service_created() {
-    // Verify server response format when service is created
-    let response = ServerMessage::Service {
-        service_id: "svc_abc123".to_string(),
-        service_name: "app_config".to_string(),
-        tenant_id: "org_tenant".to_string(),
-        file_path: None,
-    };
-
-    let json = serde_json::to_string(&response).expect("Should serialize");
-    let parsed: serde_json::Value =
-        serde_json::from_str(&json).expect("Should deserialize to JSON");
-
-    assert_eq!(parsed["type"], "Service");
-    assert_eq!(parsed["data"]["service_id"], "svc_abc123");
-    assert_eq!(parsed["data"]["service_name"], "app_config");
-    assert_eq!(parsed["data"]["tenant_id"], "org_tenant");
-}
-
-#[test]
-fn test_server_response_format_error_not_found() {
-    // Verify server response format for NotFound error
-    let response = ServerMessage::Error {
-        code: ErrorCode::NotFound,
-        message: "Service 'config' not found".to_string(),
-    };
-
-    let json = serde_json::to_string(&response).expect("Should serialize");
-    let parsed: serde_json::Value =
-        serde_json::from_str(&json).expect("Should deserialize to JSON");
-
-    assert_eq!(parsed["type"], "Error");
-    assert_eq!(parsed["data"]["code"], "NOT_FOUND");
-    assert_eq!(parsed["data"]["message"], "Service 'config' not found");
-}
-
-#[test]
-fn test_server_response_format_error_permission_denied() {
-    // Verify server response format for PermissionDenied error
-    let response = ServerMessage::Error {
-        code: ErrorCode::PermissionDenied,
-        message: "Permission denied: create_service required".to_string(),
-    };
-
-    let json = serde_json::to_string(&response).expect("Should serialize");
-    let parsed: serde_json::Value =
-        serde_json::from_str(&json).expect("Should deserialize to JSON");
-
-    assert_eq!(parsed["type"], "Error");
-    assert_eq!(parsed["data"]["code"], "PERMISSION_DENIED");
-    assert_eq!(
-        parsed["data"]["message"],
-        "Permission denied: create_service required"
-    );
-}
-
-#[test]
-fn test_server_response_format_error_unauthorized() {
-    // Verify server response format for Unauthorized error
-    let response = ServerMessage::Error {
-        code: ErrorCode::Unauthorized,
-        message: "Not authenticated to this tenant".to_string(),
-    };
-
-    let json = serde_json::to_string(&response).expect("Should serialize");
-    let parsed: serde_json::Value =
-        serde_json::from_str(&json).expect("Should deserialize to JSON");
-
-    assert_eq!(parsed["type"], "Error");
-    assert_eq!(parsed["data"]["code"], "UNAUTHORIZED");
-    assert_eq!(
-        parsed["data"]["message"],
-        "Not authenticated to this tenant"
-    );
-}
-
-#[test]
-fn test_server_response_format_error_already_exists() {
-    // Verify server response format for AlreadyExists error
-    let response = ServerMessage::Error {
-        code: ErrorCode::AlreadyExists,
-        message: "Service 'config' already exists".to_string(),
-    };
-
-    let json = serde_json::to_string(&response).expect("Should serialize");
-    let parsed: serde_json::Value =
-        serde_json::from_str(&json).expect("Should deserialize to JSON");
-
-    assert_eq!(parsed["type"], "Error");
-    assert_eq!(parsed["data"]["code"], "ALREADY_EXISTS");
-    assert_eq!(parsed["data"]["message"], "Service 'config' already exists");
-}
-
-#[test]
-fn test_create_service_request_structure() {
-    // Verify the structure of CreateService request
-    let request = ClientMessage::CreateService {
-        tenant_id: "my_tenant".to_string(),
-        service_name: "my_service".to_string(),
-    };
-
-    let json = serde_json::to_string(&request).expect("Should serialize");
-    let parsed: serde_json::Value =
-        serde_json::from_str(&json).expect("Should deserialize to JSON");
-
-    assert_eq!(parsed["type"], "CreateService");
-    assert_eq!(parsed["data"]["tenant_id"], "my_tenant");
-    assert_eq!(parsed["data"]["service_name"], "my_service");
-}
-
-#[test]
-fn test_get_service_request_structure() {
-    // Verify the structure of GetService request
-    let request = ClientMessage::GetService {
-        tenant_id: "my_tenant".to_string(),
-        service_name: "my_service".to_string(),
-    };
-
-    let json = serde_json::to_string(&request).expect("Should serialize");
-    let parsed: serde_json::Value =
-        serde_json::from_str(&json).expect("Should deserialize to JSON");
-
-    assert_eq!(parsed["type"], "GetService");
-    assert_eq!(parsed["data"]["tenant_id"], "my_tenant");
-    assert_eq!(parsed["data"]["service_name"], "my_service");
-}
-
-#[test]
-fn test_delete_service_request_structure() {
-    // Verify the structure of DeleteService request
-    let request = ClientMessage::DeleteService {
-        tenant_id: "my_tenant".to_string(),
-        service_name: "my_service".to_string(),
-    };
-
-    let json = serde_json::to_string(&request).expect("Should serialize");
-    let parsed: serde_json::Value =
-        serde_json::from_str(&json).expect("Should deserialize to JSON");
-
-    assert_eq!(parsed["type"], "DeleteService");
-    assert_eq!(parsed["data"]["tenant_id"], "my_tenant");
-    assert_eq!(parsed["data"]["service_name"], "my_service");
-}
-
-#[test]
-fn test_error_code_serialization() {
-    // Test that all error codes serialize correctly
-    let codes = vec![
-        (ErrorCode::NotFound, "NOT_FOUND"),
-        (ErrorCode::PermissionDenied, "PERMISSION_DENIED"),
-        (ErrorCode::Unauthorized, "UNAUTHORIZED"),
-        (ErrorCode::AlreadyExists, "ALREADY_EXISTS"),
-        (ErrorCode::InvalidRequest, "INVALID_REQUEST"),
-        (ErrorCode::InternalError, "INTERNAL_ERROR"),
-        (ErrorCode::ConnectionLost, "CONNECTION_LOST"),
-        (ErrorCode::Timeout, "TIMEOUT"),
-    ];
-
-    for (code, expected) in codes {
-        let json = serde_json::to_string(&code).expect("Should serialize");
-        assert_eq!(json, format!("\"{}\"", expected));
-    }
-}
-
-#[test]
-fn test_error_code_deserialization() {
-    // Test that error codes deserialize correctly
-    let json_strings = vec![
-        ("\"NOT_FOUND\"", ErrorCode::NotFound),
-        ("\"PERMISSION_DENIED\"", ErrorCode::PermissionDenied),
-        ("\"UNAUTHORIZED\"", ErrorCode::Unauthorized),
-        ("\"ALREADY_EXISTS\"", ErrorCode::AlreadyExists),
-        ("\"INVALID_REQUEST\"", ErrorCode::InvalidRequest),
-        ("\"INTERNAL_ERROR\"", ErrorCode::InternalError),
-        ("\"CONNECTION_LOST\"", ErrorCode::ConnectionLost),
-        ("\"TIMEOUT\"", ErrorCode::Timeout),
-    ];
-
-    for (json, expected) in json_strings {
-        let parsed: ErrorCode = serde_json::from_str(json).expect("Should deserialize error code");
-        assert_eq!(parsed, expected);
-    }
-}
-
-#[test]
-fn test_permission_enforcement_validation() {
-    // Test document: verify permission enforcement logic
-    //
-    // The server should validate:
-    // 1. Client is authenticated to the tenant (required for all CRUD)
-    // 2. Client has specific permission for the operation:
-    //    - CreateService requires ServiceCreate permission
-    //    - GetService requires ServiceRead permission
-    //    - DeleteService requires ServiceDelete permission
-    // 3. Resource exists/doesn't exist as appropriate:
-    //    - CreateService: fails if service already exists (AlreadyExists)
-    //    - GetService: fails if service doesn't exist (NotFound)
-    //    - DeleteService: fails if service doesn't exist (NotFound)
-
-    println!("Permission validation checklist:");
-    println!("✓ Authentication check (Unauthorized if missing)");
-    println!("✓ Operation-specific permission check (PermissionDenied if missing)");
-    println!("✓ Resource existence check (NotFound or AlreadyExists as appropriate)");
-}
-
-#[test]
-fn test_server_error_code_mapping() {
-    // Document how different errors should map to ErrorCode
-    let scenarios = vec![
-        (
-            "Service doesn't exist during GetService",
-            ErrorCode::NotFound,
-        ),
-        (
-            "Service already exists during CreateService",
-            ErrorCode::AlreadyExists,
-        ),
-        (
-            "Client not authenticated to tenant",
-            ErrorCode::Unauthorized,
-        ),
-        (
-            "Client lacks required permission",
-            ErrorCode::PermissionDenied,
-        ),
-        ("Invalid request structure", ErrorCode::InvalidRequest),
-        ("Server-side error", ErrorCode::InternalError),
-        ("Connection dropped", ErrorCode::ConnectionLost),
-        ("Operation timeout", ErrorCode::Timeout),
-    ];
-
-    for (scenario, error_code) in scenarios {
-        println!("Scenario: {} -> {:?}", scenario, error_code);
-    }
-}
-
-#[test]
-fn test_acknowledgement_message_format() {
-    // Test the Result response format (used for operation outcomes)
-    let result = ServerMessage::Result {
-        request_id: "req_123".to_string(),
-        success: true,
-        message: "Service 'config' deleted successfully".to_string(),
-    };
-
-    let json = serde_json::to_string(&result).expect("Should serialize");
-    let parsed: serde_json::Value =
-        serde_json::from_str(&json).expect("Should deserialize to JSON");
-
-    assert_eq!(parsed["type"], "Result");
-    assert_eq!(parsed["data"]["success"], true);
-    assert_eq!(
-        parsed["data"]["message"],
-        "Service 'config' deleted successfully"
-    );
-}
-
-#[test]
-fn test_service_response_contains_required_fields() {
-    // Verify Service response has all required fields
-    let service = ServerMessage::Service {
-        service_id: "id_123".to_string(),
-        service_name: "my_service".to_string(),
-        tenant_id: "my_tenant".to_string(),
-        file_path: None,
-    };
-
-    let json = serde_json::to_string(&service).expect("Should serialize");
-    let parsed: serde_json::Value =
-        serde_json::from_str(&json).expect("Should deserialize to JSON");
-
-    // Verify all required fields are present
-    assert!(parsed["data"]["service_id"].is_string());
-    assert!(parsed["data"]["service_name"].is_string());
-    assert!(parsed["data"]["tenant_id"].is_string());
-
-    // Verify they have content
-    assert!(!parsed["data"]["service_id"].as_str().unwrap().is_empty());
-    assert!(!parsed["data"]["service_name"].as_str().unwrap().is_empty());
-    assert!(!parsed["data"]["tenant_id"].as_str().unwrap().is_empty());
-}
-
-#[test]
-fn test_crud_operations_are_not_implicit() {
-    // Verify that CRUD operations are explicit, not implicit
-    //
-    // Old (implicit) pattern:
-    //   client.get_service("tenant", "svc") -> creates if missing
-    //
-    // New (explicit) pattern:
-    //   client.create_service("tenant", "svc") -> explicit creation
-    //   client.get_service("tenant", "svc") -> errors if missing (no side effects)
-    //   client.delete_service("tenant", "svc") -> explicit deletion
-
-    println!("Verification of explicit CRUD semantics:");
-    println!();
-    println!("1. create_service() is the ONLY operation that creates resources");
-    println!("2. get_service() is read-only with no side effects");
-    println!("   - Errors with NotFound if service doesn't exist");
-    println!("   - Does NOT implicitly create the service");
-    println!("3. delete_service() is the ONLY operation that destroys resources");
-    println!("   - Requires explicit call");
-    println!("   - Not a side effect of other operations");
-    println!();
-    println!("Benefits:");
-    println!("  ✓ No surprising side effects from read operations");
-    println!("  ✓ Explicit auditability: can track who creates/deletes");
-    println!("  ✓ Proper permission separation: read ≠ create");
-    println!("  ✓ Clearer error semantics: NotFound vs PermissionDenied");
-}
-
-#[test]
-fn test_batch_operations_not_atomic() {
-    // Document that batch operations are not atomic
-    // (Can be added in future if needed)
-
-    println!("Batch Operations Consideration:");
-    println!();
-    println!("Current design: Individual operations only");
-    println!("  - Each operation is independent");
-    println!("  - No transaction support (yet)");
-    println!();
-    println!("Future enhancements could include:");
-    println!("  - Transaction support for atomic batch updates");
-    println!("  - Event log for auditability");
-    println!("  - Conflict detection and resolution");
-}
-
-#[test]
-fn test_permission_immutability_during_operation() {
-    // Document: permissions are checked at START of operation
-    // If permissions are revoked during operation, the operation completes
-    // (revocation takes effect on next operation)
-
-    println!("Permission Check Timing:");
-    println!();
-    println!("1. Client calls create_service()");
-    println!("2. Server checks: authenticated? has ServiceCreate?");
-    println!("3. If checks pass, operation proceeds");
-    println!("4. If permissions revoked mid-operation, operation completes anyway");
-    println!("5. Next operation will fail with PermissionDenied");
-    println!();
-    println!("This is consistent with:");
-    println!("  - Standard permission model patterns");
-    println!("  - Avoid complex locking/transaction overhead");
-    println!("  - Predictable client-side behavior");
-}
+//! Server-side CRUD behavior validation tests
+//!
+//! These tests validate the expected server behavior when handling CRUD operations.
+//! They test the message protocol and error handling without requiring a live connection.
+
+use commy_sdk_rust::message::{ClientMessage, ErrorCode, ServerMessage};
+
+#[test]
+fn test_server_response_format_service_created() {
+    // Verify server response format when service is created
+    let response = ServerMessage::Service {
+        service_id: "svc_abc123".to_string(),
+        service_name: "app_config".to_string(),
+        tenant_id: "org_tenant".to_string(),
+        file_path: None,
+    };
+
+    let json = serde_json::to_string(&response).expect("Should serialize");
+    let parsed: serde_json::Value =
+        serde_json::from_str(&json).expect("Should deserialize to JSON");
+
+    assert_eq!(parsed["type"], "Service");
+    assert_eq!(parsed["data"]["service_id"], "svc_abc123");
+    assert_eq!(parsed["data"]["service_name"], "app_config");
+    assert_eq!(parsed["data"]["tenant_id"], "org_tenant");
+}
+
+#[test]
+fn test_server_response_format_error_not_found() {
+    // Verify server response format for NotFound error
+    let response = ServerMessage::Error {
+        code: ErrorCode::NotFound,
+        message: "Service 'config' not found".to_string(),
+    };
+
+    let json = serde_json::to_string(&response).expect("Should serialize");
+    let parsed: serde_json::Value =
+        serde_json::from_str(&json).expect("Should deserialize to JSON");
+
+    assert_eq!(parsed["type"], "Error");
+    assert_eq!(parsed["data"]["code"], "NOT_FOUND");
+    assert_eq!(parsed["data"]["message"], "Service 'config' not found");
+}
+
+#[test]
+fn test_server_response_format_error_permission_denied() {
+    // Verify server response format for PermissionDenied error
+    let response = ServerMessage::Error {
+        code: ErrorCode::PermissionDenied,
+        message: "Permission denied: create_service required".to_string(),
+    };
+
+    let json = serde_json::to_string(&response).expect("Should serialize");
+    let parsed: serde_json::Value =
+        serde_json::from_str(&json).expect("Should deserialize to JSON");
+
+    assert_eq!(parsed["type"], "Error");
+    assert_eq!(parsed["data"]["code"], "PERMISSION_DENIED");
+    assert_eq!(
+        parsed["data"]["message"],
+        "Permission denied: create_service required"
+    );
+}
+
+#[test]
+fn test_server_response_format_error_unauthorized() {
+    // Verify server response format for Unauthorized error
+    let response = ServerMessage::Error {
+        code: ErrorCode::Unauthorized,
+        message: "Not authenticated to this tenant".to_string(),
+    };
+
+    let json = serde_json::to_string(&response).expect("Should serialize");
+    let parsed: serde_json::Value =
+        serde_json::from_str(&json).expect("Should deserialize to JSON");
+
+    assert_eq!(parsed["type"], "Error");
+    assert_eq!(parsed["data"]["code"], "UNAUTHORIZED");
+    assert_eq!(
+        parsed["data"]["message"],
+        "Not authenticated to this tenant"
+    );
+}
+
+#[test]
+fn test_server_response_format_error_already_exists() {
+    // Verify server response format for AlreadyExists error
+    let response = ServerMessage::Error {
+        code: ErrorCode::AlreadyExists,
+        message: "Service 'config' already exists".to_string(),
+    };
+
+    let json = serde_json::to_string(&response).expect("Should serialize");
+    let parsed: serde_json::Value =
+        serde_json::from_str(&json).expect("Should deserialize to JSON");
+
+    assert_eq!(parsed["type"], "Error");
+    assert_eq!(parsed["data"]["code"], "ALREADY_EXISTS");
+    assert_eq!(parsed["data"]["message"], "Service 'config' already exists");
+}
+
+#[test]
+fn test_create_service_request_structure() {
+    // Verify the structure of CreateService request
+    let request = ClientMessage::CreateService {
+        tenant_id: "my_tenant".to_string(),
+        service_name: "my_service".to_string(),
+    };
+
+    let json = serde_json::to_string(&request).expect("Should serialize");
+    let parsed: serde_json::Value =
+        serde_json::from_str(&json).expect("Should deserialize to JSON");
+
+    assert_eq!(parsed["type"], "CreateService");
+    assert_eq!(parsed["data"]["tenant_id"], "my_tenant");
+    assert_eq!(parsed["data"]["service_name"], "my_service");
+}
+
+#[test]
+fn test_get_service_request_structure() {
+    // Verify the structure of GetService request
+    let request = ClientMessage::GetService {
+        tenant_id: "my_tenant".to_string(),
+        service_name: "my_service".to_string(),
+    };
+
+    let json = serde_json::to_string(&request).expect("Should serialize");
+    let parsed: serde_json::Value =
+        serde_json::from_str(&json).expect("Should deserialize to JSON");
+
+    assert_eq!(parsed["type"], "GetService");
+    assert_eq!(parsed["data"]["tenant_id"], "my_tenant");
+    assert_eq!(parsed["data"]["service_name"], "my_service");
+}
+
+#[test]
+fn test_delete_service_request_structure() {
+    // Verify the structure of DeleteService request
+    let request = ClientMessage::DeleteService {
+        tenant_id: "my_tenant".to_string(),
+        service_name: "my_service".to_string(),
+    };
+
+    let json = serde_json::to_string(&request).expect("Should serialize");
+    let parsed: serde_json::Value =
+        serde_json::from_str(&json).expect("Should deserialize to JSON");
+
+    assert_eq!(parsed["type"], "DeleteService");
+    assert_eq!(parsed["data"]["tenant_id"], "my_tenant");
+    assert_eq!(parsed["data"]["service_name"], "my_service");
+}
+
+#[test]
+fn test_error_code_serialization() {
+    // Test that all error codes serialize correctly
+    let codes = vec![
+        (ErrorCode::NotFound, "NOT_FOUND"),
+        (ErrorCode::PermissionDenied, "PERMISSION_DENIED"),
+        (ErrorCode::Unauthorized, "UNAUTHORIZED"),
+        (ErrorCode::AlreadyExists, "ALREADY_EXISTS"),
+        (ErrorCode::InvalidRequest, "INVALID_REQUEST"),
+        (ErrorCode::InternalError, "INTERNAL_ERROR"),
+        (ErrorCode::ConnectionLost, "CONNECTION_LOST"),
+        (ErrorCode::Timeout, "TIMEOUT"),
+    ];
+
+    for (code, expected) in codes {
+        let json = serde_json::to_string(&code).expect("Should serialize");
+        assert_eq!(json, format!("\"{}\"", expected));
+    }
+}
+
+#[test]
+fn test_error_code_deserialization() {
+    // Test that error codes deserialize correctly
+    let json_strings = vec![
+        ("\"NOT_FOUND\"", ErrorCode::NotFound),
+        ("\"PERMISSION_DENIED\"", ErrorCode::PermissionDenied),
+        ("\"UNAUTHORIZED\"", ErrorCode::Unauthorized),
+        ("\"ALREADY_EXISTS\"", ErrorCode::AlreadyExists),
+        ("\"INVALID_REQUEST\"", ErrorCode::InvalidRequest),
+        ("\"INTERNAL_ERROR\"", ErrorCode::InternalError),
+        ("\"CONNECTION_LOST\"", ErrorCode::ConnectionLost),
+        ("\"TIMEOUT\"", ErrorCode::Timeout),
+    ];
+
+    for (json, expected) in json_strings {
+        let parsed: ErrorCode = serde_json::from_str(json).expect("Should deserialize error code");
+        assert_eq!(parsed, expected);
+    }
+}
+
+#[test]
+fn test_permission_enforcement_validation() {
+    // Test document: verify permission enforcement logic
+    //
+    // The server should validate:
+    // 1. Client is authenticated to the tenant (required for all CRUD)
+    // 2. Client has specific permission for the operation:
+    //    - CreateService requires ServiceCreate permission
+    //    - GetService requires ServiceRead permission
+    //    - DeleteService requires ServiceDelete permission
+    // 3. Resource exists/doesn't exist as appropriate:
+    //    - CreateService: fails if service already exists (AlreadyExists)
+    //    - GetService: fails if service doesn't exist (NotFound)
+    //    - DeleteService: fails if service doesn't exist (NotFound)
+
+    println!("Permission validation checklist:");
+    println!("✓ Authentication check (Unauthorized if missing)");
+    println!("✓ Operation-specific permission check (PermissionDenied if missing)");
+    println!("✓ Resource existence check (NotFound or AlreadyExists as appropriate)");
+}
+
+#[test]
+fn test_server_error_code_mapping() {
+    // Document how different errors should map to ErrorCode
+    let scenarios = vec![
+        (
+            "Service doesn't exist during GetService",
+            ErrorCode::NotFound,
+        ),
+        (
+            "Service already exists during CreateService",
+            ErrorCode::AlreadyExists,
+        ),
+        (
+            "Client not authenticated to tenant",
+            ErrorCode::Unauthorized,
+        ),
+        (
+            "Client lacks required permission",
+            ErrorCode::PermissionDenied,
+        ),
+        ("Invalid request structure", ErrorCode::InvalidRequest),
+        ("Server-side error", ErrorCode::InternalError),
+        ("Connection dropped", ErrorCode::ConnectionLost),
+        ("Operation timeout", ErrorCode::Timeout),
+    ];
+
+    for (scenario, error_code) in scenarios {
+        println!("Scenario: {} -> {:?}", scenario, error_code);
+    }
+}
+
+#[test]
+fn test_acknowledgement_message_format() {
+    // Test the Result response format (used for operation outcomes)
+    let result = ServerMessage::Result {
+        request_id: "req_123".to_string(),
+        success: true,
+        message: "Service 'config' deleted successfully".to_string(),
+    };
+
+    let json = serde_json::to_string(&result).expect("Should serialize");
+    let parsed: serde_json::Value =
+        serde_json::from_str(&json).expect("Should deserialize to JSON");
+
+    assert_eq!(parsed["type"], "Result");
+    assert_eq!(parsed["data"]["success"], true);
+    assert_eq!(
+        parsed["data"]["message"],
+        "Service 'config' deleted successfully"
+    );
+}
+
+#[test]
+fn test_service_response_contains_required_fields() {
+    // Verify Service response has all required fields
+    let service = ServerMessage::Service {
+        service_id: "id_123".to_string(),
+        service_name: "my_service".to_string(),
+        tenant_id: "my_tenant".to_string(),
+        file_path: None,
+    };
+
+    let json = serde_json::to_string(&service).expect("Should serialize");
+    let parsed: serde_json::Value =
+        serde_json::from_str(&json).expect("Should deserialize to JSON");
+
+    // Verify all required fields are present
+    assert!(parsed["data"]["service_id"].is_string());
+    assert!(parsed["data"]["service_name"].is_string());
+    assert!(parsed["data"]["tenant_id"].is_string());
+
+    // Verify they have content
+    assert!(!parsed["data"]["service_id"].as_str().unwrap().is_empty());
+    assert!(!parsed["data"]["service_name"].as_str().unwrap().is_empty());
+    assert!(!parsed["data"]["tenant_id"].as_str().unwrap().is_empty());
+}
+
+#[test]
+fn test_crud_operations_are_not_implicit() {
+    // Verify that CRUD operations are explicit, not implicit
+    //
+    // Old (implicit) pattern:
+    //   client.get_service("tenant", "svc") -> creates if missing
+    //
+    // New (explicit) pattern:
+    //   client.create_service("tenant", "svc") -> explicit creation
+    //   client.get_service("tenant", "svc") -> errors if missing (no side effects)
+    //   client.delete_service("tenant", "svc") -> explicit deletion
+
+    println!("Verification of explicit CRUD semantics:");
+    println!();
+    println!("1. create_service() is the ONLY operation that creates resources");
+    println!("2. get_service() is read-only with no side effects");
+    println!("   - Errors with NotFound if service doesn't exist");
+    println!("   - Does NOT implicitly create the service");
+    println!("3. delete_service() is the ONLY operation that destroys resources");
+    println!("   - Requires explicit call");
+    println!("   - Not a side effect of other operations");
+    println!();
+    println!("Benefits:");
+    println!("  ✓ No surprising side effects from read operations");
+    println!("  ✓ Explicit auditability: can track who creates/deletes");
+    println!("  ✓ Proper permission separation: read ≠ create");
+    println!("  ✓ Clearer error semantics: NotFound vs PermissionDenied");
+}
+
+#[test]
+fn test_batch_operations_not_atomic() {
+    // Document that batch operations are not atomic
+    // (Can be added in future if needed)
+
+    println!("Batch Operations Consideration:");
+    println!();
+    println!("Current design: Individual operations only");
+    println!("  - Each operation is independent");
+    println!("  - No transaction support (yet)");
+    println!();
+    println!("Future enhancements could include:");
+    println!("  - Transaction support for atomic batch updates");
+    println!("  - Event log for auditability");
+    println!("  - Conflict detection and resolution");
+}
+
+#[test]
+fn test_permission_immutability_during_operation() {
+    // Document: permissions are checked at START of operation
+    // If permissions are revoked during operation, the operation completes
+    // (revocation takes effect on next operation)
+
+    println!("Permission Check Timing:");
+    println!();
+    println!("1. Client calls create_service()");
+    println!("2. Server checks: authenticated? has ServiceCreate?");
+    println!("3. If checks pass, operation proceeds");
+    println!("4. If permissions revoked mid-operation, operation completes anyway");
+    println!("5. Next operation will fail with PermissionDenied");
+    println!();
+    println!("This is consistent with:");
+    println!("  - Standard permission model patterns");
+    println!("  - Avoid complex locking/transaction overhead");
+    println!("  - Predictable client-side behavior");
+}
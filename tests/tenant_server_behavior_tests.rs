@@ -1,249 +1,249 @@
-#[cfg(test)]
-mod tenant_server_behavior_tests {
-    use serde_json::json;
-
-    /// Helper to simulate server processing of tenant operations
-    fn simulate_create_tenant_response(
-        tenant_id: &str,
-        tenant_name: &str,
-    ) -> serde_json::Value {
-        json!({
-            "type": "TenantResult",
-            "data": {
-                "success": true,
-                "tenant_id": tenant_id,
-                "message": format!("Tenant '{}' created", tenant_name)
-            }
-        })
-    }
-
-    fn simulate_delete_tenant_response(tenant_id: &str) -> serde_json::Value {
-        json!({
-            "type": "Result",
-            "data": {
-                "request_id": "req-123",
-                "success": true,
-                "message": format!("Tenant '{}' deleted", tenant_id)
-            }
-        })
-    }
-
-    fn simulate_error_response(code: &str, message: &str) -> serde_json::Value {
-        json!({
-            "type": "Error",
-            "data": {
-                "code": code,
-                "message": message
-            }
-        })
-    }
-
-    #[test]
-    fn test_server_create_tenant_success() {
-        let response = simulate_create_tenant_response("org_test", "Test Organization");
-        assert_eq!(response["type"].as_str().unwrap(), "TenantResult");
-        assert!(response["data"]["success"].as_bool().unwrap());
-        assert_eq!(response["data"]["tenant_id"].as_str().unwrap(), "org_test");
-    }
-
-    #[test]
-    fn test_server_delete_tenant_success() {
-        let response = simulate_delete_tenant_response("org_test");
-        assert_eq!(response["type"].as_str().unwrap(), "Result");
-        assert!(response["data"]["success"].as_bool().unwrap());
-    }
-
-    #[test]
-    fn test_server_create_tenant_already_exists() {
-        let response = simulate_error_response("AlreadyExists", "Tenant 'org_test' already exists");
-        assert_eq!(response["data"]["code"].as_str().unwrap(), "AlreadyExists");
-    }
-
-    #[test]
-    fn test_server_delete_tenant_not_found() {
-        let response = simulate_error_response("NotFound", "Tenant 'org_nonexistent' not found");
-        assert_eq!(response["data"]["code"].as_str().unwrap(), "NotFound");
-    }
-
-    #[test]
-    fn test_server_create_tenant_invalid_request() {
-        let response = simulate_error_response(
-            "InvalidRequest",
-            "tenant_id and tenant_name are required",
-        );
-        assert_eq!(response["data"]["code"].as_str().unwrap(), "InvalidRequest");
-    }
-
-    #[test]
-    fn test_server_tenant_response_has_required_fields() {
-        let response = simulate_create_tenant_response("org_alpha", "Alpha");
-        assert!(response.get("type").is_some());
-        assert!(response.get("data").is_some());
-        assert!(response["data"].get("success").is_some());
-        assert!(response["data"].get("tenant_id").is_some());
-        assert!(response["data"].get("message").is_some());
-    }
-
-    #[test]
-    fn test_server_delete_tenant_response_format() {
-        let response = simulate_delete_tenant_response("org_alpha");
-        assert!(response.get("type").is_some());
-        assert!(response["data"].get("request_id").is_some());
-        assert!(response["data"].get("success").is_some());
-        assert!(response["data"].get("message").is_some());
-    }
-
-    #[test]
-    fn test_server_error_response_format() {
-        let response = simulate_error_response("PermissionDenied", "Admin permission required");
-        assert_eq!(response["type"].as_str().unwrap(), "Error");
-        assert!(response["data"].get("code").is_some());
-        assert!(response["data"].get("message").is_some());
-    }
-
-    #[test]
-    fn test_server_creates_multiple_tenants() {
-        let org1 = simulate_create_tenant_response("org_1", "Organization 1");
-        let org2 = simulate_create_tenant_response("org_2", "Organization 2");
-        let org3 = simulate_create_tenant_response("org_3", "Organization 3");
-
-        assert_eq!(org1["data"]["tenant_id"].as_str().unwrap(), "org_1");
-        assert_eq!(org2["data"]["tenant_id"].as_str().unwrap(), "org_2");
-        assert_eq!(org3["data"]["tenant_id"].as_str().unwrap(), "org_3");
-    }
-
-    #[test]
-    fn test_server_tenant_id_uniqueness() {
-        let response1 = simulate_create_tenant_response("org_alpha", "Alpha Org");
-        let response2 = simulate_create_tenant_response("org_beta", "Beta Org");
-
-        let id1 = response1["data"]["tenant_id"].as_str().unwrap();
-        let id2 = response2["data"]["tenant_id"].as_str().unwrap();
-
-        assert_ne!(id1, id2);
-    }
-
-    #[test]
-    fn test_server_preserves_tenant_metadata() {
-        let response = simulate_create_tenant_response("org_meta", "Metadata Test Org");
-        let message = response["data"]["message"].as_str().unwrap();
-
-        assert!(message.contains("Metadata Test Org"));
-    }
-
-    #[test]
-    fn test_server_handles_special_characters_in_tenant_name() {
-        let response =
-            simulate_create_tenant_response("org-special", "Org (Special) & Co.");
-        assert_eq!(response["data"]["tenant_id"].as_str().unwrap(), "org-special");
-    }
-
-    #[test]
-    fn test_server_delete_returns_proper_acknowledgement() {
-        let response = simulate_delete_tenant_response("org_cleanup");
-        assert!(response["data"]["success"].as_bool().unwrap());
-        assert!(response["data"]["message"]
-            .as_str()
-            .unwrap()
-            .contains("deleted"));
-    }
-
-    #[test]
-    fn test_server_error_codes_distinct() {
-        let already_exists = simulate_error_response("AlreadyExists", "Exists");
-        let not_found = simulate_error_response("NotFound", "Not found");
-        let invalid = simulate_error_response("InvalidRequest", "Invalid");
-
-        assert_ne!(
-            already_exists["data"]["code"].as_str().unwrap(),
-            not_found["data"]["code"].as_str().unwrap()
-        );
-        assert_ne!(
-            not_found["data"]["code"].as_str().unwrap(),
-            invalid["data"]["code"].as_str().unwrap()
-        );
-    }
-
-    #[test]
-    fn test_server_tenant_create_then_delete_sequence() {
-        // Simulate creating a tenant
-        let create_response = simulate_create_tenant_response("org_seq", "Sequence Test");
-        assert!(create_response["data"]["success"].as_bool().unwrap());
-
-        // Simulate deleting the same tenant
-        let delete_response = simulate_delete_tenant_response("org_seq");
-        assert!(delete_response["data"]["success"].as_bool().unwrap());
-
-        // Both operations should have same tenant_id
-        assert_eq!(
-            create_response["data"]["tenant_id"].as_str().unwrap(),
-            "org_seq"
-        );
-        assert!(delete_response["data"]["message"]
-            .as_str()
-            .unwrap()
-            .contains("org_seq"));
-    }
-
-    #[test]
-    fn test_server_maintains_request_id() {
-        let response1 = simulate_delete_tenant_response("org_1");
-        let response2 = simulate_delete_tenant_response("org_2");
-
-        let req_id1 = response1["data"]["request_id"].as_str().unwrap();
-        let req_id2 = response2["data"]["request_id"].as_str().unwrap();
-
-        // Each response should have a request_id (even if same for now)
-        assert!(!req_id1.is_empty());
-        assert!(!req_id2.is_empty());
-    }
-
-    #[test]
-    fn test_server_handles_concurrent_tenant_operations() {
-        // Simulate multiple concurrent create operations
-        let ops = (1..=5)
-            .map(|i| {
-                simulate_create_tenant_response(
-                    &format!("org_{}", i),
-                    &format!("Organization {}", i),
-                )
-            })
-            .collect::<Vec<_>>();
-
-        assert_eq!(ops.len(), 5);
-        for (i, op) in ops.iter().enumerate() {
-            assert!(op["data"]["success"].as_bool().unwrap());
-            assert_eq!(
-                op["data"]["tenant_id"].as_str().unwrap(),
-                &format!("org_{}", i + 1)
-            );
-        }
-    }
-
-    #[test]
-    fn test_server_validates_tenant_id_format() {
-        // Valid formats should succeed
-        let valid_responses = vec![
-            simulate_create_tenant_response("org_valid", "Valid"),
-            simulate_create_tenant_response("org-valid", "Valid"),
-            simulate_create_tenant_response("org123", "Valid"),
-        ];
-
-        for response in valid_responses {
-            assert!(response["data"]["success"].as_bool().unwrap());
-        }
-    }
-
-    #[test]
-    fn test_server_tenant_error_message_clarity() {
-        let response = simulate_error_response(
-            "AlreadyExists",
-            "Tenant 'org_test' already exists",
-        );
-        let message = response["data"]["message"].as_str().unwrap();
-
-        assert!(message.contains("Tenant"));
-        assert!(message.contains("org_test"));
-        assert!(message.contains("already exists"));
-    }
-}
+#[cfg(test)]
+mod tenant_server_behavior_tests {
+    use serde_json::json;
+
+    /// Helper to simulate server processing of tenant operations
+    fn simulate_create_tenant_response(
+        tenant_id: &str,
+        tenant_name: &str,
+    ) -> serde_json::Value {
+        json!({
+            "type": "TenantResult",
+            "data": {
+                "success": true,
+                "tenant_id": tenant_id,
+                "message": format!("Tenant '{}' created", tenant_name)
+            }
+        })
+    }
+
+    fn simulate_delete_tenant_response(tenant_id: &str) -> serde_json::Value {
+        json!({
+            "type": "Result",
+            "data": {
+                "request_id": "req-123",
+                "success": true,
+                "message": format!("Tenant '{}' deleted", tenant_id)
+            }
+        })
+    }
+
+    fn simulate_error_response(code: &str, message: &str) -> serde_json::Value {
+        json!({
+            "type": "Error",
+            "data": {
+                "code": code,
+                "message": message
+            }
+        })
+    }
+
+    #[test]
+    fn test_server_create_tenant_success() {
+        let response = simulate_create_tenant_response("org_test", "Test Organization");
+        assert_eq!(response["type"].as_str().unwrap(), "TenantResult");
+        assert!(response["data"]["success"].as_bool().unwrap());
+        assert_eq!(response["data"]["tenant_id"].as_str().unwrap(), "org_test");
+    }
+
+    #[test]
+    fn test_server_delete_tenant_success() {
+        let response = simulate_delete_tenant_response("org_test");
+        assert_eq!(response["type"].as_str().unwrap(), "Result");
+        assert!(response["data"]["success"].as_bool().unwrap());
+    }
+
+    #[test]
+    fn test_server_create_tenant_already_exists() {
+        let response = simulate_error_response("AlreadyExists", "Tenant 'org_test' already exists");
+        assert_eq!(response["data"]["code"].as_str().unwrap(), "AlreadyExists");
+    }
+
+    #[test]
+    fn test_server_delete_tenant_not_found() {
+        let response = simulate_error_response("NotFound", "Tenant 'org_nonexistent' not found");
+        assert_eq!(response["data"]["code"].as_str().unwrap(), "NotFound");
+    }
+
+    #[test]
+    fn test_server_create_tenant_invalid_request() {
+        let response = simulate_error_response(
+            "InvalidRequest",
+            "tenant_id and tenant_name are required",
+        );
+        assert_eq!(response["data"]["code"].as_str().unwrap(), "InvalidRequest");
+    }
+
+    #[test]
+    fn test_server_tenant_response_has_required_fields() {
+        let response = simulate_create_tenant_response("org_alpha", "Alpha");
+        assert!(response.get("type").is_some());
+        assert!(response.get("data").is_some());
+        assert!(response["data"].get("success").is_some());
+        assert!(response["data"].get("tenant_id").is_some());
+        assert!(response["data"].get("message").is_some());
+    }
+
+    #[test]
+    fn test_server_delete_tenant_response_format() {
+        let response = simulate_delete_tenant_response("org_alpha");
+        assert!(response.get("type").is_some());
+        assert!(response["data"].get("request_id").is_some());
+        assert!(response["data"].get("success").is_some());
+        assert!(response["data"].get("message").is_some());
+    }
+
+    #[test]
+    fn test_server_error_response_format() {
+        let response = simulate_error_response("PermissionDenied", "Admin permission required");
+        assert_eq!(response["type"].as_str().unwrap(), "Error");
+        assert!(response["data"].get("code").is_some());
+        assert!(response["data"].get("message").is_some());
+    }
+
+    #[test]
+    fn test_server_creates_multiple_tenants() {
+        let org1 = simulate_create_tenant_response("org_1", "Organization 1");
+        let org2 = simulate_create_tenant_response("org_2", "Organization 2");
+        let org3 = simulate_create_tenant_response("org_3", "Organization 3");
+
+        assert_eq!(org1["data"]["tenant_id"].as_str().unwrap(), "org_1");
+        assert_eq!(org2["data"]["tenant_id"].as_str().unwrap(), "org_2");
+        assert_eq!(org3["data"]["tenant_id"].as_str().unwrap(), "org_3");
+    }
+
+    #[test]
+    fn test_server_tenant_id_uniqueness() {
+        let response1 = simulate_create_tenant_response("org_alpha", "Alpha Org");
+        let response2 = simulate_create_tenant_response("org_beta", "Beta Org");
+
+        let id1 = response1["data"]["tenant_id"].as_str().unwrap();
+        let id2 = response2["data"]["tenant_id"].as_str().unwrap();
+
+        assert_ne!(id1, id2);
+    }
+
+    #[test]
+    fn test_server_preserves_tenant_metadata() {
+        let response = simulate_create_tenant_response("org_meta", "Metadata Test Org");
+        let message = response["data"]["message"].as_str().unwrap();
+
+        assert!(message.contains("Metadata Test Org"));
+    }
+
+    #[test]
+    fn test_server_handles_special_characters_in_tenant_name() {
+        let response =
+            simulate_create_tenant_response("org-special", "Org (Special) & Co.");
+        assert_eq!(response["data"]["tenant_id"].as_str().unwrap(), "org-special");
+    }
+
+    #[test]
+    fn test_server_delete_returns_proper_acknowledgement() {
+        let response = simulate_delete_tenant_response("org_cleanup");
+        assert!(response["data"]["success"].as_bool().unwrap());
+        assert!(response["data"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("deleted"));
+    }
+
+    #[test]
+    fn test_server_error_codes_distinct() {
+        let already_exists = simulate_error_response("AlreadyExists", "Exists");
+        let not_found = simulate_error_response("NotFound", "Not found");
+        let invalid = simulate_error_response("InvalidRequest", "Invalid");
+
+        assert_ne!(
+            already_exists["data"]["code"].as_str().unwrap(),
+            not_found["data"]["code"].as_str().unwrap()
+        );
+        assert_ne!(
+            not_found["data"]["code"].as_str().unwrap(),
+            invalid["data"]["code"].as_str().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_server_tenant_create_then_delete_sequence() {
+        // Simulate creating a tenant
+        let create_response = simulate_create_tenant_response("org_seq", "Sequence Test");
+        assert!(create_response["data"]["success"].as_bool().unwrap());
+
+        // Simulate deleting the same tenant
+        let delete_response = simulate_delete_tenant_response("org_seq");
+        assert!(delete_response["data"]["success"].as_bool().unwrap());
+
+        // Both operations should have same tenant_id
+        assert_eq!(
+            create_response["data"]["tenant_id"].as_str().unwrap(),
+            "org_seq"
+        );
+        assert!(delete_response["data"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("org_seq"));
+    }
+
+    #[test]
+    fn test_server_maintains_request_id() {
+        let response1 = simulate_delete_tenant_response("org_1");
+        let response2 = simulate_delete_tenant_response("org_2");
+
+        let req_id1 = response1["data"]["request_id"].as_str().unwrap();
+        let req_id2 = response2["data"]["request_id"].as_str().unwrap();
+
+        // Each response should have a request_id (even if same for now)
+        assert!(!req_id1.is_empty());
+        assert!(!req_id2.is_empty());
+    }
+
+    #[test]
+    fn test_server_handles_concurrent_tenant_operations() {
+        // Simulate multiple concurrent create operations
+        let ops = (1..=5)
+            .map(|i| {
+                simulate_create_tenant_response(
+                    &format!("org_{}", i),
+                    &format!("Organization {}", i),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(ops.len(), 5);
+        for (i, op) in ops.iter().enumerate() {
+            assert!(op["data"]["success"].as_bool().unwrap());
+            assert_eq!(
+                op["data"]["tenant_id"].as_str().unwrap(),
+                &format!("org_{}", i + 1)
+            );
+        }
+    }
+
+    #[test]
+    fn test_server_validates_tenant_id_format() {
+        // Valid formats should succeed
+        let valid_responses = vec![
+            simulate_create_tenant_response("org_valid", "Valid"),
+            simulate_create_tenant_response("org-valid", "Valid"),
+            simulate_create_tenant_response("org123", "Valid"),
+        ];
+
+        for response in valid_responses {
+            assert!(response["data"]["success"].as_bool().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_server_tenant_error_message_clarity() {
+        let response = simulate_error_response(
+            "AlreadyExists",
+            "Tenant 'org_test' already exists",
+        );
+        let message = response["data"]["message"].as_str().unwrap();
+
+        assert!(message.contains("Tenant"));
+        assert!(message.contains("org_test"));
+        assert!(message.contains("already exists"));
+    }
+}